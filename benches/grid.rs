@@ -0,0 +1,109 @@
+//! Grid geometry and the selection state machine, which run on every key
+//! press while the overlay is open. Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use windows::Win32::Foundation::RECT;
+
+// tactile-win is a binary, so the modules under test are built in here;
+// the benchmarks use only part of each
+#[allow(dead_code)]
+#[path = "../src"]
+mod app {
+    pub mod grid;
+    pub mod region;
+    pub mod selection;
+}
+
+use app::{grid, region, selection};
+use grid::{Grid, TilePos};
+use selection::{SelectionState, TileSelector};
+
+/// A 4K work area, less a taskbar
+const WORK_AREA: RECT = RECT {
+    left: 0,
+    top: 0,
+    right: 3840,
+    bottom: 2080,
+};
+
+/// The default grid and the largest the overlay has keys for
+const SIZES: [(u32, u32); 2] = [(4, 2), (8, 4)];
+
+fn grids() -> impl Iterator<Item = (String, Grid)> {
+    SIZES.into_iter().map(|(cols, rows)| {
+        let grid = Grid::new(cols, rows, 10, WORK_AREA).with_min_tile_size(200);
+        (format!("{}x{}", cols, rows), grid)
+    })
+}
+
+fn bench_tile_rect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tile_rect");
+    for (size, grid) in grids() {
+        let last = TilePos {
+            col: grid.cols - 1,
+            row: grid.rows - 1,
+        };
+        group.bench_with_input(BenchmarkId::from_parameter(size), &grid, |b, grid| {
+            b.iter(|| grid.tile_rect(black_box(last)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_combine_tiles(c: &mut Criterion) {
+    let mut group = c.benchmark_group("combine_tiles");
+    for (size, grid) in grids() {
+        let first = TilePos { col: 0, row: 0 };
+        let last = TilePos {
+            col: grid.cols - 1,
+            row: grid.rows - 1,
+        };
+        group.bench_with_input(BenchmarkId::from_parameter(size), &grid, |b, grid| {
+            b.iter(|| grid.combine_tiles(black_box(first), black_box(last)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_rect_to_span(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rect_to_span");
+    // A window dragged roughly over the middle, as snap_on_move sees it
+    let rect = RECT {
+        left: 1013,
+        top: 377,
+        right: 2861,
+        bottom: 1702,
+    };
+    for (size, grid) in grids() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &grid, |b, grid| {
+            b.iter(|| grid.rect_to_span(black_box(rect)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_selection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("selection");
+    for (size, grid) in grids() {
+        let mut selector = TileSelector::new(grid);
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| {
+                selector.reset();
+                selector.handle_key(black_box('Q'));
+                let state = selector.handle_key(black_box('S'));
+                debug_assert!(matches!(state, SelectionState::Complete(_)));
+                state
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_tile_rect,
+    bench_combine_tiles,
+    bench_rect_to_span,
+    bench_selection
+);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+//! Config files are edited by hand, and a bad one must never panic the
+//! tray process. Run with `cargo fuzz run config`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use windows::Win32::Foundation::RECT;
+
+// tactile-win is a binary, so the modules under test are built in here
+#[allow(dead_code)]
+#[path = "../../src"]
+mod app {
+    pub mod config;
+    pub mod grid;
+    pub mod keys;
+    pub mod region;
+    pub mod rules;
+    pub mod selection;
+}
+
+use app::config::Config;
+use app::{config, grid, keys, region, rules, selection};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(mut config) = Config::parse(text) else {
+        return;
+    };
+    config.validate();
+
+    // What startup does with a freshly loaded config
+    let work_area = RECT {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+    let _ = config.cramped_tiles(work_area, 96);
+    let profile = config.auto_profile(2);
+    let _ = config.grid_dimensions(Some(0), &[r"\\.\DISPLAY1"], profile);
+
+    // The settings dialog saves what it loaded, which must load again
+    let saved = toml::to_string_pretty(&config).expect("config serializes");
+    Config::parse(&saved).expect("saved config loads");
+});
@@ -0,0 +1,45 @@
+//! Regions are typed into the config and onto the command line, and a bad
+//! one must never panic the tray process. Run with `cargo fuzz run region`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use windows::Win32::Foundation::RECT;
+
+// tactile-win is a binary, so the modules under test are built in here
+#[allow(dead_code)]
+#[path = "../../src"]
+mod app {
+    pub mod grid;
+    pub mod region;
+}
+
+use app::grid::{self, Grid};
+use app::region::Region;
+
+const WORK_AREA: RECT = RECT {
+    left: 0,
+    top: 0,
+    right: 1920,
+    bottom: 1040,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(region) = text.parse::<Region>() else {
+        return;
+    };
+    // Every grid size the config allows
+    for cols in 1..=8 {
+        for rows in 1..=4 {
+            let grid = Grid::new(cols, rows, 10, WORK_AREA);
+            let _ = region.resolve(&grid);
+        }
+    }
+
+    // Regions are saved as written, which must parse back the same
+    let reparsed: Region = region.to_string().parse().expect("saved region parses");
+    assert_eq!(reparsed, region);
+});
@@ -0,0 +1,311 @@
+use std::cell::RefCell;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::Media::Speech::{ISpVoice, SpVoice, SPF_ASYNC, SPF_PURGEBEFORESPEAK};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_APARTMENTTHREADED,
+};
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+use windows::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPI_GETHIGHCONTRAST, SPI_GETSCREENREADER, SPI_SETHIGHCONTRAST,
+    SPI_SETNONCLIENTMETRICS, SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    WM_SETTINGCHANGE, WM_SYSCOLORCHANGE, WM_THEMECHANGED,
+};
+
+use crate::config::AnnounceMode;
+use crate::grid::TilePos;
+
+// SAPI voice, created on first announcement (COM objects stay on this thread)
+thread_local! {
+    static VOICE: RefCell<Option<ISpVoice>> = const { RefCell::new(None) };
+}
+
+/// True when a screen reader has told Windows it is running
+pub fn is_screen_reader_active() -> bool {
+    unsafe {
+        let mut active = windows::core::BOOL(0);
+        SystemParametersInfoW(
+            SPI_GETSCREENREADER,
+            0,
+            Some(&mut active as *mut _ as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+        .is_ok()
+            && active.as_bool()
+    }
+}
+
+/// True when a Windows high-contrast theme is active
+pub fn is_high_contrast() -> bool {
+    unsafe {
+        let mut hc = HIGHCONTRASTW {
+            cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+            ..Default::default()
+        };
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            hc.cbSize,
+            Some(&mut hc as *mut _ as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+        .is_ok()
+            && (hc.dwFlags.0 & HCF_HIGHCONTRASTON.0) != 0
+    }
+}
+
+/// True when "Transparency effects" is turned off in Windows personalization
+pub fn is_transparency_disabled() -> bool {
+    unsafe {
+        let mut enabled: u32 = 1;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let result = RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("EnableTransparency"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut enabled as *mut _ as *mut _),
+            Some(&mut size),
+        );
+        result.is_ok() && enabled == 0
+    }
+}
+
+/// Whether a WM_SETTINGCHANGE for `action`, naming the settings `area`
+/// (empty if none), can change the overlay's colors, transparency or fonts
+fn is_appearance_setting(action: u32, area: &str) -> bool {
+    let action = SYSTEM_PARAMETERS_INFO_ACTION(action);
+    // Light/dark mode, accent color and transparency are "ImmersiveColorSet"
+    action == SPI_SETHIGHCONTRAST
+        || action == SPI_SETNONCLIENTMETRICS
+        || matches!(area, "ImmersiveColorSet" | "WindowsThemeElement")
+}
+
+/// Whether a window message says the system theme, colors or fonts changed
+///
+/// # Safety
+/// `lparam` must be the message's own LPARAM.
+pub unsafe fn is_appearance_change(msg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
+    match msg {
+        WM_THEMECHANGED | WM_SYSCOLORCHANGE => true,
+        WM_SETTINGCHANGE => {
+            let area = if lparam.0 == 0 {
+                String::new()
+            } else {
+                unsafe { PCWSTR(lparam.0 as *const u16).to_string() }.unwrap_or_default()
+            };
+            is_appearance_setting(wparam.0 as u32, &area)
+        }
+        _ => false,
+    }
+}
+
+/// The Windows "Make text bigger" setting as a percentage (100-225)
+fn system_text_scale() -> u32 {
+    unsafe {
+        let mut factor: u32 = 100;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let result = RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Accessibility"),
+            w!("TextScaleFactor"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut factor as *mut _ as *mut _),
+            Some(&mut size),
+        );
+        if result.is_ok() {
+            factor
+        } else {
+            100
+        }
+    }
+}
+
+/// Combine the system text scale with the configured multiplier. Bad
+/// multipliers fall back to 1 and the result is kept to 50%-400%.
+fn combine_text_scale(system_percent: u32, multiplier: f32) -> i32 {
+    let multiplier = if multiplier.is_finite() && multiplier > 0.0 {
+        multiplier
+    } else {
+        1.0
+    };
+    let percent = (system_percent.clamp(100, 225) as f32 * multiplier).round() as i32;
+    percent.clamp(50, 400)
+}
+
+/// Text size as a percentage of normal, for labels and dialog fonts
+pub fn text_scale_percent(multiplier: f32) -> i32 {
+    combine_text_scale(system_text_scale(), multiplier)
+}
+
+fn create_voice() -> Option<ISpVoice> {
+    unsafe {
+        // S_FALSE (already initialized) is fine; SAPI only needs an apartment
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        CoCreateInstance(&SpVoice, None, CLSCTX_ALL).ok()
+    }
+}
+
+/// Speak an overlay state change, interrupting any previous announcement
+pub fn announce(mode: AnnounceMode, text: &str) {
+    let enabled = match mode {
+        AnnounceMode::Always => true,
+        AnnounceMode::Auto => is_screen_reader_active(),
+        AnnounceMode::Never => false,
+    };
+    if !enabled {
+        return;
+    }
+
+    VOICE.with(|voice| {
+        let mut voice = voice.borrow_mut();
+        if voice.is_none() {
+            *voice = create_voice();
+        }
+        if let Some(ref voice) = *voice {
+            let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+            let flags = (SPF_ASYNC.0 | SPF_PURGEBEFORESPEAK.0) as u32;
+            unsafe {
+                let _ = voice.Speak(PCWSTR(wide.as_ptr()), flags, None);
+            }
+        }
+    });
+}
+
+/// Named fraction for `len` of `count` cells, e.g. 1 of 2 = "half"
+fn fraction_name(len: u32, count: u32) -> Option<&'static str> {
+    // Compare against known fractions in twelfths
+    if !(len * 12).is_multiple_of(count) {
+        return None;
+    }
+    match len * 12 / count {
+        6 => Some("half"),
+        4 => Some("third"),
+        8 => Some("two-thirds"),
+        3 => Some("quarter"),
+        9 => Some("three-quarters"),
+        _ => None,
+    }
+}
+
+/// How a span covers one axis of the grid
+enum Extent {
+    Full,
+    /// Edge-anchored named fraction, e.g. ("left", "half")
+    Named(&'static str, &'static str),
+    Partial,
+}
+
+fn axis_extent(start: u32, end: u32, count: u32, edges: (&'static str, &'static str)) -> Extent {
+    let len = end - start + 1;
+    if len == count {
+        return Extent::Full;
+    }
+    let edge = if start == 0 {
+        edges.0
+    } else if end == count - 1 {
+        edges.1
+    } else {
+        return Extent::Partial;
+    };
+    match fraction_name(len, count) {
+        Some(fraction) => Extent::Named(edge, fraction),
+        None => Extent::Partial,
+    }
+}
+
+fn range_name(axis: &str, start: u32, end: u32) -> String {
+    if start == end {
+        format!("{} {}", axis, start + 1)
+    } else {
+        format!("{}s {} to {}", axis, start + 1, end + 1)
+    }
+}
+
+/// Describe the region spanned by two tiles in words, for announcements:
+/// "full screen", "left half", "top-right quarter", or a column/row range.
+pub fn describe_span(cols: u32, rows: u32, a: TilePos, b: TilePos) -> String {
+    let (col_start, col_end) = (a.col.min(b.col), a.col.max(b.col));
+    let (row_start, row_end) = (a.row.min(b.row), a.row.max(b.row));
+
+    let horizontal = axis_extent(col_start, col_end, cols, ("left", "right"));
+    let vertical = axis_extent(row_start, row_end, rows, ("top", "bottom"));
+
+    match (horizontal, vertical) {
+        (Extent::Full, Extent::Full) => "full screen".to_string(),
+        (Extent::Named(side, fraction), Extent::Full)
+        | (Extent::Full, Extent::Named(side, fraction)) => format!("{} {}", side, fraction),
+        (Extent::Named(h, "half"), Extent::Named(v, "half")) => format!("{}-{} quarter", v, h),
+        _ => format!(
+            "{}, {}",
+            range_name("column", col_start, col_end),
+            range_name("row", row_start, row_end)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(col: u32, row: u32) -> TilePos {
+        TilePos { col, row }
+    }
+
+    #[test]
+    fn test_is_appearance_setting() {
+        assert!(is_appearance_setting(0, "ImmersiveColorSet"));
+        assert!(is_appearance_setting(SPI_SETHIGHCONTRAST.0, ""));
+        // Work area and unrelated policy changes
+        assert!(!is_appearance_setting(0x002F, ""));
+        assert!(!is_appearance_setting(0, "Policy"));
+    }
+
+    #[test]
+    fn test_combine_text_scale() {
+        assert_eq!(combine_text_scale(100, 1.0), 100);
+        assert_eq!(combine_text_scale(150, 1.0), 150);
+        assert_eq!(combine_text_scale(150, 1.5), 225);
+        assert_eq!(combine_text_scale(225, 4.0), 400);
+        assert_eq!(combine_text_scale(0, 0.1), 50);
+        assert_eq!(combine_text_scale(100, f32::NAN), 100);
+        assert_eq!(combine_text_scale(100, -2.0), 100);
+    }
+
+    #[test]
+    fn test_describe_full_and_halves() {
+        assert_eq!(describe_span(4, 2, pos(0, 0), pos(3, 1)), "full screen");
+        assert_eq!(describe_span(4, 2, pos(0, 0), pos(1, 1)), "left half");
+        assert_eq!(describe_span(4, 2, pos(3, 1), pos(2, 0)), "right half");
+        assert_eq!(describe_span(4, 2, pos(0, 0), pos(3, 0)), "top half");
+    }
+
+    #[test]
+    fn test_describe_quarters_and_thirds() {
+        assert_eq!(
+            describe_span(4, 2, pos(0, 0), pos(1, 0)),
+            "top-left quarter"
+        );
+        assert_eq!(
+            describe_span(4, 2, pos(2, 1), pos(3, 1)),
+            "bottom-right quarter"
+        );
+        assert_eq!(describe_span(6, 1, pos(0, 0), pos(1, 0)), "left third");
+        assert_eq!(
+            describe_span(6, 1, pos(2, 0), pos(5, 0)),
+            "right two-thirds"
+        );
+        assert_eq!(describe_span(4, 1, pos(0, 0), pos(0, 0)), "left quarter");
+    }
+
+    #[test]
+    fn test_describe_ranges() {
+        assert_eq!(
+            describe_span(4, 2, pos(1, 0), pos(2, 0)),
+            "columns 2 to 3, row 1"
+        );
+        assert_eq!(describe_span(3, 3, pos(1, 1), pos(1, 1)), "column 2, row 2");
+    }
+}
@@ -0,0 +1,457 @@
+use std::time::Instant;
+use windows::Win32::Foundation::{HWND, LPARAM, RECT, WPARAM};
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::UI::WindowsAndMessaging::{
+    FindWindowExW, SendMessageTimeoutW, HWND_MESSAGE, SMTO_ABORTIFHUNG, WM_COPYDATA,
+};
+
+use crate::enumwin::WindowEntry;
+use crate::ipc::{Status, REQUEST_TIMEOUT};
+use crate::rules::PlacementStep;
+use crate::CLASS_NAME;
+
+/// COPYDATASTRUCT dwData marking a placement batch ("TWPB"); the data is
+/// the batch as a JSON array of steps
+pub const BATCH_COPYDATA_ID: usize = 0x5457_5042;
+
+/// Command-line argument adding a step, as `--place <window>=<region>`
+const PLACE_ARG: &str = "--place";
+
+/// How far, in pixels, a window may land from where it was sent and still
+/// count as moved; consoles and other windows that size in steps of a
+/// character cell land a little short
+const LANDED_TOLERANCE: i32 = 8;
+
+/// Steps given as `--place` arguments, in order
+pub fn parse_args(args: &[String]) -> Result<Vec<PlacementStep>, String> {
+    let mut steps = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == PLACE_ARG {
+            let step = args
+                .next()
+                .ok_or_else(|| format!("{} needs <window>=<region>", PLACE_ARG))?;
+            steps.push(PlacementStep::parse(step)?);
+        }
+    }
+    Ok(steps)
+}
+
+/// For each step, the index into `windows` of the window it moves: the
+/// first (topmost) match not already taken by an earlier step. Fails
+/// naming the first step nothing matches, so no window is moved.
+pub fn match_steps(steps: &[PlacementStep], windows: &[WindowEntry]) -> Result<Vec<usize>, String> {
+    let mut taken: Vec<usize> = Vec::new();
+    for step in steps {
+        let index = windows
+            .iter()
+            .enumerate()
+            .position(|(index, entry)| !taken.contains(&index) && step.window.matches(&entry.info))
+            .ok_or_else(|| format!("no window matches {}", step.window.describe()))?;
+        taken.push(index);
+    }
+    Ok(taken)
+}
+
+/// The steps that match a window, in order, leaving out those nothing
+/// matches, so a layout still places the programs that are open
+pub fn present_steps(steps: &[PlacementStep], windows: &[WindowEntry]) -> Vec<PlacementStep> {
+    let mut present = Vec::new();
+    for step in steps {
+        present.push(step.clone());
+        if match_steps(&present, windows).is_err() {
+            present.pop();
+        }
+    }
+    present
+}
+
+/// A matched window's move, and where to put it back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlannedMove {
+    pub hwnd: HWND,
+    pub from: RECT,
+    pub to: RECT,
+}
+
+/// Why a transaction's moves were undone
+#[derive(Debug, PartialEq)]
+pub struct RolledBack {
+    pub status: Status,
+    pub reason: String,
+}
+
+/// Why a window at `rect` isn't where it was sent, if it isn't
+fn misplaced(rect: Option<RECT>, wanted: &RECT) -> Option<String> {
+    let Some(rect) = rect else {
+        return Some("lost its window".to_string());
+    };
+    let edges = [
+        (rect.left, wanted.left),
+        (rect.top, wanted.top),
+        (rect.right, wanted.right),
+        (rect.bottom, wanted.bottom),
+    ];
+    if edges
+        .iter()
+        .all(|&(edge, wanted)| (edge - wanted).abs() <= LANDED_TOLERANCE)
+    {
+        return None;
+    }
+    Some(format!(
+        "landed at {},{} {}x{}",
+        rect.left,
+        rect.top,
+        rect.right - rect.left,
+        rect.bottom - rect.top
+    ))
+}
+
+/// Make every move, or none. `move_window` must have finished moving when
+/// it returns, so `window_rect` reads back where each window landed. If a
+/// move fails, a window lands elsewhere or `deadline` passes, the windows
+/// already moved are put back, last first. Once all have moved, each
+/// window is checked again, since an app can move its window back or a
+/// later step can push an earlier one; if one has, every move is undone.
+pub fn run_transaction<E: std::fmt::Display>(
+    moves: &[PlannedMove],
+    deadline: Instant,
+    mut move_window: impl FnMut(HWND, &RECT) -> Result<(), E>,
+    mut window_rect: impl FnMut(HWND) -> Option<RECT>,
+) -> Result<(), RolledBack> {
+    for (step, planned) in moves.iter().enumerate() {
+        // Moves made so far, this step's included once it has moved
+        let mut done = step;
+        let failure = if Instant::now() >= deadline {
+            Some((Status::TimedOut, "timed out".to_string()))
+        } else if let Err(e) = move_window(planned.hwnd, &planned.to) {
+            Some((Status::Failed, format!("failed: {}", e)))
+        } else {
+            done += 1;
+            let rect = window_rect(planned.hwnd);
+            if Instant::now() >= deadline {
+                Some((Status::TimedOut, "timed out".to_string()))
+            } else {
+                misplaced(rect, &planned.to).map(|failure| (Status::Failed, failure))
+            }
+        };
+        if let Some((status, failure)) = failure {
+            for applied in moves[..done].iter().rev() {
+                let _ = move_window(applied.hwnd, &applied.from);
+            }
+            let reason = format!("step {} {}; undid {} moves", step + 1, failure, done);
+            return Err(RolledBack { status, reason });
+        }
+    }
+    for (step, planned) in moves.iter().enumerate() {
+        // A window placed twice only has to end up at its last place
+        if moves[step + 1..]
+            .iter()
+            .any(|later| later.hwnd == planned.hwnd)
+        {
+            continue;
+        }
+        if let Some(failure) = misplaced(window_rect(planned.hwnd), &planned.to) {
+            for applied in moves.iter().rev() {
+                let _ = move_window(applied.hwnd, &applied.from);
+            }
+            let reason = format!(
+                "step {} {} once all had moved; undid {} moves",
+                step + 1,
+                failure,
+                moves.len()
+            );
+            return Err(RolledBack {
+                status: Status::Failed,
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Hand the batch to the already-running instance and wait for it to run.
+/// None if there isn't one, else how the batch went.
+pub fn send_to_running_instance(steps: &[PlacementStep]) -> Option<Status> {
+    let hwnd = unsafe { FindWindowExW(Some(HWND_MESSAGE), None, CLASS_NAME, None) }.ok()?;
+    let json = serde_json::to_vec(steps).ok()?;
+    let data = COPYDATASTRUCT {
+        dwData: BATCH_COPYDATA_ID,
+        cbData: json.len() as u32,
+        lpData: json.as_ptr() as *mut _,
+    };
+    let mut result = 0;
+    // Allow for the request's own timeout, plus time to roll back
+    let timeout = (REQUEST_TIMEOUT * 2).as_millis() as u32;
+    let sent = unsafe {
+        SendMessageTimeoutW(
+            hwnd,
+            WM_COPYDATA,
+            WPARAM(0),
+            LPARAM(&data as *const _ as isize),
+            SMTO_ABORTIFHUNG,
+            timeout,
+            Some(&mut result),
+        )
+    };
+    if sent.0 == 0 {
+        return Some(Status::TimedOut);
+    }
+    Some(Status::from_code(result as isize).unwrap_or(Status::Failed))
+}
+
+/// The batch carried by a WM_COPYDATA message, if it is one
+///
+/// # Safety
+/// `lparam` must be the LPARAM of a WM_COPYDATA message being handled.
+pub unsafe fn receive(lparam: LPARAM) -> Option<Result<Vec<PlacementStep>, String>> {
+    let data = unsafe { &*(lparam.0 as *const COPYDATASTRUCT) };
+    if data.dwData != BATCH_COPYDATA_ID || data.lpData.is_null() {
+        return None;
+    }
+    let bytes =
+        unsafe { std::slice::from_raw_parts(data.lpData as *const u8, data.cbData as usize) };
+    Some(serde_json::from_slice(bytes).map_err(|e| e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::WindowInfo;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use windows::Win32::Graphics::Gdi::HMONITOR;
+
+    fn entry(id: usize, process: &str) -> WindowEntry {
+        WindowEntry {
+            hwnd: HWND(id as *mut _),
+            info: WindowInfo {
+                process: process.to_string(),
+                ..Default::default()
+            },
+            rect: RECT::default(),
+            monitor: HMONITOR::default(),
+            cloaked: false,
+            alt_tab: true,
+        }
+    }
+
+    fn rect(left: i32) -> RECT {
+        RECT {
+            left,
+            top: 0,
+            right: left + 100,
+            bottom: 100,
+        }
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args: Vec<String> = [
+            "--place",
+            "notepad.exe=left-half",
+            "--verbose",
+            "--place",
+            "class: CabinetWClass, title:Downloads = right 40%",
+        ]
+        .map(String::from)
+        .to_vec();
+        let steps = parse_args(&args).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].window.process.as_deref(), Some("notepad.exe"));
+        assert_eq!(steps[0].region.to_string(), "left-half");
+        assert_eq!(steps[1].window.class.as_deref(), Some("CabinetWClass"));
+        assert_eq!(steps[1].window.title.as_deref(), Some("Downloads"));
+        assert_eq!(steps[1].region.to_string(), "right 40%");
+
+        assert!(parse_args(&["--place".to_string()]).is_err());
+        assert!(parse_args(&["--place".to_string(), "notepad.exe".to_string()]).is_err());
+        assert!(parse_args(&["--place".to_string(), "pid:4=left".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_steps_round_trip_as_json() {
+        let steps = parse_args(&["--place".to_string(), "code.exe=top-left".to_string()]).unwrap();
+        let json = serde_json::to_string(&steps).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"process":"code.exe","class":null,"title":null,"region":"top-left"}]"#
+        );
+        let parsed: Vec<PlacementStep> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, steps);
+    }
+
+    #[test]
+    fn test_match_steps() {
+        let windows = [
+            entry(1, "code.exe"),
+            entry(2, "wt.exe"),
+            entry(3, "code.exe"),
+        ];
+        let step = |arg: &str| PlacementStep::parse(arg).unwrap();
+        // Each step takes the topmost match left over
+        let steps = [
+            step("code.exe=left-half"),
+            step("wt.exe=right-half"),
+            step("code.exe=top-half"),
+        ];
+        assert_eq!(match_steps(&steps, &windows), Ok(vec![0, 1, 2]));
+
+        let steps = [step("wt.exe=left-half"), step("wt.exe=right-half")];
+        assert_eq!(
+            match_steps(&steps, &windows),
+            Err("no window matches wt.exe".to_string())
+        );
+        // Layouts skip the steps left without a window instead
+        let steps = [
+            step("wt.exe=left-half"),
+            step("notepad.exe=top-half"),
+            step("wt.exe=right-half"),
+            step("code.exe=bottom-half"),
+        ];
+        let present = present_steps(&steps, &windows);
+        assert_eq!(present, [steps[0].clone(), steps[3].clone()]);
+    }
+
+    #[test]
+    fn test_transaction_rolls_back() {
+        let moves: Vec<PlannedMove> = (1..=3)
+            .map(|id| PlannedMove {
+                hwnd: HWND(id as *mut _),
+                from: rect(0),
+                to: rect(id * 100),
+            })
+            .collect();
+
+        let later = Instant::now() + Duration::from_secs(60);
+        let mut made = Vec::new();
+        let result = run_transaction(
+            &moves,
+            later,
+            |hwnd, rect| {
+                if hwnd.0 as usize == 3 {
+                    return Err("access denied");
+                }
+                made.push((hwnd.0 as usize, rect.left));
+                Ok(())
+            },
+            |hwnd| Some(rect(hwnd.0 as i32 * 100)),
+        );
+        assert_eq!(
+            result,
+            Err(RolledBack {
+                status: Status::Failed,
+                reason: "step 3 failed: access denied; undid 2 moves".to_string(),
+            })
+        );
+        // Moved forward, then back in reverse order
+        assert_eq!(made, [(1, 100), (2, 200), (2, 0), (1, 0)]);
+
+        let mut count = 0;
+        let result = run_transaction(
+            &moves,
+            later,
+            |_, _| {
+                count += 1;
+                Ok::<(), &str>(())
+            },
+            |hwnd| Some(rect(hwnd.0 as i32 * 100 + 5)),
+        );
+        assert_eq!((result, count), (Ok(()), 3));
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_misplaced() {
+        let moves: Vec<PlannedMove> = (1..=3)
+            .map(|id| PlannedMove {
+                hwnd: HWND(id as *mut _),
+                from: rect(0),
+                to: rect(id * 100),
+            })
+            .collect();
+        let later = Instant::now() + Duration::from_secs(60);
+        let placed = RefCell::new(HashMap::new());
+        let result = run_transaction(
+            &moves,
+            later,
+            |hwnd, rect| {
+                // The second window refuses to go anywhere
+                if hwnd.0 as usize != 2 {
+                    placed.borrow_mut().insert(hwnd.0 as usize, rect.left);
+                }
+                Ok::<(), &str>(())
+            },
+            |hwnd| Some(rect(*placed.borrow().get(&(hwnd.0 as usize)).unwrap_or(&0))),
+        );
+        assert_eq!(
+            result,
+            Err(RolledBack {
+                status: Status::Failed,
+                reason: "step 2 landed at 0,0 100x100; undid 2 moves".to_string(),
+            })
+        );
+        // The first window is back where it started, the third never moved
+        assert_eq!(placed.into_inner(), HashMap::from([(1, 0)]));
+
+        // A window that closes mid-transaction counts as a failed step
+        let result = run_transaction(&moves, later, |_, _| Ok::<(), &str>(()), |_| None);
+        assert_eq!(
+            result.map_err(|e| e.reason),
+            Err("step 1 lost its window; undid 1 moves".to_string())
+        );
+    }
+
+    #[test]
+    fn test_transaction_checks_final_places() {
+        let moves: Vec<PlannedMove> = (1..=2)
+            .map(|id| PlannedMove {
+                hwnd: HWND(id as *mut _),
+                from: rect(0),
+                to: rect(id * 100),
+            })
+            .collect();
+        let later = Instant::now() + Duration::from_secs(60);
+        let placed = RefCell::new(HashMap::new());
+        let result = run_transaction(
+            &moves,
+            later,
+            |hwnd, rect| {
+                let mut placed = placed.borrow_mut();
+                placed.insert(hwnd.0 as usize, rect.left);
+                // The first window's app puts it back once the second moves
+                if hwnd.0 as usize == 2 && rect.left != 0 {
+                    placed.insert(1, 0);
+                }
+                Ok::<(), &str>(())
+            },
+            |hwnd| Some(rect(placed.borrow()[&(hwnd.0 as usize)])),
+        );
+        assert_eq!(
+            result.map_err(|e| e.reason),
+            Err("step 1 landed at 0,0 100x100 once all had moved; undid 2 moves".to_string())
+        );
+        assert_eq!(placed.into_inner(), HashMap::from([(1, 0), (2, 0)]));
+    }
+
+    #[test]
+    fn test_transaction_times_out() {
+        let moves = [PlannedMove {
+            hwnd: HWND::default(),
+            from: rect(0),
+            to: rect(100),
+        }];
+        let mut count = 0;
+        let result = run_transaction(
+            &moves,
+            Instant::now(),
+            |_, _| {
+                count += 1;
+                Ok::<(), &str>(())
+            },
+            |_| Some(rect(100)),
+        );
+        assert_eq!(result.map_err(|e| e.status), Err(Status::TimedOut));
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Mutex;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+use crate::config::Config;
+use crate::grid::TilePos;
+
+/// Asks the main window to deliver published app events
+pub const WM_APP_EVENT: u32 = 0x8005; // WM_APP + 5
+
+/// Something that happened inside tactile-win that other parts of it may
+/// want to react to
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppEvent {
+    /// A window was tiled from the overlay
+    PlacementPerformed {
+        hwnd: HWND,
+        first: TilePos,
+        last: TilePos,
+        cols: u32,
+        rows: u32,
+        /// 1-based, in monitor enumeration order
+        monitor: usize,
+    },
+    /// The config was replaced, e.g. saved from Settings
+    ConfigChanged,
+    /// Monitors were connected or disconnected
+    MonitorsChanged { count: usize },
+    /// The overlay was opened, or moved to another monitor
+    OverlayShown {
+        monitor: usize,
+        cols: u32,
+        rows: u32,
+    },
+    /// The overlay was closed
+    OverlayHidden,
+}
+
+impl fmt::Display for AppEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppEvent::PlacementPerformed {
+                first,
+                last,
+                cols,
+                rows,
+                monitor,
+                ..
+            } => write!(
+                f,
+                "Placed a window on monitor {} at ({},{})-({},{}) of {}x{}",
+                monitor, first.col, first.row, last.col, last.row, cols, rows
+            ),
+            AppEvent::ConfigChanged => write!(f, "Config changed"),
+            AppEvent::MonitorsChanged { count } => write!(f, "{} monitors connected", count),
+            AppEvent::OverlayShown {
+                monitor,
+                cols,
+                rows,
+            } => write!(
+                f,
+                "Overlay shown on monitor {} ({}x{})",
+                monitor, cols, rows
+            ),
+            AppEvent::OverlayHidden => write!(f, "Overlay hidden"),
+        }
+    }
+}
+
+/// Called with each published event
+pub type Subscriber = fn(&AppEvent);
+/// Reads the config the app is running with
+pub type ConfigSource = fn() -> Option<Config>;
+
+// Main window, which delivers events from its message loop; 0 until set
+static BUS_HWND: AtomicIsize = AtomicIsize::new(0);
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+// Reads the config in use, for subscribers; set by init
+static CONFIG_SOURCE: Mutex<Option<ConfigSource>> = Mutex::new(None);
+
+thread_local! {
+    // Events wait here for the message loop; all publishing happens on the
+    // main thread
+    static QUEUE: RefCell<Vec<AppEvent>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Deliver events through `hwnd`, which must pass WM_APP_EVENT on to
+/// deliver_pending. Subscribers read the config in use from `config`.
+pub fn init(hwnd: HWND, config: ConfigSource) {
+    BUS_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
+    if let Ok(mut guard) = CONFIG_SOURCE.lock() {
+        *guard = Some(config);
+    }
+    if QUEUE.with(|queue| !queue.borrow().is_empty()) {
+        post_delivery();
+    }
+}
+
+/// The config the app is running with, for subscribers to react to a
+/// ConfigChanged or to look up their settings
+pub fn config() -> Option<Config> {
+    let source = (*CONFIG_SOURCE.lock().ok()?)?;
+    source()
+}
+
+/// Call `subscriber` with every event published from now on
+pub fn subscribe(subscriber: Subscriber) {
+    if let Ok(mut guard) = SUBSCRIBERS.lock() {
+        guard.push(subscriber);
+    }
+}
+
+/// Queue `event` for the subscribers. They get it from the message loop,
+/// not from inside the publisher, so publishing while holding the app
+/// state is fine.
+pub fn publish(event: AppEvent) {
+    let first = QUEUE.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        queue.push(event);
+        queue.len() == 1
+    });
+    if first {
+        post_delivery();
+    }
+}
+
+fn post_delivery() {
+    let hwnd = BUS_HWND.load(Ordering::SeqCst);
+    if hwnd != 0 {
+        unsafe {
+            let _ = PostMessageW(
+                Some(HWND(hwnd as *mut _)),
+                WM_APP_EVENT,
+                WPARAM(0),
+                LPARAM(0),
+            );
+        }
+    }
+}
+
+/// Hand every queued event to every subscriber, in publishing order
+pub fn deliver_pending() {
+    let events = QUEUE.with(|queue| queue.take());
+    // Copied out so subscribers can publish or subscribe in turn
+    let subscribers = SUBSCRIBERS
+        .lock()
+        .map(|guard| guard.clone())
+        .unwrap_or_default();
+    for event in &events {
+        for subscriber in &subscribers {
+            subscriber(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_descriptions() {
+        let placed = AppEvent::PlacementPerformed {
+            hwnd: HWND(std::ptr::null_mut()),
+            first: TilePos { col: 0, row: 0 },
+            last: TilePos { col: 1, row: 1 },
+            cols: 4,
+            rows: 2,
+            monitor: 2,
+        };
+        assert_eq!(
+            placed.to_string(),
+            "Placed a window on monitor 2 at (0,0)-(1,1) of 4x2"
+        );
+        assert_eq!(
+            AppEvent::MonitorsChanged { count: 3 }.to_string(),
+            "3 monitors connected"
+        );
+        assert_eq!(AppEvent::OverlayHidden.to_string(), "Overlay hidden");
+    }
+}
@@ -0,0 +1,157 @@
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, PostMessageW, HWND_MESSAGE};
+
+use crate::CLASS_NAME;
+
+/// Asks the main window to run a command; wparam is its index in COMMANDS,
+/// or for a layout COMMANDS.len() plus twice the layout's index, plus one
+/// to preview it
+pub const WM_RUN_COMMAND: u32 = 0x8003; // WM_APP + 3
+
+/// Actions that can be started from outside the overlay: the taskbar
+/// jump list, or a command-line argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    ShowGrid,
+    Settings,
+    Equalize,
+    Restore,
+    PreviewEqualize,
+    IdentifyMonitors,
+    /// Stop answering the hotkeys, or start again
+    TogglePause,
+    /// Place config.layouts[index]
+    ApplyLayout(usize),
+    /// Outline where placing config.layouts[index] would move windows
+    PreviewLayout(usize),
+}
+
+/// The commands that aren't for one layout
+pub const COMMANDS: [Command; 7] = [
+    Command::ShowGrid,
+    Command::Settings,
+    Command::Equalize,
+    Command::Restore,
+    Command::PreviewEqualize,
+    Command::IdentifyMonitors,
+    Command::TogglePause,
+];
+
+/// Argument placing a layout, as `--layout=<n>` counting from 1
+const LAYOUT_ARG: &str = "--layout=";
+/// Argument previewing a layout, as `--preview-layout=<n>`
+const PREVIEW_LAYOUT_ARG: &str = "--preview-layout=";
+
+/// Index into config.layouts from the `<n>` of a layout argument
+fn layout_number(number: &str) -> Option<usize> {
+    number.parse::<usize>().ok()?.checked_sub(1)
+}
+
+impl Command {
+    /// Command-line argument that runs this command
+    pub fn arg(self) -> String {
+        let arg = match self {
+            Command::ShowGrid => "--show-grid",
+            Command::Settings => "--settings",
+            Command::Equalize => "--equalize",
+            Command::Restore => "--restore",
+            Command::PreviewEqualize => "--preview-equalize",
+            Command::IdentifyMonitors => "--identify-monitors",
+            Command::TogglePause => "--pause",
+            Command::ApplyLayout(index) => return format!("{}{}", LAYOUT_ARG, index + 1),
+            Command::PreviewLayout(index) => {
+                return format!("{}{}", PREVIEW_LAYOUT_ARG, index + 1);
+            }
+        };
+        arg.to_string()
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::ShowGrid => "Show grid",
+            Command::Settings => "Open settings",
+            Command::Equalize => "Equalize windows",
+            Command::Restore => "Restore window sizes",
+            Command::PreviewEqualize => "Preview equalize",
+            Command::IdentifyMonitors => "Identify monitors",
+            Command::TogglePause => "Pause or resume",
+            Command::ApplyLayout(_) => "Apply layout",
+            Command::PreviewLayout(_) => "Preview layout",
+        }
+    }
+
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        if let Some(number) = arg.strip_prefix(LAYOUT_ARG) {
+            return layout_number(number).map(Command::ApplyLayout);
+        }
+        if let Some(number) = arg.strip_prefix(PREVIEW_LAYOUT_ARG) {
+            return layout_number(number).map(Command::PreviewLayout);
+        }
+        COMMANDS.into_iter().find(|command| command.arg() == arg)
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Command::ApplyLayout(index) => COMMANDS.len() + 2 * index,
+            Command::PreviewLayout(index) => COMMANDS.len() + 2 * index + 1,
+            _ => COMMANDS
+                .iter()
+                .position(|&command| command == self)
+                .unwrap(),
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        if let Some(&command) = COMMANDS.get(index) {
+            return Some(command);
+        }
+        let layout = index - COMMANDS.len();
+        Some(if layout.is_multiple_of(2) {
+            Command::ApplyLayout(layout / 2)
+        } else {
+            Command::PreviewLayout(layout / 2)
+        })
+    }
+
+    /// Ask the main window `hwnd` to run this command
+    pub fn post_to(self, hwnd: HWND) -> bool {
+        unsafe { PostMessageW(Some(hwnd), WM_RUN_COMMAND, WPARAM(self.index()), LPARAM(0)).is_ok() }
+    }
+
+    /// Hand the command to the already-running instance. False if there
+    /// isn't one.
+    pub fn send_to_running_instance(self) -> bool {
+        unsafe { FindWindowExW(Some(HWND_MESSAGE), None, CLASS_NAME, None) }
+            .is_ok_and(|hwnd| self.post_to(hwnd))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_and_indexes_round_trip() {
+        let layouts = [Command::ApplyLayout(2), Command::PreviewLayout(2)];
+        for command in COMMANDS.into_iter().chain(layouts) {
+            assert_eq!(Command::from_arg(&command.arg()), Some(command));
+            assert_eq!(Command::from_index(command.index()), Some(command));
+        }
+        assert_eq!(Command::ApplyLayout(0).arg(), "--layout=1");
+        assert_eq!(Command::from_arg("--layout=0"), None);
+        assert_eq!(Command::from_arg("--layout=first"), None);
+        assert_eq!(
+            Command::from_arg("--preview-layout=1"),
+            Some(Command::PreviewLayout(0))
+        );
+        assert_eq!(Command::from_arg("--sleep"), None);
+        assert_eq!(
+            Command::from_index(COMMANDS.len()),
+            Some(Command::ApplyLayout(0))
+        );
+        assert_eq!(
+            Command::from_index(COMMANDS.len() + 1),
+            Some(Command::PreviewLayout(0))
+        );
+    }
+}
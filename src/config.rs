@@ -1,12 +1,71 @@
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use windows::Win32::Foundation::RECT;
+
+use crate::grid::{scale_for_dpi, Grid, KeyboardLayout};
+use crate::keys::{Hotkey, MonitorKeys};
+use crate::region::Region;
+use crate::rules::{PlacementRule, PlacementStep, RulesMode, WindowRule};
+use crate::selection::SecondKey;
+
+/// Environment variable naming a config file to use instead of the one in
+/// the home directory, e.g. for a portable install or a test run
+const CONFIG_PATH_VAR: &str = "TACTILE_WIN_CONFIG";
+
+/// How long a save waits for another writer to finish with the file
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Copies of the config file kept from before each save, newest first
+const BACKUPS_KEPT: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
+    pub hotkey: HotkeyConfig,
     pub grid: GridConfig,
     pub appearance: AppearanceConfig,
+    pub accessibility: AccessibilityConfig,
+    pub behavior: BehaviorConfig,
+    pub opacity: OpacityConfig,
+    pub rules: RulesConfig,
+    pub updates: UpdatesConfig,
+    pub history: HistoryConfig,
+    pub logging: LoggingConfig,
+    /// Named grid sizes, switched between as monitors come and go
+    pub profiles: Vec<Profile>,
+    /// Windows placed together with one hotkey
+    pub layouts: Vec<Layout>,
+}
+
+/// The hotkey that opens the grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    /// Any of "ctrl", "alt", "shift" and "win"
+    pub modifiers: Vec<String>,
+    /// A letter, digit, F1-F24, or a key name such as "space"
+    pub key: String,
+}
+
+/// Space along a monitor's edges kept clear of tiles, for a dock or bar
+/// that doesn't reserve it from Windows' work area itself. Sizes are
+/// pixels in from the monitor's edges, so a strut overlapping the taskbar
+/// only reserves what the taskbar doesn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Strut {
+    /// Display device name, e.g. "\\.\DISPLAY1"; every monitor if unset
+    pub device: Option<String>,
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +74,105 @@ pub struct GridConfig {
     pub cols: u32,
     pub rows: u32,
     pub gap: i32,
+    /// Smallest tile width or height, in the same units as `gap`. Smaller
+    /// tiles are warned about, and the gap shrinks to keep them this size.
+    pub min_tile_size: i32,
+    /// Treat `gap` and overlay label sizes as device-independent pixels,
+    /// scaled by the target monitor's DPI (96 DPI = 1:1).
+    pub dpi_scaling: bool,
+    /// Which keyboard layout's rows map to grid rows. Not used with
+    /// physical_keys, whose positions are always US QWERTY's.
+    pub keyboard_layout: KeyboardLayout,
+    /// Match grid keys by where they sit on the keyboard, as on US QWERTY,
+    /// labelling tiles with what the active layout types there
+    pub physical_keys: bool,
+    /// Whether the second key marks the region's opposite corner or its size
+    pub second_key: SecondKey,
+    /// Keys that move the overlay straight to a monitor
+    pub monitor_keys: MonitorKeys,
+    /// Keys that place the window in one press while the overlay is open,
+    /// e.g. "G" = "full"
+    pub bindings: BTreeMap<String, Region>,
+    /// Grid sizes for particular virtual desktops, in place of cols/rows
+    pub desktops: Vec<DesktopGrid>,
+    /// Grid sizes for particular monitors, after desktops' but ahead of
+    /// profiles'
+    pub monitors: Vec<MonitorGrid>,
+    /// Space kept clear along monitor edges, for docks that don't reserve
+    /// it from the work area
+    pub struts: Vec<Strut>,
+    /// Window classes of such docks, to keep their space clear wherever
+    /// they are
+    pub dock_classes: Vec<String>,
+}
+
+/// A named grid size, used automatically while `monitors` monitors are
+/// connected, or whenever no other profile matches if `monitors` is unset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub monitors: Option<usize>,
+    pub cols: u32,
+    pub rows: u32,
+}
+
+/// Windows placed together by one hotkey, e.g. an editor and a terminal
+/// side by side
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    pub name: String,
+    /// Hotkey modifiers and key, as in [hotkey]
+    pub modifiers: Vec<String>,
+    pub key: String,
+    /// Only applied while this profile is active; always if unset
+    pub profile: Option<String>,
+    /// Also applied on switching to this virtual desktop, numbered as in
+    /// Task View from 1. A layout with a desktop needs no key.
+    pub desktop: Option<u32>,
+    /// Where each window goes, as in a placement batch. Steps whose
+    /// window isn't open are skipped.
+    pub steps: Vec<PlacementStep>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            modifiers: HotkeyConfig::default().modifiers,
+            key: String::new(),
+            profile: None,
+            desktop: None,
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl Layout {
+    /// The layout's hotkey, or why it can't be used
+    pub fn hotkey(&self) -> Result<Hotkey, String> {
+        Hotkey::parse(&self.modifiers, &self.key)
+    }
+}
+
+/// Grid size used while a given virtual desktop is shown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesktopGrid {
+    /// Desktop number as shown in Task View, starting from 1
+    pub desktop: u32,
+    pub cols: u32,
+    pub rows: u32,
+}
+
+/// Grid size used on a given monitor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorGrid {
+    /// The monitor's id as the setup wizard saves it, which stays with the
+    /// monitor, or its display device name, e.g. "\\.\DISPLAY1"
+    pub device: String,
+    pub cols: u32,
+    pub rows: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,16 +180,284 @@ pub struct GridConfig {
 pub struct AppearanceConfig {
     pub tile_color: u32,
     pub highlight_color: u32,
-    pub background_color: u32,
+    /// Between the tiles and around the grid. Formerly background_color,
+    /// which is still read.
+    #[serde(alias = "background_color")]
+    pub gap_color: u32,
     pub text_color: u32,
     pub alpha: u8,
+    /// Opacity of the gaps, apart from the tiles'
+    pub gap_alpha: u8,
+    /// Opaque high-contrast palette with thicker borders and larger labels
+    pub high_contrast: HighContrastMode,
+    /// What each tile shows
+    pub label: LabelContent,
+    /// After the first key, draw a small map in each tile of the region
+    /// that tile would select as the second key
+    pub combo_hints: bool,
+    /// Let mouse clicks pass through the overlay to the windows beneath,
+    /// leaving it a purely visual guide
+    pub click_through: bool,
+}
+
+/// Tile label content on the overlay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelContent {
+    /// The tile's key
+    #[default]
+    Key,
+    /// The keys still needed to select the tile, e.g. "QQ" then "Q"
+    Pair,
+    /// 1-based column and row, e.g. "2,1"
+    Coordinates,
+    None,
+}
+
+/// When to switch the overlay to its high-contrast palette
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighContrastMode {
+    /// Follow the Windows high-contrast theme; also go opaque when
+    /// transparency effects are turned off
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// When to speak overlay state changes through text-to-speech
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnounceMode {
+    /// Only while a screen reader is running
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    pub announce: AnnounceMode,
+    /// Extra multiplier for overlay labels and the settings dialog, on top
+    /// of the Windows "Make text bigger" setting
+    pub text_scale: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BehaviorConfig {
+    /// Reopening the overlay within this many seconds of Escape restores
+    /// the first-key selection (0 disables)
+    pub restore_selection_secs: u32,
+    /// Wait this long (0-500 ms) after the hotkey before drawing the
+    /// overlay, so keys typed quickly never flash it
+    pub show_delay_ms: u32,
+    /// What happens to the moved window's focus and z-order
+    pub after_move: AfterMove,
+    /// Snap windows to the nearest grid cells after the user drags or
+    /// resizes them (opt-in)
+    pub snap_on_move: bool,
+    /// How overlay keys are captured
+    pub input: InputBackend,
+    /// What happens when the target is being dragged, in a menu or behind
+    /// a modal dialog as the overlay closes
+    pub when_busy: WhenBusy,
+    /// What happens when the target is maximized
+    pub when_maximized: WhenMaximized,
+    /// Truly maximize windows when the selection is the whole grid,
+    /// rather than sizing them to it
+    pub maximize_full_grid: bool,
+    /// Unhook the keyboard while the session is locked; it is hooked
+    /// again on unlock
+    pub pause_when_locked: bool,
+    /// Start silently; when off, a notification says tactile-win is
+    /// running and how to open the grid
+    pub start_hidden: bool,
+    /// What the grid hotkey does while the overlay is open
+    pub when_open: WhenOpen,
+    /// What happens when the target closes while the overlay is open
+    pub when_target_closes: WhenTargetCloses,
+    /// Put an invisible splitter between tiled windows that share a grid
+    /// line, dragged to resize both (opt-in)
+    pub drag_splits: bool,
+    /// Pixels Ctrl+Alt+Shift+Arrow moves the split between the active
+    /// tiled window and its neighbor
+    pub split_step: u32,
+    /// After tiling a window Windows Snap placed alongside others, offer
+    /// to move them into the tiles beside it
+    pub follow_snap_groups: bool,
+    /// Wait for Enter after a selection, outlining where the window will
+    /// go, before moving it (opt-in)
+    pub confirm_with_enter: bool,
+    /// Offer to undo a placement that shrinks the window's area by more
+    /// than this percent, for apps laid out with care (0 disables)
+    pub confirm_shrink_percent: u8,
+}
+
+/// What an opacity key does to the target window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpacityStep {
+    More,
+    Less,
+    /// Back to the window's own opacity
+    Reset,
+}
+
+/// Overlay keys that change the target window's opacity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpacityConfig {
+    /// Keys that make the target more or less opaque, or as it was;
+    /// empty to leave a key to the grid
+    pub more: String,
+    pub less: String,
+    pub reset: String,
+    /// Percent of full opacity each key press changes
+    pub step: u8,
+    /// Least opaque a window is made, in percent
+    pub min: u8,
+}
+
+/// Keyboard capture while the overlay is open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBackend {
+    /// Low-level keyboard hook; sees every key
+    #[default]
+    Hook,
+    /// Temporary RegisterHotKey bindings for the overlay's keys, for when
+    /// security software flags keyboard hooks
+    Hotkeys,
+}
+
+/// Handling of a target that is busy in a modal loop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenBusy {
+    /// Move it once the loop ends
+    #[default]
+    Defer,
+    /// Leave it, and say why in a notification
+    Cancel,
+}
+
+/// Handling of a maximized target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenMaximized {
+    /// Restore it, then move it
+    #[default]
+    Restore,
+    /// Leave it maximized and unmoved
+    Skip,
+    /// Keep it maximized when the selection is the whole grid; restore and
+    /// move it otherwise
+    Maximize,
+}
+
+/// What the grid hotkey does while the overlay is already open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenOpen {
+    /// Nothing; the selection carries on
+    Ignore,
+    /// Close the overlay as Escape does, so the hotkey toggles it
+    #[default]
+    Close,
+}
+
+/// Handling of a target closed before the selection is finished
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenTargetCloses {
+    /// Close the overlay, and say why in a notification
+    #[default]
+    Cancel,
+    /// Carry on with the foreground window as the target
+    Retarget,
+}
+
+/// Post-move handling of the tiled window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterMove {
+    /// Leave focus and z-order untouched
+    #[default]
+    Keep,
+    /// Bring the window to the top without activating it
+    Raise,
+    /// Bring the window to the top and give it focus
+    Activate,
+}
+
+/// Which windows the hotkey may tile, and how some are spaced
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RulesConfig {
+    pub mode: RulesMode,
+    /// Windows that are never tiled
+    pub block: Vec<WindowRule>,
+    /// In allowlist mode, the only windows that are tiled
+    pub allow: Vec<WindowRule>,
+    /// Grid size, gap and margin for particular windows, first match wins
+    pub placement: Vec<PlacementRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdatesConfig {
+    /// Periodically check GitHub for a newer release (opt-in)
+    pub check: bool,
+    pub interval_hours: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Log each placement with a timestamp, for exporting (opt-in)
+    pub enabled: bool,
+    /// Count which fractions of the screen placements use, and suggest a
+    /// grid that fits them better (opt-in)
+    pub suggest_grid: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Also print the log to a console, as --verbose does, and log window
+    /// titles in full
+    pub verbose: bool,
+    /// Log how long each overlay paint takes, warning about slow ones
+    pub paint_timing: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            hotkey: HotkeyConfig::default(),
             grid: GridConfig::default(),
             appearance: AppearanceConfig::default(),
+            accessibility: AccessibilityConfig::default(),
+            behavior: BehaviorConfig::default(),
+            opacity: OpacityConfig::default(),
+            rules: RulesConfig::default(),
+            updates: UpdatesConfig::default(),
+            history: HistoryConfig::default(),
+            logging: LoggingConfig::default(),
+            profiles: Vec::new(),
+            layouts: Vec::new(),
+        }
+    }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            modifiers: vec!["ctrl".to_string(), "shift".to_string()],
+            key: "G".to_string(),
         }
     }
 }
@@ -42,24 +468,168 @@ impl Default for GridConfig {
             cols: 4,
             rows: 2,
             gap: 10,
+            min_tile_size: 100,
+            dpi_scaling: false,
+            keyboard_layout: KeyboardLayout::Qwerty,
+            physical_keys: false,
+            second_key: SecondKey::Corner,
+            monitor_keys: MonitorKeys::Function,
+            bindings: BTreeMap::new(),
+            desktops: Vec::new(),
+            monitors: Vec::new(),
+            struts: Vec::new(),
+            dock_classes: Vec::new(),
         }
     }
 }
 
+impl GridConfig {
+    /// Columns and rows on virtual desktop `desktop` (0-based), if it has a
+    /// grid of its own
+    fn desktop_dimensions(&self, desktop: Option<usize>) -> Option<(u32, u32)> {
+        let index = desktop?;
+        self.desktops
+            .iter()
+            .find(|grid| grid.desktop as usize == index + 1)
+            .map(|grid| (grid.cols, grid.rows))
+    }
+
+    /// Columns and rows on the monitor going by any of `names`, if it has a
+    /// grid of its own
+    pub fn monitor_dimensions(&self, names: &[&str]) -> Option<(u32, u32)> {
+        self.monitors
+            .iter()
+            .find(|grid| grid.is_for(names))
+            .map(|grid| (grid.cols, grid.rows))
+    }
+
+    /// Give the monitor going by `names` a grid of its own under the first
+    /// of them, replacing any it had under any of them
+    pub fn set_monitor_grid(&mut self, names: &[&str], cols: u32, rows: u32) {
+        let Some(&name) = names.first() else {
+            return;
+        };
+        self.monitors.retain(|grid| !grid.is_for(names));
+        self.monitors.push(MonitorGrid {
+            device: name.to_string(),
+            cols,
+            rows,
+        });
+    }
+}
+
+impl MonitorGrid {
+    /// Whether this is the grid for a monitor going by `names`
+    fn is_for(&self, names: &[&str]) -> bool {
+        names
+            .iter()
+            .any(|name| self.device.eq_ignore_ascii_case(name))
+    }
+}
+
 impl Default for AppearanceConfig {
     fn default() -> Self {
         Self {
             tile_color: 0x00805030,      // Teal-ish
             highlight_color: 0x0000A0FF, // Orange
-            background_color: 0x00302020, // Dark gray-brown
+            gap_color: 0x00302020,       // Dark gray-brown
             text_color: 0x00FFFFFF,      // White
             alpha: 220,
+            gap_alpha: 220,
+            high_contrast: HighContrastMode::Auto,
+            label: LabelContent::Key,
+            combo_hints: false,
+            click_through: false,
+        }
+    }
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        Self {
+            announce: AnnounceMode::Auto,
+            text_scale: 1.0,
+        }
+    }
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            restore_selection_secs: 5,
+            show_delay_ms: 0,
+            after_move: AfterMove::Keep,
+            snap_on_move: false,
+            input: InputBackend::Hook,
+            when_busy: WhenBusy::Defer,
+            when_maximized: WhenMaximized::Restore,
+            maximize_full_grid: false,
+            pause_when_locked: false,
+            start_hidden: true,
+            when_open: WhenOpen::Close,
+            when_target_closes: WhenTargetCloses::Cancel,
+            drag_splits: false,
+            split_step: 50,
+            follow_snap_groups: true,
+            confirm_with_enter: false,
+            confirm_shrink_percent: 0,
+        }
+    }
+}
+
+impl Default for OpacityConfig {
+    fn default() -> Self {
+        Self {
+            more: "=".to_string(),
+            less: "-".to_string(),
+            reset: "0".to_string(),
+            step: 10,
+            min: 20,
+        }
+    }
+}
+
+impl HotkeyConfig {
+    /// The configured hotkey, or why it can't be used
+    pub fn hotkey(&self) -> Result<Hotkey, String> {
+        Hotkey::parse(&self.modifiers, &self.key)
+    }
+}
+
+impl OpacityConfig {
+    /// What `key` does to the target's opacity, if it's an opacity key
+    pub fn step_for(&self, key: char) -> Option<OpacityStep> {
+        let is = |bound: &str| {
+            let mut chars = bound.chars();
+            chars.next().is_some_and(|c| c.eq_ignore_ascii_case(&key)) && chars.next().is_none()
+        };
+        if is(&self.more) {
+            Some(OpacityStep::More)
+        } else if is(&self.less) {
+            Some(OpacityStep::Less)
+        } else if is(&self.reset) {
+            Some(OpacityStep::Reset)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            check: false,
+            interval_hours: 24,
         }
     }
 }
 
 impl Config {
+    /// `~/.tactile-win.toml`, unless TACTILE_WIN_CONFIG names another file
     pub fn config_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_PATH_VAR) {
+            return Some(PathBuf::from(path));
+        }
         dirs::home_dir().map(|p| p.join(".tactile-win.toml"))
     }
 
@@ -72,26 +642,464 @@ impl Config {
                     None
                 }
             })
-            .and_then(|contents| toml::from_str(&contents).ok())
+            .and_then(|contents| Self::parse(&contents).ok())
             .unwrap_or_default()
     }
 
-    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some(path) = Self::config_path() {
-            let contents = toml::to_string_pretty(self)?;
-            fs::write(path, contents)?;
+    /// A config file's contents, with values not yet clamped by validate
+    pub fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Write the options changed from `base`, the config this one was
+    /// edited from, into the config file. Whatever else the file says now,
+    /// such as another writer's changes since `base`, is kept, and this
+    /// config takes it up. Writers take turns, and the file is replaced in
+    /// one step, so it's never seen half written.
+    pub fn save(&mut self, base: &Config) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = Self::config_path() else {
+            return Ok(());
+        };
+        let _lock = lock_config()?;
+        let ours = to_table(self)?;
+        let table = match on_disk(&path)? {
+            Some(mut table) => {
+                merge_changes(&mut table, &to_table(base)?, &ours);
+                table
+            }
+            // A missing or unreadable file has nothing worth keeping
+            None => ours,
+        };
+        if path.exists() {
+            back_up(&path)?;
+        }
+        write_atomically(&path, &toml::to_string_pretty(&table)?)?;
+        if let Ok(mut saved) = toml::Value::Table(table).try_into::<Config>() {
+            saved.validate();
+            *self = saved;
         }
         Ok(())
     }
 
+    /// The config file as it is now, validated, to take up edits made to
+    /// it while running. Unlike load, a file that doesn't parse is an
+    /// error rather than the defaults. None if there's no file.
+    pub fn reload() -> Result<Option<Config>, Box<dyn std::error::Error>> {
+        let Some(path) = Self::config_path() else {
+            return Ok(None);
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut config = Self::parse(&contents)?;
+        config.validate();
+        Ok(Some(config))
+    }
+
+    /// Whether `other` sets every option as this config does
+    pub fn same_as(&self, other: &Config) -> bool {
+        match (to_table(self), to_table(other)) {
+            (Ok(ours), Ok(theirs)) => ours == theirs,
+            _ => false,
+        }
+    }
+
+    /// Put the config file back as it was before the last save, using up
+    /// that backup so the next restore goes back one save further. Returns
+    /// the restored config and how many backups are left, or None if there
+    /// are none.
+    pub fn restore_backup() -> Result<Option<(Config, usize)>, Box<dyn std::error::Error>> {
+        let Some(path) = Self::config_path() else {
+            return Ok(None);
+        };
+        let _lock = lock_config()?;
+        let mut backups = backups(&path);
+        if backups.is_empty() {
+            return Ok(None);
+        }
+        let latest = backups.remove(0);
+        let contents = fs::read_to_string(&latest)?;
+        let mut config = Self::parse(&contents)?;
+        config.validate();
+        write_atomically(&path, &contents)?;
+        fs::remove_file(&latest)?;
+        Ok(Some((config, backups.len())))
+    }
+
     pub fn validate(&mut self) {
         // Clamp values to valid ranges
         self.grid.cols = self.grid.cols.clamp(1, 8);
         self.grid.rows = self.grid.rows.clamp(1, 4);
         self.grid.gap = self.grid.gap.clamp(0, 50);
+        self.grid.min_tile_size = self.grid.min_tile_size.clamp(0, 1000);
+        self.behavior.show_delay_ms = self.behavior.show_delay_ms.min(500);
+        self.behavior.confirm_shrink_percent = self.behavior.confirm_shrink_percent.min(100);
+        self.opacity.step = self.opacity.step.clamp(1, 100);
+        self.opacity.min = self.opacity.min.clamp(10, 100);
+        // Physical keys go by US QWERTY positions whatever the layout
+        if self.grid.physical_keys {
+            self.grid.keyboard_layout = KeyboardLayout::Qwerty;
+        }
+        for grid in &mut self.grid.desktops {
+            grid.cols = grid.cols.clamp(1, 8);
+            grid.rows = grid.rows.clamp(1, 4);
+        }
+        for grid in &mut self.grid.monitors {
+            grid.cols = grid.cols.clamp(1, 8);
+            grid.rows = grid.rows.clamp(1, 4);
+        }
+        for profile in &mut self.profiles {
+            profile.cols = profile.cols.clamp(1, 8);
+            profile.rows = profile.rows.clamp(1, 4);
+        }
+        for rule in &mut self.rules.placement {
+            rule.cols = rule.cols.map(|cols| cols.clamp(1, 8));
+            rule.rows = rule.rows.map(|rows| rows.clamp(1, 4));
+            rule.gap = rule.gap.map(|gap| gap.clamp(0, 50));
+            rule.margin = rule.margin.map(|margin| margin.clamp(0, 50));
+        }
+    }
+
+    /// Grid size on virtual desktop `desktop` and the monitor going by
+    /// `monitor`'s names while
+    /// profile `profile` is active: a desktop's own grid comes first, then
+    /// the monitor's, then the profile's, then the default size
+    pub fn grid_dimensions(
+        &self,
+        desktop: Option<usize>,
+        monitor: &[&str],
+        profile: Option<usize>,
+    ) -> (u32, u32) {
+        let profile = profile.and_then(|index| self.profiles.get(index));
+        self.grid
+            .desktop_dimensions(desktop)
+            .or_else(|| self.grid.monitor_dimensions(monitor))
+            .or(profile.map(|profile| (profile.cols, profile.rows)))
+            .unwrap_or((self.grid.cols, self.grid.rows))
+    }
+
+    /// Width and height of the smallest tiles any configured grid size
+    /// would have on `work_area` at `dpi`, if under `min_tile_size`
+    pub fn cramped_tiles(&self, work_area: RECT, dpi: u32) -> Option<(i32, i32)> {
+        let desktops = self.grid.desktops.iter().map(|grid| (grid.cols, grid.rows));
+        let monitors = self.grid.monitors.iter().map(|grid| (grid.cols, grid.rows));
+        let profiles = self.profiles.iter().map(|p| (p.cols, p.rows));
+        let sizes = desktops
+            .chain(monitors)
+            .chain(profiles)
+            .chain([(self.grid.cols, self.grid.rows)]);
+        let gap = scale_for_dpi(self.grid.gap, dpi);
+        let (width, height) = sizes
+            .map(|(cols, rows)| Grid::new(cols, rows, gap, work_area).natural_tile_size())
+            .fold((i32::MAX, i32::MAX), |(w1, h1), (w2, h2)| {
+                (w1.min(w2), h1.min(h2))
+            });
+        let min = scale_for_dpi(self.grid.min_tile_size, dpi);
+        (width < min || height < min).then_some((width, height))
+    }
+
+    /// The profile for `monitors` connected monitors: the first that names
+    /// that count, else the first that names none
+    pub fn auto_profile(&self, monitors: usize) -> Option<usize> {
+        let position = |wanted: Option<usize>| {
+            self.profiles
+                .iter()
+                .position(|profile| profile.monitors == wanted)
+        };
+        position(Some(monitors)).or_else(|| position(None))
+    }
+
+    /// Keys in the config file that aren't options, or no longer are, and
+    /// options other options override, all of which loading ignores
+    pub fn check_file() -> Vec<KeyIssue> {
+        Self::config_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map_or_else(Vec::new, |contents| {
+                let mut issues = check_keys(&contents, DEPRECATED);
+                if let Ok(config) = Self::parse(&contents) {
+                    issues.extend(config.overridden());
+                }
+                issues
+            })
+    }
+
+    /// Options set but overridden by others, as validate resolves them
+    fn overridden(&self) -> Option<KeyIssue> {
+        (self.grid.physical_keys && self.grid.keyboard_layout != KeyboardLayout::Qwerty).then(
+            || KeyIssue::Overridden {
+                path: "grid.keyboard_layout".to_string(),
+                by: "grid.physical_keys, which always goes by US QWERTY positions",
+            },
+        )
+    }
+}
+
+fn to_table(config: &Config) -> Result<toml::Table, toml::ser::Error> {
+    match toml::Value::try_from(config)? {
+        toml::Value::Table(table) => Ok(table),
+        _ => Ok(toml::Table::new()),
+    }
+}
+
+/// Apply to `file` the options `ours` changed from `base`: set the ones
+/// it set differently and drop the ones it dropped, leaving the rest as
+/// the file has them
+fn merge_changes(file: &mut toml::Table, base: &toml::Table, ours: &toml::Table) {
+    for (key, value) in ours {
+        match (value, base.get(key)) {
+            (toml::Value::Table(ours), Some(toml::Value::Table(base))) => {
+                let mut table = match file.get(key) {
+                    Some(toml::Value::Table(table)) => table.clone(),
+                    _ => toml::Table::new(),
+                };
+                merge_changes(&mut table, base, ours);
+                // Tables the file leaves out stay out, unless changed
+                if !table.is_empty() || file.contains_key(key) {
+                    file.insert(key.clone(), toml::Value::Table(table));
+                }
+            }
+            (value, base) if base != Some(value) => {
+                file.insert(key.clone(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    for key in base.keys() {
+        if !ours.contains_key(key) {
+            file.remove(key);
+        }
+    }
+}
+
+/// Backups of the config file at `path`, newest first: copies beside it
+/// named for when they were taken, e.g. .tactile-win.toml.1767225600000.bak
+fn backups(path: &Path) -> Vec<PathBuf> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Vec::new();
+    };
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<(u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let taken = backup_time(name, file_name.to_str()?)?;
+            Some((taken, entry.path()))
+        })
+        .collect();
+    backups.sort_by_key(|&(taken, _)| std::cmp::Reverse(taken));
+    backups.into_iter().map(|(_, path)| path).collect()
+}
+
+/// When the backup named `file_name` of config file `name` was taken, in
+/// milliseconds since the epoch (seconds for older backups, which sort
+/// before them); None if it's not one
+fn backup_time(name: &str, file_name: &str) -> Option<u64> {
+    file_name
+        .strip_prefix(name)?
+        .strip_prefix('.')?
+        .strip_suffix(".bak")?
+        .parse()
+        .ok()
+}
+
+/// The config file at `path` as a table, or None if it's missing or
+/// unreadable. A file that doesn't parse is an error, so a save doesn't
+/// replace hand edits that only need a typo fixed.
+fn on_disk(path: &Path) -> Result<Option<toml::Table>, Box<dyn std::error::Error>> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    match toml::from_str(&contents) {
+        Ok(table) => Ok(Some(table)),
+        Err(e) => Err(format!(
+            "{} doesn't parse, so it was left as it is; fix or delete it first: {}",
+            path.display(),
+            e
+        )
+        .into()),
+    }
+}
+
+/// Copy the config file at `path` to a new backup, then delete all but
+/// the newest BACKUPS_KEPT
+fn back_up(path: &Path) -> io::Result<()> {
+    let mut taken = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    let backup = loop {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.bak", taken));
+        let backup = path.with_file_name(name);
+        // Two saves in the same millisecond each keep their own backup
+        if !backup.exists() {
+            break backup;
+        }
+        taken += 1;
+    };
+    fs::copy(path, backup)?;
+    for old in backups(path).iter().skip(BACKUPS_KEPT) {
+        let _ = fs::remove_file(old);
+    }
+    Ok(())
+}
+
+/// Hold the lock config writers take turns with, in this process or
+/// another instance, until the returned file is dropped
+fn lock_config() -> io::Result<File> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(std::env::temp_dir().join("tactile-win-config.lock"))?;
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    loop {
+        match lock.try_lock() {
+            Ok(()) => return Ok(lock),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "another instance is still writing the config file",
+                ));
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
     }
 }
 
+/// Write `contents` to a temporary file beside `path`, then rename it over
+/// `path`, so readers see the old file or the new one and never part of one
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(name);
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Options that were renamed or dropped, by dotted path, with what to do
+/// instead. Entries go here rather than disappearing silently.
+const DEPRECATED: &[(&str, &str)] = &[(
+    "appearance.background_color",
+    "it's read as appearance.gap_color, its new name",
+)];
+
+/// A key in a config file that loading ignores
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyIssue {
+    /// Not an option at all, e.g. a typo, with the option it's closest to
+    Unknown {
+        path: String,
+        suggestion: Option<String>,
+    },
+    /// Renamed or dropped; the advice says what to use instead
+    Deprecated { path: String, advice: &'static str },
+    /// Set, but has no effect with another option that's set
+    Overridden { path: String, by: &'static str },
+}
+
+impl fmt::Display for KeyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyIssue::Unknown {
+                path,
+                suggestion: Some(suggestion),
+            } => write!(f, "{} isn't an option (did you mean {}?)", path, suggestion),
+            KeyIssue::Unknown { path, .. } => write!(f, "{} isn't an option", path),
+            KeyIssue::Deprecated { path, advice } => {
+                write!(f, "{} is deprecated; {}", path, advice)
+            }
+            KeyIssue::Overridden { path, by } => write!(f, "{} is overridden by {}", path, by),
+        }
+    }
+}
+
+/// Keys in `contents` that don't match an option, checked against the
+/// tables Config serializes to. Lists of tables, and maps keyed by the
+/// user like bindings, aren't checked.
+fn check_keys(contents: &str, deprecated: &[(&str, &'static str)]) -> Vec<KeyIssue> {
+    let Ok(toml::Value::Table(table)) = toml::from_str::<toml::Value>(contents) else {
+        return Vec::new();
+    };
+    let Ok(toml::Value::Table(known)) = toml::Value::try_from(Config::default()) else {
+        return Vec::new();
+    };
+    let mut issues = Vec::new();
+    check_table("", &table, &known, deprecated, &mut issues);
+    issues
+}
+
+fn check_table(
+    prefix: &str,
+    table: &toml::Table,
+    known: &toml::Table,
+    deprecated: &[(&str, &'static str)],
+    issues: &mut Vec<KeyIssue>,
+) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if let Some(&(_, advice)) = deprecated.iter().find(|(old, _)| *old == path) {
+            issues.push(KeyIssue::Deprecated { path, advice });
+            continue;
+        }
+        match known.get(key) {
+            None => {
+                let suggestion = known
+                    .keys()
+                    .map(|option| (edit_distance(key, option), option))
+                    .filter(|&(distance, _)| distance <= 2)
+                    .min()
+                    .map(|(_, option)| option.clone());
+                issues.push(KeyIssue::Unknown { path, suggestion });
+            }
+            // Empty by default for maps keyed by the user, like bindings
+            Some(toml::Value::Table(options)) if !options.is_empty() => {
+                if let toml::Value::Table(table) = value {
+                    check_table(&path, table, options, deprecated, issues);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Single-character insertions, deletions and substitutions turning `a`
+/// into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,6 +1110,14 @@ mod tests {
         assert_eq!(config.grid.cols, 4);
         assert_eq!(config.grid.rows, 2);
         assert_eq!(config.grid.gap, 10);
+        assert!(!config.grid.dpi_scaling);
+        assert!(!config.updates.check);
+        assert!(!config.history.enabled);
+        assert!(!config.history.suggest_grid);
+        assert!(!config.logging.verbose);
+        assert!(config.behavior.start_hidden);
+        assert!(!config.behavior.snap_on_move);
+        assert_eq!(config.appearance.high_contrast, HighContrastMode::Auto);
     }
 
     #[test]
@@ -111,15 +1127,107 @@ mod tests {
 cols = 6
 rows = 3
 gap = 5
+dpi_scaling = true
+keyboard_layout = "dvorak"
+second_key = "extent"
+bindings = { "G" = "full", "V" = "left-half", "B" = "right-two-thirds" }
+desktops = [{ desktop = 2, cols = 3, rows = 1 }]
 
 [appearance]
 alpha = 200
+high_contrast = "on"
+label = "pair"
+combo_hints = true
+click_through = true
+
+[accessibility]
+announce = "always"
+text_scale = 1.5
+
+[behavior]
+restore_selection_secs = 0
+show_delay_ms = 150
+after_move = "activate"
+snap_on_move = true
+input = "hotkeys"
+when_busy = "cancel"
+when_maximized = "skip"
+maximize_full_grid = true
+pause_when_locked = true
+when_open = "ignore"
+when_target_closes = "retarget"
+
+[rules]
+mode = "allowlist"
+allow = [{ process = "notepad.exe" }, { class = "CabinetWClass" }]
+placement = [
+    { process = "WindowsTerminal.exe", gap = 0, margin = 4 },
+    { process = "obs64.exe", cols = 2, rows = 1 },
+]
 "#;
         let config: Config = toml::from_str(toml_str).unwrap();
         assert_eq!(config.grid.cols, 6);
         assert_eq!(config.grid.rows, 3);
         assert_eq!(config.grid.gap, 5);
+        assert!(config.grid.dpi_scaling);
+        assert_eq!(config.grid.keyboard_layout, KeyboardLayout::Dvorak);
+        assert_eq!(config.grid.second_key, SecondKey::Extent);
+        assert_eq!(config.grid.bindings.len(), 3);
+        assert_eq!(config.grid.bindings["B"].to_string(), "right-two-thirds");
+        assert_eq!(config.grid_dimensions(Some(1), &[], None), (3, 1));
+        assert_eq!(config.grid_dimensions(Some(0), &[], None), (6, 3));
+        assert_eq!(config.grid_dimensions(None, &[], None), (6, 3));
         assert_eq!(config.appearance.alpha, 200);
+        assert_eq!(config.appearance.high_contrast, HighContrastMode::On);
+        assert_eq!(config.appearance.label, LabelContent::Pair);
+        assert!(config.appearance.combo_hints);
+        assert!(config.appearance.click_through);
+        assert_eq!(config.accessibility.announce, AnnounceMode::Always);
+        assert_eq!(config.accessibility.text_scale, 1.5);
+        assert_eq!(config.behavior.restore_selection_secs, 0);
+        assert_eq!(config.behavior.show_delay_ms, 150);
+        assert_eq!(config.behavior.after_move, AfterMove::Activate);
+        assert!(config.behavior.snap_on_move);
+        assert_eq!(config.behavior.input, InputBackend::Hotkeys);
+        assert_eq!(config.behavior.when_busy, WhenBusy::Cancel);
+        assert_eq!(config.behavior.when_maximized, WhenMaximized::Skip);
+        assert!(config.behavior.maximize_full_grid);
+        assert!(config.behavior.pause_when_locked);
+        assert_eq!(config.behavior.when_open, WhenOpen::Ignore);
+        assert_eq!(
+            config.behavior.when_target_closes,
+            WhenTargetCloses::Retarget
+        );
+        assert_eq!(config.rules.mode, RulesMode::Allowlist);
+        assert_eq!(config.rules.allow.len(), 2);
+        assert_eq!(
+            config.rules.allow[1].class.as_deref(),
+            Some("CabinetWClass")
+        );
+        let placement = &config.rules.placement[0];
+        assert_eq!(
+            placement.rule.process.as_deref(),
+            Some("WindowsTerminal.exe")
+        );
+        assert_eq!((placement.gap, placement.margin), (Some(0), Some(4)));
+        let placement = &config.rules.placement[1];
+        assert_eq!((placement.cols, placement.rows), (Some(2), Some(1)));
+        assert_eq!(placement.gap, None);
+    }
+
+    #[test]
+    fn test_physical_keys_override_layout() {
+        let mut config = Config::default();
+        config.grid.keyboard_layout = KeyboardLayout::Dvorak;
+        assert_eq!(config.overridden(), None);
+
+        config.grid.physical_keys = true;
+        assert!(matches!(
+            config.overridden(),
+            Some(KeyIssue::Overridden { ref path, .. }) if path == "grid.keyboard_layout"
+        ));
+        config.validate();
+        assert_eq!(config.grid.keyboard_layout, KeyboardLayout::Qwerty);
     }
 
     #[test]
@@ -127,8 +1235,302 @@ alpha = 200
         let mut config = Config::default();
         config.grid.cols = 100;
         config.grid.rows = 0;
+        config.grid.desktops.push(DesktopGrid {
+            desktop: 1,
+            cols: 0,
+            rows: 9,
+        });
         config.validate();
         assert_eq!(config.grid.cols, 8);
         assert_eq!(config.grid.rows, 1);
+        assert_eq!(config.grid_dimensions(Some(0), &[], None), (1, 4));
+    }
+
+    #[test]
+    fn test_merge_changes() {
+        let base = Config::default();
+        let mut ours = base.clone();
+        ours.grid.cols = 6;
+        // Another writer changed rows since, and the user added a binding
+        let mut file: toml::Table = toml::from_str(
+            "[grid]\ncols = 4\nrows = 3\n\n[grid.bindings]\nG = \"full\"\n\n[logging]\nverbose = true\n",
+        )
+        .unwrap();
+        merge_changes(
+            &mut file,
+            &to_table(&base).unwrap(),
+            &to_table(&ours).unwrap(),
+        );
+        let merged: Config = toml::Value::Table(file.clone()).try_into().unwrap();
+        assert_eq!((merged.grid.cols, merged.grid.rows), (6, 3));
+        assert_eq!(merged.grid.bindings.len(), 1);
+        assert!(merged.logging.verbose);
+        // Unchanged tables the file leaves out aren't written
+        assert!(!file.contains_key("appearance"));
+    }
+
+    #[test]
+    fn test_backup_time() {
+        let name = ".tactile-win.toml";
+        assert_eq!(
+            backup_time(name, ".tactile-win.toml.1767225600000.bak"),
+            Some(1767225600000)
+        );
+        assert_eq!(backup_time(name, ".tactile-win.toml"), None);
+        assert_eq!(backup_time(name, ".tactile-win.toml.123.tmp"), None);
+        assert_eq!(backup_time(name, ".tactile-win-stats.toml.1.bak"), None);
+    }
+
+    #[test]
+    fn test_back_up_keeps_quick_saves_apart() {
+        let dir = std::env::temp_dir().join(format!("tactile-win-backup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".tactile-win.toml");
+        for save in ["first", "second", "third"] {
+            fs::write(&path, save).unwrap();
+            back_up(&path).unwrap();
+        }
+        // Newest first, none overwritten
+        let saved: Vec<String> = backups(&path)
+            .iter()
+            .map(|backup| fs::read_to_string(backup).unwrap())
+            .collect();
+        assert_eq!(saved, ["third", "second", "first"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_on_disk() {
+        let dir = std::env::temp_dir().join(format!("tactile-win-on-disk-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".tactile-win.toml");
+        assert!(on_disk(&path).unwrap().is_none());
+        fs::write(&path, "[grid]\ncols = 5\n").unwrap();
+        let table = on_disk(&path).unwrap().unwrap();
+        assert_eq!(table["grid"]["cols"].as_integer(), Some(5));
+        // A typo is kept for its author to fix, not saved over
+        fs::write(&path, "[grid\ncols = 5\n").unwrap();
+        assert!(on_disk(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_opacity_keys() {
+        let mut opacity = OpacityConfig::default();
+        assert_eq!(opacity.step_for('='), Some(OpacityStep::More));
+        assert_eq!(opacity.step_for('0'), Some(OpacityStep::Reset));
+        assert_eq!(opacity.step_for('G'), None);
+        opacity.less = "t".to_string();
+        opacity.reset = String::new();
+        assert_eq!(opacity.step_for('T'), Some(OpacityStep::Less));
+        assert_eq!(opacity.step_for('0'), None);
+    }
+
+    #[test]
+    fn test_hotkey() {
+        assert_eq!(Config::default().hotkey.hotkey(), Ok(Hotkey::DEFAULT));
+        let config = Config::parse(
+            r#"
+[hotkey]
+modifiers = ["win"]
+key = "T"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.hotkey.hotkey().unwrap().to_string(), "Win+T");
+        let config = Config::parse("[hotkey]\nkey = \"F12\"").unwrap();
+        // Ctrl+Shift unless set otherwise
+        assert_eq!(
+            config.hotkey.hotkey().unwrap().to_string(),
+            "Ctrl+Shift+F12"
+        );
+    }
+
+    #[test]
+    fn test_layouts() {
+        let config = Config::parse(
+            r#"
+[[layouts]]
+name = "coding"
+key = "1"
+steps = [
+    { process = "Code.exe", region = "left-two-thirds" },
+    { class = "CASCADIA_HOSTING_WINDOW_CLASS", region = "right-third" },
+]
+
+[[layouts]]
+name = "meetings"
+modifiers = ["ctrl", "alt"]
+key = "2"
+profile = "docked"
+
+[[layouts]]
+name = "comms"
+desktop = 1
+"#,
+        )
+        .unwrap();
+        let coding = &config.layouts[0];
+        assert_eq!(coding.hotkey().unwrap().to_string(), "Ctrl+Shift+1");
+        assert_eq!(coding.steps.len(), 2);
+        assert_eq!(coding.steps[1].region.to_string(), "right-third");
+        let meetings = &config.layouts[1];
+        assert_eq!(meetings.hotkey().unwrap().to_string(), "Ctrl+Alt+2");
+        assert_eq!(meetings.profile.as_deref(), Some("docked"));
+        assert_eq!(meetings.desktop, None);
+        assert_eq!(config.layouts[2].desktop, Some(1));
+        assert!(Layout::default().hotkey().is_err());
+    }
+
+    #[test]
+    fn test_same_as() {
+        let config = Config::default();
+        assert!(config.same_as(&Config::default()));
+        // Comments and the order options are written in don't count
+        let written = Config::parse("# mine\n[behavior]\nsnap_on_move = false\n").unwrap();
+        assert!(written.same_as(&config));
+        let mut changed = Config::default();
+        changed.appearance.alpha = 100;
+        assert!(!changed.same_as(&config));
+    }
+
+    #[test]
+    fn test_cramped_tiles() {
+        let laptop = RECT {
+            left: 0,
+            top: 0,
+            right: 1366,
+            bottom: 728,
+        };
+        let mut config = Config::default();
+        assert_eq!(config.cramped_tiles(laptop, 96), None);
+
+        // 8 columns with 50px gaps leave 114px tiles; at 200% scale the
+        // gaps double as well
+        config.grid.cols = 8;
+        config.grid.gap = 50;
+        config.grid.min_tile_size = 150;
+        assert_eq!(config.cramped_tiles(laptop, 96), Some((114, 289)));
+        assert_eq!(config.cramped_tiles(laptop, 192), Some((58, 214)));
+
+        // Every size counts, not just the default one
+        config.grid.cols = 2;
+        config.profiles.push(Profile {
+            name: "thin".to_string(),
+            monitors: None,
+            cols: 2,
+            rows: 4,
+        });
+        assert_eq!(config.cramped_tiles(laptop, 96), Some((608, 119)));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let toml_str = r#"
+[grid]
+cols = 4
+rows = 2
+desktops = [{ desktop = 2, cols = 2, rows = 1 }]
+
+[[profiles]]
+name = "docked"
+monitors = 3
+cols = 6
+rows = 2
+
+[[profiles]]
+name = "laptop"
+cols = 3
+rows = 1
+"#;
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.auto_profile(3), Some(0));
+        assert_eq!(config.auto_profile(1), Some(1));
+        assert_eq!(config.grid_dimensions(None, &[], Some(0)), (6, 2));
+        assert_eq!(config.grid_dimensions(Some(1), &[], Some(0)), (2, 1));
+        assert_eq!(config.grid_dimensions(None, &[], None), (4, 2));
+
+        let mut docked_only = config.clone();
+        docked_only.profiles.truncate(1);
+        assert_eq!(docked_only.auto_profile(2), None);
+    }
+
+    #[test]
+    fn test_monitor_grids() {
+        let toml_str = r#"
+[grid]
+desktops = [{ desktop = 2, cols = 2, rows = 1 }]
+monitors = [{ device = '\\.\DISPLAY2', cols = 6, rows = 3 }]
+
+[[profiles]]
+name = "laptop"
+cols = 3
+rows = 1
+"#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        let first = [r"\\?\DISPLAY#ABC0001#1", r"\\.\DISPLAY1"];
+        let second = [r"\\?\DISPLAY#ABC0002#2", r"\\.\display2"];
+        assert_eq!(config.grid_dimensions(None, &second, Some(0)), (6, 3));
+        assert_eq!(config.grid_dimensions(Some(1), &second, Some(0)), (2, 1));
+        assert_eq!(config.grid_dimensions(None, &first, Some(0)), (3, 1));
+
+        // Saved under its id, in place of the grid under its device name
+        config.grid.set_monitor_grid(&second, 8, 2);
+        assert_eq!(config.grid.monitors.len(), 1);
+        assert_eq!(config.grid.monitors[0].device, second[0]);
+        assert_eq!(config.grid_dimensions(None, &second, None), (8, 2));
+        assert_eq!(config.grid_dimensions(None, &second[..1], None), (8, 2));
+    }
+
+    #[test]
+    fn test_check_keys() {
+        let toml_str = r#"
+colour = "blue"
+
+[grid]
+colss = 6
+bindings = { "G" = "full" }
+desktops = [{ desktop = 2, cols = 2, rows = 1 }]
+
+[apperance]
+alpha = 200
+
+[behavior]
+old_option = true
+"#;
+        let deprecated = [("behavior.old_option", "use new_option")];
+        let issues = check_keys(toml_str, &deprecated);
+        let unknown = |path: &str, suggestion: Option<&str>| KeyIssue::Unknown {
+            path: path.to_string(),
+            suggestion: suggestion.map(str::to_string),
+        };
+        // In the order they're in the file
+        assert_eq!(
+            issues,
+            vec![
+                unknown("colour", None),
+                unknown("grid.colss", Some("cols")),
+                unknown("apperance", Some("appearance")),
+                KeyIssue::Deprecated {
+                    path: "behavior.old_option".to_string(),
+                    advice: "use new_option",
+                },
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "grid.colss isn't an option (did you mean cols?)"
+        );
+        assert!(check_keys("[grid]\ncols = 6\n", DEPRECATED).is_empty());
+        // Renamed options are still read under their old name
+        let renamed = "[appearance]\nbackground_color = 0x123456\n";
+        let config = Config::parse(renamed).unwrap();
+        assert_eq!(config.appearance.gap_color, 0x123456);
+        assert_eq!(
+            check_keys(renamed, DEPRECATED)[0].to_string(),
+            "appearance.background_color is deprecated; it's read as appearance.gap_color, its new name"
+        );
+        assert_eq!(edit_distance("colss", "cols"), 1);
+        assert_eq!(edit_distance("gap", "alpha"), 4);
     }
 }
@@ -0,0 +1,420 @@
+use crate::config::Config;
+
+/// What each option does, by table ("" for the top level) and key, put
+/// above it in the default config. Lines starting "e.g." are examples for
+/// options that are empty by default.
+const OPTIONS: &[(&str, &str, &str)] = &[
+    (
+        "",
+        "profiles",
+        "Named grid sizes, used while `monitors` monitors are connected; one\n\
+         without `monitors` is used when no other matches. Replaces [grid]'s\n\
+         cols and rows while active.\n\
+         e.g. profiles = [{ name = \"docked\", monitors = 3, cols = 6, rows = 2 }]",
+    ),
+    (
+        "",
+        "layouts",
+        "Windows placed together with one hotkey (modifiers default to ctrl and\n\
+         shift), optionally only while `profile` is active. A layout with a\n\
+         `desktop` is also placed on switching to that virtual desktop. Each\n\
+         step matches a window by process, class or title and gives its region;\n\
+         steps whose window isn't open are skipped.\n\
+         e.g. layouts = [{ name = \"coding\", key = \"1\", steps = [{ process = \"Code.exe\", region = \"left-half\" }] }]",
+    ),
+    (
+        "hotkey",
+        "modifiers",
+        "The grid hotkey's modifiers: any of \"ctrl\", \"alt\", \"shift\" and \"win\"",
+    ),
+    (
+        "hotkey",
+        "key",
+        "Its key: a letter, digit, F1-F24, or Space, PageUp, PageDown, Home,\n\
+         End, Insert, Delete, Pause or ScrollLock. Keys that type something\n\
+         need Ctrl, Alt or Win.",
+    ),
+    ("grid", "cols", "Columns, 1-8"),
+    ("grid", "rows", "Rows, 1-4"),
+    (
+        "grid",
+        "gap",
+        "Pixels between tiles and around the grid, 0-50",
+    ),
+    (
+        "grid",
+        "min_tile_size",
+        "Smallest tile width or height, 0-1000, in the same units as gap.\n\
+         Smaller tiles are warned about, and the gap shrinks to keep them this size.",
+    ),
+    (
+        "grid",
+        "dpi_scaling",
+        "Treat gap and overlay label sizes as device-independent pixels,\n\
+         scaled by each monitor's DPI",
+    ),
+    (
+        "grid",
+        "keyboard_layout",
+        "Keyboard rows that map to grid rows: \"qwerty\", \"dvorak\" or \"colemak\".\n\
+         Not used with physical_keys, which always goes by US QWERTY positions",
+    ),
+    (
+        "grid",
+        "physical_keys",
+        "Match grid keys by where they sit on the keyboard, as on US QWERTY,\n\
+         rather than the letter they type, so the grid keeps its shape on AZERTY\n\
+         or QWERTZ; tiles show what your layout types there",
+    ),
+    (
+        "grid",
+        "second_key",
+        "What the second key picks: the region's opposite \"corner\", or its\n\
+         size (\"extent\")",
+    ),
+    (
+        "grid",
+        "monitor_keys",
+        "Keys that move the overlay straight to a monitor, numbered as Tab\n\
+         visits them: \"function\" (F1-F9) or \"none\"",
+    ),
+    (
+        "grid",
+        "bindings",
+        "Keys that place the window in one press while the overlay is open, on\n\
+         keys the grid doesn't use. Regions are names (full, left-half,\n\
+         top-right, right-two-thirds...), a side and percentage (left 33%), grid\n\
+         tiles (colspan(0..2, row 1)) or rect(x, y, width, height).\n\
+         e.g. \"G\" = \"full\"",
+    ),
+    (
+        "grid",
+        "desktops",
+        "Grid sizes for virtual desktops, numbered as in Task View; these come\n\
+         before monitors' and profiles'\n\
+         e.g. desktops = [{ desktop = 2, cols = 6, rows = 3 }]",
+    ),
+    (
+        "grid",
+        "monitors",
+        "Grid sizes for monitors, by the id the setup wizard saves or by display\n\
+         device name; these come before profiles'\n\
+         e.g. monitors = [{ device = '\\\\.\\DISPLAY2', cols = 6, rows = 2 }]",
+    ),
+    (
+        "grid",
+        "struts",
+        "Pixels kept clear of tiles along monitor edges, for a dock that doesn't\n\
+         reserve its space from Windows' work area; without a device, on every\n\
+         monitor\n\
+         e.g. struts = [{ device = '\\\\.\\DISPLAY1', left = 64 }]",
+    ),
+    (
+        "grid",
+        "dock_classes",
+        "Window classes of such docks, whose space is kept clear wherever they are\n\
+         e.g. dock_classes = [\"MyDockWindow\"]",
+    ),
+    ("appearance", "tile_color", "Colors are 0x00BBGGRR"),
+    ("appearance", "highlight_color", "Selected tiles"),
+    (
+        "appearance",
+        "gap_color",
+        "Between the tiles and around the grid",
+    ),
+    ("appearance", "text_color", "Tile labels"),
+    ("appearance", "alpha", "Tile opacity, 0-255"),
+    (
+        "appearance",
+        "gap_alpha",
+        "Gap opacity, 0-255; at 0 the gaps are see-through and let clicks\n\
+         through to the windows beneath",
+    ),
+    (
+        "appearance",
+        "high_contrast",
+        "Opaque palette with thicker borders and larger labels: \"auto\"\n\
+         (follow Windows high contrast, or transparency effects being off), \"on\"\n\
+         or \"off\"",
+    ),
+    (
+        "appearance",
+        "label",
+        "What each tile shows: its \"key\", the keys still needed (\"pair\"),\n\
+         its \"coordinates\", or \"none\"",
+    ),
+    (
+        "appearance",
+        "combo_hints",
+        "After the first key, draw in each tile the region it would select",
+    ),
+    (
+        "appearance",
+        "click_through",
+        "Let mouse clicks pass through the overlay to the windows beneath",
+    ),
+    (
+        "accessibility",
+        "announce",
+        "Speak overlay state changes: \"auto\" (while a screen reader runs),\n\
+         \"always\" or \"never\"",
+    ),
+    (
+        "accessibility",
+        "text_scale",
+        "Overlay label and settings text multiplier, on top of Windows' \"Make\n\
+         text bigger\"",
+    ),
+    (
+        "behavior",
+        "restore_selection_secs",
+        "Reopening the overlay within this many seconds of Escape keeps the\n\
+         first key (0 disables)",
+    ),
+    (
+        "behavior",
+        "show_delay_ms",
+        "Wait this long (0-500 ms) after the hotkey before drawing the overlay,\n\
+         so keys typed quickly never flash it",
+    ),
+    (
+        "behavior",
+        "after_move",
+        "The moved window: \"keep\" focus and z-order, \"raise\" it to the top,\n\
+         or \"activate\" it",
+    ),
+    (
+        "behavior",
+        "snap_on_move",
+        "Snap windows to the nearest grid cells after you drag or resize them",
+    ),
+    (
+        "behavior",
+        "input",
+        "How overlay keys are captured: \"hook\" (keyboard hook) or \"hotkeys\"\n\
+         (for when security software flags hooks)",
+    ),
+    (
+        "behavior",
+        "when_busy",
+        "Target mid-drag, in a menu or behind a modal dialog: \"defer\" the\n\
+         move until it's done, or \"cancel\" it",
+    ),
+    (
+        "behavior",
+        "when_maximized",
+        "Maximized target: \"restore\" then move it, \"skip\" it, or\n\
+         \"maximize\" (a whole-grid selection keeps it maximized)",
+    ),
+    (
+        "behavior",
+        "maximize_full_grid",
+        "Selecting the whole grid maximizes the window rather than sizing it",
+    ),
+    (
+        "behavior",
+        "pause_when_locked",
+        "Unhook the keyboard while the session is locked",
+    ),
+    (
+        "behavior",
+        "start_hidden",
+        "Start silently; false shows a notification that it's running",
+    ),
+    (
+        "behavior",
+        "when_open",
+        "The grid hotkey while the overlay is open: \"close\" it as Escape\n\
+         does, or \"ignore\" it",
+    ),
+    (
+        "behavior",
+        "when_target_closes",
+        "Target closed while the overlay is open: \"cancel\" the selection, or\n\
+         \"retarget\" the foreground window",
+    ),
+    (
+        "behavior",
+        "drag_splits",
+        "Drag the line between two tiled windows to resize both",
+    ),
+    (
+        "behavior",
+        "split_step",
+        "Pixels Ctrl+Alt+Shift+Arrow moves the split between the active tiled\n\
+         window and its neighbor",
+    ),
+    (
+        "behavior",
+        "follow_snap_groups",
+        "After tiling a window from a Windows Snap group, offer to move the\n\
+         rest of the group beside it",
+    ),
+    (
+        "behavior",
+        "confirm_with_enter",
+        "After the second key, outline where the window will go and move it\n\
+         only on Enter; another key starts the selection over",
+    ),
+    (
+        "behavior",
+        "confirm_shrink_percent",
+        "After a placement shrinks the window's area by more than this percent,\n\
+         offer to put it back as it was (0 turns this off)",
+    ),
+    (
+        "opacity",
+        "more",
+        "Overlay keys that make the target window more or less opaque, or\n\
+         back as it was; grid keys and bindings come first, and \"\" turns one off",
+    ),
+    ("opacity", "less", ""),
+    ("opacity", "reset", ""),
+    ("opacity", "step", "Percent each press changes, 1-100"),
+    (
+        "opacity",
+        "min",
+        "Least opaque a window goes, 10-100 percent",
+    ),
+    (
+        "rules",
+        "mode",
+        "\"blocklist\" tiles every window but `block`'s; \"allowlist\" tiles\n\
+         only `allow`'s. Rules match on process (executable name), class\n\
+         (window class) and title (substring); run tactile-win --list-windows\n\
+         to see them.",
+    ),
+    (
+        "rules",
+        "block",
+        "Windows never tiled, in either mode\n\
+         e.g. block = [{ class = \"ConsoleWindowClass\" }]",
+    ),
+    (
+        "rules",
+        "allow",
+        "In allowlist mode, the only windows tiled\n\
+         e.g. allow = [{ process = \"notepad.exe\" }]",
+    ),
+    (
+        "rules",
+        "placement",
+        "Grid size, gap and edge margin for particular windows; the first\n\
+         match wins\n\
+         e.g. placement = [{ process = \"WindowsTerminal.exe\", gap = 0, margin = 0 }]",
+    ),
+    (
+        "updates",
+        "check",
+        "Check GitHub releases periodically and notify from the tray",
+    ),
+    ("updates", "interval_hours", "Hours between checks"),
+    (
+        "history",
+        "enabled",
+        "Log placements (time, program, hashed title, monitor, region) for\n\
+         export from the tray",
+    ),
+    (
+        "history",
+        "suggest_grid",
+        "Count which fractions of the screen placements use, and suggest a\n\
+         grid that fits them better",
+    ),
+    (
+        "logging",
+        "verbose",
+        "Also print the log to a console, as --verbose does, and log window\n\
+         titles in full",
+    ),
+    (
+        "logging",
+        "paint_timing",
+        "Log how long each overlay paint takes, warning about slow ones",
+    ),
+];
+
+fn describe(table: &str, key: &str) -> Option<&'static str> {
+    OPTIONS
+        .iter()
+        .find(|(t, k, _)| *t == table && *k == key)
+        .map(|(_, _, doc)| *doc)
+}
+
+/// The table and key a line of serialized config sets, given the table
+/// it's in. Tables nested in another, like [grid.bindings], are options
+/// of the outer one.
+fn option_of<'a>(line: &'a str, table: &'a str) -> Option<(&'a str, &'a str)> {
+    match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+        Some(name) => name.rsplit_once('.'),
+        None => line.split_once(" = ").map(|(key, _)| (table, key)),
+    }
+}
+
+/// The default config as TOML, every option commented with what it does
+/// and what it takes
+pub fn default_config() -> String {
+    let toml = toml::to_string_pretty(&Config::default()).unwrap_or_default();
+    let mut out = String::from(
+        "# Tactile-Win configuration, with every option at its default.\n\
+         # Save as ~/.tactile-win.toml; options left out keep these values.\n\n",
+    );
+    let mut table = "";
+    for line in toml.lines() {
+        if let Some((parent, key)) = option_of(line, table) {
+            for doc in describe(parent, key).unwrap_or_default().lines() {
+                out.push_str("# ");
+                out.push_str(doc);
+                out.push('\n');
+            }
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            table = name;
+        }
+        // Colors read better in hex, as they're written
+        let hex = line.split_once(" = ").and_then(|(key, value)| {
+            let color: u32 = value.parse().ok()?;
+            key.ends_with("_color")
+                .then(|| format!("{} = 0x{:08X}", key, color))
+        });
+        out.push_str(hex.as_deref().unwrap_or(line));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_option_is_described() {
+        let toml = toml::to_string_pretty(&Config::default()).unwrap();
+        let mut table = "";
+        for line in toml.lines() {
+            if let Some((parent, key)) = option_of(line, table) {
+                assert!(
+                    describe(parent, key).is_some(),
+                    "{}.{} needs a description in OPTIONS",
+                    parent,
+                    key
+                );
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name;
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_config_round_trips() {
+        let text = default_config();
+        let parsed = Config::parse(&text).unwrap();
+        let expected = toml::to_string(&Config::default()).unwrap();
+        assert_eq!(toml::to_string(&parsed).unwrap(), expected);
+        assert!(text.contains("tile_color = 0x00805030"));
+        assert!(text.contains("# Columns, 1-8\ncols = 4\n"));
+        // Examples stay comments
+        assert!(text.contains("# e.g. \"G\" = \"full\"\n[grid.bindings]"));
+    }
+}
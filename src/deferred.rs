@@ -0,0 +1,172 @@
+use std::time::Instant;
+use windows::Win32::Foundation::{HWND, RECT};
+
+/// How a window with a deferred move looks on a poll
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Poll {
+    /// The window has closed
+    Closed,
+    /// The window is open at `rect`, and `busy` says why it can't be moved
+    /// yet, if it can't
+    Open {
+        rect: Option<RECT>,
+        busy: Option<&'static str>,
+    },
+}
+
+/// A move held back until its window is free and settled
+struct Waiting<T> {
+    target: HWND,
+    placement: T,
+    /// Window rect at the last poll; the move waits for it to stop changing
+    last_rect: Option<RECT>,
+    give_up_at: Instant,
+}
+
+/// Moves held back while their windows were busy or animating
+pub struct DeferredMoves<T> {
+    waiting: Vec<Waiting<T>>,
+}
+
+impl<T> Default for DeferredMoves<T> {
+    fn default() -> Self {
+        Self {
+            waiting: Vec::new(),
+        }
+    }
+}
+
+/// What became of the deferred moves on a poll
+pub struct Polled<T> {
+    /// Moves whose windows are free and settled, to make now
+    pub ready: Vec<(HWND, T)>,
+    /// Moves given up on, with what their window was still doing
+    pub dropped: Vec<(HWND, T, &'static str)>,
+    /// Windows that closed while their moves waited
+    pub closed: Vec<HWND>,
+}
+
+impl<T> DeferredMoves<T> {
+    /// Hold `placement` back until `target`, now at `rect`, is free, in
+    /// place of any move already waiting for it
+    pub fn push(&mut self, target: HWND, placement: T, rect: Option<RECT>, give_up_at: Instant) {
+        self.waiting.retain(|waiting| waiting.target != target);
+        self.waiting.push(Waiting {
+            target,
+            placement,
+            last_rect: rect,
+            give_up_at,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// Look at each waiting window with `poll`. A move is ready once its
+    /// window is free and hasn't moved since the last poll; one whose
+    /// window closed, or is still busy at its give-up time, is dropped.
+    pub fn poll(&mut self, now: Instant, mut poll: impl FnMut(HWND) -> Poll) -> Polled<T> {
+        let mut polled = Polled {
+            ready: Vec::new(),
+            dropped: Vec::new(),
+            closed: Vec::new(),
+        };
+        for mut waiting in std::mem::take(&mut self.waiting) {
+            let waiting_on = match poll(waiting.target) {
+                Poll::Closed => {
+                    polled.closed.push(waiting.target);
+                    continue;
+                }
+                Poll::Open { rect, busy } => {
+                    let settled = rect == waiting.last_rect;
+                    waiting.last_rect = rect;
+                    busy.or_else(|| (!settled).then_some("animating"))
+                }
+            };
+            match waiting_on {
+                None => polled.ready.push((waiting.target, waiting.placement)),
+                Some(reason) if now >= waiting.give_up_at => {
+                    polled
+                        .dropped
+                        .push((waiting.target, waiting.placement, reason))
+                }
+                // Check again on the next poll
+                Some(_) => self.waiting.push(waiting),
+            }
+        }
+        polled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn rect(left: i32) -> RECT {
+        RECT {
+            left,
+            top: 0,
+            right: left + 100,
+            bottom: 100,
+        }
+    }
+
+    #[test]
+    fn test_waits_then_moves_or_drops() {
+        let start = Instant::now();
+        let give_up_at = start + Duration::from_secs(30);
+        let (busy, animating, closed) = (
+            HWND(0x10 as *mut _),
+            HWND(0x20 as *mut _),
+            HWND(0x30 as *mut _),
+        );
+        let mut deferred = DeferredMoves::default();
+        for (target, placement) in [(busy, "left"), (animating, "right"), (closed, "top")] {
+            deferred.push(target, placement, Some(rect(0)), give_up_at);
+        }
+
+        let poll = |moved: i32| {
+            move |hwnd: HWND| match hwnd.0 as usize {
+                0x10 => Poll::Open {
+                    rect: Some(rect(0)),
+                    busy: Some("showing a menu"),
+                },
+                0x20 => Poll::Open {
+                    rect: Some(rect(moved)),
+                    busy: None,
+                },
+                _ => Poll::Closed,
+            }
+        };
+        // The second window is still animating on the first poll, and
+        // settled on the second
+        let polled = deferred.poll(start, poll(50));
+        assert!(polled.ready.is_empty() && polled.dropped.is_empty());
+        assert_eq!(polled.closed, [closed]);
+        let polled = deferred.poll(start, poll(50));
+        assert_eq!(polled.ready, [(animating, "right")]);
+        assert!(polled.dropped.is_empty());
+
+        // The busy window is given up on once its time is up
+        assert!(deferred.poll(start, poll(50)).dropped.is_empty());
+        let polled = deferred.poll(give_up_at, poll(50));
+        assert_eq!(polled.dropped, [(busy, "left", "showing a menu")]);
+        assert!(deferred.is_empty());
+    }
+
+    #[test]
+    fn test_push_replaces_waiting_move() {
+        let later = Instant::now() + Duration::from_secs(30);
+        let target = HWND(0x10 as *mut _);
+        let mut deferred = DeferredMoves::default();
+        deferred.push(target, "left", None, later);
+        deferred.push(target, "right", None, later);
+        let polled = deferred.poll(Instant::now(), |_| Poll::Open {
+            rect: None,
+            busy: None,
+        });
+        assert_eq!(polled.ready, [(target, "right")]);
+    }
+}
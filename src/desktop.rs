@@ -0,0 +1,167 @@
+use std::thread;
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{CloseHandle, HWND, LPARAM, WAIT_OBJECT_0, WPARAM};
+use windows::Win32::System::Registry::{
+    RegCloseKey, RegGetValueW, RegNotifyChangeKeyValue, RegOpenKeyExW, HKEY, HKEY_CURRENT_USER,
+    KEY_NOTIFY, REG_NOTIFY_CHANGE_LAST_SET, RRF_RT_REG_BINARY,
+};
+use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject, INFINITE};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+use crate::log::warning;
+
+/// Posted to the main window when another virtual desktop is shown; wParam
+/// is its index, counting from 0 in Task View's order
+pub const WM_DESKTOP_SWITCHED: u32 = 0x8009; // WM_APP + 9
+
+// Explorer keeps the desktop list under VirtualDesktops here. The current
+// desktop is there too on Windows 11, and under SessionInfo on Windows 10.
+const EXPLORER_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer";
+
+/// Size of a desktop GUID in the registry's binary values
+const DESKTOP_ID_LEN: usize = 16;
+
+fn read_binary(subkey: &str, value: PCWSTR) -> Option<Vec<u8>> {
+    let subkey = HSTRING::from(subkey);
+    unsafe {
+        let mut size = 0u32;
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &subkey,
+            value,
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut size),
+        )
+        .ok()
+        .ok()?;
+        let mut data = vec![0u8; size as usize];
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &subkey,
+            value,
+            RRF_RT_REG_BINARY,
+            None,
+            Some(data.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+        .ok()
+        .ok()?;
+        data.truncate(size as usize);
+        Some(data)
+    }
+}
+
+fn current_desktop_id() -> Option<Vec<u8>> {
+    let key = format!("{}\\VirtualDesktops", EXPLORER_KEY);
+    read_binary(&key, w!("CurrentVirtualDesktop")).or_else(|| {
+        let mut session = 0u32;
+        unsafe { ProcessIdToSessionId(std::process::id(), &mut session) }.ok()?;
+        let key = format!(
+            "{}\\SessionInfo\\{}\\VirtualDesktops",
+            EXPLORER_KEY, session
+        );
+        read_binary(&key, w!("CurrentVirtualDesktop"))
+    })
+}
+
+/// Position of desktop `id` in Explorer's list of desktop IDs
+fn desktop_index(ids: &[u8], id: &[u8]) -> Option<usize> {
+    if id.len() != DESKTOP_ID_LEN {
+        return None;
+    }
+    ids.chunks_exact(DESKTOP_ID_LEN)
+        .position(|candidate| candidate == id)
+}
+
+/// Index of the virtual desktop being shown, counting from 0 in Task
+/// View's order. None if Windows hasn't recorded it, e.g. before a
+/// second desktop was ever created.
+pub fn current_desktop() -> Option<usize> {
+    let key = format!("{}\\VirtualDesktops", EXPLORER_KEY);
+    let ids = read_binary(&key, w!("VirtualDesktopIDs"))?;
+    desktop_index(&ids, &current_desktop_id()?)
+}
+
+/// Post WM_DESKTOP_SWITCHED to `hwnd` whenever the virtual desktop being
+/// shown changes, from a thread of its own. Explorer records the switch
+/// in the registry, so its key is watched rather than the shell's
+/// undocumented desktop interfaces.
+pub fn watch_desktops(hwnd: HWND) {
+    let notify = hwnd.0 as isize;
+    thread::spawn(move || {
+        let mut key = HKEY::default();
+        let opened = unsafe {
+            RegOpenKeyExW(
+                HKEY_CURRENT_USER,
+                &HSTRING::from(EXPLORER_KEY),
+                None,
+                KEY_NOTIFY,
+                &mut key,
+            )
+        };
+        if let Err(e) = opened.ok() {
+            warning!("Can't watch for virtual desktop switches: {}", e);
+            return;
+        }
+        let event = match unsafe { CreateEventW(None, false, false, None) } {
+            Ok(event) => event,
+            Err(e) => {
+                warning!("Can't watch for virtual desktop switches: {}", e);
+                unsafe {
+                    let _ = RegCloseKey(key);
+                }
+                return;
+            }
+        };
+        let mut last = current_desktop();
+        loop {
+            // Notifies once per call, so it is asked for again each time
+            let asked = unsafe {
+                RegNotifyChangeKeyValue(key, true, REG_NOTIFY_CHANGE_LAST_SET, Some(event), true)
+            };
+            if let Err(e) = asked.ok() {
+                warning!("Stopped watching for virtual desktop switches: {}", e);
+                break;
+            }
+            if unsafe { WaitForSingleObject(event, INFINITE) } != WAIT_OBJECT_0 {
+                break;
+            }
+            let now = current_desktop();
+            if now != last {
+                last = now;
+                if let Some(index) = now {
+                    unsafe {
+                        let _ = PostMessageW(
+                            Some(HWND(notify as *mut _)),
+                            WM_DESKTOP_SWITCHED,
+                            WPARAM(index),
+                            LPARAM(0),
+                        );
+                    }
+                }
+            }
+        }
+        unsafe {
+            let _ = CloseHandle(event);
+            let _ = RegCloseKey(key);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desktop_index() {
+        let ids: Vec<u8> = (1..=3).flat_map(|n| [n; DESKTOP_ID_LEN]).collect();
+        assert_eq!(desktop_index(&ids, &[1; DESKTOP_ID_LEN]), Some(0));
+        assert_eq!(desktop_index(&ids, &[3; DESKTOP_ID_LEN]), Some(2));
+        assert_eq!(desktop_index(&ids, &[4; DESKTOP_ID_LEN]), None);
+        assert_eq!(desktop_index(&ids, &[1; 8]), None);
+        assert_eq!(desktop_index(&[], &[1; DESKTOP_ID_LEN]), None);
+    }
+}
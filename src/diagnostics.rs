@@ -0,0 +1,501 @@
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{CloseHandle, GlobalFree, HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Gdi::{GetStockObject, HBRUSH, WHITE_BRUSH};
+use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, RegisterClassW, SetForegroundWindow,
+    ShowWindow, CS_HREDRAW, CS_VREDRAW, ES_AUTOVSCROLL, ES_MULTILINE, ES_READONLY, HMENU, SW_SHOW,
+    WINDOW_STYLE, WM_CLOSE, WM_COMMAND, WM_CREATE, WM_DESTROY, WNDCLASSW, WS_BORDER, WS_CAPTION,
+    WS_CHILD, WS_EX_DLGMODALFRAME, WS_OVERLAPPED, WS_SYSMENU, WS_TABSTOP, WS_VISIBLE, WS_VSCROLL,
+};
+
+use crate::config::{Config, InputBackend};
+use crate::icon::window_icon;
+use crate::keyboard::{grid_hotkey, is_hook_installed};
+use crate::keys::Hotkey;
+use crate::log::{error, info};
+use crate::settings::{center_window, set_dialog_font};
+use crate::window::{get_all_monitors, get_monitor_work_area};
+
+const DIAGNOSTICS_CLASS_NAME: PCWSTR = w!("TactileWinDiagnostics");
+
+const ID_COPY_BTN: i32 = 120;
+const ID_CLOSE_BTN: i32 = 121;
+
+// Use atomic for HWND tracking since HWND is not Send
+static DIAGNOSTICS_HWND: AtomicIsize = AtomicIsize::new(0);
+// Report shown in the open window, and copied from it
+static REPORT: Mutex<String> = Mutex::new(String::new());
+static TEXT_PERCENT: AtomicIsize = AtomicIsize::new(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// Works, but something may not behave as expected
+    Warning,
+    Failed,
+}
+
+/// The result of one check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, outcome: Outcome, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome,
+            detail: detail.into(),
+        }
+    }
+}
+
+fn check_hotkey(registered: bool, hotkey: Hotkey) -> Check {
+    if registered {
+        Check::new(
+            "Grid hotkey",
+            Outcome::Ok,
+            format!("{} is registered", hotkey),
+        )
+    } else {
+        Check::new(
+            "Grid hotkey",
+            Outcome::Failed,
+            format!(
+                "{} is not registered; another program may have taken it",
+                hotkey
+            ),
+        )
+    }
+}
+
+fn check_hook(input: InputBackend, installed: bool) -> Check {
+    match (input, installed) {
+        (InputBackend::Hotkeys, _) => Check::new(
+            "Keyboard hook",
+            Outcome::Ok,
+            "not used; overlay keys are hotkeys (input = \"hotkeys\")",
+        ),
+        (InputBackend::Hook, true) => Check::new("Keyboard hook", Outcome::Ok, "installed"),
+        (InputBackend::Hook, false) => Check::new(
+            "Keyboard hook",
+            Outcome::Failed,
+            "not installed; if security software blocks it, try input = \"hotkeys\"",
+        ),
+    }
+}
+
+fn check_monitors() -> Check {
+    let monitors = get_all_monitors();
+    let without_work_area = monitors
+        .iter()
+        .filter(|&&monitor| get_monitor_work_area(monitor).is_none())
+        .count();
+    if monitors.is_empty() {
+        Check::new("Monitors", Outcome::Failed, "none could be enumerated")
+    } else if without_work_area > 0 {
+        Check::new(
+            "Monitors",
+            Outcome::Failed,
+            format!(
+                "{} found, {} without a work area",
+                monitors.len(),
+                without_work_area
+            ),
+        )
+    } else {
+        Check::new(
+            "Monitors",
+            Outcome::Ok,
+            format!("{} found, each with a work area", monitors.len()),
+        )
+    }
+}
+
+/// Whether the config at `path` can be read and parsed
+fn check_config(path: Option<&Path>) -> Check {
+    let Some(path) = path else {
+        return Check::new("Config", Outcome::Failed, "no home directory to keep it in");
+    };
+    if !path.exists() {
+        return Check::new(
+            "Config",
+            Outcome::Ok,
+            format!("{} doesn't exist yet; defaults in use", path.display()),
+        );
+    }
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            return Check::new(
+                "Config",
+                Outcome::Failed,
+                format!("can't read {}: {}", path.display(), e),
+            );
+        }
+    };
+    match toml::from_str::<Config>(&contents) {
+        Ok(_) => Check::new("Config", Outcome::Ok, format!("{} parses", path.display())),
+        Err(e) => Check::new(
+            "Config",
+            Outcome::Failed,
+            format!(
+                "{} doesn't parse, so defaults are in use: {}",
+                path.display(),
+                e.message()
+            ),
+        ),
+    }
+}
+
+/// Whether settings can be saved to `path`, without changing it
+fn check_config_writable(path: Option<&Path>) -> Check {
+    let Some(path) = path else {
+        return Check::new("Config write access", Outcome::Failed, "no config path");
+    };
+    let result = if path.exists() {
+        OpenOptions::new().append(true).open(path).map(drop)
+    } else {
+        // Nothing to open; see whether its directory takes a new file
+        let probe = path.with_extension("toml.check");
+        fs::write(&probe, "").and_then(|()| fs::remove_file(&probe))
+    };
+    match result {
+        Ok(()) => Check::new("Config write access", Outcome::Ok, "settings can be saved"),
+        Err(e) => Check::new(
+            "Config write access",
+            Outcome::Failed,
+            format!("can't write {}: {}", path.display(), e),
+        ),
+    }
+}
+
+/// Whether tactile-win runs as administrator; None if that can't be told
+fn is_elevated() -> Option<bool> {
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).ok()?;
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut size = 0;
+        let result = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut _ as *mut _),
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut size,
+        );
+        let _ = CloseHandle(token);
+        result.ok()?;
+        Some(elevation.TokenIsElevated != 0)
+    }
+}
+
+fn check_elevation(elevated: Option<bool>) -> Check {
+    match elevated {
+        Some(true) => Check::new(
+            "Elevation",
+            Outcome::Ok,
+            "running as administrator; every window can be moved",
+        ),
+        Some(false) => Check::new(
+            "Elevation",
+            Outcome::Warning,
+            "not running as administrator; windows of programs that are can't be moved",
+        ),
+        None => Check::new("Elevation", Outcome::Warning, "couldn't be determined"),
+    }
+}
+
+/// Run every check. `hotkey_registered` is whether registering the grid
+/// hotkey last succeeded.
+pub fn run_checks(hotkey_registered: bool, input: InputBackend) -> Vec<Check> {
+    let config_path = Config::config_path();
+    vec![
+        check_hotkey(hotkey_registered, grid_hotkey()),
+        check_hook(input, is_hook_installed()),
+        check_monitors(),
+        check_config(config_path.as_deref()),
+        check_config_writable(config_path.as_deref()),
+        check_elevation(is_elevated()),
+    ]
+}
+
+/// The checks as text to paste into a bug report, with Windows line endings
+/// for the edit control and clipboard
+pub fn format_report(checks: &[Check]) -> String {
+    let mut report = format!(
+        "Tactile-Win {} diagnostics\r\n\r\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    for check in checks {
+        let outcome = match check.outcome {
+            Outcome::Ok => "OK",
+            Outcome::Warning => "WARNING",
+            Outcome::Failed => "FAILED",
+        };
+        report.push_str(&format!(
+            "[{}] {}: {}\r\n",
+            outcome, check.name, check.detail
+        ));
+    }
+    report
+}
+
+/// Open a window showing `report`, with a button to copy it
+pub fn show_diagnostics(report: String, text_percent: i32) {
+    let failed = report
+        .lines()
+        .filter(|line| line.starts_with("[FAILED]"))
+        .count();
+    info!("Diagnostics ran; {} checks failed", failed);
+    if let Ok(mut guard) = REPORT.lock() {
+        *guard = report;
+    }
+    TEXT_PERCENT.store(text_percent as isize, Ordering::SeqCst);
+
+    let existing = DIAGNOSTICS_HWND.load(Ordering::SeqCst);
+    if existing != 0 {
+        // Show the new report in place of the old one
+        unsafe {
+            let _ = DestroyWindow(HWND(existing as *mut _));
+        }
+    }
+
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap();
+
+        let wc = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(diagnostics_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: DIAGNOSTICS_CLASS_NAME,
+            hbrBackground: HBRUSH(GetStockObject(WHITE_BRUSH).0),
+            hIcon: window_icon(),
+            ..Default::default()
+        };
+        let _ = RegisterClassW(&wc);
+
+        let hwnd = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            DIAGNOSTICS_CLASS_NAME,
+            w!("Tactile-Win Diagnostics"),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU,
+            100,
+            100,
+            560 * text_percent / 100,
+            330 * text_percent / 100,
+            None,
+            None,
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+
+        if let Ok(hwnd) = hwnd {
+            DIAGNOSTICS_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
+            let _ = ShowWindow(hwnd, SW_SHOW);
+            let _ = SetForegroundWindow(hwnd);
+        }
+    }
+}
+
+unsafe fn create_control(
+    hwnd: HWND,
+    class: PCWSTR,
+    text: &str,
+    style: WINDOW_STYLE,
+    (x, y, width, height): (i32, i32, i32, i32),
+    id: Option<i32>,
+) {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap();
+        let text: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+        let _ = CreateWindowExW(
+            Default::default(),
+            class,
+            PCWSTR(text.as_ptr()),
+            style,
+            x,
+            y,
+            width,
+            height,
+            Some(hwnd),
+            id.map(|id| HMENU(id as isize as *mut _)),
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+    }
+}
+
+unsafe fn create_controls(hwnd: HWND) {
+    unsafe {
+        let report = REPORT.lock().map(|guard| guard.clone()).unwrap_or_default();
+        let edit_style = WS_CHILD
+            | WS_VISIBLE
+            | WS_BORDER
+            | WS_VSCROLL
+            | WINDOW_STYLE((ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL) as u32);
+        create_control(
+            hwnd,
+            w!("EDIT"),
+            &report,
+            edit_style,
+            (15, 15, 515, 210),
+            None,
+        );
+
+        let button_style = WS_CHILD | WS_VISIBLE | WS_TABSTOP;
+        create_control(
+            hwnd,
+            w!("BUTTON"),
+            "Copy to Clipboard",
+            button_style,
+            (290, 240, 130, 30),
+            Some(ID_COPY_BTN),
+        );
+        create_control(
+            hwnd,
+            w!("BUTTON"),
+            "Close",
+            button_style,
+            (430, 240, 100, 30),
+            Some(ID_CLOSE_BTN),
+        );
+    }
+}
+
+/// Put `text` on the clipboard, owned by `hwnd`
+unsafe fn copy_to_clipboard(hwnd: HWND, text: &str) -> windows::core::Result<()> {
+    unsafe {
+        let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+        let size = wide.len() * std::mem::size_of::<u16>();
+        let memory = GlobalAlloc(GMEM_MOVEABLE, size)?;
+        let locked = GlobalLock(memory) as *mut u16;
+        if locked.is_null() {
+            let _ = GlobalFree(Some(memory));
+            return Err(windows::core::Error::from_win32());
+        }
+        ptr::copy_nonoverlapping(wide.as_ptr(), locked, wide.len());
+        let _ = GlobalUnlock(memory);
+
+        if let Err(e) = OpenClipboard(Some(hwnd)) {
+            let _ = GlobalFree(Some(memory));
+            return Err(e);
+        }
+        let _ = EmptyClipboard();
+        // The clipboard owns the memory once this succeeds
+        let result = SetClipboardData(CF_UNICODETEXT.0 as u32, Some(HANDLE(memory.0)));
+        if result.is_err() {
+            let _ = GlobalFree(Some(memory));
+        }
+        let _ = CloseClipboard();
+        result.map(drop)
+    }
+}
+
+unsafe extern "system" fn diagnostics_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CREATE => {
+                create_controls(hwnd);
+                set_dialog_font(hwnd, TEXT_PERCENT.load(Ordering::SeqCst) as i32);
+                center_window(hwnd);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                match id {
+                    ID_COPY_BTN => {
+                        let report = REPORT.lock().map(|guard| guard.clone()).unwrap_or_default();
+                        if let Err(e) = copy_to_clipboard(hwnd, &report) {
+                            error!("Failed to copy diagnostics: {}", e);
+                        }
+                    }
+                    ID_CLOSE_BTN => {
+                        let _ = DestroyWindow(hwnd);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                // A replacement window may already be registered
+                let _ = DIAGNOSTICS_HWND.compare_exchange(
+                    hwnd.0 as isize,
+                    0,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_config() {
+        let dir = std::env::temp_dir().join(format!("tactile-win-diag-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".tactile-win.toml");
+
+        assert_eq!(check_config(Some(&path)).outcome, Outcome::Ok);
+        assert_eq!(check_config_writable(Some(&path)).outcome, Outcome::Ok);
+        // The probe for write access leaves nothing behind
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+
+        fs::write(&path, "[grid]\ncols = \"four\"\n").unwrap();
+        let check = check_config(Some(&path));
+        assert_eq!(check.outcome, Outcome::Failed);
+        assert!(check.detail.contains("doesn't parse"));
+
+        fs::write(&path, "[grid]\ncols = 4\n").unwrap();
+        assert_eq!(check_config(Some(&path)).outcome, Outcome::Ok);
+        assert_eq!(check_config(None).outcome, Outcome::Failed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_report() {
+        let checks = [
+            check_hotkey(true, Hotkey::DEFAULT),
+            check_hook(InputBackend::Hook, false),
+            check_elevation(Some(false)),
+        ];
+        let report = format_report(&checks);
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("Tactile-Win "));
+        assert_eq!(lines[2], "[OK] Grid hotkey: Ctrl+Shift+G is registered");
+        assert!(lines[3].starts_with("[FAILED] Keyboard hook: not installed"));
+        assert!(lines[4].starts_with("[WARNING] Elevation:"));
+        assert!(report.ends_with("\r\n"));
+    }
+}
@@ -0,0 +1,472 @@
+use std::fmt;
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetTopWindow, GetWindow, GetWindowLongW, IsWindow, IsWindowVisible, GWL_EXSTYLE, GW_HWNDNEXT,
+    GW_OWNER, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+};
+
+use crate::config::WhenTargetCloses;
+use crate::rules::WindowInfo;
+use crate::window::{
+    get_class_name, get_process_name, get_window_monitor, get_window_rect, get_window_title,
+    is_cloaked, is_shell_class,
+};
+
+/// Queries about top-level windows, so listing and filtering can run
+/// against fake windows in tests
+pub trait WindowSystem {
+    /// Every top-level window, topmost first
+    fn top_level_windows(&self) -> Vec<HWND>;
+    /// Whether `hwnd` is still a window, i.e. hasn't been destroyed
+    fn exists(&self, hwnd: HWND) -> bool;
+    fn is_visible(&self, hwnd: HWND) -> bool;
+    /// Hidden by DWM, e.g. on another virtual desktop or a suspended app
+    fn is_cloaked(&self, hwnd: HWND) -> bool;
+    fn has_owner(&self, hwnd: HWND) -> bool;
+    fn ex_style(&self, hwnd: HWND) -> u32;
+    fn class_name(&self, hwnd: HWND) -> String;
+    fn title(&self, hwnd: HWND) -> String;
+    /// Executable file name, e.g. "notepad.exe"
+    fn process_name(&self, hwnd: HWND) -> String;
+    fn rect(&self, hwnd: HWND) -> Option<RECT>;
+    fn monitor(&self, hwnd: HWND) -> HMONITOR;
+}
+
+/// The real windows on this desktop
+pub struct Win32Windows;
+
+impl WindowSystem for Win32Windows {
+    fn top_level_windows(&self) -> Vec<HWND> {
+        let mut windows = Vec::new();
+        unsafe {
+            let mut hwnd = GetTopWindow(None).ok();
+            while let Some(window) = hwnd {
+                windows.push(window);
+                hwnd = GetWindow(window, GW_HWNDNEXT).ok();
+            }
+        }
+        windows
+    }
+
+    fn exists(&self, hwnd: HWND) -> bool {
+        unsafe { IsWindow(Some(hwnd)).as_bool() }
+    }
+
+    fn is_visible(&self, hwnd: HWND) -> bool {
+        unsafe { IsWindowVisible(hwnd).as_bool() }
+    }
+
+    fn is_cloaked(&self, hwnd: HWND) -> bool {
+        is_cloaked(hwnd)
+    }
+
+    fn has_owner(&self, hwnd: HWND) -> bool {
+        unsafe { GetWindow(hwnd, GW_OWNER).is_ok_and(|owner| !owner.0.is_null()) }
+    }
+
+    fn ex_style(&self, hwnd: HWND) -> u32 {
+        unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 }
+    }
+
+    fn class_name(&self, hwnd: HWND) -> String {
+        get_class_name(hwnd)
+    }
+
+    fn title(&self, hwnd: HWND) -> String {
+        get_window_title(hwnd)
+    }
+
+    fn process_name(&self, hwnd: HWND) -> String {
+        get_process_name(hwnd)
+    }
+
+    fn rect(&self, hwnd: HWND) -> Option<RECT> {
+        get_window_rect(hwnd)
+    }
+
+    fn monitor(&self, hwnd: HWND) -> HMONITOR {
+        get_window_monitor(hwnd)
+    }
+}
+
+/// Why Alt-Tab wouldn't list this window, or None if it would: visible,
+/// uncloaked, unowned application windows that aren't tool windows or shell
+/// surfaces
+pub fn alt_tab_rejection(system: &impl WindowSystem, hwnd: HWND) -> Option<&'static str> {
+    if !system.is_visible(hwnd) {
+        return Some("hidden");
+    }
+    if system.is_cloaked(hwnd) {
+        return Some("cloaked");
+    }
+    if system.has_owner(hwnd) {
+        return Some("owned popup");
+    }
+    if system.ex_style(hwnd) & (WS_EX_TOOLWINDOW.0 | WS_EX_NOACTIVATE.0) != 0 {
+        return Some("tool window");
+    }
+    if is_shell_class(&system.class_name(hwnd)) {
+        return Some("shell window");
+    }
+    None
+}
+
+/// A top-level window and what the tiling features need to know about it
+#[derive(Debug, Clone)]
+pub struct WindowEntry {
+    pub hwnd: HWND,
+    pub info: WindowInfo,
+    pub rect: RECT,
+    pub monitor: HMONITOR,
+    pub cloaked: bool,
+    /// Whether Alt-Tab would list it
+    pub alt_tab: bool,
+}
+
+impl fmt::Display for WindowEntry {
+    /// One line with everything rules and layouts match on
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} | {} | {} | {},{} {}x{} | monitor {:#x}",
+            self.info.title,
+            self.info.class,
+            self.info.process,
+            self.rect.left,
+            self.rect.top,
+            self.rect.right - self.rect.left,
+            self.rect.bottom - self.rect.top,
+            self.monitor.0 as isize,
+        )?;
+        if self.cloaked {
+            f.write_str(" | cloaked")?;
+        }
+        if self.alt_tab {
+            f.write_str(" | alt-tab")?;
+        }
+        Ok(())
+    }
+}
+
+/// Which windows `list_windows` returns. The default is every visible,
+/// uncloaked window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowFilter {
+    /// Only windows Alt-Tab would list
+    pub alt_tab_only: bool,
+    /// Also list cloaked windows
+    pub include_cloaked: bool,
+    /// Only windows on this monitor
+    pub monitor: Option<HMONITOR>,
+}
+
+/// Visible top-level windows matching `filter`, topmost first
+pub fn list_windows(system: &impl WindowSystem, filter: &WindowFilter) -> Vec<WindowEntry> {
+    system
+        .top_level_windows()
+        .into_iter()
+        .filter(|&hwnd| system.is_visible(hwnd))
+        .filter_map(|hwnd| {
+            let cloaked = system.is_cloaked(hwnd);
+            if cloaked && !filter.include_cloaked {
+                return None;
+            }
+            let alt_tab = alt_tab_rejection(system, hwnd).is_none();
+            if filter.alt_tab_only && !alt_tab {
+                return None;
+            }
+            let monitor = system.monitor(hwnd);
+            if filter.monitor.is_some_and(|wanted| wanted != monitor) {
+                return None;
+            }
+            Some(WindowEntry {
+                hwnd,
+                info: WindowInfo {
+                    process: system.process_name(hwnd),
+                    class: system.class_name(hwnd),
+                    title: system.title(hwnd),
+                },
+                rect: system.rect(hwnd)?,
+                monitor,
+                cloaked,
+                alt_tab,
+            })
+        })
+        .collect()
+}
+
+/// The window a selection made for `target` should move: `target` while it
+/// exists, else per `when_closed` nothing or the topmost window Alt-Tab
+/// would list that `allowed` accepts
+pub fn surviving_target(
+    system: &impl WindowSystem,
+    target: HWND,
+    when_closed: WhenTargetCloses,
+    allowed: impl Fn(&WindowEntry) -> bool,
+) -> Option<HWND> {
+    if system.exists(target) {
+        return Some(target);
+    }
+    match when_closed {
+        WhenTargetCloses::Cancel => None,
+        WhenTargetCloses::Retarget => {
+            let filter = WindowFilter {
+                alt_tab_only: true,
+                ..Default::default()
+            };
+            list_windows(system, &filter)
+                .into_iter()
+                .find(|entry| allowed(entry))
+                .map(|entry| entry.hwnd)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeWindow {
+        title: &'static str,
+        class: &'static str,
+        process: &'static str,
+        visible: bool,
+        cloaked: bool,
+        owned: bool,
+        ex_style: u32,
+        left: i32,
+        monitor: isize,
+        /// Destroyed: no longer listed or a window at all
+        closed: bool,
+    }
+
+    impl Default for FakeWindow {
+        fn default() -> Self {
+            Self {
+                title: "",
+                class: "Window",
+                process: "app.exe",
+                visible: true,
+                cloaked: false,
+                owned: false,
+                ex_style: 0,
+                left: 0,
+                monitor: 1,
+                closed: false,
+            }
+        }
+    }
+
+    /// Fake windows in z-order; a window's HWND is its index + 1
+    struct FakeSystem(Vec<FakeWindow>);
+
+    impl FakeSystem {
+        fn window(&self, hwnd: HWND) -> &FakeWindow {
+            &self.0[hwnd.0 as usize - 1]
+        }
+    }
+
+    impl WindowSystem for FakeSystem {
+        fn top_level_windows(&self) -> Vec<HWND> {
+            (1..=self.0.len())
+                .map(|i| HWND(i as *mut _))
+                .filter(|&hwnd| self.exists(hwnd))
+                .collect()
+        }
+        fn exists(&self, hwnd: HWND) -> bool {
+            !self.window(hwnd).closed
+        }
+        fn is_visible(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).visible
+        }
+        fn is_cloaked(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).cloaked
+        }
+        fn has_owner(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).owned
+        }
+        fn ex_style(&self, hwnd: HWND) -> u32 {
+            self.window(hwnd).ex_style
+        }
+        fn class_name(&self, hwnd: HWND) -> String {
+            self.window(hwnd).class.to_string()
+        }
+        fn title(&self, hwnd: HWND) -> String {
+            self.window(hwnd).title.to_string()
+        }
+        fn process_name(&self, hwnd: HWND) -> String {
+            self.window(hwnd).process.to_string()
+        }
+        fn rect(&self, hwnd: HWND) -> Option<RECT> {
+            let left = self.window(hwnd).left;
+            Some(RECT {
+                left,
+                top: 0,
+                right: left + 800,
+                bottom: 600,
+            })
+        }
+        fn monitor(&self, hwnd: HWND) -> HMONITOR {
+            HMONITOR(self.window(hwnd).monitor as *mut _)
+        }
+    }
+
+    fn desktop() -> FakeSystem {
+        FakeSystem(vec![
+            FakeWindow {
+                title: "Taskbar",
+                class: "Shell_TrayWnd",
+                process: "explorer.exe",
+                ..Default::default()
+            },
+            FakeWindow {
+                title: "notes.txt - Notepad",
+                class: "Notepad",
+                process: "notepad.exe",
+                ..Default::default()
+            },
+            FakeWindow {
+                title: "Save As",
+                class: "#32770",
+                process: "notepad.exe",
+                owned: true,
+                ..Default::default()
+            },
+            FakeWindow {
+                title: "Palette",
+                ex_style: WS_EX_TOOLWINDOW.0,
+                left: 1920,
+                monitor: 2,
+                ..Default::default()
+            },
+            FakeWindow {
+                title: "Mail",
+                process: "outlook.exe",
+                cloaked: true,
+                ..Default::default()
+            },
+            FakeWindow {
+                title: "Hidden helper",
+                visible: false,
+                ..Default::default()
+            },
+            FakeWindow {
+                title: "Terminal",
+                process: "wt.exe",
+                left: 2000,
+                monitor: 2,
+                ..Default::default()
+            },
+        ])
+    }
+
+    /// One line per window, for comparing whole listings at once
+    fn snapshot(entries: &[WindowEntry]) -> Vec<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_lists_visible_uncloaked_windows() {
+        let entries = list_windows(&desktop(), &WindowFilter::default());
+        assert_eq!(
+            snapshot(&entries),
+            [
+                r#""Taskbar" | Shell_TrayWnd | explorer.exe | 0,0 800x600 | monitor 0x1"#,
+                r#""notes.txt - Notepad" | Notepad | notepad.exe | 0,0 800x600 | monitor 0x1 | alt-tab"#,
+                r#""Save As" | #32770 | notepad.exe | 0,0 800x600 | monitor 0x1"#,
+                r#""Palette" | Window | app.exe | 1920,0 800x600 | monitor 0x2"#,
+                r#""Terminal" | Window | wt.exe | 2000,0 800x600 | monitor 0x2 | alt-tab"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let system = desktop();
+        let alt_tab = WindowFilter {
+            alt_tab_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            snapshot(&list_windows(&system, &alt_tab)),
+            [
+                r#""notes.txt - Notepad" | Notepad | notepad.exe | 0,0 800x600 | monitor 0x1 | alt-tab"#,
+                r#""Terminal" | Window | wt.exe | 2000,0 800x600 | monitor 0x2 | alt-tab"#,
+            ]
+        );
+
+        let with_cloaked = WindowFilter {
+            include_cloaked: true,
+            ..Default::default()
+        };
+        let entries = list_windows(&system, &with_cloaked);
+        assert_eq!(entries.len(), 6);
+        assert_eq!(
+            snapshot(&entries)[4],
+            r#""Mail" | Window | outlook.exe | 0,0 800x600 | monitor 0x1 | cloaked"#
+        );
+
+        let second_monitor = WindowFilter {
+            monitor: Some(HMONITOR(2 as *mut _)),
+            ..Default::default()
+        };
+        assert_eq!(
+            snapshot(&list_windows(&system, &second_monitor)),
+            [
+                r#""Palette" | Window | app.exe | 1920,0 800x600 | monitor 0x2"#,
+                r#""Terminal" | Window | wt.exe | 2000,0 800x600 | monitor 0x2 | alt-tab"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alt_tab_rejections() {
+        let system = desktop();
+        let reasons: Vec<_> = system
+            .top_level_windows()
+            .into_iter()
+            .map(|hwnd| alt_tab_rejection(&system, hwnd))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                Some("shell window"),
+                None,
+                Some("owned popup"),
+                Some("tool window"),
+                Some("cloaked"),
+                Some("hidden"),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_target_closed_mid_selection() {
+        let mut system = desktop();
+        let notepad = HWND(2 as *mut _);
+        let terminal = HWND(7 as *mut _);
+        let anything = |_: &WindowEntry| true;
+        assert_eq!(
+            surviving_target(&system, terminal, WhenTargetCloses::Cancel, anything),
+            Some(terminal)
+        );
+
+        system.0[6].closed = true;
+        assert_eq!(
+            surviving_target(&system, terminal, WhenTargetCloses::Cancel, anything),
+            None
+        );
+        // The topmost Alt-Tab window takes over, unless the rules exclude it
+        assert_eq!(
+            surviving_target(&system, terminal, WhenTargetCloses::Retarget, anything),
+            Some(notepad)
+        );
+        let not_notepad = |entry: &WindowEntry| entry.info.process != "notepad.exe";
+        assert_eq!(
+            surviving_target(&system, terminal, WhenTargetCloses::Retarget, not_notepad),
+            None
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
+use windows::Win32::UI::WindowsAndMessaging::{
+    KillTimer, SetTimer, CHILDID_SELF, EVENT_OBJECT_DESTROY, EVENT_OBJECT_LOCATIONCHANGE,
+    EVENT_OBJECT_SHOW, EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_MOVESIZEEND, OBJID_WINDOW,
+    USER_TIMER_MINIMUM, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
+};
+
+/// Window changes a feature can subscribe to, each delivered for a whole
+/// top-level or child window rather than its scrollbars or other objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// The user finished moving or resizing a window
+    MoveSizeEnd,
+    /// A window became visible
+    Shown,
+    /// A window was destroyed
+    Destroyed,
+    /// A window came to the foreground
+    Foreground,
+    /// A window moved or resized, by the user or programmatically
+    LocationChanged,
+}
+
+impl WindowEvent {
+    fn raw(self) -> u32 {
+        match self {
+            WindowEvent::MoveSizeEnd => EVENT_SYSTEM_MOVESIZEEND,
+            WindowEvent::Shown => EVENT_OBJECT_SHOW,
+            WindowEvent::Destroyed => EVENT_OBJECT_DESTROY,
+            WindowEvent::Foreground => EVENT_SYSTEM_FOREGROUND,
+            WindowEvent::LocationChanged => EVENT_OBJECT_LOCATIONCHANGE,
+        }
+    }
+
+    fn from_raw(event: u32) -> Option<Self> {
+        match event {
+            EVENT_SYSTEM_MOVESIZEEND => Some(WindowEvent::MoveSizeEnd),
+            EVENT_OBJECT_SHOW => Some(WindowEvent::Shown),
+            EVENT_OBJECT_DESTROY => Some(WindowEvent::Destroyed),
+            EVENT_SYSTEM_FOREGROUND => Some(WindowEvent::Foreground),
+            EVENT_OBJECT_LOCATIONCHANGE => Some(WindowEvent::LocationChanged),
+            _ => None,
+        }
+    }
+
+    /// How long a window must go without this event before it's delivered.
+    /// Events that come in bursts, like a window being shown then placed or
+    /// Alt+Tab passing over several windows, are delivered once at the end.
+    fn debounce(self) -> Duration {
+        match self {
+            WindowEvent::MoveSizeEnd | WindowEvent::Destroyed => Duration::ZERO,
+            WindowEvent::Shown | WindowEvent::Foreground => Duration::from_millis(100),
+            WindowEvent::LocationChanged => Duration::from_millis(150),
+        }
+    }
+}
+
+/// Called with the window an event was for
+pub type EventHandler = fn(HWND);
+
+/// Events waiting out their debounce, at most one per event and window
+#[derive(Debug, Default)]
+struct Debouncer {
+    pending: Vec<Pending>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    event: WindowEvent,
+    hwnd: isize,
+    due: Instant,
+}
+
+impl Debouncer {
+    /// Hold `event` for `hwnd`, pushing back any held one that hasn't been
+    /// delivered yet
+    fn push(&mut self, event: WindowEvent, hwnd: isize, now: Instant) {
+        let due = now + event.debounce();
+        match self
+            .pending
+            .iter_mut()
+            .find(|pending| pending.event == event && pending.hwnd == hwnd)
+        {
+            Some(pending) => pending.due = due,
+            None => self.pending.push(Pending { event, hwnd, due }),
+        }
+    }
+
+    /// Remove and return the events whose debounce is over, in the order
+    /// they were first held
+    fn take_due(&mut self, now: Instant) -> Vec<(WindowEvent, isize)> {
+        let (due, waiting) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| pending.due <= now);
+        self.pending = waiting;
+        due.into_iter()
+            .map(|pending: Pending| (pending.event, pending.hwnd))
+            .collect()
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        self.pending.iter().map(|pending| pending.due).min()
+    }
+}
+
+// Installed hooks, one per subscribed event
+static HOOKS: Mutex<Vec<(WindowEvent, isize)>> = Mutex::new(Vec::new());
+static HANDLERS: Mutex<Vec<(WindowEvent, EventHandler)>> = Mutex::new(Vec::new());
+static DEBOUNCER: Mutex<Debouncer> = Mutex::new(Debouncer {
+    pending: Vec::new(),
+});
+// Timer that delivers debounced events, 0 when none is set
+static DISPATCH_TIMER: AtomicUsize = AtomicUsize::new(0);
+// When the dispatch timer is due to fire, None when none is set
+static DISPATCH_DUE: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Call every handler subscribed to `event`
+fn dispatch(event: WindowEvent, hwnd: HWND) {
+    // Copied out so handlers can subscribe without deadlocking
+    let handlers: Vec<EventHandler> = HANDLERS
+        .lock()
+        .map(|guard| {
+            guard
+                .iter()
+                .filter(|&&(subscribed, _)| subscribed == event)
+                .map(|&(_, handler)| handler)
+                .collect()
+        })
+        .unwrap_or_default();
+    for handler in handlers {
+        handler(hwnd);
+    }
+}
+
+/// Whether a dispatch timer due at `scheduled` has to be set again for
+/// events next due at `next`. A timer firing before the next event is due
+/// is left to run; the timer procedure schedules the rest.
+fn needs_reschedule(scheduled: Option<Instant>, next: Option<Instant>) -> bool {
+    match (scheduled, next) {
+        (Some(scheduled), Some(next)) => next < scheduled,
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// Set the dispatch timer for the next held event, if any, unless it is
+/// already set to fire by then
+fn schedule_dispatch() {
+    let next = DEBOUNCER.lock().ok().and_then(|guard| guard.next_due());
+    let Ok(mut scheduled) = DISPATCH_DUE.lock() else {
+        return;
+    };
+    if !needs_reschedule(*scheduled, next) {
+        return;
+    }
+    *scheduled = next;
+    unsafe {
+        let previous = DISPATCH_TIMER.swap(0, Ordering::SeqCst);
+        if previous != 0 {
+            let _ = KillTimer(None, previous);
+        }
+        if let Some(next) = next {
+            let delay = next.saturating_duration_since(Instant::now()).as_millis() as u32;
+            let timer = SetTimer(
+                None,
+                0,
+                delay.max(USER_TIMER_MINIMUM),
+                Some(dispatch_timer_proc),
+            );
+            DISPATCH_TIMER.store(timer, Ordering::SeqCst);
+        }
+    }
+}
+
+unsafe extern "system" fn dispatch_timer_proc(_hwnd: HWND, _msg: u32, _id: usize, _time: u32) {
+    // The timer repeats until killed; schedule_dispatch sets the next one
+    let fired = DISPATCH_TIMER.swap(0, Ordering::SeqCst);
+    if fired != 0 {
+        unsafe {
+            let _ = KillTimer(None, fired);
+        }
+    }
+    if let Ok(mut scheduled) = DISPATCH_DUE.lock() {
+        *scheduled = None;
+    }
+    let due = DEBOUNCER
+        .lock()
+        .map(|mut guard| guard.take_due(Instant::now()))
+        .unwrap_or_default();
+    for (event, hwnd) in due {
+        dispatch(event, HWND(hwnd as *mut _));
+    }
+    schedule_dispatch();
+}
+
+unsafe extern "system" fn win_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    // Only whole windows, not scrollbars, carets or other objects
+    if hwnd.is_invalid() || id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+    let Some(event) = WindowEvent::from_raw(event) else {
+        return;
+    };
+    if event.debounce().is_zero() {
+        dispatch(event, hwnd);
+        return;
+    }
+    if let Ok(mut guard) = DEBOUNCER.lock() {
+        guard.push(event, hwnd.0 as isize, Instant::now());
+    }
+    schedule_dispatch();
+}
+
+/// Call `handler` for every `event` from other processes' windows,
+/// hooking the event the first time anything subscribes to it. Events
+/// arrive through this thread's message loop.
+pub fn subscribe(event: WindowEvent, handler: EventHandler) -> windows::core::Result<()> {
+    if let Ok(mut guard) = HANDLERS.lock() {
+        guard.push((event, handler));
+    }
+    let Ok(mut hooks) = HOOKS.lock() else {
+        return Ok(());
+    };
+    if hooks.iter().any(|&(hooked, _)| hooked == event) {
+        return Ok(());
+    }
+    unsafe {
+        let hook = SetWinEventHook(
+            event.raw(),
+            event.raw(),
+            None,
+            Some(win_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        );
+        if hook.is_invalid() {
+            return Err(windows::core::Error::from_win32());
+        }
+        hooks.push((event, hook.0 as isize));
+    }
+    Ok(())
+}
+
+/// Remove every hook and subscription, dropping events still held back
+pub fn unsubscribe_all() {
+    let hooks = HOOKS
+        .lock()
+        .map(|mut guard| std::mem::take(&mut *guard))
+        .unwrap_or_default();
+    for (_, hook) in hooks {
+        unsafe {
+            let _ = UnhookWinEvent(HWINEVENTHOOK(hook as *mut _));
+        }
+    }
+    if let Ok(mut guard) = HANDLERS.lock() {
+        guard.clear();
+    }
+    if let Ok(mut guard) = DEBOUNCER.lock() {
+        guard.pending.clear();
+    }
+    schedule_dispatch();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_events_round_trip() {
+        for event in [
+            WindowEvent::MoveSizeEnd,
+            WindowEvent::Shown,
+            WindowEvent::Destroyed,
+            WindowEvent::Foreground,
+            WindowEvent::LocationChanged,
+        ] {
+            assert_eq!(WindowEvent::from_raw(event.raw()), Some(event));
+        }
+        assert_eq!(WindowEvent::from_raw(0), None);
+    }
+
+    #[test]
+    fn test_dispatch_timer_kept_unless_due_earlier() {
+        let now = Instant::now();
+        let later = now + Duration::from_millis(150);
+        assert!(needs_reschedule(None, Some(later)));
+        // A burst only pushes its event back, so the timer set stays
+        assert!(!needs_reschedule(Some(now), Some(later)));
+        assert!(!needs_reschedule(Some(later), Some(later)));
+        assert!(needs_reschedule(Some(later), Some(now)));
+        assert!(needs_reschedule(Some(later), None));
+        assert!(!needs_reschedule(None, None));
+    }
+
+    #[test]
+    fn test_debouncer_coalesces_bursts() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        debouncer.push(WindowEvent::LocationChanged, 1, start);
+        debouncer.push(WindowEvent::Shown, 1, start);
+        let later = start + Duration::from_millis(100);
+        debouncer.push(WindowEvent::LocationChanged, 1, later);
+        debouncer.push(WindowEvent::LocationChanged, 2, later);
+
+        // Shown is due first; window 1's moves were pushed back by the second
+        assert_eq!(
+            debouncer.next_due(),
+            Some(start + Duration::from_millis(100))
+        );
+        assert_eq!(debouncer.take_due(later), [(WindowEvent::Shown, 1)]);
+        assert!(debouncer
+            .take_due(later + Duration::from_millis(149))
+            .is_empty());
+        assert_eq!(
+            debouncer.take_due(later + Duration::from_millis(150)),
+            [
+                (WindowEvent::LocationChanged, 1),
+                (WindowEvent::LocationChanged, 2)
+            ]
+        );
+        assert_eq!(debouncer.next_due(), None);
+    }
+}
@@ -1,11 +1,88 @@
+use serde::{Deserialize, Serialize};
 use windows::Win32::Foundation::RECT;
 
-#[derive(Clone)]
+/// DPI at which device-independent pixels equal physical pixels
+pub const BASE_DPI: u32 = 96;
+
+/// Scale a device-independent length to physical pixels for the given DPI,
+/// rounding to the nearest pixel.
+pub fn scale_for_dpi(value: i32, dpi: u32) -> i32 {
+    let scaled = value as i64 * dpi as i64 + BASE_DPI as i64 / 2;
+    (scaled / BASE_DPI as i64) as i32
+}
+
+const NUMBER_ROW: [char; 8] = ['1', '2', '3', '4', '5', '6', '7', '8'];
+
+/// Keyboard layout whose top/home/bottom rows map to grid rows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    #[default]
+    Qwerty,
+    Dvorak,
+    Colemak,
+}
+
+impl KeyboardLayout {
+    /// First 8 keys of the top, home and bottom letter rows
+    fn letter_rows(self) -> [[char; 8]; 3] {
+        match self {
+            KeyboardLayout::Qwerty => [
+                ['Q', 'W', 'E', 'R', 'T', 'Y', 'U', 'I'],
+                ['A', 'S', 'D', 'F', 'G', 'H', 'J', 'K'],
+                ['Z', 'X', 'C', 'V', 'B', 'N', 'M', ','],
+            ],
+            KeyboardLayout::Dvorak => [
+                ['\'', ',', '.', 'P', 'Y', 'F', 'G', 'C'],
+                ['A', 'O', 'E', 'U', 'I', 'D', 'H', 'T'],
+                [';', 'Q', 'J', 'K', 'X', 'B', 'M', 'W'],
+            ],
+            KeyboardLayout::Colemak => [
+                ['Q', 'W', 'F', 'P', 'G', 'J', 'L', 'U'],
+                ['A', 'R', 'S', 'T', 'D', 'H', 'N', 'E'],
+                ['Z', 'X', 'C', 'V', 'B', 'K', 'M', ','],
+            ],
+        }
+    }
+}
+
+/// Start and end of tile `index` of `count` along one axis. Space left
+/// after the gaps and margins is shared out so tile sizes differ by at most
+/// a pixel and the last tile ends exactly one margin before `end`.
+fn tile_edges(start: i32, end: i32, count: u32, gap: i32, margin: i32, index: u32) -> (i32, i32) {
+    let (start, end, gap, margin) = (start as i64, end as i64, gap as i64, margin as i64);
+    let (count, index) = (count as i64, index as i64);
+    let available = end - start - margin * 2 - gap * (count - 1);
+    let offset = |i: i64| start + margin + gap * i + available * i / count;
+    let (first, last) = (offset(index), offset(index + 1) - gap);
+    (first as i32, last as i32)
+}
+
+/// Gap and margin along an axis `length` long with `count` tiles, shrunk
+/// in proportion as far as needed for tiles at least `min` long to fit,
+/// down to none when even that isn't enough
+fn fit_spacing(length: i32, count: u32, gap: i32, margin: i32, min: i32) -> (i32, i32) {
+    let (length, count, min) = (length as i64, count as i64, min as i64);
+    let spacing = margin as i64 * 2 + gap as i64 * (count - 1);
+    let room = (length - min * count).max(0);
+    if spacing <= room {
+        return (gap, margin);
+    }
+    let shrink = |value: i32| (value as i64 * room / spacing) as i32;
+    (shrink(gap), shrink(margin))
+}
+
+#[derive(Debug, Clone)]
 pub struct Grid {
     pub cols: u32,
     pub rows: u32,
     pub gap: i32,
+    /// Space between the outer tiles and the work area's edges
+    pub margin: i32,
+    /// Tile size the gap and margin give way to on small work areas
+    pub min_tile_size: i32,
     pub work_area: RECT,
+    pub keyboard_layout: KeyboardLayout,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,11 +97,57 @@ impl Grid {
             cols,
             rows,
             gap,
+            margin: gap,
+            min_tile_size: 0,
             work_area,
+            keyboard_layout: KeyboardLayout::default(),
+        }
+    }
+
+    /// Use an alternate keyboard layout's rows for key mapping
+    pub fn with_keyboard_layout(mut self, layout: KeyboardLayout) -> Self {
+        self.keyboard_layout = layout;
+        self
+    }
+
+    /// Inset the outer tiles by `margin` instead of the gap
+    pub fn with_margin(mut self, margin: i32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Shrink the gap and margin where they would leave tiles smaller
+    /// than `min` pixels
+    pub fn with_min_tile_size(mut self, min: i32) -> Self {
+        self.min_tile_size = min;
+        self
+    }
+
+    /// Same grid laid out over a different work area, e.g. after the
+    /// taskbar moved or auto-hid
+    pub fn with_work_area(mut self, work_area: RECT) -> Self {
+        self.work_area = work_area;
+        self
+    }
+
+    /// Keys for a grid row. For 4 rows the number row is added at the top;
+    /// otherwise grid rows start at the layout's top letter row.
+    fn key_row(&self, row: u32) -> Option<[char; 8]> {
+        if row >= self.rows {
+            return None;
+        }
+        let letters = self.keyboard_layout.letter_rows();
+        if self.rows == 4 {
+            match row {
+                0 => Some(NUMBER_ROW),
+                _ => letters.get(row as usize - 1).copied(),
+            }
+        } else {
+            letters.get(row as usize).copied()
         }
     }
 
-    /// Key layout for up to 4 rows × 8 columns:
+    /// Key layout for up to 4 rows × 8 columns (QWERTY shown):
     ///
     /// For 1-3 rows (top-left starts with Q):
     ///   Row 0: Q W E R T Y U I
@@ -41,114 +164,106 @@ impl Grid {
     pub fn key_to_tile(&self, key: char) -> Option<TilePos> {
         let key = key.to_ascii_uppercase();
 
-        // Determine row offset: for 4 rows, number keys are row 0
-        let use_number_row = self.rows == 4;
-
-        let (keyboard_row, col) = match key {
-            // Number keys row
-            '1' => (0, 0),
-            '2' => (0, 1),
-            '3' => (0, 2),
-            '4' => (0, 3),
-            '5' => (0, 4),
-            '6' => (0, 5),
-            '7' => (0, 6),
-            '8' => (0, 7),
-            // QWERTY row
-            'Q' => (1, 0),
-            'W' => (1, 1),
-            'E' => (1, 2),
-            'R' => (1, 3),
-            'T' => (1, 4),
-            'Y' => (1, 5),
-            'U' => (1, 6),
-            'I' => (1, 7),
-            // ASDF row
-            'A' => (2, 0),
-            'S' => (2, 1),
-            'D' => (2, 2),
-            'F' => (2, 3),
-            'G' => (2, 4),
-            'H' => (2, 5),
-            'J' => (2, 6),
-            'K' => (2, 7),
-            // ZXCV row
-            'Z' => (3, 0),
-            'X' => (3, 1),
-            'C' => (3, 2),
-            'V' => (3, 3),
-            'B' => (3, 4),
-            'N' => (3, 5),
-            'M' => (3, 6),
-            ',' => (3, 7),
-            _ => return None,
-        };
-
-        // Map keyboard row to grid row
-        let row = if use_number_row {
-            // 4 rows: keyboard rows 0-3 map directly to grid rows 0-3
-            keyboard_row
-        } else {
-            // 1-3 rows: keyboard rows 1-3 (QWER/ASDF/ZXCV) map to grid rows 0-2
-            if keyboard_row == 0 {
-                return None; // Number keys not used for <4 rows
-            }
-            keyboard_row - 1
-        };
-
-        if col < self.cols && row < self.rows {
-            Some(TilePos { col, row })
-        } else {
-            None
-        }
+        (0..self.rows).find_map(|row| {
+            let col = self.key_row(row)?.iter().position(|&k| k == key)? as u32;
+            (col < self.cols).then_some(TilePos { col, row })
+        })
     }
 
     /// Returns the key character for a given tile position
     pub fn tile_to_key(&self, pos: TilePos) -> Option<char> {
-        const KEYS_3ROW: [[char; 8]; 3] = [
-            ['Q', 'W', 'E', 'R', 'T', 'Y', 'U', 'I'],
-            ['A', 'S', 'D', 'F', 'G', 'H', 'J', 'K'],
-            ['Z', 'X', 'C', 'V', 'B', 'N', 'M', ','],
-        ];
-        const KEYS_4ROW: [[char; 8]; 4] = [
-            ['1', '2', '3', '4', '5', '6', '7', '8'],
-            ['Q', 'W', 'E', 'R', 'T', 'Y', 'U', 'I'],
-            ['A', 'S', 'D', 'F', 'G', 'H', 'J', 'K'],
-            ['Z', 'X', 'C', 'V', 'B', 'N', 'M', ','],
-        ];
-
-        if pos.row >= self.rows || pos.col >= self.cols {
+        if pos.col >= self.cols {
             return None;
         }
-
-        if self.rows == 4 {
-            Some(KEYS_4ROW[pos.row as usize][pos.col as usize])
-        } else {
-            Some(KEYS_3ROW[pos.row as usize][pos.col as usize])
-        }
+        self.key_row(pos.row)?.get(pos.col as usize).copied()
     }
 
-    pub fn tile_rect(&self, pos: TilePos) -> RECT {
-        let work_width = self.work_area.right - self.work_area.left;
-        let work_height = self.work_area.bottom - self.work_area.top;
+    /// Gap and margin between columns, after giving way to the minimum
+    /// tile size
+    pub fn col_spacing(&self) -> (i32, i32) {
+        let width = self.work_area.right - self.work_area.left;
+        fit_spacing(width, self.cols, self.gap, self.margin, self.min_tile_size)
+    }
 
-        let total_gap_x = self.gap * (self.cols as i32 + 1);
-        let total_gap_y = self.gap * (self.rows as i32 + 1);
+    /// Gap and margin between rows, as `col_spacing`
+    pub fn row_spacing(&self) -> (i32, i32) {
+        let height = self.work_area.bottom - self.work_area.top;
+        fit_spacing(height, self.rows, self.gap, self.margin, self.min_tile_size)
+    }
 
-        let tile_width = (work_width - total_gap_x) / self.cols as i32;
-        let tile_height = (work_height - total_gap_y) / self.rows as i32;
+    /// Width and height of the smallest tile at the configured gap and
+    /// margin, before giving way to the minimum; negative when the
+    /// spacing alone doesn't fit
+    pub fn natural_tile_size(&self) -> (i32, i32) {
+        let size = |length: i32, count: u32| {
+            let count = count as i32;
+            (length - self.margin * 2 - self.gap * (count - 1)).div_euclid(count)
+        };
+        (
+            size(self.work_area.right - self.work_area.left, self.cols),
+            size(self.work_area.bottom - self.work_area.top, self.rows),
+        )
+    }
 
-        let left = self.work_area.left + self.gap + (pos.col as i32 * (tile_width + self.gap));
-        let top = self.work_area.top + self.gap + (pos.row as i32 * (tile_height + self.gap));
+    pub fn tile_rect(&self, pos: TilePos) -> RECT {
+        let (col_gap, col_margin) = self.col_spacing();
+        let (row_gap, row_margin) = self.row_spacing();
+        let (left, right) = tile_edges(
+            self.work_area.left,
+            self.work_area.right,
+            self.cols,
+            col_gap,
+            col_margin,
+            pos.col,
+        );
+        let (top, bottom) = tile_edges(
+            self.work_area.top,
+            self.work_area.bottom,
+            self.rows,
+            row_gap,
+            row_margin,
+            pos.row,
+        );
 
         RECT {
             left,
             top,
-            right: left + tile_width,
-            bottom: top + tile_height,
+            right,
+            bottom,
         }
     }
 
+    /// Closest grid span to an arbitrary rect: each edge snaps to the
+    /// nearest matching tile edge, ties going to the earlier tile. The span
+    /// is never empty, even for rects outside the work area.
+    pub fn rect_to_span(&self, rect: RECT) -> (TilePos, TilePos) {
+        // Index of the edge nearest `target`, from `from` onwards
+        fn nearest(from: u32, count: u32, target: i32, edge: impl Fn(u32) -> i32) -> u32 {
+            (from..count)
+                .min_by_key(|&i| (edge(i) - target).abs())
+                .unwrap_or(from)
+        }
+
+        let col_edges = |col| self.tile_rect(TilePos { col, row: 0 });
+        let row_edges = |row| self.tile_rect(TilePos { col: 0, row });
+
+        let left = nearest(0, self.cols, rect.left, |c| col_edges(c).left);
+        let right = nearest(left, self.cols, rect.right, |c| col_edges(c).right);
+        let top = nearest(0, self.rows, rect.top, |r| row_edges(r).top);
+        let bottom = nearest(top, self.rows, rect.bottom, |r| row_edges(r).bottom);
+
+        (
+            TilePos {
+                col: left,
+                row: top,
+            },
+            TilePos {
+                col: right,
+                row: bottom,
+            },
+        )
+    }
+
     pub fn combine_tiles(&self, pos1: TilePos, pos2: TilePos) -> RECT {
         let rect1 = self.tile_rect(pos1);
         let rect2 = self.tile_rect(pos2);
@@ -160,6 +275,15 @@ impl Grid {
             bottom: rect1.bottom.max(rect2.bottom),
         }
     }
+
+    /// Whether `rect` is the span of every tile, i.e. the whole grid
+    pub fn is_full_grid(&self, rect: RECT) -> bool {
+        let last = TilePos {
+            col: self.cols - 1,
+            row: self.rows - 1,
+        };
+        rect == self.combine_tiles(TilePos { col: 0, row: 0 }, last)
+    }
 }
 
 #[cfg(test)]
@@ -239,6 +363,45 @@ mod tests {
         assert_eq!(grid.tile_to_key(TilePos { col: 7, row: 3 }), Some(','));
     }
 
+    #[test]
+    fn test_key_to_tile_dvorak() {
+        let grid =
+            Grid::new(8, 3, 10, test_work_area()).with_keyboard_layout(KeyboardLayout::Dvorak);
+
+        assert_eq!(grid.key_to_tile('\''), Some(TilePos { col: 0, row: 0 }));
+        assert_eq!(grid.key_to_tile('p'), Some(TilePos { col: 3, row: 0 }));
+        assert_eq!(grid.key_to_tile('a'), Some(TilePos { col: 0, row: 1 }));
+        assert_eq!(grid.key_to_tile('u'), Some(TilePos { col: 3, row: 1 }));
+        assert_eq!(grid.key_to_tile(';'), Some(TilePos { col: 0, row: 2 }));
+        // QWERTY positions no longer apply
+        assert_eq!(grid.key_to_tile('r'), None);
+        assert_eq!(grid.tile_to_key(TilePos { col: 7, row: 1 }), Some('T'));
+    }
+
+    #[test]
+    fn test_key_to_tile_colemak_4row() {
+        let grid =
+            Grid::new(4, 4, 10, test_work_area()).with_keyboard_layout(KeyboardLayout::Colemak);
+
+        assert_eq!(grid.key_to_tile('1'), Some(TilePos { col: 0, row: 0 }));
+        assert_eq!(grid.key_to_tile('f'), Some(TilePos { col: 2, row: 1 }));
+        assert_eq!(grid.key_to_tile('t'), Some(TilePos { col: 3, row: 2 }));
+        assert_eq!(grid.key_to_tile('v'), Some(TilePos { col: 3, row: 3 }));
+        // Col 4+ out of bounds
+        assert_eq!(grid.key_to_tile('d'), None);
+        assert_eq!(grid.tile_to_key(TilePos { col: 1, row: 2 }), Some('R'));
+    }
+
+    #[test]
+    fn test_keyboard_layout_config() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            layout: KeyboardLayout,
+        }
+        let parsed: Wrapper = toml::from_str("layout = \"colemak\"").unwrap();
+        assert_eq!(parsed.layout, KeyboardLayout::Colemak);
+    }
+
     #[test]
     fn test_tile_rect() {
         let grid = Grid::new(4, 2, 10, test_work_area());
@@ -248,6 +411,17 @@ mod tests {
         assert_eq!(rect.top, 10);
     }
 
+    #[test]
+    fn test_scale_for_dpi() {
+        assert_eq!(scale_for_dpi(10, 96), 10);
+        assert_eq!(scale_for_dpi(10, 144), 15);
+        assert_eq!(scale_for_dpi(10, 192), 20);
+        assert_eq!(scale_for_dpi(48, 120), 60);
+        // Rounds to nearest pixel
+        assert_eq!(scale_for_dpi(5, 120), 6);
+        assert_eq!(scale_for_dpi(0, 192), 0);
+    }
+
     #[test]
     fn test_combine_tiles() {
         let grid = Grid::new(4, 2, 10, test_work_area());
@@ -265,4 +439,311 @@ mod tests {
         assert_eq!(combined.right, rect2.right);
         assert_eq!(combined.bottom, rect2.bottom);
     }
+
+    #[test]
+    fn test_is_full_grid() {
+        let grid = Grid::new(4, 2, 10, test_work_area());
+
+        let whole = grid.combine_tiles(TilePos { col: 0, row: 0 }, TilePos { col: 3, row: 1 });
+        assert!(grid.is_full_grid(whole));
+        let most = grid.combine_tiles(TilePos { col: 0, row: 0 }, TilePos { col: 2, row: 1 });
+        assert!(!grid.is_full_grid(most));
+        assert!(!grid.is_full_grid(test_work_area()));
+    }
+
+    fn span(c1: u32, r1: u32, c2: u32, r2: u32) -> (TilePos, TilePos) {
+        (TilePos { col: c1, row: r1 }, TilePos { col: c2, row: r2 })
+    }
+
+    #[test]
+    fn test_rect_to_span_round_trips_tiles() {
+        let grid = Grid::new(6, 3, 10, test_work_area());
+        for col in 0..6 {
+            for row in 0..3 {
+                let pos = TilePos { col, row };
+                assert_eq!(grid.rect_to_span(grid.tile_rect(pos)), (pos, pos));
+            }
+        }
+        let whole = span(0, 0, 5, 2);
+        assert_eq!(
+            grid.rect_to_span(grid.combine_tiles(whole.0, whole.1)),
+            whole
+        );
+        assert_eq!(grid.rect_to_span(test_work_area()), whole);
+    }
+
+    #[test]
+    fn test_rect_to_span_gaps_and_ties() {
+        // Columns 10-477, 487-955, ...: edges in a gap snap to the tile
+        // edge on the same side
+        let grid = Grid::new(4, 2, 10, test_work_area());
+        let rect = |left, right| RECT {
+            left,
+            top: 10,
+            right,
+            bottom: 535,
+        };
+        assert_eq!(grid.rect_to_span(rect(480, 950)), span(1, 0, 1, 0));
+        assert_eq!(grid.rect_to_span(rect(5, 482)), span(0, 0, 0, 0));
+        assert_eq!(grid.rect_to_span(rect(248, 954)), span(0, 0, 1, 0));
+        assert_eq!(grid.rect_to_span(rect(249, 954)), span(1, 0, 1, 0));
+
+        // Exactly halfway between two left edges (0 and 480) rounds to the earlier
+        let grid = Grid::new(4, 1, 0, test_work_area());
+        assert_eq!(grid.rect_to_span(rect(240, 960)), span(0, 0, 1, 0));
+        assert_eq!(grid.rect_to_span(rect(241, 960)), span(1, 0, 1, 0));
+    }
+
+    #[test]
+    fn test_margin() {
+        // Flush with the work area's edges, still 10px between tiles
+        let grid = Grid::new(4, 1, 10, test_work_area()).with_margin(0);
+        assert_eq!(grid.tile_rect(TilePos { col: 0, row: 0 }).left, 0);
+        assert_eq!(grid.tile_rect(TilePos { col: 0, row: 0 }).right, 472);
+        assert_eq!(grid.tile_rect(TilePos { col: 1, row: 0 }).left, 482);
+        assert_eq!(grid.tile_rect(TilePos { col: 3, row: 0 }).right, 1920);
+        assert_eq!(grid.tile_rect(TilePos { col: 3, row: 0 }).bottom, 1080);
+    }
+
+    #[test]
+    fn test_min_tile_size() {
+        let work_area = RECT {
+            left: 0,
+            top: 0,
+            right: 600,
+            bottom: 300,
+        };
+        // 8 columns with 50px gaps leave 18px tiles
+        let grid = Grid::new(8, 1, 50, work_area);
+        assert_eq!(grid.natural_tile_size(), (18, 200));
+
+        // The gap and margin shrink instead, to 13px
+        let grid = grid.with_min_tile_size(60);
+        assert_eq!(grid.col_spacing(), (13, 13));
+        assert_eq!(grid.row_spacing(), (50, 50));
+        for col in 0..8 {
+            let tile = grid.tile_rect(TilePos { col, row: 0 });
+            assert!(tile.right - tile.left >= 60);
+        }
+
+        // Too narrow for the minimum at all: no spacing, tiles never negative
+        let grid = Grid::new(8, 1, 50, work_area).with_work_area(RECT {
+            right: 100,
+            ..work_area
+        });
+        assert_eq!(grid.natural_tile_size(), (-44, 200));
+        let grid = grid.with_min_tile_size(60);
+        assert_eq!(grid.col_spacing(), (0, 0));
+        for col in 0..8 {
+            let tile = grid.tile_rect(TilePos { col, row: 0 });
+            assert!(tile.right - tile.left >= 12);
+        }
+    }
+
+    #[test]
+    fn test_rect_to_span_outside_and_negative() {
+        let grid = Grid::new(
+            2,
+            2,
+            0,
+            RECT {
+                left: -1920,
+                top: -200,
+                right: 0,
+                bottom: 880,
+            },
+        );
+        // Left half of a monitor left of the primary
+        let rect = RECT {
+            left: -1930,
+            top: -250,
+            right: -950,
+            bottom: 900,
+        };
+        assert_eq!(grid.rect_to_span(rect), span(0, 0, 0, 1));
+
+        // Entirely off to the right and inverted: still a one-tile span
+        let rect = RECT {
+            left: 5000,
+            top: 5000,
+            right: 4000,
+            bottom: 4000,
+        };
+        assert_eq!(grid.rect_to_span(rect), span(1, 1, 1, 1));
+    }
+
+    #[test]
+    fn test_rect_to_span() {
+        // Column edges 10-477, 487-955, 965-1432, 1442-1910; rows 10-535, 545-1070
+        let grid = Grid::new(4, 2, 10, test_work_area());
+
+        let rect = RECT {
+            left: 500,
+            top: 20,
+            right: 1400,
+            bottom: 600,
+        };
+        assert_eq!(
+            grid.rect_to_span(rect),
+            (TilePos { col: 1, row: 0 }, TilePos { col: 2, row: 0 })
+        );
+
+        // Edges never cross: a sliver at the far right is one tile wide
+        let rect = RECT {
+            left: 1900,
+            top: 0,
+            right: 1905,
+            bottom: 1080,
+        };
+        assert_eq!(
+            grid.rect_to_span(rect),
+            (TilePos { col: 3, row: 0 }, TilePos { col: 3, row: 1 })
+        );
+    }
+
+    #[test]
+    fn test_tiles_cover_work_area() {
+        // 1880px of tiles across 3 columns doesn't divide evenly
+        let grid = Grid::new(3, 3, 10, test_work_area());
+        let whole = grid.combine_tiles(TilePos { col: 0, row: 0 }, TilePos { col: 2, row: 2 });
+        assert_eq!(
+            whole,
+            RECT {
+                left: 10,
+                top: 10,
+                right: 1910,
+                bottom: 1070,
+            }
+        );
+
+        // Neighbours are exactly one gap apart and sizes differ by at most 1px
+        let tile = |col| grid.tile_rect(TilePos { col, row: 0 });
+        let widths: Vec<i32> = (0..3).map(|col| tile(col).right - tile(col).left).collect();
+        assert!(widths.iter().max().unwrap() - widths.iter().min().unwrap() <= 1);
+        for col in 0..2 {
+            assert_eq!(tile(col + 1).left - tile(col).right, 10);
+        }
+    }
+
+    mod props {
+        use super::*;
+        use proptest::prelude::*;
+
+        /// Grids anywhere on the virtual desktop, with tiles at least a
+        /// pixel in each direction
+        fn grids() -> impl Strategy<Value = Grid> {
+            (
+                (1u32..=12, 1u32..=8, 0i32..=40),
+                (-5000i32..5000, -3000i32..3000),
+                (0i32..4000, 0i32..3000),
+            )
+                .prop_map(|((cols, rows, gap), (left, top), (extra_w, extra_h))| {
+                    let width = gap * (cols as i32 + 1) + cols as i32 + extra_w;
+                    let height = gap * (rows as i32 + 1) + rows as i32 + extra_h;
+                    let work_area = RECT {
+                        left,
+                        top,
+                        right: left + width,
+                        bottom: top + height,
+                    };
+                    Grid::new(cols, rows, gap, work_area)
+                })
+        }
+
+        fn all_tiles(grid: &Grid) -> Vec<TilePos> {
+            (0..grid.rows)
+                .flat_map(|row| (0..grid.cols).map(move |col| TilePos { col, row }))
+                .collect()
+        }
+
+        fn contains(outer: RECT, inner: RECT) -> bool {
+            inner.left >= outer.left
+                && inner.top >= outer.top
+                && inner.right <= outer.right
+                && inner.bottom <= outer.bottom
+        }
+
+        proptest! {
+            #[test]
+            fn tiles_never_overlap(grid in grids()) {
+                let tiles = all_tiles(&grid);
+                for (i, &a) in tiles.iter().enumerate() {
+                    for &b in &tiles[i + 1..] {
+                        let (ra, rb) = (grid.tile_rect(a), grid.tile_rect(b));
+                        prop_assert!(
+                            ra.right <= rb.left
+                                || rb.right <= ra.left
+                                || ra.bottom <= rb.top
+                                || rb.bottom <= ra.top,
+                            "{:?} and {:?} overlap",
+                            a,
+                            b
+                        );
+                    }
+                }
+            }
+
+            #[test]
+            fn combined_rects_stay_in_work_area(
+                grid in grids(),
+                a in (0u32..12, 0u32..8),
+                b in (0u32..12, 0u32..8),
+            ) {
+                let a = TilePos { col: a.0 % grid.cols, row: a.1 % grid.rows };
+                let b = TilePos { col: b.0 % grid.cols, row: b.1 % grid.rows };
+                let combined = grid.combine_tiles(a, b);
+                prop_assert!(combined.left < combined.right && combined.top < combined.bottom);
+                prop_assert!(contains(grid.work_area, combined));
+            }
+
+            #[test]
+            fn rect_to_span_inverts_tile_rect(
+                grid in grids(),
+                a in (0u32..12, 0u32..8),
+                b in (0u32..12, 0u32..8),
+            ) {
+                for pos in all_tiles(&grid) {
+                    prop_assert_eq!(grid.rect_to_span(grid.tile_rect(pos)), (pos, pos));
+                }
+                let first = TilePos {
+                    col: (a.0 % grid.cols).min(b.0 % grid.cols),
+                    row: (a.1 % grid.rows).min(b.1 % grid.rows),
+                };
+                let last = TilePos {
+                    col: (a.0 % grid.cols).max(b.0 % grid.cols),
+                    row: (a.1 % grid.rows).max(b.1 % grid.rows),
+                };
+                prop_assert_eq!(
+                    grid.rect_to_span(grid.combine_tiles(first, last)),
+                    (first, last)
+                );
+            }
+
+            #[test]
+            fn gaps_are_exact(grid in grids()) {
+                let work = grid.work_area;
+                let corner = grid.tile_rect(TilePos { col: 0, row: 0 });
+                let far = grid.tile_rect(TilePos { col: grid.cols - 1, row: grid.rows - 1 });
+                prop_assert_eq!(corner.left - work.left, grid.gap);
+                prop_assert_eq!(corner.top - work.top, grid.gap);
+                prop_assert_eq!(work.right - far.right, grid.gap);
+                prop_assert_eq!(work.bottom - far.bottom, grid.gap);
+
+                for col in 1..grid.cols {
+                    let prev = grid.tile_rect(TilePos { col: col - 1, row: 0 });
+                    let next = grid.tile_rect(TilePos { col, row: 0 });
+                    prop_assert_eq!(next.left - prev.right, grid.gap);
+                    let (w1, w2) = (prev.right - prev.left, next.right - next.left);
+                    prop_assert!((w1 - w2).abs() <= 1);
+                }
+                for row in 1..grid.rows {
+                    let prev = grid.tile_rect(TilePos { col: 0, row: row - 1 });
+                    let next = grid.tile_rect(TilePos { col: 0, row });
+                    prop_assert_eq!(next.top - prev.bottom, grid.gap);
+                    let (h1, h2) = (prev.bottom - prev.top, next.bottom - next.top);
+                    prop_assert!((h1 - h2).abs() <= 1);
+                }
+            }
+        }
+    }
 }
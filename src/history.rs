@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::grid::TilePos;
+
+/// One overlay placement, as logged when `[history] enabled` is set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacementRecord {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Executable name, e.g. "notepad.exe"
+    pub exe: String,
+    /// Hash of the window title; titles can name private documents, so
+    /// only whether two placements were the same window is kept
+    pub title_hash: String,
+    /// 1-based, in monitor enumeration order
+    pub monitor: usize,
+    /// Spoken-style description, e.g. "left half"
+    pub region: String,
+    pub first_col: u32,
+    pub first_row: u32,
+    pub last_col: u32,
+    pub last_row: u32,
+    pub grid_cols: u32,
+    pub grid_rows: u32,
+}
+
+const CSV_HEADER: &str = "timestamp,exe,title_hash,monitor,region,first_col,first_row,\
+                          last_col,last_row,grid_cols,grid_rows";
+
+impl PlacementRecord {
+    pub fn new(
+        exe: &str,
+        title: &str,
+        monitor: usize,
+        region: String,
+        (first, last): (TilePos, TilePos),
+        (grid_cols, grid_rows): (u32, u32),
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Self {
+            timestamp,
+            exe: exe.to_string(),
+            title_hash: title_hash(title),
+            monitor,
+            region,
+            first_col: first.col,
+            first_row: first.row,
+            last_col: last.col,
+            last_row: last.row,
+            grid_cols,
+            grid_rows,
+        }
+    }
+
+    fn to_csv_line(&self) -> String {
+        [
+            self.timestamp.to_string(),
+            csv_field(&self.exe),
+            self.title_hash.clone(),
+            self.monitor.to_string(),
+            csv_field(&self.region),
+            self.first_col.to_string(),
+            self.first_row.to_string(),
+            self.last_col.to_string(),
+            self.last_row.to_string(),
+            self.grid_cols.to_string(),
+            self.grid_rows.to_string(),
+        ]
+        .join(",")
+    }
+}
+
+/// FNV-1a, which unlike std's hasher is stable across builds, so hashes
+/// from different runs can be compared
+fn title_hash(title: &str) -> String {
+    let hash = title.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Quote a CSV field if it holds a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(records: &[PlacementRecord]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for record in records {
+        csv.push_str(&record.to_csv_line());
+        csv.push('\n');
+    }
+    csv
+}
+
+/// The log: one JSON record per line, so appending never rewrites it
+fn log_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|p| p.join(".tactile-win-history.jsonl"))
+}
+
+pub fn append(record: &PlacementRecord) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = log_path() else {
+        return Ok(());
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Every logged placement, skipping lines that don't parse
+pub fn load() -> Vec<PlacementRecord> {
+    log_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| parse_log(&contents))
+        .unwrap_or_default()
+}
+
+fn parse_log(contents: &str) -> Vec<PlacementRecord> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Write `records` into `dir` as placement-history.csv and
+/// placement-history.json, returning the CSV's path
+pub fn export(
+    records: &[PlacementRecord],
+    dir: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let csv_path = dir.join("placement-history.csv");
+    fs::write(&csv_path, to_csv(records))?;
+    fs::write(
+        dir.join("placement-history.json"),
+        serde_json::to_string_pretty(records)?,
+    )?;
+    Ok(csv_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(exe: &str, region: &str) -> PlacementRecord {
+        let first = TilePos { col: 0, row: 0 };
+        let last = TilePos { col: 1, row: 1 };
+        PlacementRecord {
+            timestamp: 1_700_000_000,
+            ..PlacementRecord::new(
+                exe,
+                "Notes.txt",
+                2,
+                region.to_string(),
+                (first, last),
+                (4, 2),
+            )
+        }
+    }
+
+    #[test]
+    fn test_title_hash() {
+        // Published FNV-1a 64 test vectors
+        assert_eq!(title_hash(""), "cbf29ce484222325");
+        assert_eq!(title_hash("a"), "af63dc4c8601ec8c");
+        assert_ne!(title_hash("Notes.txt"), title_hash("notes.txt"));
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let records = [
+            record("notepad.exe", "left half"),
+            record("my,app.exe", "say \"hi\""),
+        ];
+        let csv = to_csv(&records);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "1700000000,notepad.exe,{},2,left half,0,0,1,1,4,2",
+                title_hash("Notes.txt")
+            )
+        );
+        assert!(lines[2].starts_with("1700000000,\"my,app.exe\","));
+        assert!(lines[2].contains(",\"say \"\"hi\"\"\","));
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let records = vec![
+            record("notepad.exe", "left half"),
+            record("code.exe", "full screen"),
+        ];
+        let log: String = records
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap() + "\n")
+            .chain(std::iter::once("not json\n".to_string()))
+            .collect();
+        assert_eq!(parse_log(&log), records);
+    }
+}
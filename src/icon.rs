@@ -0,0 +1,180 @@
+use std::sync::OnceLock;
+use windows::Win32::Graphics::Gdi::{CreateBitmap, DeleteObject};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateIconIndirect, GetSystemMetrics, HICON, ICONINFO, SM_CXICON, SM_CXSMICON,
+};
+
+/// What the tray icon tells the user about the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconState {
+    Active,
+    /// The keyboard hook is paused, e.g. while the session is locked
+    Paused,
+    /// The hotkey or keyboard hook couldn't be set up; the log says why
+    Error,
+}
+
+impl IconState {
+    /// Tile color, as 0xRRGGBB
+    fn color(self) -> u32 {
+        match self {
+            IconState::Active => 0x2F80ED,
+            IconState::Paused => 0x8A8A8A,
+            IconState::Error => 0xD93025,
+        }
+    }
+
+    /// Said after the app's name in the tray tooltip
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            IconState::Active => None,
+            IconState::Paused => Some("paused"),
+            IconState::Error => Some("not working, see the log"),
+        }
+    }
+}
+
+/// `color` halfway to white
+fn lighten(color: u32) -> u32 {
+    let channel = |shift: u32| {
+        let c = (color >> shift) & 0xFF;
+        (c + (0xFF - c) / 2) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+/// Outline of the badge
+const BADGE_EDGE: u32 = 0x202020;
+
+/// Pixels of the grid glyph, `size` square and top row first, as
+/// 0xAARRGGBB: a 2x2 grid of tiles in `color` with the top left one
+/// lit, as if selected, on a transparent background. With `badge`, a
+/// white dot covers the bottom right corner.
+fn glyph(size: u32, color: u32, badge: bool) -> Vec<u32> {
+    let gap = (size / 8).max(1);
+    let edge = (size / 16).max(1);
+    let tile = (size.saturating_sub(gap + edge * 2) / 2).max(1);
+    let margin = size.saturating_sub(tile * 2 + gap) / 2;
+    // Which tile, if any, a row or column of pixels falls in
+    let cell = |p: u32| {
+        let p = p.checked_sub(margin)?;
+        if p < tile {
+            Some(0)
+        } else if p >= tile + gap && p < tile * 2 + gap {
+            Some(1)
+        } else {
+            None
+        }
+    };
+    let radius = (size / 4).max(2) as f32;
+    // How far a pixel's center is from the badge's
+    let from_badge = |p: u32| p as f32 + 0.5 - (size as f32 - radius);
+    let mut pixels = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let distance = from_badge(x).hypot(from_badge(y));
+            if badge && distance <= radius {
+                pixels.push(if distance <= radius - edge as f32 {
+                    0xFFFF_FFFF
+                } else {
+                    0xFF00_0000 | BADGE_EDGE
+                });
+                continue;
+            }
+            pixels.push(match (cell(x), cell(y)) {
+                (Some(0), Some(0)) => 0xFF00_0000 | lighten(color),
+                (Some(_), Some(_)) => 0xFF00_0000 | color,
+                _ => 0,
+            });
+        }
+    }
+    pixels
+}
+
+/// Draw the grid glyph into a `size` pixel square icon, badged with
+/// `badge`, for the owner to DestroyIcon
+pub fn create_icon(size: i32, state: IconState, badge: bool) -> windows::core::Result<HICON> {
+    let pixels = glyph(size as u32, state.color(), badge);
+    // Every pixel of the mask is 0, leaving transparency to the alpha
+    // channel; rows are padded to a 16-bit boundary
+    let mask_bits = vec![0u8; (size as usize).div_ceil(16) * 2 * size as usize];
+    unsafe {
+        let color = CreateBitmap(size, size, 1, 32, Some(pixels.as_ptr() as *const _));
+        let mask = CreateBitmap(size, size, 1, 1, Some(mask_bits.as_ptr() as *const _));
+        let info = ICONINFO {
+            fIcon: true.into(),
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let icon = CreateIconIndirect(&info);
+        let _ = DeleteObject(color.into());
+        let _ = DeleteObject(mask.into());
+        icon
+    }
+}
+
+/// Icon for the tray, at the small icon size, badged while the overlay
+/// is open
+pub fn tray_icon(state: IconState, overlay_open: bool) -> windows::core::Result<HICON> {
+    let size = unsafe { GetSystemMetrics(SM_CXSMICON) };
+    create_icon(size, state, overlay_open)
+}
+
+/// Icon for the app's own windows, at the large icon size; drawn once and
+/// shared, so not to be destroyed
+pub fn window_icon() -> HICON {
+    static ICON: OnceLock<isize> = OnceLock::new();
+    let icon = ICON.get_or_init(|| {
+        let size = unsafe { GetSystemMetrics(SM_CXICON) };
+        create_icon(size, IconState::Active, false).map_or(0, |icon| icon.0 as isize)
+    });
+    HICON(*icon as *mut _)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyph() {
+        for size in [16, 20, 24, 32, 48, 64] {
+            let pixels = glyph(size, IconState::Active.color(), false);
+            assert_eq!(pixels.len(), (size * size) as usize);
+            let at = |x: u32, y: u32| pixels[(y * size + x) as usize];
+            // Transparent margin around the tiles and gap between them
+            assert_eq!(at(0, 0), 0, "{}px corner", size);
+            assert_eq!(at(size / 2, size / 2), 0, "{}px center", size);
+            // The lit tile and an unlit one
+            assert_eq!(at(size / 4, size / 4), 0xFF97BFF6);
+            assert_eq!(at(size * 3 / 4, size * 3 / 4), 0xFF2F80ED);
+        }
+    }
+
+    #[test]
+    fn test_badge() {
+        for size in [16, 20, 24, 32, 48, 64] {
+            let plain = glyph(size, IconState::Active.color(), false);
+            let badged = glyph(size, IconState::Active.color(), true);
+            // The middle of the badge, over the bottom right tile
+            let at = size - size / 4;
+            let corner = (at * size + at) as usize;
+            assert_eq!(plain[corner], 0xFF2F80ED, "{}px", size);
+            assert_eq!(badged[corner], 0xFFFFFFFF, "{}px", size);
+            // The rest of the glyph is as it was
+            assert_eq!(plain[..size as usize], badged[..size as usize]);
+        }
+    }
+
+    #[test]
+    fn test_states_look_different() {
+        let states = [IconState::Active, IconState::Paused, IconState::Error];
+        let colors: Vec<u32> = states.iter().map(|state| state.color()).collect();
+        assert!(colors
+            .iter()
+            .enumerate()
+            .all(|(i, c)| !colors[..i].contains(c)));
+        assert_eq!(IconState::Active.label(), None);
+    }
+}
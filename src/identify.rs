@@ -0,0 +1,245 @@
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateFontW, CreateSolidBrush, DeleteObject, DrawTextW, EndPaint, FillRect,
+    SelectObject, SetBkMode, SetTextColor, CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET, DEFAULT_QUALITY,
+    DRAW_TEXT_FORMAT, DT_BOTTOM, DT_CENTER, DT_SINGLELINE, DT_VCENTER, HDC, OUT_DEFAULT_PRECIS,
+    PAINTSTRUCT, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetClientRect, RegisterClassW,
+    SetLayeredWindowAttributes, SetTimer, ShowWindow, LWA_ALPHA, SW_SHOWNOACTIVATE, WM_DESTROY,
+    WM_LBUTTONUP, WM_PAINT, WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+    WS_EX_TOPMOST, WS_POPUP,
+};
+
+use crate::log::{error, info};
+use crate::window::{get_all_monitors, get_monitor_bounds, get_monitor_device};
+
+const IDENTIFY_CLASS_NAME: PCWSTR = w!("TactileWinIdentify");
+
+const CLOSE_TIMER_ID: usize = 1;
+/// How long the numbers stay up, unless clicked away sooner
+const SHOW_MS: u32 = 3000;
+
+// The overlay's default colors and opacity
+const BACKGROUND_COLOR: u32 = 0x00302020;
+const TEXT_COLOR: u32 = 0x00FFFFFF;
+const ALPHA: u8 = 230;
+
+/// What one identify window shows: its monitor's number, counted as Set
+/// Up Monitors counts them, and display device name
+struct Label {
+    hwnd: isize,
+    number: usize,
+    device: String,
+}
+
+static LABELS: Mutex<Vec<Label>> = Mutex::new(Vec::new());
+
+/// A square a third of the monitor's shorter side, in its middle
+fn label_rect(bounds: RECT) -> RECT {
+    let (width, height) = (bounds.right - bounds.left, bounds.bottom - bounds.top);
+    let size = width.min(height) / 3;
+    let left = bounds.left + (width - size) / 2;
+    let top = bounds.top + (height - size) / 2;
+    RECT {
+        left,
+        top,
+        right: left + size,
+        bottom: top + size,
+    }
+}
+
+/// Draw `text` in a bold font `height` pixels tall
+unsafe fn draw_text(hdc: HDC, text: &str, height: i32, rect: &mut RECT, format: DRAW_TEXT_FORMAT) {
+    unsafe {
+        let font = CreateFontW(
+            height,
+            0,
+            0,
+            0,
+            700, // Bold
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            DEFAULT_QUALITY,
+            0,
+            w!("Segoe UI"),
+        );
+        let old_font = SelectObject(hdc, font.into());
+        let mut text: Vec<u16> = text.encode_utf16().collect();
+        let _ = DrawTextW(hdc, &mut text, rect, DT_CENTER | DT_SINGLELINE | format);
+        SelectObject(hdc, old_font);
+        let _ = DeleteObject(font.into());
+    }
+}
+
+fn paint(hwnd: HWND) {
+    let label = LABELS.lock().ok().and_then(|labels| {
+        labels
+            .iter()
+            .find(|label| label.hwnd == hwnd.0 as isize)
+            .map(|label| (label.number, label.device.clone()))
+    });
+    unsafe {
+        let mut ps = PAINTSTRUCT::default();
+        let hdc = BeginPaint(hwnd, &mut ps);
+        let mut client = RECT::default();
+        let _ = GetClientRect(hwnd, &mut client);
+        let brush = CreateSolidBrush(COLORREF(BACKGROUND_COLOR));
+        FillRect(hdc, &client, brush);
+        let _ = DeleteObject(brush.into());
+
+        if let Some((number, device)) = label {
+            let size = client.bottom;
+            SetBkMode(hdc, TRANSPARENT);
+            SetTextColor(hdc, COLORREF(TEXT_COLOR));
+            let mut number_rect = client;
+            draw_text(
+                hdc,
+                &number.to_string(),
+                size * 3 / 5,
+                &mut number_rect,
+                DT_VCENTER,
+            );
+            let mut device_rect = RECT {
+                bottom: client.bottom - size / 16,
+                ..client
+            };
+            draw_text(hdc, &device, size / 10, &mut device_rect, DT_BOTTOM);
+        }
+        let _ = EndPaint(hwnd, &ps);
+    }
+}
+
+unsafe extern "system" fn identify_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_PAINT => {
+                paint(hwnd);
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == CLOSE_TIMER_ID => {
+                dismiss();
+                LRESULT(0)
+            }
+            WM_LBUTTONUP => {
+                dismiss();
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                if let Ok(mut labels) = LABELS.lock() {
+                    labels.retain(|label| label.hwnd != hwnd.0 as isize);
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+/// Close the identify windows, if they're up
+fn dismiss() {
+    // Taken first, as each window removes itself while being destroyed
+    let labels = LABELS
+        .lock()
+        .map(|mut labels| std::mem::take(&mut *labels))
+        .unwrap_or_default();
+    for label in labels {
+        unsafe {
+            let _ = DestroyWindow(HWND(label.hwnd as *mut _));
+        }
+    }
+}
+
+/// Show each monitor's number and device name on it for a few seconds,
+/// like the Identify button in Windows' display settings, to tell which
+/// screen is which when writing per-monitor config
+pub fn identify_monitors() {
+    dismiss();
+    unsafe {
+        let Ok(hinstance) = GetModuleHandleW(None) else {
+            return;
+        };
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(identify_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: IDENTIFY_CLASS_NAME,
+            ..Default::default()
+        };
+        let _ = RegisterClassW(&wc);
+
+        let monitors: Vec<(String, RECT)> = get_all_monitors()
+            .into_iter()
+            .filter_map(|monitor| {
+                Some((get_monitor_device(monitor)?, get_monitor_bounds(monitor)?))
+            })
+            .collect();
+        info!("Identifying {} monitors", monitors.len());
+        for (index, (device, bounds)) in monitors.into_iter().enumerate() {
+            let rect = label_rect(bounds);
+            let hwnd = match CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                IDENTIFY_CLASS_NAME,
+                w!("Identify Monitors"),
+                WS_POPUP,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                None,
+                None,
+                Some(hinstance.into()),
+                None,
+            ) {
+                Ok(hwnd) => hwnd,
+                Err(e) => {
+                    error!("Failed to create the identify window for {}: {}", device, e);
+                    continue;
+                }
+            };
+            if let Ok(mut labels) = LABELS.lock() {
+                labels.push(Label {
+                    hwnd: hwnd.0 as isize,
+                    number: index + 1,
+                    device,
+                });
+            }
+            let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), ALPHA, LWA_ALPHA);
+            let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            SetTimer(Some(hwnd), CLOSE_TIMER_ID, SHOW_MS, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_rect() {
+        // Second of two 1080p monitors side by side
+        let bounds = RECT {
+            left: 1920,
+            top: 0,
+            right: 3840,
+            bottom: 1080,
+        };
+        let rect = label_rect(bounds);
+        assert_eq!(
+            (rect.left, rect.top, rect.right, rect.bottom),
+            (2700, 360, 3060, 720)
+        );
+    }
+}
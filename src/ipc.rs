@@ -0,0 +1,282 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::command::Command;
+use crate::log::warning;
+
+/// At most this many requests from other processes per RATE_WINDOW
+const RATE_LIMIT: usize = 10;
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// How long a request may run before it's abandoned
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of a request from another process, sent back as the reply to
+/// its message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Done,
+    /// Ran, but couldn't do what was asked
+    Failed,
+    /// Another request was still running
+    Busy,
+    /// Too many requests in the last second
+    RateLimited,
+    /// Took longer than REQUEST_TIMEOUT, and was undone
+    TimedOut,
+    /// The request couldn't be read
+    Malformed,
+    /// Will run once the request that is running has finished; not to be
+    /// sent again
+    Queued,
+}
+
+const STATUSES: [Status; 7] = [
+    Status::Done,
+    Status::Failed,
+    Status::Busy,
+    Status::RateLimited,
+    Status::TimedOut,
+    Status::Malformed,
+    Status::Queued,
+];
+
+impl Status {
+    /// Message result carrying this status; 0 is left for "not handled"
+    pub fn code(self) -> isize {
+        STATUSES.iter().position(|&status| status == self).unwrap() as isize + 1
+    }
+
+    pub fn from_code(code: isize) -> Option<Self> {
+        usize::try_from(code - 1)
+            .ok()
+            .and_then(|index| STATUSES.get(index).copied())
+    }
+
+    /// Process exit code for a command-line client
+    pub fn exit_code(self) -> i32 {
+        self.code() as i32 - 1
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Status::Done => "done",
+            Status::Failed => "failed",
+            Status::Busy => "busy with another request",
+            Status::RateLimited => "too many requests",
+            Status::TimedOut => "timed out",
+            Status::Malformed => "malformed request",
+            Status::Queued => "queued behind another request",
+        })
+    }
+}
+
+/// Admits requests one at a time and within the rate limit. Commands that
+/// arrive while one runs are queued rather than refused, as their senders
+/// aren't waiting for a reply; they count against the limit once, when
+/// they arrive.
+#[derive(Debug, Default)]
+struct Dispatcher {
+    /// When recent requests were admitted, oldest first
+    recent: VecDeque<Instant>,
+    running: bool,
+    queued: VecDeque<Command>,
+}
+
+impl Dispatcher {
+    /// Count a request arriving at `now` against the rate limit
+    fn count(&mut self, now: Instant) -> Result<(), Status> {
+        while self
+            .recent
+            .front()
+            .is_some_and(|&admitted| now.duration_since(admitted) >= RATE_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= RATE_LIMIT {
+            return Err(Status::RateLimited);
+        }
+        self.recent.push_back(now);
+        Ok(())
+    }
+
+    fn admit(&mut self, now: Instant) -> Result<(), Status> {
+        if self.running {
+            return Err(Status::Busy);
+        }
+        self.count(now)?;
+        self.running = true;
+        Ok(())
+    }
+
+    /// Take in `command`, arriving at `now`: true if it's to run now, false
+    /// if it was queued behind the request that's running
+    fn admit_command(&mut self, command: Command, now: Instant) -> Result<bool, Status> {
+        self.count(now)?;
+        if self.running {
+            self.queued.push_back(command);
+            return Ok(false);
+        }
+        self.running = true;
+        Ok(true)
+    }
+
+    /// The next queued command, admitted to run, unless a request is running
+    fn next_queued(&mut self) -> Option<Command> {
+        if self.running {
+            return None;
+        }
+        let command = self.queued.pop_front()?;
+        self.running = true;
+        Some(command)
+    }
+
+    fn finish(&mut self) {
+        self.running = false;
+    }
+}
+
+thread_local! {
+    static DISPATCHER: RefCell<Dispatcher> = RefCell::new(Dispatcher::default());
+}
+
+/// Run a request from another process unless one is already running (a
+/// message loop inside it can deliver the next) or requests are arriving
+/// too fast. `request` gets the deadline it should give up by.
+pub fn dispatch(request: impl FnOnce(Instant) -> Status) -> Status {
+    let now = Instant::now();
+    if let Err(status) = DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().admit(now)) {
+        warning!("Refused a request: {}", status);
+        return status;
+    }
+    let status = request(now + REQUEST_TIMEOUT);
+    DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().finish());
+    status
+}
+
+/// Run `command` from another process, or queue it behind the request
+/// that is running, for run_queued; Queued if it was. Commands over the
+/// rate limit are dropped, and RateLimited.
+pub fn dispatch_command(command: Command, run: fn(Command)) -> Status {
+    let now = Instant::now();
+    match DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().admit_command(command, now)) {
+        Ok(true) => {
+            run(command);
+            DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().finish());
+            Status::Done
+        }
+        Ok(false) => Status::Queued,
+        Err(status) => {
+            warning!("Dropped the {:?} command: {}", command, status);
+            status
+        }
+    }
+}
+
+/// Run the commands queued while another request ran, now that none is.
+/// They were counted against the rate limit when they arrived.
+pub fn run_queued(run: fn(Command)) {
+    while let Some(command) = DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().next_queued()) {
+        run(command);
+        DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().finish());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes_round_trip() {
+        for status in STATUSES {
+            assert_eq!(Status::from_code(status.code()), Some(status));
+        }
+        assert_eq!(Status::from_code(0), None);
+        assert_eq!(Status::Done.exit_code(), 0);
+        assert_eq!(Status::from_code(STATUSES.len() as isize + 1), None);
+    }
+
+    #[test]
+    fn test_dispatcher_serializes_requests() {
+        let start = Instant::now();
+        let mut dispatcher = Dispatcher::default();
+        assert_eq!(dispatcher.admit(start), Ok(()));
+        // Overlapping requests are refused until the first finishes
+        assert_eq!(dispatcher.admit(start), Err(Status::Busy));
+        dispatcher.finish();
+        assert_eq!(dispatcher.admit(start), Ok(()));
+    }
+
+    #[test]
+    fn test_dispatcher_rate_limits() {
+        let start = Instant::now();
+        let mut dispatcher = Dispatcher::default();
+        for _ in 0..RATE_LIMIT {
+            assert_eq!(dispatcher.admit(start), Ok(()));
+            dispatcher.finish();
+        }
+        let almost = start + RATE_WINDOW - Duration::from_millis(1);
+        assert_eq!(dispatcher.admit(almost), Err(Status::RateLimited));
+        assert_eq!(dispatcher.admit(start + RATE_WINDOW), Ok(()));
+    }
+
+    #[test]
+    fn test_dispatcher_queues_commands() {
+        let start = Instant::now();
+        let mut dispatcher = Dispatcher::default();
+        assert_eq!(dispatcher.admit(start), Ok(()));
+        assert_eq!(
+            dispatcher.admit_command(Command::Equalize, start),
+            Ok(false)
+        );
+        assert_eq!(dispatcher.next_queued(), None);
+        dispatcher.finish();
+        assert_eq!(dispatcher.next_queued(), Some(Command::Equalize));
+        dispatcher.finish();
+        // Running the queued command didn't count it a second time
+        assert_eq!(dispatcher.recent.len(), 2);
+        assert_eq!(dispatcher.next_queued(), None);
+    }
+
+    #[test]
+    fn test_queued_commands_run_once() {
+        thread_local! {
+            static RAN: RefCell<Vec<Command>> = const { RefCell::new(Vec::new()) };
+        }
+        fn run(command: Command) {
+            RAN.with(|ran| ran.borrow_mut().push(command));
+        }
+        let start = Instant::now();
+        DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().admit(start).unwrap());
+        assert_eq!(dispatch_command(Command::Restore, run), Status::Queued);
+        DISPATCHER.with(|dispatcher| dispatcher.borrow_mut().finish());
+        run_queued(run);
+        assert_eq!(dispatch_command(Command::Equalize, run), Status::Done);
+        assert_eq!(
+            RAN.with(|ran| ran.take()),
+            [Command::Restore, Command::Equalize]
+        );
+    }
+
+    #[test]
+    fn test_dispatcher_rate_limits_commands() {
+        let start = Instant::now();
+        let mut dispatcher = Dispatcher::default();
+        assert_eq!(dispatcher.admit(start), Ok(()));
+        for _ in 1..RATE_LIMIT {
+            assert_eq!(
+                dispatcher.admit_command(Command::ShowGrid, start),
+                Ok(false)
+            );
+        }
+        // Over the limit, a command is refused rather than queued
+        assert_eq!(
+            dispatcher.admit_command(Command::Restore, start),
+            Err(Status::RateLimited)
+        );
+        assert_eq!(dispatcher.queued.len(), RATE_LIMIT - 1);
+    }
+}
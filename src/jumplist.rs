@@ -0,0 +1,80 @@
+use std::sync::Mutex;
+use windows::core::{Interface, HSTRING};
+use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+use windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PROPVARIANT};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+};
+use windows::Win32::System::Variant::VT_LPWSTR;
+use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+use windows::Win32::UI::Shell::{
+    DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, SHStrDupW,
+    ShellLink,
+};
+
+use crate::command::{Command, COMMANDS};
+
+// Names of the layouts on the jump list when it was last built
+static LISTED_LAYOUTS: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Shell link titled `label` that relaunches this exe with the command's
+/// argument
+fn task_link(exe: &HSTRING, command: Command, label: &str) -> windows::core::Result<IShellLinkW> {
+    unsafe {
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.SetPath(exe)?;
+        link.SetArguments(&HSTRING::from(command.arg()))?;
+        link.SetIconLocation(exe, 0)?;
+
+        // Tasks show their title property, which has to be a VT_LPWSTR
+        let mut title = PROPVARIANT::default();
+        (*title.Anonymous.Anonymous).vt = VT_LPWSTR;
+        (*title.Anonymous.Anonymous).Anonymous.pwszVal = SHStrDupW(&HSTRING::from(label))?;
+        let store: IPropertyStore = link.cast()?;
+        let result = store
+            .SetValue(&PKEY_Title, &title)
+            .and_then(|_| store.Commit());
+        let _ = PropVariantClear(&mut title);
+        result?;
+        Ok(link)
+    }
+}
+
+/// Add the commands, and one task per layout in `layouts`, to the taskbar
+/// button's jump list, shown while the settings window is open or the app
+/// is pinned. Left alone if it already lists these layouts.
+pub fn register_jump_list(layouts: &[String]) -> windows::core::Result<()> {
+    if LISTED_LAYOUTS
+        .lock()
+        .is_ok_and(|listed| listed.as_deref() == Some(layouts))
+    {
+        return Ok(());
+    }
+    let exe = std::env::current_exe().map_err(|_| windows::core::Error::from_win32())?;
+    let exe = HSTRING::from(exe.as_os_str());
+    unsafe {
+        // S_FALSE (already initialized) is fine
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let list: ICustomDestinationList =
+            CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+        let mut slots = 0u32;
+        let _removed: IObjectArray = list.BeginList(&mut slots)?;
+
+        let tasks: IObjectCollection =
+            CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+        for command in COMMANDS {
+            tasks.AddObject(&task_link(&exe, command, command.title())?)?;
+        }
+        for (index, name) in layouts.iter().enumerate() {
+            let title = format!("Apply layout: {}", name);
+            tasks.AddObject(&task_link(&exe, Command::ApplyLayout(index), &title)?)?;
+        }
+        list.AddUserTasks(&tasks)?;
+        list.CommitList()?;
+    }
+    if let Ok(mut listed) = LISTED_LAYOUTS.lock() {
+        *listed = Some(layouts.to_vec());
+    }
+    Ok(())
+}
@@ -1,88 +1,330 @@
-use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU32, Ordering};
 use std::sync::Mutex;
-use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
-use windows::Win32::UI::Input::KeyboardAndMouse::{VK_ESCAPE, VK_TAB, VIRTUAL_KEY};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, GetKeyboardLayout, MapVirtualKeyExW, RegisterHotKey, SendInput, ToUnicodeEx,
+    UnregisterHotKey, HKL, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY,
+    KEYEVENTF_KEYUP, MAPVK_VK_TO_CHAR, MAPVK_VK_TO_VSC, MAPVK_VSC_TO_VK, MOD_ALT, MOD_CONTROL,
+    MOD_NOREPEAT, MOD_SHIFT, MOD_WIN, VIRTUAL_KEY, VK_DELETE, VK_DOWN, VK_ESCAPE, VK_F1, VK_F9,
+    VK_LCONTROL, VK_LEFT, VK_LMENU, VK_LSHIFT, VK_LWIN, VK_RCONTROL, VK_RETURN, VK_RIGHT, VK_RMENU,
+    VK_RSHIFT, VK_RWIN, VK_TAB, VK_UP,
+};
+use windows::Win32::UI::Input::{RegisterRawInputDevices, RAWINPUTDEVICE, RIDEV_INPUTSINK};
 use windows::Win32::UI::WindowsAndMessaging::{
-    CallNextHookEx, SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT,
-    WH_KEYBOARD_LL, WM_KEYDOWN,
+    CallNextHookEx, GetForegroundWindow, GetMessageTime, GetWindowThreadProcessId,
+    SetWindowsHookExW, UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT, LLKHF_INJECTED, WH_KEYBOARD_LL,
+    WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
 };
 
+use crate::keys::{char_to_vk, vk_to_char, Hotkey, MonitorKeys};
+use crate::log::{error, warning};
+
 static HOOK_ACTIVE: AtomicBool = AtomicBool::new(false);
 static HOOK_HANDLE: AtomicIsize = AtomicIsize::new(0);
+// Tick count of the last event the hook saw, for the watchdog
+static LAST_HOOK_TICK: AtomicU32 = AtomicU32::new(0);
+// Tick count of the last key event raw input saw, which the hook should
+// have seen too
+static LAST_KEY_TICK: AtomicU32 = AtomicU32::new(0);
+// HKL of the foreground thread, as of the last refresh_keyboard_layout()
+static KEYBOARD_LAYOUT: AtomicIsize = AtomicIsize::new(0);
+// Window receiving the hotkey bank's WM_HOTKEY, when used instead of the hook
+static HOTKEY_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+// Whether F1-F9 pick a monitor while the overlay is open
+static MONITOR_KEYS: AtomicBool = AtomicBool::new(false);
+// Whether grid keys are matched by scan code, where they sit on the
+// keyboard, rather than by the virtual key the layout gives them
+static PHYSICAL_KEYS: AtomicBool = AtomicBool::new(false);
+
+// The grid hotkey, packed by Hotkey::pack
+static GRID_HOTKEY: AtomicU32 = AtomicU32::new(Hotkey::DEFAULT.pack());
+
+// Key whose next key-up the hook hides, after its key-down fired our hotkey
+static SWALLOW_KEY_UP: AtomicU32 = AtomicU32::new(0);
+// Modifiers physically held, one bit per entry of MODIFIER_KEYS, from
+// non-injected hook events
+static PHYSICAL_MODIFIERS: AtomicU32 = AtomicU32::new(0);
+
+// Left and right Ctrl, Shift, Alt and Windows keys
+const MODIFIER_KEYS: [VIRTUAL_KEY; 8] = [
+    VK_LCONTROL,
+    VK_RCONTROL,
+    VK_LSHIFT,
+    VK_RSHIFT,
+    VK_LMENU,
+    VK_RMENU,
+    VK_LWIN,
+    VK_RWIN,
+];
+
+// Hotkey ids for the bank are this plus the virtual key code
+const HOTKEY_BANK_BASE: i32 = 0x1000;
+
+// Every key the grid, bindings and opacity keys can use
+const GRID_KEYS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ,.;'-=";
+// Grid keys by scan code, as they sit on a US keyboard: each row's first
+// scan code and its keys from left to right
+const SCAN_ROWS: [(u32, &str); 4] = [
+    (0x02, "1234567890-="),
+    (0x10, "QWERTYUIOP"),
+    (0x1E, "ASDFGHJKL;'"),
+    (0x2C, "ZXCVBNM,."),
+];
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyInput {
     GridKey(char),
+    Arrow(Direction),
+    /// Move the overlay to this monitor, 0-based in Tab order
+    Monitor(usize),
     Tab,
     Escape,
+    Enter,
+    /// Hide the target window, to restore from the tray
+    Hide,
     Other,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Up,
+    Right,
+    Down,
+}
+
 pub type KeyCallback = fn(KeyInput);
 
+/// The grid hotkey, as registered
+pub fn grid_hotkey() -> Hotkey {
+    Hotkey::unpack(GRID_HOTKEY.load(Ordering::SeqCst))
+}
+
+/// Change the grid hotkey the hook lets through; register it separately
+pub fn set_grid_hotkey(hotkey: Hotkey) {
+    GRID_HOTKEY.store(hotkey.pack(), Ordering::SeqCst);
+}
+
 static KEY_CALLBACK: Mutex<Option<KeyCallback>> = Mutex::new(None);
 
-fn vk_to_char(vk: u32) -> Option<char> {
-    match vk {
-        // Number keys (top row for 4-row grids)
-        0x31 => Some('1'), // VK_1
-        0x32 => Some('2'), // VK_2
-        0x33 => Some('3'), // VK_3
-        0x34 => Some('4'), // VK_4
-        0x35 => Some('5'), // VK_5
-        0x36 => Some('6'), // VK_6
-        0x37 => Some('7'), // VK_7
-        0x38 => Some('8'), // VK_8
-        // QWERTY row
-        0x51 => Some('Q'), // VK_Q
-        0x57 => Some('W'), // VK_W
-        0x45 => Some('E'), // VK_E
-        0x52 => Some('R'), // VK_R
-        0x54 => Some('T'), // VK_T
-        0x59 => Some('Y'), // VK_Y
-        0x55 => Some('U'), // VK_U
-        0x49 => Some('I'), // VK_I
-        // ASDF row
-        0x41 => Some('A'), // VK_A
-        0x53 => Some('S'), // VK_S
-        0x44 => Some('D'), // VK_D
-        0x46 => Some('F'), // VK_F
-        0x47 => Some('G'), // VK_G
-        0x48 => Some('H'), // VK_H
-        0x4A => Some('J'), // VK_J
-        0x4B => Some('K'), // VK_K
-        // ZXCV row
-        0x5A => Some('Z'), // VK_Z
-        0x58 => Some('X'), // VK_X
-        0x43 => Some('C'), // VK_C
-        0x56 => Some('V'), // VK_V
-        0x42 => Some('B'), // VK_B
-        0x4E => Some('N'), // VK_N
-        0x4D => Some('M'), // VK_M
-        0xBC => Some(','), // VK_OEM_COMMA
+fn vk_to_direction(vk: u32) -> Option<Direction> {
+    match VIRTUAL_KEY(vk as u16) {
+        VK_LEFT => Some(Direction::Left),
+        VK_UP => Some(Direction::Up),
+        VK_RIGHT => Some(Direction::Right),
+        VK_DOWN => Some(Direction::Down),
         _ => None,
     }
 }
 
+/// The monitor a function key picks, 0-based
+fn vk_to_monitor(vk: u32) -> Option<usize> {
+    (VK_F1.0 as u32..=VK_F9.0 as u32)
+        .contains(&vk)
+        .then(|| (vk - VK_F1.0 as u32) as usize)
+}
+
+fn vk_to_input(vk: u32) -> KeyInput {
+    if VIRTUAL_KEY(vk as u16) == VK_ESCAPE {
+        KeyInput::Escape
+    } else if VIRTUAL_KEY(vk as u16) == VK_TAB {
+        KeyInput::Tab
+    } else if VIRTUAL_KEY(vk as u16) == VK_RETURN {
+        KeyInput::Enter
+    } else if VIRTUAL_KEY(vk as u16) == VK_DELETE {
+        KeyInput::Hide
+    } else if let Some(c) = vk_to_char(vk) {
+        KeyInput::GridKey(c)
+    } else if let Some(direction) = vk_to_direction(vk) {
+        KeyInput::Arrow(direction)
+    } else if let Some(monitor) = vk_to_monitor(vk)
+        && MONITOR_KEYS.load(Ordering::SeqCst)
+    {
+        KeyInput::Monitor(monitor)
+    } else {
+        KeyInput::Other
+    }
+}
+
+/// The grid key at scan code `scan` on a US keyboard
+fn scan_to_char(scan: u32) -> Option<char> {
+    SCAN_ROWS
+        .iter()
+        .find_map(|&(first, keys)| keys.chars().nth(scan.checked_sub(first)? as usize))
+}
+
+/// Inverse of scan_to_char: where a grid key sits on a US keyboard
+fn char_to_scan(key: char) -> Option<u32> {
+    SCAN_ROWS
+        .iter()
+        .find_map(|&(first, keys)| keys.find(key).map(|i| first + i as u32))
+}
+
+/// The overlay input for the key with virtual key `vk` and scan code
+/// `scan`. With `physical` keys, grid keys go by where the key sits, so the
+/// grid keeps its shape on AZERTY or QWERTZ; other keys go by virtual key.
+fn key_input(vk: u32, scan: u32, physical: bool) -> KeyInput {
+    if !physical {
+        return vk_to_input(vk);
+    }
+    match (scan_to_char(scan), vk_to_input(vk)) {
+        (Some(c), _) => KeyInput::GridKey(c),
+        // A grid letter on a key that has none on a US keyboard
+        (None, KeyInput::GridKey(_)) => KeyInput::Other,
+        (None, input) => input,
+    }
+}
+
+fn modifier_bit(vk: u32) -> Option<u32> {
+    MODIFIER_KEYS
+        .iter()
+        .position(|m| m.0 as u32 == vk)
+        .map(|i| 1 << i)
+}
+
+/// Whether `vk` pressed with `modifiers` (bits as from modifier_bit) is
+/// `hotkey`, the grid hotkey, which the hook lets through to the
+/// registered hotkey instead of reading it as an overlay key
+fn is_grid_hotkey(vk: u32, modifiers: u32, hotkey: Hotkey) -> bool {
+    // Either key of each pair, as ordered in MODIFIER_KEYS
+    let pairs = [
+        (0b11, MOD_CONTROL.0),
+        (0b1100, MOD_SHIFT.0),
+        (0b11_0000, MOD_ALT.0),
+        (0b1100_0000, MOD_WIN.0),
+    ];
+    vk == hotkey.vk
+        && pairs
+            .iter()
+            .all(|&(bits, flag)| (modifiers & bits != 0) == (hotkey.modifiers & flag != 0))
+}
+
+/// Every virtual key the overlay responds to
+fn overlay_keys() -> impl Iterator<Item = u32> {
+    GRID_KEYS
+        .chars()
+        .filter_map(grid_key_vk)
+        .chain([VK_LEFT, VK_UP, VK_RIGHT, VK_DOWN].map(|vk| vk.0 as u32))
+        .chain([VK_TAB, VK_ESCAPE, VK_RETURN, VK_DELETE].map(|vk| vk.0 as u32))
+        .chain((VK_F1.0 as u32..=VK_F9.0 as u32).filter(|_| MONITOR_KEYS.load(Ordering::SeqCst)))
+}
+
+/// The virtual key a grid key is pressed with: the one it names or, with
+/// physical keys, whatever the active layout has where it sits
+fn grid_key_vk(key: char) -> Option<u32> {
+    if !PHYSICAL_KEYS.load(Ordering::SeqCst) {
+        return char_to_vk(key);
+    }
+    let scan = char_to_scan(key)?;
+    let vk = unsafe { MapVirtualKeyExW(scan, MAPVK_VSC_TO_VK, Some(active_layout())) };
+    (vk != 0).then_some(vk)
+}
+
+/// Keyboard layout of the thread owning the foreground window
+fn foreground_layout() -> HKL {
+    unsafe {
+        let thread_id = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        GetKeyboardLayout(thread_id)
+    }
+}
+
+/// Re-read the foreground keyboard layout. Returns true if it changed since
+/// the last call, meaning overlay labels need to be redrawn.
+///
+/// Key matching itself needs no rebuild: the hook maps virtual keys, which
+/// Windows already translates through the active layout.
+pub fn refresh_keyboard_layout() -> bool {
+    let layout = foreground_layout().0 as isize;
+    KEYBOARD_LAYOUT.swap(layout, Ordering::SeqCst) != layout
+}
+
+/// The foreground keyboard layout, as of the last refresh_keyboard_layout()
+fn active_layout() -> HKL {
+    HKL(KEYBOARD_LAYOUT.load(Ordering::SeqCst) as *mut _)
+}
+
+/// What `layout` types, unshifted, on the key where `key` sits on a US
+/// keyboard. A dead key gives the accent it adds.
+fn typed_at(key: char, layout: HKL) -> Option<char> {
+    let scan = char_to_scan(key)?;
+    let mut typed = [0u16; 4];
+    let count = unsafe {
+        let vk = MapVirtualKeyExW(scan, MAPVK_VSC_TO_VK, Some(layout));
+        // No keys held; flag bit 2 leaves the layout's dead key state alone
+        ToUnicodeEx(vk, scan, &[0; 256], &mut typed, 0x4, Some(layout))
+    };
+    if count == 0 {
+        return None;
+    }
+    char::decode_utf16(typed)
+        .next()?
+        .ok()
+        .filter(|c| !c.is_control())
+        .and_then(|c| c.to_uppercase().next())
+}
+
+/// Character the active keyboard layout produces for a grid key, used as the
+/// tile label so it matches what the user sees on their keyboard. With
+/// physical keys that's whatever is typed where the key sits.
+pub fn key_label(key: char) -> char {
+    let layout = active_layout();
+    if PHYSICAL_KEYS.load(Ordering::SeqCst) {
+        return typed_at(key, layout).unwrap_or(key);
+    }
+    let Some(vk) = char_to_vk(key) else {
+        return key;
+    };
+
+    // Low word holds the unshifted character; 0 means no translation
+    let mapped = unsafe { MapVirtualKeyExW(vk, MAPVK_VK_TO_CHAR, Some(layout)) } & 0xFFFF;
+    char::from_u32(mapped)
+        .filter(|c| *c != '\0')
+        .and_then(|c| c.to_uppercase().next())
+        .unwrap_or(key)
+}
+
 unsafe extern "system" fn keyboard_hook_proc(
     code: i32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
     unsafe {
+        if code >= 0 {
+            let kb_struct = *(lparam.0 as *const KBDLLHOOKSTRUCT);
+            LAST_HOOK_TICK.store(kb_struct.time, Ordering::SeqCst);
+
+            let message = wparam.0 as u32;
+            let is_up = message == WM_KEYUP || message == WM_SYSKEYUP;
+            let is_down = message == WM_KEYDOWN || message == WM_SYSKEYDOWN;
+            if let Some(bit) = modifier_bit(kb_struct.vkCode)
+                && (kb_struct.flags.0 & LLKHF_INJECTED.0) == 0
+            {
+                if is_down {
+                    PHYSICAL_MODIFIERS.fetch_or(bit, Ordering::SeqCst);
+                } else if is_up {
+                    PHYSICAL_MODIFIERS.fetch_and(!bit, Ordering::SeqCst);
+                }
+            }
+
+            // The app never saw this key go down, so it shouldn't see it go up
+            if is_up
+                && SWALLOW_KEY_UP
+                    .compare_exchange(kb_struct.vkCode, 0, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            {
+                return LRESULT(1);
+            }
+        }
+
         if code >= 0 && wparam.0 as u32 == WM_KEYDOWN {
             let kb_struct = *(lparam.0 as *const KBDLLHOOKSTRUCT);
             let vk = kb_struct.vkCode;
+            if is_grid_hotkey(vk, PHYSICAL_MODIFIERS.load(Ordering::SeqCst), grid_hotkey()) {
+                return CallNextHookEx(None, code, wparam, lparam);
+            }
 
-            let input = if VIRTUAL_KEY(vk as u16) == VK_ESCAPE {
-                KeyInput::Escape
-            } else if VIRTUAL_KEY(vk as u16) == VK_TAB {
-                KeyInput::Tab
-            } else if let Some(c) = vk_to_char(vk) {
-                KeyInput::GridKey(c)
-            } else {
-                KeyInput::Other
-            };
+            let input = key_input(vk, kb_struct.scanCode, PHYSICAL_KEYS.load(Ordering::SeqCst));
 
             if let Ok(callback_guard) = KEY_CALLBACK.lock() {
                 if let Some(callback) = *callback_guard {
@@ -110,13 +352,214 @@ pub fn install_keyboard_hook(callback: KeyCallback) -> windows::core::Result<()>
 
         HOOK_HANDLE.store(hook.0 as isize, Ordering::SeqCst);
         HOOK_ACTIVE.store(true, Ordering::SeqCst);
+        // Keys pressed before it was installed don't count against it
+        LAST_HOOK_TICK.store(LAST_KEY_TICK.load(Ordering::SeqCst), Ordering::SeqCst);
         Ok(())
     }
 }
 
+/// Hide the coming key-up of `vk` from other applications. Call when a
+/// hotkey on `vk` fires, since RegisterHotKey only eats the key-down. Does
+/// nothing if the key was already released.
+pub fn swallow_key_up(vk: u32) {
+    let held = unsafe { GetAsyncKeyState(vk as i32) } < 0;
+    if held && HOOK_HANDLE.load(Ordering::SeqCst) != 0 {
+        SWALLOW_KEY_UP.store(vk, Ordering::SeqCst);
+    }
+}
+
+/// Modifiers Windows thinks are down that aren't physically held
+fn stuck_modifiers(logical: u32, physical: u32) -> u32 {
+    logical & !physical
+}
+
+/// Send key-ups for modifiers left logically down after the hotkey chord,
+/// e.g. when focus moved to another window between the key-down and key-up.
+/// Needs the hook to know what is physically held, so does nothing without
+/// it.
+pub fn release_stuck_modifiers() {
+    if HOOK_HANDLE.load(Ordering::SeqCst) == 0 {
+        return;
+    }
+    let logical = MODIFIER_KEYS
+        .iter()
+        .enumerate()
+        .filter(|(_, vk)| unsafe { GetAsyncKeyState(vk.0 as i32) } < 0)
+        .fold(0, |mask, (i, _)| mask | 1 << i);
+    let stuck = stuck_modifiers(logical, PHYSICAL_MODIFIERS.load(Ordering::SeqCst));
+    if stuck == 0 {
+        return;
+    }
+
+    let inputs: Vec<INPUT> = MODIFIER_KEYS
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| stuck & (1 << i) != 0)
+        .map(|(_, &vk)| {
+            let mut flags = KEYEVENTF_KEYUP;
+            if matches!(vk, VK_RCONTROL | VK_RMENU | VK_LWIN | VK_RWIN) {
+                flags |= KEYEVENTF_EXTENDEDKEY;
+            }
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: vk,
+                        dwFlags: flags,
+                        ..Default::default()
+                    },
+                },
+            }
+        })
+        .collect();
+    unsafe {
+        SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+}
+
+/// Capture overlay keys with RegisterHotKey on `hwnd` instead of a
+/// low-level hook, for systems where security software flags
+/// WH_KEYBOARD_LL. The keys are only registered while input is active, and
+/// their WM_HOTKEY messages must be passed to `handle_hotkey_input`.
+pub fn install_hotkey_input(hwnd: HWND, callback: KeyCallback) {
+    if let Ok(mut callback_guard) = KEY_CALLBACK.lock() {
+        *callback_guard = Some(callback);
+    }
+    HOTKEY_WINDOW.store(hwnd.0 as isize, Ordering::SeqCst);
+}
+
+fn register_hotkey_bank(hwnd: HWND) {
+    let failed = overlay_keys()
+        .filter(|&vk| unsafe {
+            RegisterHotKey(Some(hwnd), HOTKEY_BANK_BASE + vk as i32, MOD_NOREPEAT, vk).is_err()
+        })
+        .count();
+    if failed > 0 {
+        warning!("{} overlay keys are taken by other programs", failed);
+    }
+}
+
+fn unregister_hotkey_bank(hwnd: HWND) {
+    for vk in overlay_keys() {
+        unsafe {
+            let _ = UnregisterHotKey(Some(hwnd), HOTKEY_BANK_BASE + vk as i32);
+        }
+    }
+}
+
+/// Pass a WM_HOTKEY from the hotkey bank to the key callback. Returns false
+/// if `id` isn't one of the bank's hotkeys.
+pub fn handle_hotkey_input(id: i32) -> bool {
+    if HOTKEY_WINDOW.load(Ordering::SeqCst) == 0
+        || !(HOTKEY_BANK_BASE..HOTKEY_BANK_BASE + 0x100).contains(&id)
+    {
+        return false;
+    }
+    let vk = (id - HOTKEY_BANK_BASE) as u32;
+    let physical = PHYSICAL_KEYS.load(Ordering::SeqCst);
+    let scan = if physical {
+        unsafe { MapVirtualKeyExW(vk, MAPVK_VK_TO_VSC, Some(active_layout())) }
+    } else {
+        0
+    };
+    let callback = KEY_CALLBACK.lock().ok().and_then(|guard| *guard);
+    if let Some(callback) = callback {
+        callback(key_input(vk, scan, physical));
+    }
+    true
+}
+
+/// Have keyboard raw input sent to `hwnd` from any foreground window, as
+/// a record of key activity that doesn't depend on the hook. Without it
+/// the watchdog never fires.
+pub fn watch_key_activity(hwnd: HWND) -> windows::core::Result<()> {
+    // Generic desktop controls, keyboard
+    let device = RAWINPUTDEVICE {
+        usUsagePage: 0x01,
+        usUsage: 0x06,
+        dwFlags: RIDEV_INPUTSINK,
+        hwndTarget: hwnd,
+    };
+    unsafe { RegisterRawInputDevices(&[device], std::mem::size_of::<RAWINPUTDEVICE>() as u32) }
+}
+
+/// Note that a key event arrived as raw input. Call from WM_INPUT.
+pub fn note_key_activity() {
+    let tick = unsafe { GetMessageTime() } as u32;
+    LAST_KEY_TICK.store(tick, Ordering::SeqCst);
+}
+
+/// True when a key event arrived more than `threshold_ms` after the hook
+/// last fired. Tick counts wrap every 49.7 days, so compare by difference.
+fn is_hook_stale(last_hook: u32, last_key: u32, threshold_ms: u32) -> bool {
+    let behind = last_key.wrapping_sub(last_hook) as i32;
+    behind > threshold_ms as i32
+}
+
+/// Replace the low-level hook with a fresh one, keeping the callback and
+/// active state. Used when Windows has silently dropped the old hook.
+pub fn reinstall_keyboard_hook() -> windows::core::Result<()> {
+    if HOTKEY_WINDOW.load(Ordering::SeqCst) != 0 {
+        // The hotkey bank has no hook to lose
+        return Ok(());
+    }
+    unsafe {
+        let active = HOOK_ACTIVE.load(Ordering::SeqCst);
+        let old = HOOK_HANDLE.swap(0, Ordering::SeqCst);
+        if old != 0 {
+            // Fails harmlessly if Windows already removed it
+            let _ = UnhookWindowsHookEx(HHOOK(old as *mut _));
+        }
+
+        let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), None, 0)?;
+        HOOK_HANDLE.store(hook.0 as isize, Ordering::SeqCst);
+        HOOK_ACTIVE.store(active, Ordering::SeqCst);
+        // Keys pressed before it was installed don't count against it
+        LAST_HOOK_TICK.store(LAST_KEY_TICK.load(Ordering::SeqCst), Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Remove the low-level hook but keep its callback, so
+/// `reinstall_keyboard_hook` can resume it. The hotkey bank is left alone.
+pub fn pause_keyboard_hook() {
+    set_hook_active(false);
+    let handle = HOOK_HANDLE.swap(0, Ordering::SeqCst);
+    if handle != 0 {
+        unsafe {
+            let _ = UnhookWindowsHookEx(HHOOK(handle as *mut _));
+        }
+    }
+}
+
+/// Windows removes low-level hooks that time out without telling us. If
+/// raw input saw key events the hook never did, reinstall it. Returns
+/// true if the hook was reinstalled.
+pub fn check_hook_watchdog(threshold_ms: u32) -> bool {
+    if HOOK_HANDLE.load(Ordering::SeqCst) == 0 {
+        return false;
+    }
+    let last_hook = LAST_HOOK_TICK.load(Ordering::SeqCst);
+    let last_key = LAST_KEY_TICK.load(Ordering::SeqCst);
+    if !is_hook_stale(last_hook, last_key, threshold_ms) {
+        return false;
+    }
+    match reinstall_keyboard_hook() {
+        Ok(()) => {
+            warning!("Keyboard hook stopped receiving input; reinstalled it");
+            true
+        }
+        Err(e) => {
+            error!("Keyboard hook watchdog failed to reinstall hook: {}", e);
+            false
+        }
+    }
+}
+
 pub fn uninstall_keyboard_hook() {
     unsafe {
-        HOOK_ACTIVE.store(false, Ordering::SeqCst);
+        set_hook_active(false);
+        HOTKEY_WINDOW.store(0, Ordering::SeqCst);
 
         let handle = HOOK_HANDLE.swap(0, Ordering::SeqCst);
         if handle != 0 {
@@ -133,6 +576,130 @@ pub fn is_hook_active() -> bool {
     HOOK_ACTIVE.load(Ordering::SeqCst)
 }
 
+/// Whether the low-level hook is installed, whether or not it is
+/// capturing overlay keys right now
+pub fn is_hook_installed() -> bool {
+    HOOK_HANDLE.load(Ordering::SeqCst) != 0
+}
+
+/// Choose the keys that pick a monitor. Takes effect the next time the
+/// overlay opens.
+pub fn set_monitor_keys(keys: MonitorKeys) {
+    if !HOOK_ACTIVE.load(Ordering::SeqCst) {
+        MONITOR_KEYS.store(keys == MonitorKeys::Function, Ordering::SeqCst);
+    }
+}
+
+/// Match grid keys by where they sit on the keyboard rather than by what
+/// they type. Takes effect the next time the overlay opens.
+pub fn set_physical_keys(physical: bool) {
+    if !HOOK_ACTIVE.load(Ordering::SeqCst) {
+        PHYSICAL_KEYS.store(physical, Ordering::SeqCst);
+    }
+}
+
+/// Start or stop capturing overlay keys
 pub fn set_hook_active(active: bool) {
-    HOOK_ACTIVE.store(active, Ordering::SeqCst);
+    let was_active = HOOK_ACTIVE.swap(active, Ordering::SeqCst);
+    let hotkey_window = HOTKEY_WINDOW.load(Ordering::SeqCst);
+    if hotkey_window != 0 && was_active != active {
+        let hwnd = HWND(hotkey_window as *mut _);
+        if active {
+            register_hotkey_bank(hwnd);
+        } else {
+            unregister_hotkey_bank(hwnd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_to_vk_round_trip() {
+        for key in GRID_KEYS.chars() {
+            let vk = char_to_vk(key).unwrap();
+            assert_eq!(vk_to_char(vk), Some(key));
+        }
+        assert_eq!(char_to_vk('?'), None);
+    }
+
+    #[test]
+    fn test_physical_keys() {
+        for key in GRID_KEYS.chars() {
+            assert_eq!(scan_to_char(char_to_scan(key).unwrap()), Some(key));
+        }
+        assert_eq!(char_to_scan('Q'), Some(0x10));
+        assert_eq!(scan_to_char(0x01), None); // Escape
+
+        // AZERTY's A sits where US keyboards have Q
+        let (vk_a, scan_q) = (0x41, 0x10);
+        assert_eq!(key_input(vk_a, scan_q, false), KeyInput::GridKey('A'));
+        assert_eq!(key_input(vk_a, scan_q, true), KeyInput::GridKey('Q'));
+        // Other keys still go by virtual key
+        let escape = VK_ESCAPE.0 as u32;
+        assert_eq!(key_input(escape, 0x01, true), KeyInput::Escape);
+        // A letter on the extra key beside left Shift, which isn't a grid key
+        assert_eq!(key_input(0x41, 0x56, true), KeyInput::Other);
+    }
+
+    #[test]
+    fn test_modifier_tracking() {
+        assert_eq!(modifier_bit(VK_LCONTROL.0 as u32), Some(1));
+        assert_eq!(modifier_bit(VK_RWIN.0 as u32), Some(1 << 7));
+        assert_eq!(modifier_bit(0x47), None); // G
+
+        let (lctrl, lshift) = (1, 1 << 2);
+        assert_eq!(stuck_modifiers(lctrl | lshift, lshift), lctrl);
+        assert_eq!(stuck_modifiers(lctrl, lctrl | lshift), 0);
+
+        let (rctrl, lalt, lwin) = (1 << 1, 1 << 4, 1 << 6);
+        let grid = Hotkey::DEFAULT;
+        assert!(is_grid_hotkey(0x47, lctrl | lshift, grid));
+        assert!(is_grid_hotkey(0x47, rctrl | lshift, grid));
+        assert!(!is_grid_hotkey(0x47, lshift, grid));
+        assert!(!is_grid_hotkey(0x47, lctrl | lshift | lalt, grid));
+        assert!(!is_grid_hotkey(0x46, lctrl | lshift, grid));
+        let win_t = Hotkey {
+            modifiers: MOD_WIN.0,
+            vk: 0x54,
+        };
+        assert!(is_grid_hotkey(0x54, lwin, win_t));
+        assert!(!is_grid_hotkey(0x54, lwin | lshift, win_t));
+    }
+
+    #[test]
+    fn test_function_keys_pick_monitors() {
+        assert_eq!(vk_to_monitor(VK_F1.0 as u32), Some(0));
+        assert_eq!(vk_to_monitor(VK_F9.0 as u32), Some(8));
+        assert_eq!(vk_to_monitor(VK_F9.0 as u32 + 1), None);
+        assert_eq!(vk_to_monitor(VK_TAB.0 as u32), None);
+    }
+
+    #[test]
+    fn test_overlay_keys_all_map_to_input() {
+        let keys: Vec<u32> = overlay_keys().collect();
+        assert_eq!(keys.len(), 10 + 26 + 6 + 4 + 4);
+        assert!(keys.iter().all(|&vk| vk_to_input(vk) != KeyInput::Other));
+        assert_eq!(vk_to_input(VK_ESCAPE.0 as u32), KeyInput::Escape);
+        assert_eq!(vk_to_input(VK_DELETE.0 as u32), KeyInput::Hide);
+        assert_eq!(vk_to_input(0x41), KeyInput::GridKey('A'));
+        assert_eq!(
+            vk_to_input(VK_DOWN.0 as u32),
+            KeyInput::Arrow(Direction::Down)
+        );
+    }
+
+    #[test]
+    fn test_hook_staleness() {
+        assert!(!is_hook_stale(1_000, 1_000, 30_000));
+        assert!(!is_hook_stale(1_000, 20_000, 30_000));
+        assert!(is_hook_stale(1_000, 40_000, 30_000));
+        // Hook event after the last input (e.g. injected key) is never stale
+        assert!(!is_hook_stale(50_000, 40_000, 30_000));
+        // Across the tick count wrap
+        assert!(is_hook_stale(u32::MAX - 5_000, 40_000, 30_000));
+        assert!(!is_hook_stale(u32::MAX - 5_000, 10_000, 30_000));
+    }
 }
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// RegisterHotKey's modifier flags and the virtual keys named here, spelled
+// out so the config can use this module without the Win32 input bindings
+const MOD_ALT: u32 = 0x0001;
+const MOD_CONTROL: u32 = 0x0002;
+const MOD_SHIFT: u32 = 0x0004;
+const MOD_WIN: u32 = 0x0008;
+const VK_G: u32 = 0x47;
+const VK_F1: u32 = 0x70;
+
+/// Keys that move the overlay straight to a monitor, so a selection on
+/// another screen needs no Tab cycling
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitorKeys {
+    /// F1 for the first monitor, F2 for the second, up to F9
+    #[default]
+    Function,
+    /// Only Tab changes monitor; function keys pass through
+    None,
+}
+
+/// A key chord for RegisterHotKey: MOD_ flags and a virtual key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: u32,
+    pub vk: u32,
+}
+
+// Modifier names and MOD_ flags, in the order they're written
+const MODIFIER_NAMES: [(&str, u32); 4] = [
+    ("Ctrl", MOD_CONTROL),
+    ("Alt", MOD_ALT),
+    ("Shift", MOD_SHIFT),
+    ("Win", MOD_WIN),
+];
+
+// Hotkey keys named rather than typed
+const KEY_NAMES: [(&str, u32); 14] = [
+    ("Space", 0x20),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("End", 0x23),
+    ("Home", 0x24),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Pause", 0x13),
+    ("ScrollLock", 0x91),
+    ("`", 0xC0),
+    ("/", 0xBF),
+    ("[", 0xDB),
+    ("]", 0xDD),
+    ("\\", 0xDC),
+];
+
+impl Hotkey {
+    /// Ctrl+Shift+G
+    pub const DEFAULT: Hotkey = Hotkey {
+        modifiers: MOD_CONTROL | MOD_SHIFT,
+        vk: VK_G,
+    };
+
+    /// The hotkey for `modifiers` ("ctrl", "alt", "shift" or "win") and
+    /// `key`: a letter, digit, F1-F24 or a name from KEY_NAMES. Keys that
+    /// type something need Ctrl, Alt or Win, or they'd stop typing it;
+    /// Shift alone would stop it typing its capital or symbol.
+    pub fn parse(modifiers: &[String], key: &str) -> Result<Self, String> {
+        let mut flags = 0;
+        for modifier in modifiers {
+            let flag = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => MOD_CONTROL,
+                "alt" => MOD_ALT,
+                "shift" => MOD_SHIFT,
+                "win" | "windows" => MOD_WIN,
+                _ => return Err(format!("unknown modifier \"{}\"", modifier)),
+            };
+            flags |= flag;
+        }
+        let key = key.trim();
+        let function_key = key
+            .strip_prefix(['F', 'f'])
+            .and_then(|n| n.parse::<u32>().ok())
+            .filter(|n| (1..=24).contains(n));
+        let vk = if let Some(n) = function_key {
+            VK_F1 + n - 1
+        } else if let Some(&(_, vk)) = KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        {
+            vk
+        } else {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => char_to_vk(c.to_ascii_uppercase())
+                    .ok_or_else(|| format!("unknown key \"{}\"", key))?,
+                _ => return Err(format!("unknown key \"{}\"", key)),
+            }
+        };
+        let types = vk_to_char(vk).is_some() || (0xBA..=0xDE).contains(&vk) || vk == 0x20;
+        if flags & !MOD_SHIFT == 0 && types {
+            return Err(format!("\"{}\" needs Ctrl, Alt or Win", key));
+        }
+        Ok(Self {
+            modifiers: flags,
+            vk,
+        })
+    }
+
+    pub const fn pack(self) -> u32 {
+        self.modifiers << 16 | self.vk
+    }
+
+    pub const fn unpack(packed: u32) -> Self {
+        Self {
+            modifiers: packed >> 16,
+            vk: packed & 0xFFFF,
+        }
+    }
+}
+
+/// Written as in menus, e.g. "Ctrl+Shift+G"
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, flag) in MODIFIER_NAMES {
+            if self.modifiers & flag != 0 {
+                write!(f, "{}+", name)?;
+            }
+        }
+        if let Some(&(name, _)) = KEY_NAMES.iter().find(|(_, vk)| *vk == self.vk) {
+            f.write_str(name)
+        } else if (VK_F1..VK_F1 + 24).contains(&self.vk) {
+            write!(f, "F{}", self.vk - VK_F1 + 1)
+        } else {
+            write!(f, "{}", vk_to_char(self.vk).unwrap_or('?'))
+        }
+    }
+}
+
+/// The grid key a virtual key types, on any layout's grid rows
+pub fn vk_to_char(vk: u32) -> Option<char> {
+    match vk {
+        // Number keys (1-8 are the top row for 4-row grids)
+        0x30..=0x39 => char::from_u32(vk), // VK_0..VK_9
+        // Letters, covering the QWERTY, Dvorak and Colemak grid rows
+        0x41..=0x5A => char::from_u32(vk), // VK_A..VK_Z
+        // Punctuation used by the grid rows
+        0xBC => Some(','),  // VK_OEM_COMMA
+        0xBE => Some('.'),  // VK_OEM_PERIOD
+        0xBA => Some(';'),  // VK_OEM_1
+        0xDE => Some('\''), // VK_OEM_7
+        // Free for bindings and window opacity
+        0xBD => Some('-'), // VK_OEM_MINUS
+        0xBB => Some('='), // VK_OEM_PLUS
+        _ => None,
+    }
+}
+
+/// Inverse of vk_to_char: the virtual key that produces a grid key
+pub fn char_to_vk(key: char) -> Option<u32> {
+    match key {
+        '0'..='9' | 'A'..='Z' => Some(key as u32),
+        ',' => Some(0xBC),  // VK_OEM_COMMA
+        '.' => Some(0xBE),  // VK_OEM_PERIOD
+        ';' => Some(0xBA),  // VK_OEM_1
+        '\'' => Some(0xDE), // VK_OEM_7
+        '-' => Some(0xBD),  // VK_OEM_MINUS
+        '=' => Some(0xBB),  // VK_OEM_PLUS
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() {
+        let parse = |modifiers: &[&str], key| {
+            let modifiers: Vec<String> = modifiers.iter().map(|m| m.to_string()).collect();
+            Hotkey::parse(&modifiers, key)
+        };
+        assert_eq!(parse(&["ctrl", "shift"], "g"), Ok(Hotkey::DEFAULT));
+        assert_eq!(Hotkey::DEFAULT.to_string(), "Ctrl+Shift+G");
+        let hotkey = parse(&["Shift", "Win", "Alt"], "space").unwrap();
+        assert_eq!(hotkey.to_string(), "Alt+Shift+Win+Space");
+        assert_eq!(parse(&[], "F13").unwrap().to_string(), "F13");
+        assert_eq!(parse(&["ctrl"], "`").unwrap().vk, 0xC0);
+        assert_eq!(Hotkey::unpack(hotkey.pack()), hotkey);
+
+        assert!(parse(&["hyper"], "G").is_err());
+        assert!(parse(&["ctrl"], "F25").is_err());
+        assert!(parse(&["ctrl"], "Enter").is_err());
+        // It would stop G typing anywhere
+        assert!(parse(&[], "G").is_err());
+        // Nor could capital G be typed
+        assert!(parse(&["shift"], "G").is_err());
+        assert!(parse(&["shift"], "space").is_err());
+        assert_eq!(parse(&["shift"], "F5").unwrap().to_string(), "Shift+F5");
+    }
+}
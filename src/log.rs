@@ -0,0 +1,144 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use windows::Win32::System::Console::{AllocConsole, AttachConsole, ATTACH_PARENT_PROCESS};
+
+use crate::bus;
+
+/// Also print to a console, attaching or opening one if needed
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+/// Characters of a window title logged unless verbose: enough to tell
+/// windows apart without recording which documents or pages were open
+const TITLE_CHARS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+/// Log a progress message, like println!
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+/// Log something that failed but that tactile-win can do without
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+/// Log a failure, like eprintln!
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Error, format_args!($($arg)*))
+    };
+}
+
+pub(crate) use {error, info, warning};
+
+/// Release builds have no console of their own, so the log lives here,
+/// started afresh each run
+fn log_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|p| p.join(".tactile-win.log"))
+}
+
+/// Use the console of the shell that started us, or open a new one
+pub fn attach_console() {
+    unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS).is_err() {
+            let _ = AllocConsole();
+        }
+    }
+}
+
+/// Start logging to the log file, and to a console if `verbose`
+pub fn init(verbose: bool) {
+    if verbose {
+        attach_console();
+    }
+    VERBOSE.store(verbose, Ordering::SeqCst);
+    let file = log_path().and_then(|path| File::create(path).ok());
+    if let Ok(mut guard) = LOG_FILE.lock() {
+        *guard = file;
+    }
+}
+
+/// Log every app event as it's delivered
+pub fn register() {
+    bus::subscribe(|event| info!("{}", event));
+}
+
+fn format_line(level: Level, args: fmt::Arguments) -> String {
+    match level {
+        Level::Info => args.to_string(),
+        Level::Warn => format!("warning: {}", args),
+        Level::Error => format!("error: {}", args),
+    }
+}
+
+/// `title`, quoted, for a log line: whole when logging verbosely, else
+/// cut to its first TITLE_CHARS characters
+pub fn window_title(title: &str) -> String {
+    shorten_title(title, VERBOSE.load(Ordering::SeqCst))
+}
+
+fn shorten_title(title: &str, whole: bool) -> String {
+    let mut chars = title.chars();
+    let kept: String = chars.by_ref().take(TITLE_CHARS).collect();
+    if whole || chars.next().is_none() {
+        format!("{:?}", title)
+    } else {
+        format!("{:?}...", kept)
+    }
+}
+
+pub fn write(level: Level, args: fmt::Arguments) {
+    let line = format_line(level, args);
+    if VERBOSE.load(Ordering::SeqCst) {
+        match level {
+            Level::Info => println!("{}", line),
+            Level::Warn | Level::Error => eprintln!("{}", line),
+        }
+    }
+    if let Ok(mut guard) = LOG_FILE.lock()
+        && let Some(ref mut file) = *guard
+    {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line(Level::Info, format_args!("Grid: {}x{}", 4, 2)),
+            "Grid: 4x2"
+        );
+        assert_eq!(
+            format_line(Level::Error, format_args!("Failed: {}", "denied")),
+            "error: Failed: denied"
+        );
+    }
+
+    #[test]
+    fn test_shorten_title() {
+        let title = "Budget 2026.xlsx - Excel";
+        assert_eq!(shorten_title(title, false), "\"Budget 2\"...");
+        assert_eq!(shorten_title(title, true), "\"Budget 2026.xlsx - Excel\"");
+        assert_eq!(shorten_title("Notepad", false), "\"Notepad\"");
+        // Cut by characters, not bytes
+        assert_eq!(shorten_title("Überblick", false), "\"Überblic\"...");
+    }
+}
@@ -1,65 +1,323 @@
+// Release builds are GUI programs, so starting at login never flashes a
+// console; run with --verbose to log to one
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+mod accessibility;
+mod batch;
+mod bus;
+mod command;
 mod config;
+mod configdoc;
+mod deferred;
+mod desktop;
+mod diagnostics;
+mod enumwin;
+mod events;
 mod grid;
+mod history;
+mod icon;
+mod identify;
+mod ipc;
+mod jumplist;
 mod keyboard;
+mod keys;
+mod log;
+mod managed;
+mod opacity;
 mod overlay;
+mod picker;
+mod placement;
+mod practice;
+mod region;
+mod rules;
 mod selection;
 mod settings;
+mod snapgroup;
+mod snapshot;
+mod splitter;
+mod stats;
+mod status;
+mod struts;
+mod toast;
 mod tray;
+mod update;
+mod watch;
 mod window;
+mod wizard;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use windows::core::{w, PCWSTR};
-use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Foundation::{HANDLE, HWND, LPARAM, LRESULT, RECT, WPARAM};
 use windows::Win32::Graphics::Gdi::HMONITOR;
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Power::{
+    RegisterSuspendResumeNotification, UnregisterSuspendResumeNotification,
+};
+use windows::Win32::System::RemoteDesktop::{
+    WTSRegisterSessionNotification, WTSUnRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+};
+use windows::Win32::System::SystemInformation::GetLocalTime;
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_CONTROL, MOD_SHIFT, VK_G,
+    RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_SHIFT,
+    VIRTUAL_KEY, VK_DOWN, VK_F, VK_H, VK_LEFT, VK_RIGHT, VK_UP,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
-    TranslateMessage, HWND_MESSAGE, MSG, WINDOW_EX_STYLE, WINDOW_STYLE, WM_HOTKEY, WNDCLASSW,
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, KillTimer, PostMessageW,
+    RegisterClassW, SetTimer, TranslateMessage, DEVICE_NOTIFY_WINDOW_HANDLE, HWND_MESSAGE, MSG,
+    PBT_APMRESUMEAUTOMATIC, WINDOW_EX_STYLE, WINDOW_STYLE, WM_COPYDATA, WM_HOTKEY, WM_INPUT,
+    WM_POWERBROADCAST, WM_TIMER, WM_WTSSESSION_CHANGE, WNDCLASSW, WTS_CONSOLE_CONNECT,
+    WTS_CONSOLE_DISCONNECT, WTS_REMOTE_CONNECT, WTS_REMOTE_DISCONNECT, WTS_SESSION_LOCK,
+    WTS_SESSION_UNLOCK,
 };
 
-use crate::config::Config;
-use crate::grid::Grid;
-use crate::keyboard::{install_keyboard_hook, set_hook_active, uninstall_keyboard_hook, KeyInput};
-use crate::overlay::Overlay;
-use crate::selection::{SelectionState, TileSelector};
-use crate::settings::show_settings;
-use crate::tray::{set_settings_callback, TrayIcon};
+use crate::accessibility::{announce, describe_span, text_scale_percent};
+use crate::batch::{match_steps, present_steps, run_transaction, PlannedMove};
+use crate::bus::{deliver_pending, publish, AppEvent, WM_APP_EVENT};
+use crate::command::{Command, WM_RUN_COMMAND};
+use crate::config::{
+    AfterMove, Config, InputBackend, OpacityStep, WhenBusy, WhenMaximized, WhenOpen,
+};
+use crate::deferred::{DeferredMoves, Poll};
+use crate::desktop::{current_desktop, watch_desktops, WM_DESKTOP_SWITCHED};
+use crate::diagnostics::{format_report, run_checks, show_diagnostics};
+use crate::enumwin::{list_windows, surviving_target, Win32Windows, WindowEntry, WindowFilter};
+use crate::events::{subscribe, unsubscribe_all, WindowEvent};
+use crate::grid::{scale_for_dpi, Grid, TilePos, BASE_DPI};
+use crate::icon::IconState;
+use crate::identify::identify_monitors;
+use crate::ipc::Status;
+use crate::jumplist::register_jump_list;
+use crate::keyboard::{
+    check_hook_watchdog, grid_hotkey, handle_hotkey_input, install_hotkey_input,
+    install_keyboard_hook, is_hook_active, is_hook_installed, key_label, note_key_activity,
+    pause_keyboard_hook, reinstall_keyboard_hook, release_stuck_modifiers, set_grid_hotkey,
+    set_hook_active, set_monitor_keys, set_physical_keys, swallow_key_up, uninstall_keyboard_hook,
+    watch_key_activity, Direction, KeyInput,
+};
+use crate::keys::Hotkey;
+use crate::log::{attach_console, error, info, warning, window_title};
+use crate::managed::ManagedWindows;
+use crate::opacity::{reset_all, step_opacity};
+use crate::overlay::{
+    set_work_area_changed_callback, snapshot, start_latency_probe, Overlay, PreviewRect,
+};
+use crate::placement::PlacementStore;
+use crate::practice::{is_practice_window, record_practice_move, start_practice};
+use crate::rules::{is_allowed, placement_rule, PlacementStep};
+use crate::selection::{Axis, SelectionState, TileSelector};
+use crate::settings::{show_settings, tile_size_warning};
+use crate::snapgroup::{find_group, group_spans, is_stacked};
+use crate::splitter::{
+    find_shared_edges, is_edge_covered, restack_splitters, set_dragged_callback, show_splitters,
+    step_split, SharedEdge,
+};
+use crate::status::{Area, ConfigSummary, MonitorStatus, StatusReport};
+use crate::struts::{refresh_docks, set_reserved};
+use crate::toast::WM_TOAST_ANSWER;
+use crate::tray::{
+    notify, restore_tray_icon, set_diagnostics_callback, set_end_session_callback,
+    set_equalize_callback, set_export_history_callback, set_hidden_callback, set_hidden_menu,
+    set_identify_callback, set_practice_callback, set_preview_equalize_callback,
+    set_profile_callback, set_profile_menu, set_restore_callback, set_restore_settings_callback,
+    set_save_snapshot_callback, set_settings_callback, set_setup_monitors_callback,
+    set_show_grid_callback, set_tray_state, TrayIcon,
+};
+use crate::watch::{watch_config, WM_CONFIG_FILE_CHANGED};
 use crate::window::{
-    get_all_monitors, get_foreground_window, get_monitor_work_area, get_window_monitor,
-    get_work_area, move_window,
+    bring_to_foreground, busy_reason, find_target_window, get_all_monitors, get_foreground_window,
+    get_monitor_bounds, get_monitor_device, get_monitor_dpi, get_monitor_excluded,
+    get_monitor_names, get_monitor_work_area, get_target_windows, get_window_info,
+    get_window_monitor, get_window_rect, get_window_title, get_work_area, hide_window,
+    is_maximized, is_minimized, is_own_window, is_target_candidate, maximize_window,
+    minimize_window, move_window, move_window_and_wait, rects_intersect, restore_if_maximized,
+    shrink_percent, unhide_window,
 };
+use crate::wizard::show_wizard;
 
 const CLASS_NAME: PCWSTR = w!("TactileWinClass");
 const HOTKEY_ID: i32 = 1;
+const FLOAT_HOTKEY_ID: i32 = 2;
+const HIDE_HOTKEY_ID: i32 = 7;
+// Ctrl+Alt+Shift+Arrow moves the active window's split that way
+const SPLIT_HOTKEYS: [(i32, VIRTUAL_KEY, Direction); 4] = [
+    (3, VK_LEFT, Direction::Left),
+    (4, VK_UP, Direction::Up),
+    (5, VK_RIGHT, Direction::Right),
+    (6, VK_DOWN, Direction::Down),
+];
+// Layout hotkey ids are this plus the layout's index in the config
+const LAYOUT_HOTKEY_BASE: i32 = 0x100;
+// Periodically verifies the keyboard hook still receives input
+const WATCHDOG_TIMER_ID: usize = 1;
+const WATCHDOG_INTERVAL_MS: u32 = 30_000;
+// Polls windows that were busy or animating when they were to be moved
+const DEFER_TIMER_ID: usize = 2;
+const DEFER_POLL_MS: u32 = 100;
+const DEFER_TIMEOUT: Duration = Duration::from_secs(30);
+// Draws the overlay once behavior.show_delay_ms has passed
+const SHOW_TIMER_ID: usize = 3;
+// Reloads the config file once edits to it have settled, as editors may
+// write it in several steps
+const RELOAD_TIMER_ID: usize = 4;
+const RELOAD_DELAY_MS: u32 = 300;
+// Saves the placement statistics, if any placements were recorded
+const STATS_TIMER_ID: usize = 5;
+const STATS_SAVE_MS: u32 = 60_000;
+/// Draws the overlay after any keys already typed have been handled
+const WM_SHOW_OVERLAY: u32 = 0x8004; // WM_APP + 4
 
 thread_local! {
     static APP_STATE: RefCell<Option<AppState>> = const { RefCell::new(None) };
 }
+// Whether the grid hotkey is registered, for diagnostics
+static HOTKEY_REGISTERED: AtomicBool = AtomicBool::new(false);
+// Each configured layout's hotkey, None where it can't be used
+static LAYOUT_HOTKEYS: Mutex<Vec<Option<Hotkey>>> = Mutex::new(Vec::new());
 
 struct AppState {
     config: Config,
+    /// Overlay for the monitor currently being tiled
     overlay: Option<Overlay>,
+    overlay_monitor: Option<isize>,
+    /// Whether the overlay is open, so the hotkey can close it; it may not
+    /// be drawn yet while the show delay runs
+    overlay_visible: bool,
+    /// Hidden overlays for other monitors, keyed by HMONITOR
+    cached_overlays: HashMap<isize, Overlay>,
     selector: Option<TileSelector>,
     target_hwnd: Option<HWND>,
     monitors: Vec<HMONITOR>,
     current_monitor_idx: usize,
     current_work_area: Option<RECT>,
+    cancelled: Option<CancelledSelection>,
+    /// Placements waiting for their windows to leave a modal loop or
+    /// finish animating
+    deferred: DeferredMoves<DeferredMove>,
+    /// Hidden message window, which owns the timers
+    hwnd: HWND,
+    /// Window rects from before they were first tiled
+    placements: PlacementStore,
+    /// Which windows are tiled, and which the user floated
+    managed: ManagedWindows,
+    /// Index into config.profiles of the profile in use
+    profile: Option<usize>,
+    /// Profile picked from the tray, in place of following the monitors
+    profile_override: Option<usize>,
+    /// What the open overlay previews, if it does instead of picking a
+    /// tile for the target
+    previewing: Option<Preview>,
+    /// Monitors connected when last counted, to notice them changing
+    monitor_count: usize,
+    /// Edges between tiled windows that have a splitter
+    split_edges: Vec<SharedEdge>,
+    /// Where to move the windows the last one tiled was snapped alongside,
+    /// if the offer is taken up
+    snap_group: Vec<(HWND, RECT)>,
+    /// Selection outlined and waiting for Enter, with its description,
+    /// when confirm_with_enter is on
+    pending: Option<(RECT, Option<String>)>,
+    /// The last window a placement shrank past confirm_shrink_percent and
+    /// how it was, if the offer to undo it is taken up
+    shrunk: Option<ShrunkWindow>,
+    /// Windows hidden to the tray, with their titles, oldest first
+    hidden: Vec<(HWND, String)>,
+    /// Whether the hotkeys and keyboard hook were set aside with the Pause
+    /// command
+    paused: bool,
+    /// Whether the keyboard hook is paused until the session is unlocked
+    locked: bool,
+}
+
+/// How a window is put in its new place
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MoveKind {
+    /// Tile it, remembering where it was before
+    Tile,
+    /// Tile it, then maximize it
+    Maximize,
+    /// Put it back at its remembered placement
+    Restore,
+}
+
+/// Moves the overlay can outline without making them
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Preview {
+    /// Equalize Windows, on the monitor shown
+    Equalize,
+    /// Placing config.layouts[index]
+    Layout(usize),
+}
+
+/// A tile placement held back while its window was busy or animating
+struct DeferredMove {
+    rect: RECT,
+    after_move: AfterMove,
+    kind: MoveKind,
+    /// What to announce once the window has moved
+    announcement: Option<String>,
+}
+
+/// Where a window was, and whether it was maximized or minimized, before
+/// a placement shrank it
+#[derive(Clone, Copy)]
+struct ShrunkWindow {
+    hwnd: HWND,
+    rect: RECT,
+    maximized: bool,
+    minimized: bool,
+}
+
+/// First-key selection kept when the overlay is cancelled with Escape
+struct CancelledSelection {
+    first: TilePos,
+    target: HWND,
+    monitor_idx: usize,
+    at: Instant,
 }
 
 fn handle_hotkey() {
     APP_STATE.with(|state| {
         let mut state = state.borrow_mut();
         if let Some(ref mut app) = *state {
-            // Get the foreground window before showing overlay
-            app.target_hwnd = get_foreground_window();
+            // A preview gives way to the grid
+            if app.overlay_visible && app.previewing.is_some() {
+                close_overlay(app);
+            }
+
+            // Already open: don't retarget or rebuild mid-selection
+            if app.overlay_visible {
+                if app.config.behavior.when_open == WhenOpen::Close {
+                    cancel_overlay(app);
+                }
+                return;
+            }
+
+            // Pick the target before showing overlay; when opened from the tray
+            // the foreground window is our own, so fall back to the last app window
+            app.target_hwnd = find_target_window();
+
+            // Windows excluded by the block/allow rules ignore the hotkey
+            if let Some(target) = app.target_hwnd
+                && !is_allowed(&app.config.rules, &get_window_info(target))
+            {
+                app.target_hwnd = None;
+                return;
+            }
 
             if let Some(target) = app.target_hwnd {
+                if get_foreground_window() != Some(target) {
+                    bring_to_foreground(target);
+                }
+
                 // Get all monitors and find which one the window is on
                 app.monitors = get_all_monitors();
+                refresh_docks();
+                update_profile(app);
                 let window_monitor = get_window_monitor(target);
 
                 // Find the index of the current monitor
@@ -69,90 +327,1615 @@ fn handle_hotkey() {
                     .position(|&m| m == window_monitor)
                     .unwrap_or(0);
 
-                if let Some(work_area) = get_work_area(target) {
-                    app.current_work_area = Some(work_area);
-                    show_overlay_on_work_area(app, work_area);
-                }
+                if let Some(work_area) = get_work_area(target) {
+                    app.current_work_area = Some(work_area);
+                    show_overlay_on_work_area(app, window_monitor, work_area);
+                    if let Some(ref selector) = app.selector {
+                        let grid = selector.grid();
+                        announce(
+                            app.config.accessibility.announce,
+                            &format!("Grid open, {} by {}", grid.cols, grid.rows),
+                        );
+                    }
+                    restore_cancelled_selection(app, target);
+                }
+            }
+        }
+    });
+}
+
+/// Resume a first-key selection cancelled moments ago for the same window
+fn restore_cancelled_selection(app: &mut AppState, target: HWND) {
+    let Some(cancelled) = app.cancelled.take() else {
+        return;
+    };
+    let window = Duration::from_secs(app.config.behavior.restore_selection_secs as u64);
+    if cancelled.target != target
+        || cancelled.monitor_idx != app.current_monitor_idx
+        || cancelled.at.elapsed() > window
+    {
+        return;
+    }
+
+    if let Some(ref mut selector) = app.selector
+        && let SelectionState::FirstKeyPressed(pos) = selector.resume(cancelled.first)
+        && let Some(ref overlay) = app.overlay
+    {
+        overlay.set_highlight(Some(pos));
+    }
+}
+
+/// Switch to the profile for the monitors now connected, unless one was
+/// picked from the tray, and bring the tray's Profile submenu up to date
+fn update_profile(app: &mut AppState) {
+    let count = app.config.profiles.len();
+    let profile = match app.profile_override.filter(|&index| index < count) {
+        Some(index) => Some(index),
+        None => app.config.auto_profile(get_all_monitors().len()),
+    };
+    if profile != app.profile
+        && let Some(active) = profile.and_then(|index| app.config.profiles.get(index))
+    {
+        info!(
+            "Switched to the {} profile ({}x{})",
+            active.name, active.cols, active.rows
+        );
+    }
+    app.profile = profile;
+
+    let names = app.config.profiles.iter().map(|p| p.name.clone()).collect();
+    set_profile_menu(names, profile, app.profile_override.is_none());
+}
+
+/// Keep the app's own state in step with the config and the monitors;
+/// other subsystems register their own subscribers
+fn follow_app_events(event: &AppEvent) {
+    follow_profile(event);
+    follow_hotkey(event);
+    follow_layouts(event);
+    if matches!(
+        event,
+        AppEvent::ConfigChanged | AppEvent::MonitorsChanged { .. }
+    ) {
+        follow_splits();
+    }
+}
+
+/// Pick the profile afresh when the config or the monitors change
+fn follow_profile(event: &AppEvent) {
+    if !matches!(
+        event,
+        AppEvent::ConfigChanged | AppEvent::MonitorsChanged { .. }
+    ) {
+        return;
+    }
+    APP_STATE.with(|state| {
+        if let Some(ref mut app) = *state.borrow_mut() {
+            update_profile(app);
+        }
+    });
+}
+
+/// Register the grid hotkey again when [hotkey] changes, going back to the
+/// old one if the new one is taken
+fn follow_hotkey(event: &AppEvent) {
+    if !matches!(event, AppEvent::ConfigChanged) {
+        return;
+    }
+    let Some((hwnd, hotkey, paused)) = APP_STATE.with(|state| {
+        let state = state.borrow();
+        let app = state.as_ref()?;
+        Some((app.hwnd, configured_hotkey(&app.config), app.paused))
+    }) else {
+        return;
+    };
+    let previous = grid_hotkey();
+    if hotkey == previous {
+        return;
+    }
+    // Registered on resume
+    if paused {
+        set_grid_hotkey(hotkey);
+        return;
+    }
+    unsafe {
+        let _ = UnregisterHotKey(Some(hwnd), HOTKEY_ID);
+    }
+    set_grid_hotkey(hotkey);
+    if let Err(e) = register_grid_hotkey(hwnd) {
+        warning!("Failed to register {}: {}", hotkey, e);
+        notify(
+            "Hotkey unavailable",
+            &format!(
+                "{} is taken by another program; {} still opens the grid.",
+                hotkey, previous
+            ),
+        );
+        set_grid_hotkey(previous);
+        if let Err(e) = register_grid_hotkey(hwnd) {
+            error!("Failed to register {} again: {}", previous, e);
+        }
+    }
+}
+
+/// Take each of `config`'s layouts' hotkeys, logging the ones that can't
+/// be used. register_hotkey registers them.
+fn set_layout_hotkeys(config: &Config) {
+    let hotkeys = config
+        .layouts
+        .iter()
+        .map(|layout| {
+            // Layouts placed only on switching desktops have no hotkey
+            if layout.key.is_empty() && layout.desktop.is_some() {
+                return None;
+            }
+            layout
+                .hotkey()
+                .inspect_err(|e| warning!("Ignoring layout \"{}\": {}", layout.name, e))
+                .ok()
+        })
+        .collect();
+    if let Ok(mut guard) = LAYOUT_HOTKEYS.lock() {
+        *guard = hotkeys;
+    }
+}
+
+/// The layout hotkey `id` belongs to, if it's one
+fn layout_hotkey(id: i32) -> Option<Hotkey> {
+    let index = usize::try_from(id.checked_sub(LAYOUT_HOTKEY_BASE)?).ok()?;
+    LAYOUT_HOTKEYS.lock().ok()?.get(index).copied().flatten()
+}
+
+/// Register the layouts' hotkeys; ones another program has are logged
+/// and left out
+fn register_layout_hotkeys(hwnd: HWND) {
+    let hotkeys = LAYOUT_HOTKEYS
+        .lock()
+        .map(|hotkeys| hotkeys.clone())
+        .unwrap_or_default();
+    for (index, hotkey) in hotkeys.into_iter().enumerate() {
+        let Some(hotkey) = hotkey else {
+            continue;
+        };
+        let id = LAYOUT_HOTKEY_BASE + index as i32;
+        let modifiers = HOT_KEY_MODIFIERS(hotkey.modifiers);
+        if let Err(e) = unsafe { RegisterHotKey(Some(hwnd), id, modifiers, hotkey.vk) } {
+            warning!("Failed to register layout hotkey {}: {}", hotkey, e);
+        }
+    }
+}
+
+fn unregister_layout_hotkeys(hwnd: HWND) {
+    let count = LAYOUT_HOTKEYS.lock().map_or(0, |hotkeys| hotkeys.len());
+    for index in 0..count {
+        unsafe {
+            let _ = UnregisterHotKey(Some(hwnd), LAYOUT_HOTKEY_BASE + index as i32);
+        }
+    }
+}
+
+/// Register the layouts' hotkeys again when the config changes, and list
+/// the layouts on the jump list if they changed
+fn follow_layouts(event: &AppEvent) {
+    if !matches!(event, AppEvent::ConfigChanged) {
+        return;
+    }
+    APP_STATE.with(|state| {
+        if let Some(ref app) = *state.borrow() {
+            unregister_layout_hotkeys(app.hwnd);
+            set_layout_hotkeys(&app.config);
+            if !app.paused {
+                register_layout_hotkeys(app.hwnd);
+            }
+            if let Err(e) = register_jump_list(&layout_names(&app.config)) {
+                warning!("Failed to update jump list: {}", e);
+            }
+        }
+    });
+}
+
+fn layout_names(config: &Config) -> Vec<String> {
+    config
+        .layouts
+        .iter()
+        .map(|layout| layout.name.clone())
+        .collect()
+}
+
+/// Place the windows of layout `index` that are open, all or none. A
+/// layout for another profile than the active one does nothing.
+fn apply_layout(index: usize) {
+    let Some((layout, steps)) = APP_STATE.with(|state| {
+        let state = state.borrow();
+        let app = state.as_ref()?;
+        let layout = app.config.layouts.get(index)?.clone();
+        let profile = app.profile.and_then(|index| app.config.profiles.get(index));
+        if let Some(ref wanted) = layout.profile
+            && profile.is_none_or(|profile| profile.name != *wanted)
+        {
+            info!(
+                "Layout {} is for profile {}; not applied",
+                layout.name, wanted
+            );
+            return None;
+        }
+        let windows: Vec<WindowEntry> = get_target_windows()
+            .into_iter()
+            .filter(|entry| is_allowed(&app.config.rules, &entry.info))
+            .collect();
+        let steps = present_steps(&layout.steps, &windows);
+        Some((layout, steps))
+    }) else {
+        return;
+    };
+    if steps.is_empty() {
+        info!("Layout {}: none of its windows are open", layout.name);
+        return;
+    }
+    match run_batch(&steps, Instant::now() + ipc::REQUEST_TIMEOUT) {
+        Status::Done => {
+            info!(
+                "Layout {}: placed {} of {} windows",
+                layout.name,
+                steps.len(),
+                layout.steps.len()
+            );
+            APP_STATE.with(|state| {
+                if let Some(ref app) = *state.borrow() {
+                    announce(
+                        app.config.accessibility.announce,
+                        &format!("Layout {}", layout.name),
+                    );
+                }
+            });
+        }
+        Status::Busy => notify(
+            "Layout not applied",
+            &format!(
+                "{}: another placement was still running; try again.",
+                layout.name
+            ),
+        ),
+        Status::TimedOut => notify(
+            "Layout not applied",
+            &format!(
+                "{}: windows were slow to move, so the layout was undone.",
+                layout.name
+            ),
+        ),
+        _ => notify(
+            "Layout not applied",
+            &format!(
+                "{}: a window didn't end up in place, so the layout was undone.",
+                layout.name
+            ),
+        ),
+    }
+}
+
+/// Place the layout for virtual desktop `index`, counting from 0, if a
+/// layout names that desktop
+fn apply_desktop_layout(index: usize) {
+    let layout = APP_STATE.with(|state| {
+        let state = state.borrow();
+        let app = state.as_ref()?;
+        let desktop = u32::try_from(index + 1).ok();
+        app.config
+            .layouts
+            .iter()
+            .position(|layout| layout.desktop.is_some() && layout.desktop == desktop)
+    });
+    if let Some(layout) = layout {
+        apply_layout(layout);
+    }
+}
+
+/// Use profile `choice` from now on, or follow the monitors again if None
+fn choose_profile(choice: Option<usize>) {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(ref mut app) = *state {
+            app.profile_override = choice;
+            update_profile(app);
+        }
+    });
+}
+
+/// Grid from config for a monitor's work area, and the DPI its sizes use.
+/// Placement rules matching `window` change its size, gap and margin.
+fn grid_for_monitor(
+    app: &AppState,
+    window: Option<HWND>,
+    monitor: HMONITOR,
+    work_area: RECT,
+) -> (Grid, u32) {
+    let config = &app.config;
+    // Gap and label sizes are in DIPs when dpi_scaling is enabled
+    let dpi = if config.grid.dpi_scaling {
+        get_monitor_dpi(monitor)
+    } else {
+        BASE_DPI
+    };
+
+    // Only look up the desktop when some desktop has a grid of its own
+    let desktop = if config.grid.desktops.is_empty() {
+        None
+    } else {
+        current_desktop()
+    };
+    // Likewise the monitor's names, which take a call to the driver
+    let names = if config.grid.monitors.is_empty() {
+        Vec::new()
+    } else {
+        get_monitor_names(monitor)
+    };
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let (cols, rows) = config.grid_dimensions(desktop, &names, app.profile);
+
+    let rule = window.and_then(|hwnd| placement_rule(&config.rules, &get_window_info(hwnd)));
+    let cols = rule.and_then(|rule| rule.cols).unwrap_or(cols);
+    let rows = rule.and_then(|rule| rule.rows).unwrap_or(rows);
+    let gap = rule.and_then(|rule| rule.gap).unwrap_or(config.grid.gap);
+    let margin = rule.and_then(|rule| rule.margin).unwrap_or(gap);
+    let grid = Grid::new(cols, rows, scale_for_dpi(gap, dpi), work_area)
+        .with_margin(scale_for_dpi(margin, dpi))
+        .with_min_tile_size(scale_for_dpi(config.grid.min_tile_size, dpi))
+        .with_keyboard_layout(config.grid.keyboard_layout);
+    (grid, dpi)
+}
+
+fn show_overlay_on_work_area(app: &mut AppState, monitor: HMONITOR, work_area: RECT) {
+    app.pending = None;
+    let (grid, dpi) = grid_for_monitor(app, app.target_hwnd, monitor, work_area);
+
+    // Park the previous monitor's overlay and bring out this one's, so
+    // cycling monitors just swaps windows instead of resizing one
+    let monitor_key = monitor.0 as isize;
+    if app.overlay_monitor != Some(monitor_key) {
+        if let (Some(previous), Some(previous_key)) = (app.overlay.take(), app.overlay_monitor) {
+            previous.hide();
+            app.cached_overlays.insert(previous_key, previous);
+        }
+        app.overlay = app.cached_overlays.remove(&monitor_key);
+        app.overlay_monitor = Some(monitor_key);
+    }
+
+    // Create overlay if needed, or update existing
+    if app.overlay.is_none() {
+        app.overlay = Overlay::new(work_area, &app.config).ok();
+    }
+    if let Some(ref overlay) = app.overlay {
+        overlay.update_position(work_area, get_monitor_excluded(monitor));
+        overlay.set_dpi(dpi);
+        overlay.set_second_key(app.config.grid.second_key);
+        overlay.set_target_rect(app.target_hwnd.and_then(get_window_rect));
+        overlay.set_grid(grid.clone());
+        if let Some(previewing) = app.previewing {
+            let moves = match previewing {
+                Preview::Equalize => equalize_moves(app, monitor, work_area),
+                Preview::Layout(index) => layout_moves(app, index),
+            };
+            let preview = preview_moves(&moves, work_area);
+            info!("Preview: {} windows would move", preview.len());
+            announce(
+                app.config.accessibility.announce,
+                &format!("Preview, {} windows would move", preview.len()),
+            );
+            overlay.set_preview(preview);
+        } else {
+            overlay.set_preview(Vec::new());
+        }
+    }
+
+    publish(AppEvent::OverlayShown {
+        monitor: app.current_monitor_idx + 1,
+        cols: grid.cols,
+        rows: grid.rows,
+    });
+
+    // Create selector
+    app.selector = Some(
+        TileSelector::new(grid)
+            .with_bindings(&app.config.grid.bindings)
+            .with_second_key(app.config.grid.second_key),
+    );
+
+    // Activate the keyboard hook now but draw the overlay later: after the
+    // show delay, or else once keys already typed have been handled, so a
+    // chord typed before then moves the window without flashing the grid.
+    // Moving to another monitor shows it straight away.
+    if let Some(ref overlay) = app.overlay {
+        let delay = app.config.behavior.show_delay_ms;
+        unsafe {
+            if app.overlay_visible {
+                overlay.show();
+            } else if delay == 0 {
+                // Hook input is dispatched ahead of posted messages
+                let _ = PostMessageW(Some(app.hwnd), WM_SHOW_OVERLAY, WPARAM(0), LPARAM(0));
+            } else {
+                SetTimer(Some(app.hwnd), SHOW_TIMER_ID, delay, None);
+            }
+        }
+        app.overlay_visible = true;
+        set_monitor_keys(app.config.grid.monitor_keys);
+        set_physical_keys(app.config.grid.physical_keys);
+        set_hook_active(true);
+    }
+}
+
+/// Draw the overlay if it is still open, i.e. the keys typed so far didn't
+/// finish the selection
+fn show_pending_overlay() {
+    APP_STATE.with(|state| {
+        let state = state.borrow();
+        if let Some(ref app) = *state
+            && app.overlay_visible
+            && let Some(ref overlay) = app.overlay
+        {
+            overlay.show();
+        }
+    });
+}
+
+/// Rebuild the open overlay's grid if its monitor's work area changed, e.g.
+/// the taskbar auto-hid or a toolbar docked while the overlay was showing
+fn refresh_work_area() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        // Also sent when monitors are connected or disconnected
+        let count = get_all_monitors().len();
+        if count != app.monitor_count {
+            app.monitor_count = count;
+            publish(AppEvent::MonitorsChanged { count });
+        }
+        // The hook is only active while an overlay is showing
+        if !is_hook_active() {
+            return;
+        }
+        let Some(&monitor) = app.monitors.get(app.current_monitor_idx) else {
+            return;
+        };
+        let Some(work_area) = get_monitor_work_area(monitor) else {
+            return;
+        };
+        if app.current_work_area == Some(work_area) {
+            return;
+        }
+        app.current_work_area = Some(work_area);
+
+        if let Some(ref mut selector) = app.selector {
+            let grid = selector.grid().clone().with_work_area(work_area);
+            if let Some(ref overlay) = app.overlay {
+                overlay.update_position(work_area, get_monitor_excluded(monitor));
+                overlay.set_grid(grid.clone());
+            }
+            selector.set_grid(grid);
+        }
+    });
+}
+
+/// The grid span `hwnd`, at `rect`, mostly overlaps on its monitor
+fn snap_target(app: &AppState, hwnd: HWND, rect: RECT, work_area: RECT) -> RECT {
+    let (grid, _) = grid_for_monitor(app, Some(hwnd), get_window_monitor(hwnd), work_area);
+    let (first, last) = grid.rect_to_span(rect);
+    grid.combine_tiles(first, last)
+}
+
+/// Move `hwnd` from `rect` onto the grid span it mostly overlaps on its
+/// monitor. Returns whether the window was moved.
+fn snap_to_grid(app: &mut AppState, hwnd: HWND, rect: RECT, work_area: RECT) -> bool {
+    let snapped = snap_target(app, hwnd, rect, work_area);
+    if snapped == rect {
+        app.managed.mark_tiled(hwnd);
+        return false;
+    }
+    match move_window(hwnd, &snapped, AfterMove::Keep) {
+        Ok(()) => {
+            app.placements.remember(hwnd, rect);
+            app.managed.mark_tiled(hwnd);
+            true
+        }
+        Err(e) => {
+            error!("Failed to snap window: {}", e);
+            false
+        }
+    }
+}
+
+/// A window the user just dragged or resized has left the grid; snap it
+/// back on when snap_on_move is set
+fn handle_window_moved(hwnd: HWND) {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        if !is_target_candidate(hwnd) || app.managed.is_floating(hwnd) {
+            return;
+        }
+        if !app.config.behavior.snap_on_move
+            || is_maximized(hwnd)
+            || !is_allowed(&app.config.rules, &get_window_info(hwnd))
+        {
+            if app.managed.is_tiled(hwnd) {
+                info!("Tiled window moved by hand; no longer managed");
+                app.managed.mark_untiled(hwnd);
+            }
+        } else if let (Some(rect), Some(work_area)) = (get_window_rect(hwnd), get_work_area(hwnd)) {
+            snap_to_grid(app, hwnd, rect, work_area);
+        }
+        update_splitters(app, &[]);
+    });
+}
+
+/// Whether Equalize Windows snaps `entry`: maximized, minimized, floating
+/// and blocked windows are left alone
+fn is_equalized(app: &AppState, entry: &WindowEntry) -> bool {
+    !is_maximized(entry.hwnd)
+        && !is_minimized(entry.hwnd)
+        && !app.managed.is_floating(entry.hwnd)
+        && is_allowed(&app.config.rules, &entry.info)
+}
+
+/// Where Equalize Windows would move each window on `monitor`
+fn equalize_moves(app: &AppState, monitor: HMONITOR, work_area: RECT) -> Vec<PlannedMove> {
+    get_target_windows()
+        .into_iter()
+        .filter(|entry| {
+            entry.monitor == monitor
+                && is_equalized(app, entry)
+                && rects_intersect(entry.rect, work_area)
+        })
+        .map(|entry| PlannedMove {
+            hwnd: entry.hwnd,
+            from: entry.rect,
+            to: snap_target(app, entry.hwnd, entry.rect, work_area),
+        })
+        .collect()
+}
+
+/// Where placing layout `index` would move the windows it finds open
+fn layout_moves(app: &AppState, index: usize) -> Vec<PlannedMove> {
+    let Some(layout) = app.config.layouts.get(index) else {
+        return Vec::new();
+    };
+    let windows: Vec<WindowEntry> = get_target_windows()
+        .into_iter()
+        .filter(|entry| is_allowed(&app.config.rules, &entry.info))
+        .collect();
+    let steps = present_steps(&layout.steps, &windows);
+    plan_batch(app, &steps, &windows).unwrap_or_else(|e| {
+        info!("Layout {} can't be previewed: {}", layout.name, e);
+        Vec::new()
+    })
+}
+
+/// Outlines for the `moves` that land in `work_area`, labelled with their
+/// windows' programs. Windows already in place are left out.
+fn preview_moves(moves: &[PlannedMove], work_area: RECT) -> Vec<PreviewRect> {
+    moves
+        .iter()
+        .filter(|planned| planned.to != planned.from && rects_intersect(planned.to, work_area))
+        .map(|planned| PreviewRect {
+            rect: planned.to,
+            label: get_window_info(planned.hwnd).process,
+        })
+        .collect()
+}
+
+/// Dry run of Equalize Windows: open the overlay with each window's new
+/// place outlined, moving nothing. Tab previews the next monitor and
+/// Escape closes it.
+fn preview_equalize() {
+    open_preview(Preview::Equalize, None);
+}
+
+/// Dry run of placing layout `index`, shown as preview_equalize shows
+/// Equalize Windows, starting on the monitor of its first move
+fn preview_layout(index: usize) {
+    let first = APP_STATE.with(|state| {
+        let state = state.borrow();
+        let app = state.as_ref()?;
+        layout_moves(app, index).first().map(|planned| planned.hwnd)
+    });
+    open_preview(Preview::Layout(index), first);
+}
+
+/// Open the overlay outlining `preview`, on the monitor of `window` or
+/// else of the window last worked in
+fn open_preview(preview: Preview, window: Option<HWND>) {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        if app.overlay_visible {
+            close_overlay(app);
+        }
+        app.managed.prune(is_target_candidate);
+        app.monitors = get_all_monitors();
+        update_profile(app);
+
+        let monitor = window
+            .or_else(find_target_window)
+            .map(get_window_monitor)
+            .or_else(|| app.monitors.first().copied());
+        let Some(monitor) = monitor else {
+            return;
+        };
+        let Some(work_area) = get_monitor_work_area(monitor) else {
+            return;
+        };
+        app.current_monitor_idx = app.monitors.iter().position(|&m| m == monitor).unwrap_or(0);
+        app.current_work_area = Some(work_area);
+        app.target_hwnd = None;
+        app.previewing = Some(preview);
+        show_overlay_on_work_area(app, monitor, work_area);
+    });
+}
+
+/// Snap every window on screen back onto the grid, cleaning up drift from
+/// manual resizing. Maximized, minimized and floating windows are left
+/// alone.
+fn equalize_windows() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        app.managed.prune(is_target_candidate);
+        let mut moved = 0;
+        for entry in get_target_windows() {
+            if !is_equalized(app, &entry) {
+                continue;
+            }
+            let Some(work_area) = get_monitor_work_area(entry.monitor) else {
+                continue;
+            };
+            if rects_intersect(entry.rect, work_area)
+                && snap_to_grid(app, entry.hwnd, entry.rect, work_area)
+            {
+                moved += 1;
+            }
+        }
+        update_splitters(app, &[]);
+        info!("Equalized {} windows", moved);
+        announce(
+            app.config.accessibility.announce,
+            &format!("Equalized {} windows", moved),
+        );
+    });
+}
+
+/// Where each of `steps` would move the window among `windows` it
+/// matches; an error if one matches none
+fn plan_batch(
+    app: &AppState,
+    steps: &[PlacementStep],
+    windows: &[WindowEntry],
+) -> Result<Vec<PlannedMove>, String> {
+    let matched = match_steps(steps, windows)?;
+    let mut moves = Vec::new();
+    for (step, index) in steps.iter().zip(matched) {
+        let entry = &windows[index];
+        let work_area = get_monitor_work_area(entry.monitor)
+            .ok_or_else(|| format!("no work area for {}", entry.info.process))?;
+        let (grid, _) = grid_for_monitor(app, Some(entry.hwnd), entry.monitor, work_area);
+        moves.push(PlannedMove {
+            hwnd: entry.hwnd,
+            from: entry.rect,
+            to: step.region.resolve(&grid),
+        });
+    }
+    Ok(moves)
+}
+
+/// Move windows as a batch of `steps`, all or none: nothing moves unless
+/// every step matches a window, and if a move fails or `deadline` passes
+/// the windows already moved go back. Busy if the app state is in use, as
+/// when the batch arrives in a message sent while a handler is running.
+fn run_batch(steps: &[PlacementStep], deadline: Instant) -> Status {
+    APP_STATE.with(|state| {
+        let Ok(mut state) = state.try_borrow_mut() else {
+            return Status::Busy;
+        };
+        let Some(ref mut app) = *state else {
+            return Status::Failed;
+        };
+        let windows: Vec<WindowEntry> = get_target_windows()
+            .into_iter()
+            .filter(|entry| is_allowed(&app.config.rules, &entry.info))
+            .collect();
+        let moves = match plan_batch(app, steps, &windows) {
+            Ok(moves) => moves,
+            Err(e) => {
+                error!("Placement batch not run: {}", e);
+                return Status::Failed;
+            }
+        };
+        if let Err(e) = run_transaction(&moves, deadline, move_window_and_wait, get_window_rect) {
+            error!("Placement batch rolled back: {}", e.reason);
+            return e.status;
+        }
+        for planned in &moves {
+            app.placements.remember(planned.hwnd, planned.from);
+            app.managed.mark_tiled(planned.hwnd);
+        }
+        let placed: Vec<(HWND, RECT)> = moves
+            .iter()
+            .map(|planned| (planned.hwnd, planned.to))
+            .collect();
+        update_splitters(app, &placed);
+        info!("Placement batch moved {} windows", moves.len());
+        Status::Done
+    })
+}
+
+/// What the running instance is doing, for `--status`; None while the
+/// app state is in use
+fn status_report() -> Option<StatusReport> {
+    APP_STATE.with(|state| {
+        let state = state.try_borrow().ok()?;
+        let app = state.as_ref()?;
+        let profile = app.profile.and_then(|index| app.config.profiles.get(index));
+        let monitors = get_all_monitors()
+            .into_iter()
+            .map(|monitor| MonitorStatus {
+                device: get_monitor_device(monitor),
+                bounds: get_monitor_bounds(monitor).map(Area::from),
+                work_area: get_monitor_work_area(monitor).map(Area::from),
+                dpi: get_monitor_dpi(monitor),
+            })
+            .collect();
+        Some(StatusReport {
+            version: env!("CARGO_PKG_VERSION"),
+            config: ConfigSummary {
+                path: Config::config_path().map(|path| path.display().to_string()),
+                cols: profile.map_or(app.config.grid.cols, |profile| profile.cols),
+                rows: profile.map_or(app.config.grid.rows, |profile| profile.rows),
+                gap: app.config.grid.gap,
+                profile: profile.map(|profile| profile.name.clone()),
+                input: app.config.behavior.input,
+            },
+            monitors,
+            overlay_visible: app.overlay_visible,
+            keyboard_hook: is_hook_installed(),
+        })
+    })
+}
+
+/// Put every window tactile-win has tiled back where it was before, for
+/// switching from tiling back to free-form work
+fn restore_placements() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        let mut restored = 0;
+        for (hwnd, rect) in app.placements.take_all() {
+            // Closed windows are no longer candidates
+            if !is_target_candidate(hwnd) {
+                continue;
+            }
+            if is_minimized(hwnd) {
+                restore_if_maximized(hwnd);
+                let placement = DeferredMove {
+                    rect,
+                    after_move: AfterMove::Keep,
+                    kind: MoveKind::Restore,
+                    announcement: None,
+                };
+                defer_move(app, hwnd, placement);
+                restored += 1;
+            } else {
+                place_window(app, hwnd, rect, AfterMove::Keep, MoveKind::Restore);
+                restored += 1;
+            }
+        }
+        info!("Restored {} windows", restored);
+        announce(
+            app.config.accessibility.announce,
+            &format!("Restored {} windows", restored),
+        );
+    });
+}
+
+/// Float the foreground window, so equalize and snap_on_move leave it
+/// alone, or bring a floating window back under management
+fn toggle_floating() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        let Some(target) = find_target_window() else {
+            return;
+        };
+        let text = if app.managed.toggle_floating(target) {
+            "Window floating"
+        } else {
+            "Window no longer floating"
+        };
+        update_splitters(app, &[]);
+        info!("{}", text);
+        announce(app.config.accessibility.announce, text);
+    });
+}
+
+/// Hide `target`, listing it under the tray's Hidden Windows to show again
+fn hide_target(app: &mut AppState, target: HWND) {
+    let mut title = get_window_title(target);
+    if title.is_empty() {
+        title = "Untitled window".to_string();
+    }
+    hide_window(target);
+    info!("Hid {}", window_title(&title));
+    app.hidden.retain(|(hwnd, _)| *hwnd != target);
+    app.hidden.push((target, title));
+    update_hidden_menu(app);
+    announce(app.config.accessibility.announce, "Window hidden");
+}
+
+fn update_hidden_menu(app: &AppState) {
+    set_hidden_menu(app.hidden.iter().map(|(_, title)| title.clone()).collect());
+}
+
+/// Hide the foreground window to the tray
+fn hide_foreground() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        if let Some(target) = find_target_window() {
+            hide_target(app, target);
+        }
+    });
+}
+
+/// Show hidden window `index` again and activate it, or every hidden
+/// window if None
+fn show_hidden(index: Option<usize>) {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(ref mut app) = *state {
+            show_hidden_windows(app, index);
+        }
+    });
+}
+
+fn show_hidden_windows(app: &mut AppState, index: Option<usize>) {
+    let windows = match index {
+        Some(index) if index < app.hidden.len() => vec![app.hidden.remove(index)],
+        Some(_) => return,
+        None => std::mem::take(&mut app.hidden),
+    };
+    for (hwnd, title) in windows {
+        if !unhide_window(hwnd) {
+            info!("{} closed while hidden", window_title(&title));
+        } else if index.is_some() {
+            bring_to_foreground(hwnd);
+        }
+    }
+    update_hidden_menu(app);
+}
+
+/// Move the split between the active tiled window and its neighbor
+/// toward `direction`
+fn move_split(direction: Direction) {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        let Some(target) = find_target_window() else {
+            return;
+        };
+        let step = app.config.behavior.split_step as i32;
+        if !step_split(&shared_edges(app, &[]), target, direction, step) {
+            info!("No split to move {:?} of the active window", direction);
+            return;
+        }
+        update_splitters(app, &[]);
+    });
+}
+
+/// Move the overlay to monitor `index`, or cycle to the next one
+fn switch_to_monitor(index: Option<usize>) {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(ref mut app) = *state {
+            if app.monitors.len() <= 1 {
+                return; // Only one monitor, nothing to switch
+            }
+
+            let next = index.unwrap_or((app.current_monitor_idx + 1) % app.monitors.len());
+            if next >= app.monitors.len() || next == app.current_monitor_idx {
+                return;
+            }
+            app.current_monitor_idx = next;
+            let monitor = app.monitors[app.current_monitor_idx];
+
+            if let Some(work_area) = get_monitor_work_area(monitor) {
+                app.current_work_area = Some(work_area);
+
+                // Reset selection state
+                if let Some(ref mut selector) = app.selector {
+                    selector.reset();
+                }
+                if let Some(ref overlay) = app.overlay {
+                    overlay.set_highlight(None);
+                }
+
+                show_overlay_on_work_area(app, monitor, work_area);
+                announce(
+                    app.config.accessibility.announce,
+                    &format!(
+                        "Monitor {} of {}",
+                        app.current_monitor_idx + 1,
+                        app.monitors.len()
+                    ),
+                );
+            }
+        }
+    });
+}
+
+/// Move `target` to `rect`
+fn place_window(
+    app: &mut AppState,
+    target: HWND,
+    rect: RECT,
+    after_move: AfterMove,
+    kind: MoveKind,
+) {
+    if kind == MoveKind::Restore {
+        let _ = move_window(target, &rect, after_move);
+        app.managed.mark_untiled(target);
+        update_splitters(app, &[(target, rect)]);
+        return;
+    }
+    let original = get_window_rect(target);
+    if move_window(target, &rect, after_move).is_err() {
+        return;
+    }
+    if let Some(original) = original {
+        app.placements.remember(target, original);
+    }
+    app.managed.mark_tiled(target);
+    if kind == MoveKind::Maximize {
+        // Moved first so it maximizes on the grid's monitor
+        maximize_window(target);
+    }
+    update_splitters(app, &[(target, rect)]);
+}
+
+/// Edges shared by visible tiled windows on the same monitor, leaving out
+/// ones another window covers. `placed` gives where windows just moved
+/// are going, as their moves may not have landed yet.
+fn shared_edges(app: &AppState, placed: &[(HWND, RECT)]) -> Vec<SharedEdge> {
+    let rect_of = |hwnd: HWND, rect: RECT| {
+        placed
+            .iter()
+            .find(|&&(moved, _)| moved == hwnd)
+            .map_or(rect, |&(_, to)| to)
+    };
+    let windows: Vec<(HWND, RECT)> = app
+        .managed
+        .tiled()
+        .filter(|&hwnd| is_target_candidate(hwnd) && !is_minimized(hwnd) && !is_maximized(hwnd))
+        .filter_map(|hwnd| Some((hwnd, rect_of(hwnd, get_window_rect(hwnd)?))))
+        .collect();
+    let mut edges = find_shared_edges(&windows);
+    if edges.is_empty() {
+        return edges;
+    }
+    let stacked: Vec<(HWND, RECT)> = get_target_windows()
+        .into_iter()
+        .map(|entry| (entry.hwnd, rect_of(entry.hwnd, entry.rect)))
+        .collect();
+    edges.retain(|edge| {
+        get_window_monitor(edge.first) == get_window_monitor(edge.second)
+            && !is_edge_covered(edge, &stacked)
+    });
+    edges
+}
+
+/// Put a splitter on each grid line two tiled windows on the same monitor
+/// share, when drag_splits is on, and take down the rest
+fn update_splitters(app: &mut AppState, placed: &[(HWND, RECT)]) {
+    let edges = if app.config.behavior.drag_splits {
+        shared_edges(app, placed)
+    } else {
+        Vec::new()
+    };
+    if edges != app.split_edges {
+        show_splitters(&edges);
+        app.split_edges = edges;
+    } else {
+        restack_splitters();
+    }
+}
+
+/// Keep the splitters with their windows as windows move, minimize,
+/// restore or come to the front; the moves tactile-win makes land here
+/// too once they're done
+fn follow_window_changes(hwnd: HWND) {
+    APP_STATE.with(|state| {
+        // Called for every window; never wait on the app state for them
+        let Ok(mut state) = state.try_borrow_mut() else {
+            return;
+        };
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        if app.managed.is_tiled(hwnd) || !app.split_edges.is_empty() {
+            update_splitters(app, &[]);
+        }
+    });
+}
+
+/// Move the other splitters after one is dragged, or after the config
+/// turns them on or off
+fn follow_splits() {
+    APP_STATE.with(|state| {
+        if let Ok(mut state) = state.try_borrow_mut()
+            && let Some(ref mut app) = *state
+        {
+            update_splitters(app, &[]);
+        }
+    });
+}
+
+/// Move `target` once it is free and settled, polling on a timer
+fn defer_move(app: &mut AppState, target: HWND, placement: DeferredMove) {
+    app.deferred.push(
+        target,
+        placement,
+        get_window_rect(target),
+        Instant::now() + DEFER_TIMEOUT,
+    );
+    unsafe {
+        SetTimer(Some(app.hwnd), DEFER_TIMER_ID, DEFER_POLL_MS, None);
+    }
+}
+
+/// Write the placement history log out as CSV and JSON in Documents
+fn export_history() {
+    let records = history::load();
+    if records.is_empty() {
+        notify(
+            "No placement history",
+            "Set enabled = true under [history] in the config to record placements.",
+        );
+        return;
+    }
+    let Some(dir) = dirs::document_dir().or_else(dirs::home_dir) else {
+        return;
+    };
+    match history::export(&records, &dir) {
+        Ok(path) => {
+            info!("Exported placement history to {}", path.display());
+            notify(
+                "Placement history exported",
+                &format!("{} placements saved to {}", records.len(), dir.display()),
+            );
+        }
+        Err(e) => error!("Failed to export placement history: {}", e),
+    }
+}
+
+/// Save a picture of the grid as the overlay draws it on the current
+/// monitor to a PNG in Documents, for docs, sharing a layout or a bug report
+fn save_grid_snapshot() {
+    let Some(dir) = dirs::document_dir().or_else(dirs::home_dir) else {
+        return;
+    };
+    let picture = APP_STATE.with(|state| {
+        let state = state.borrow();
+        let app = state.as_ref()?;
+        let monitor = app
+            .monitors
+            .get(app.current_monitor_idx)
+            .copied()
+            .or_else(|| get_all_monitors().first().copied())?;
+        let work_area = get_monitor_work_area(monitor)?;
+        let (grid, dpi) = grid_for_monitor(app, None, monitor, work_area);
+        let name = snapshot::file_name(grid.cols, grid.rows, &unsafe { GetLocalTime() });
+        let excluded = get_monitor_excluded(monitor);
+        Some((name, snapshot(grid, work_area, excluded, dpi, &app.config)))
+    });
+    let Some((name, picture)) = picture else {
+        return;
+    };
+    let path = dir.join(name);
+    let result = picture.map_err(|e| e.to_string()).and_then(|picture| {
+        let png = snapshot::encode_png(picture.width, picture.height, &picture.rgba)
+            .map_err(|e| e.to_string())?;
+        std::fs::write(&path, png).map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(()) => {
+            info!("Saved grid snapshot to {}", path.display());
+            notify("Grid snapshot saved", &path.display().to_string());
+        }
+        Err(e) => {
+            error!("Failed to save grid snapshot: {}", e);
+            notify("Grid snapshot not saved", &e);
+        }
+    }
+}
+
+/// Switch to the grid last suggested from placement statistics
+fn apply_suggested_grid() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        let Some((cols, rows)) = stats::suggested_grid() else {
+            return;
+        };
+        let base = app.config.clone();
+        app.config.grid.cols = cols;
+        app.config.grid.rows = rows;
+        app.config.validate();
+        if let Err(e) = app.config.save(&base) {
+            error!("Failed to save config: {}", e);
+            notify("Config not saved", &e.to_string());
+        }
+        info!("Switched to the suggested {}x{} grid", cols, rows);
+        publish(AppEvent::ConfigChanged);
+    });
+}
+
+/// The target, or if it has closed since the overlay opened, whatever
+/// when_target_closes puts in its place. Retargeting updates the target.
+fn live_target(app: &mut AppState) -> Option<HWND> {
+    let target = app.target_hwnd?;
+    let rules = &app.config.rules;
+    let live = surviving_target(
+        &Win32Windows,
+        target,
+        app.config.behavior.when_target_closes,
+        |entry| !is_own_window(entry.hwnd) && is_allowed(rules, &entry.info),
+    );
+    match live {
+        Some(hwnd) if hwnd == target => return live,
+        Some(hwnd) => info!(
+            "Target closed; moving {} instead",
+            window_title(&get_window_title(hwnd))
+        ),
+        None => info!("Target closed; nothing to move"),
+    }
+    app.target_hwnd = live;
+    live
+}
+
+/// The target closing while the overlay is open cancels the selection or
+/// retargets it, as when_target_closes says
+fn handle_window_destroyed(hwnd: HWND) {
+    APP_STATE.with(|state| {
+        // Windows close all the time; never wait on the app state for them,
+        // as finishing the selection checks the target again anyway
+        let Ok(mut state) = state.try_borrow_mut() else {
+            return;
+        };
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        app.placements.forget(hwnd);
+        if app.managed.is_tiled(hwnd) {
+            app.managed.mark_untiled(hwnd);
+            update_splitters(app, &[]);
+        }
+        if !app.overlay_visible || app.previewing.is_some() || app.target_hwnd != Some(hwnd) {
+            return;
+        }
+        match live_target(app) {
+            Some(target) if target != hwnd => {
+                if let Some(ref overlay) = app.overlay {
+                    overlay.set_target_rect(get_window_rect(target));
+                }
+                announce(
+                    app.config.accessibility.announce,
+                    "Window closed; moving the active window instead",
+                );
+            }
+            Some(_) => {}
+            None => {
+                cancel_overlay(app);
+                report_not_moved(app, "closed");
+            }
+        }
+    });
+}
+
+/// Tell the user the target was left alone because it is `reason`
+fn report_not_moved(app: &AppState, reason: &str) {
+    let text = format!("The window is {}.", reason);
+    notify("Window not moved", &text);
+    announce(
+        app.config.accessibility.announce,
+        &format!("Window not moved: {}", reason),
+    );
+}
+
+/// A selection is made: move the target to `rect` now, or with
+/// confirm_with_enter outline it and wait for Enter
+fn finish_selection(app: &mut AppState, rect: RECT, region: Option<String>) {
+    if !app.config.behavior.confirm_with_enter {
+        complete_move(app, rect, region);
+        return;
+    }
+    let prompt = match region {
+        Some(ref region) => format!("{}, Enter to place", region),
+        None => "Enter to place".to_string(),
+    };
+    if let Some(ref overlay) = app.overlay {
+        overlay.set_preview(vec![PreviewRect {
+            rect,
+            label: prompt.clone(),
+        }]);
+    }
+    announce(app.config.accessibility.announce, &prompt);
+    app.pending = Some((rect, region));
+}
+
+/// Drop a selection waiting for Enter and its outline, so the next key
+/// starts a new one
+fn clear_pending(app: &mut AppState) {
+    if app.pending.take().is_none() {
+        return;
+    }
+    if let Some(ref mut selector) = app.selector {
+        selector.reset();
+    }
+    if let Some(ref overlay) = app.overlay {
+        overlay.set_preview(Vec::new());
+    }
+}
+
+/// Finish a selection: hide the overlay, move the target to `rect` and
+/// announce `region`, its description
+fn complete_move(app: &mut AppState, rect: RECT, region: Option<String>) {
+    // Hide the overlay first so it never covers the moved window
+    if let Some(ref overlay) = app.overlay {
+        overlay.hide_and_wait();
+        overlay.set_highlight(None);
+    }
+    app.overlay_visible = false;
+    publish(AppEvent::OverlayHidden);
+    set_hook_active(false);
+    release_stuck_modifiers();
+
+    let Some(target) = live_target(app) else {
+        if app.target_hwnd.is_some() {
+            report_not_moved(app, "closed");
+        }
+        return;
+    };
+    let full_grid = app
+        .selector
+        .as_ref()
+        .is_some_and(|selector| selector.grid().is_full_grid(rect));
+    if is_maximized(target) {
+        match app.config.behavior.when_maximized {
+            WhenMaximized::Restore => {}
+            WhenMaximized::Skip => {
+                report_not_moved(app, "maximized");
+                return;
+            }
+            // Already where the user asked for it
+            WhenMaximized::Maximize if full_grid => {
+                announce(app.config.accessibility.announce, "Window maximized");
+                return;
+            }
+            WhenMaximized::Maximize => {}
+        }
+    }
+
+    let after_move = app.config.behavior.after_move;
+    let kind = if full_grid && app.config.behavior.maximize_full_grid {
+        MoveKind::Maximize
+    } else {
+        MoveKind::Tile
+    };
+    let mut companions = Vec::new();
+    let announcement = region.map(|region| format!("Window moved to {}", region));
+    // Cleared when the move waits, to be announced once it's made
+    let mut moved = announcement.as_deref();
+    let placement = DeferredMove {
+        rect,
+        after_move,
+        kind,
+        announcement: announcement.clone(),
+    };
+    if is_minimized(target) {
+        // Restoring animates, and a move made mid-animation gets undone
+        restore_if_maximized(target);
+        defer_move(app, target, placement);
+        moved = None;
+    } else if let Some(reason) = busy_reason(target) {
+        // Moving a window mid-drag or behind a modal dialog can confuse its app
+        match app.config.behavior.when_busy {
+            WhenBusy::Defer => {
+                info!("Target is {}; moving it once it's free", reason);
+                defer_move(app, target, placement);
+                moved = None;
+            }
+            WhenBusy::Cancel => {
+                report_not_moved(app, reason);
+                return;
+            }
+        }
+    } else {
+        companions = snap_companions(app, target);
+        let before = get_window_rect(target).map(|before| ShrunkWindow {
+            hwnd: target,
+            rect: before,
+            maximized: is_maximized(target),
+            minimized: is_minimized(target),
+        });
+        place_window(app, target, rect, after_move, kind);
+        if let Some(before) = before {
+            // The move is queued, so the window may not be there yet
+            offer_undo_shrink(app, before, rect);
+        }
+    }
+    if is_practice_window(target) {
+        if let Some(selector) = app.selector.as_ref()
+            && let Some(&monitor) = app.monitors.get(app.current_monitor_idx)
+        {
+            record_practice_move(selector.grid().rect_to_span(rect), monitor);
+        }
+    } else if let Some(selector) = app.selector.as_ref() {
+        let grid = selector.grid();
+        let (first, last) = grid.rect_to_span(rect);
+        publish(AppEvent::PlacementPerformed {
+            hwnd: target,
+            first,
+            last,
+            cols: grid.cols,
+            rows: grid.rows,
+            monitor: app.current_monitor_idx + 1,
+        });
+        if !companions.is_empty() {
+            offer_snap_group(app, companions, (first, last));
+        }
+    }
+
+    if let Some(moved) = moved {
+        announce(app.config.accessibility.announce, moved);
+    }
+}
+
+/// Offer to put a window back as it was `before` if placing it at `after`
+/// shrank it by more than confirm_shrink_percent
+fn offer_undo_shrink(app: &mut AppState, before: ShrunkWindow, after: RECT) {
+    let limit = app.config.behavior.confirm_shrink_percent as u32;
+    let shrunk = shrink_percent(before.rect, after);
+    if limit == 0 || shrunk <= limit {
+        return;
+    }
+    info!(
+        "Placement shrank the window by {}%; offering to undo",
+        shrunk
+    );
+    app.shrunk = Some(before);
+    toast::ask(
+        &format!("Window shrunk by {}%", shrunk),
+        "Put it back the way it was?",
+        undo_shrink,
+    );
+}
+
+/// Put the window offer_undo_shrink last offered to back where it was
+fn undo_shrink() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        if let Some(shrunk) = app.shrunk.take()
+            && is_target_candidate(shrunk.hwnd)
+        {
+            let hwnd = shrunk.hwnd;
+            place_window(app, hwnd, shrunk.rect, AfterMove::Keep, MoveKind::Restore);
+            if shrunk.maximized {
+                maximize_window(hwnd);
+            } else if shrunk.minimized {
+                minimize_window(hwnd);
             }
         }
     });
 }
 
-fn show_overlay_on_work_area(app: &mut AppState, work_area: RECT) {
-    // Create grid from config
-    let grid = Grid::new(
-        app.config.grid.cols,
-        app.config.grid.rows,
-        app.config.grid.gap,
-        work_area,
-    );
-
-    // Create overlay if needed, or update existing
-    if app.overlay.is_none() {
-        app.overlay = Overlay::new(work_area, &app.config).ok();
+/// The windows `target` looks to be snapped alongside with Windows Snap,
+/// and where each was, when follow_snap_groups is on and tactile-win
+/// didn't tile it itself
+fn snap_companions(app: &AppState, target: HWND) -> Vec<(HWND, RECT)> {
+    if !app.config.behavior.follow_snap_groups || app.managed.is_tiled(target) {
+        return Vec::new();
     }
-    if let Some(ref overlay) = app.overlay {
-        overlay.update_position(work_area);
-        overlay.set_grid(grid.clone());
-    }
-
-    // Create selector
-    app.selector = Some(TileSelector::new(grid));
+    let (Some(rect), Some(work_area)) = (get_window_rect(target), get_work_area(target)) else {
+        return Vec::new();
+    };
+    let filter = WindowFilter {
+        alt_tab_only: true,
+        monitor: Some(get_window_monitor(target)),
+        ..Default::default()
+    };
+    let windows: Vec<(HWND, RECT)> = list_windows(&Win32Windows, &filter)
+        .into_iter()
+        .filter(|entry| entry.hwnd != target && !app.managed.is_floating(entry.hwnd))
+        .map(|entry| (entry.hwnd, entry.rect))
+        .collect();
+    find_group(rect, &windows, work_area)
+}
 
-    // Show overlay and activate keyboard hook
-    if let Some(ref overlay) = app.overlay {
-        overlay.show();
-        set_hook_active(true);
-    }
+/// Offer to move a just-tiled window's Snap companions into the tiles
+/// beside its new `span`, so the group isn't left broken
+fn offer_snap_group(app: &mut AppState, companions: Vec<(HWND, RECT)>, span: (TilePos, TilePos)) {
+    let Some(selector) = app.selector.as_ref() else {
+        return;
+    };
+    let grid = selector.grid();
+    let stacked = is_stacked(&companions);
+    let Some(spans) = group_spans(grid.cols, grid.rows, span, companions.len(), stacked) else {
+        info!("No room beside the window for its Snap group");
+        return;
+    };
+    app.snap_group = companions
+        .iter()
+        .zip(spans)
+        .map(|(&(hwnd, _), (first, last))| (hwnd, grid.combine_tiles(first, last)))
+        .collect();
+    let others = match companions.len() {
+        1 => "another window".to_string(),
+        count => format!("{} other windows", count),
+    };
+    let question = format!(
+        "The window was snapped with {}. Tile the rest of its group beside it?",
+        others
+    );
+    toast::ask("Move its Snap group too?", &question, move_snap_group);
 }
 
-fn switch_to_next_monitor() {
+/// Move the windows offer_snap_group last offered to into their tiles
+fn move_snap_group() {
     APP_STATE.with(|state| {
         let mut state = state.borrow_mut();
-        if let Some(ref mut app) = *state {
-            if app.monitors.len() <= 1 {
-                return; // Only one monitor, nothing to switch
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        for (hwnd, rect) in std::mem::take(&mut app.snap_group) {
+            if is_target_candidate(hwnd) {
+                place_window(app, hwnd, rect, AfterMove::Keep, MoveKind::Tile);
             }
+        }
+    });
+}
 
-            // Cycle to next monitor
-            app.current_monitor_idx = (app.current_monitor_idx + 1) % app.monitors.len();
-            let monitor = app.monitors[app.current_monitor_idx];
-
-            if let Some(work_area) = get_monitor_work_area(monitor) {
-                app.current_work_area = Some(work_area);
-
-                // Reset selection state
-                if let Some(ref mut selector) = app.selector {
-                    selector.reset();
-                }
-                if let Some(ref overlay) = app.overlay {
-                    overlay.set_highlight(None);
-                }
+/// Apply deferred placements whose windows have left their modal loop and
+/// finished animating
+fn run_deferred_moves() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        let polled = app.deferred.poll(Instant::now(), |target| {
+            if !is_target_candidate(target) {
+                return Poll::Closed;
+            }
+            Poll::Open {
+                rect: get_window_rect(target),
+                busy: busy_reason(target).or_else(|| is_minimized(target).then_some("minimized")),
+            }
+        });
 
-                show_overlay_on_work_area(app, work_area);
+        if app.deferred.is_empty() {
+            unsafe {
+                let _ = KillTimer(Some(app.hwnd), DEFER_TIMER_ID);
+            }
+        }
+        if !polled.closed.is_empty() {
+            info!("Deferred move dropped: the window closed");
+        }
+        for (_, _, reason) in polled.dropped {
+            info!("Deferred move dropped: the window is still {}", reason);
+            report_not_moved(app, &format!("still {}", reason));
+        }
+        for (target, placement) in polled.ready {
+            let DeferredMove {
+                rect,
+                after_move,
+                kind,
+                announcement,
+            } = placement;
+            place_window(app, target, rect, after_move, kind);
+            if let Some(announcement) = announcement {
+                announce(app.config.accessibility.announce, &announcement);
             }
         }
     });
 }
 
+/// Close the overlay as Escape does, keeping any first key for a quick
+/// reopen
+fn cancel_overlay(app: &mut AppState) {
+    clear_pending(app);
+    if let Some(ref mut selector) = app.selector {
+        app.cancelled = match (selector.first_tile(), app.target_hwnd) {
+            (Some(first), Some(target)) => Some(CancelledSelection {
+                first,
+                target,
+                monitor_idx: app.current_monitor_idx,
+                at: Instant::now(),
+            }),
+            _ => None,
+        };
+        selector.cancel();
+    }
+    if let Some(ref overlay) = app.overlay {
+        overlay.hide();
+        overlay.set_highlight(None);
+    }
+    app.overlay_visible = false;
+    publish(AppEvent::OverlayHidden);
+    app.previewing = None;
+    set_hook_active(false);
+    release_stuck_modifiers();
+    announce(app.config.accessibility.announce, "Grid closed");
+}
+
+/// What `key` does to the target's opacity, if the grid and bindings
+/// leave it free
+fn opacity_key(app: &AppState, key: char) -> Option<OpacityStep> {
+    let selector = app.selector.as_ref()?;
+    if selector.grid().key_to_tile(key).is_some() || selector.binding(key).is_some() {
+        return None;
+    }
+    app.config.opacity.step_for(key)
+}
+
+/// Make the target more or less opaque, leaving the overlay open for
+/// another step
+fn change_opacity(app: &AppState, step: OpacityStep) {
+    let Some(target) = app.target_hwnd else {
+        return;
+    };
+    let opacity = &app.config.opacity;
+    let text = match step_opacity(target, step, opacity.step, opacity.min) {
+        Some(percent) => format!("Opacity {}%", percent),
+        None => "This window's opacity can't be changed".to_string(),
+    };
+    info!("{}", text);
+    announce(app.config.accessibility.announce, &text);
+}
+
 fn handle_key_input(input: KeyInput) {
     APP_STATE.with(|state| {
         let mut state = state.borrow_mut();
         if let Some(ref mut app) = *state {
             match input {
-                KeyInput::Escape => {
-                    // Cancel and hide overlay
-                    if let Some(ref mut selector) = app.selector {
-                        selector.cancel();
-                    }
-                    if let Some(ref overlay) = app.overlay {
-                        overlay.hide();
-                        overlay.set_highlight(None);
-                    }
-                    set_hook_active(false);
-                }
+                KeyInput::Escape => cancel_overlay(app),
+                // A preview only moves between monitors
+                KeyInput::GridKey(_) | KeyInput::Arrow(_) if app.previewing.is_some() => {}
                 KeyInput::GridKey(key) => {
+                    if let Some(step) = opacity_key(app, key) {
+                        change_opacity(app, step);
+                        return;
+                    }
+                    // Another key instead of Enter starts over
+                    clear_pending(app);
                     if let Some(ref mut selector) = app.selector {
+                        let first_tile = selector.first_tile();
                         let new_state = selector.handle_key(key);
 
                         match new_state {
@@ -161,26 +1944,60 @@ fn handle_key_input(input: KeyInput) {
                                 if let Some(ref overlay) = app.overlay {
                                     overlay.set_highlight(Some(pos));
                                 }
+                                announce(
+                                    app.config.accessibility.announce,
+                                    &format!("Selected {}", key_label(key)),
+                                );
                             }
                             SelectionState::Complete(rect) => {
-                                // Move the window and hide overlay
-                                if let Some(target) = app.target_hwnd {
-                                    let _ = move_window(target, &rect);
-                                }
-                                if let Some(ref overlay) = app.overlay {
-                                    overlay.hide();
-                                    overlay.set_highlight(None);
-                                }
-                                set_hook_active(false);
+                                let grid = selector.grid();
+                                let region = match selector.binding(key) {
+                                    Some(region) => Some(region.to_string()),
+                                    None => first_tile.zip(grid.key_to_tile(key)).map(
+                                        |(first, second)| {
+                                            let (first, last) = selector.span(first, second);
+                                            describe_span(grid.cols, grid.rows, first, last)
+                                        },
+                                    ),
+                                };
+                                finish_selection(app, rect, region);
                             }
                             _ => {}
                         }
                     }
                 }
-                KeyInput::Tab => {
-                    // Switch to next monitor
+                KeyInput::Arrow(direction) => {
+                    // Stretch the target's span to fill the grid along one axis
+                    let axis = match direction {
+                        Direction::Up | Direction::Down => Axis::Vertical,
+                        Direction::Left | Direction::Right => Axis::Horizontal,
+                    };
+                    let current = app.target_hwnd.and_then(get_window_rect);
+                    clear_pending(app);
+                    if let (Some(selector), Some(current)) = (app.selector.as_mut(), current)
+                        && let SelectionState::Complete(rect) = selector.maximize(axis, current)
+                    {
+                        let grid = selector.grid();
+                        let (first, last) = grid.rect_to_span(rect);
+                        let region = describe_span(grid.cols, grid.rows, first, last);
+                        finish_selection(app, rect, Some(region));
+                    }
+                }
+                KeyInput::Enter => {
+                    if let Some((rect, region)) = app.pending.take() {
+                        complete_move(app, rect, region);
+                    }
+                }
+                KeyInput::Hide if app.previewing.is_none() => {
+                    if let Some(target) = app.target_hwnd {
+                        close_overlay(app);
+                        hide_target(app, target);
+                    }
+                }
+                KeyInput::Tab | KeyInput::Monitor(_) => {
+                    // Switch monitor
                 }
-                KeyInput::Other => {
+                KeyInput::Hide | KeyInput::Other => {
                     // Ignore other keys
                 }
             }
@@ -188,8 +2005,10 @@ fn handle_key_input(input: KeyInput) {
     });
 
     // Handle Tab outside of borrow to avoid borrow conflict
-    if matches!(input, KeyInput::Tab) {
-        switch_to_next_monitor();
+    match input {
+        KeyInput::Tab => switch_to_monitor(None),
+        KeyInput::Monitor(index) => switch_to_monitor(Some(index)),
+        _ => {}
     }
 }
 
@@ -202,9 +2021,147 @@ unsafe extern "system" fn window_proc(
     unsafe {
         match msg {
             WM_HOTKEY => {
-                if wparam.0 as i32 == HOTKEY_ID {
+                let id = wparam.0 as i32;
+                if id == HOTKEY_ID {
+                    // The app never got the key-down; keep its key-up away too
+                    swallow_key_up(grid_hotkey().vk);
+                    start_latency_probe();
                     handle_hotkey();
+                } else if id == FLOAT_HOTKEY_ID {
+                    swallow_key_up(VK_F.0 as u32);
+                    toggle_floating();
+                } else if id == HIDE_HOTKEY_ID {
+                    swallow_key_up(VK_H.0 as u32);
+                    hide_foreground();
+                } else if let Some(hotkey) = layout_hotkey(id) {
+                    swallow_key_up(hotkey.vk);
+                    apply_layout((id - LAYOUT_HOTKEY_BASE) as usize);
+                } else if let Some(&(_, vk, direction)) =
+                    SPLIT_HOTKEYS.iter().find(|(split_id, ..)| *split_id == id)
+                {
+                    swallow_key_up(vk.0 as u32);
+                    move_split(direction);
+                } else {
+                    // Overlay keys, when captured with hotkeys instead of the hook
+                    handle_hotkey_input(id);
+                }
+                LRESULT(0)
+            }
+            WM_RUN_COMMAND => {
+                if let Some(command) = Command::from_index(wparam.0) {
+                    if ipc::dispatch_command(command, run_command) == Status::RateLimited {
+                        notify(
+                            "Command not run",
+                            &format!("{}: too many requests; try again.", command.title()),
+                        );
+                    }
+                    ipc::run_queued(run_command);
+                }
+                LRESULT(0)
+            }
+            // Placement batches and status requests from other processes;
+            // the result is the request's ipc::Status code
+            WM_COPYDATA if status::is_request(lparam) => {
+                let requester = HWND(wparam.0 as *mut _);
+                let status = ipc::dispatch(|_| match status_report() {
+                    Some(report) => status::reply(requester, &report),
+                    None => Status::Busy,
+                });
+                ipc::run_queued(run_command);
+                LRESULT(status.code())
+            }
+            WM_COPYDATA => match batch::receive(lparam) {
+                Some(Ok(steps)) => {
+                    let status = ipc::dispatch(|deadline| run_batch(&steps, deadline));
+                    ipc::run_queued(run_command);
+                    LRESULT(status.code())
+                }
+                Some(Err(e)) => {
+                    error!("Ignored a malformed placement batch: {}", e);
+                    LRESULT(Status::Malformed.code())
+                }
+                None => DefWindowProcW(hwnd, msg, wparam, lparam),
+            },
+            WM_POWERBROADCAST if wparam.0 as u32 == PBT_APMRESUMEAUTOMATIC => {
+                revalidate_resources(hwnd, "resume from sleep");
+                LRESULT(1)
+            }
+            WM_WTSSESSION_CHANGE => {
+                let event = wparam.0 as u32;
+                // Connecting over RDP or back at the console swaps the
+                // monitors and their DPI wholesale
+                if matches!(
+                    event,
+                    WTS_CONSOLE_CONNECT
+                        | WTS_CONSOLE_DISCONNECT
+                        | WTS_REMOTE_CONNECT
+                        | WTS_REMOTE_DISCONNECT
+                ) {
+                    reset_monitors();
+                }
+                if event == WTS_SESSION_LOCK {
+                    pause_while_locked();
                 }
+                if event == WTS_SESSION_UNLOCK {
+                    APP_STATE.with(|state| {
+                        if let Some(ref mut app) = *state.borrow_mut() {
+                            app.locked = false;
+                        }
+                    });
+                }
+                if matches!(
+                    event,
+                    WTS_SESSION_UNLOCK | WTS_CONSOLE_CONNECT | WTS_REMOTE_CONNECT
+                ) {
+                    revalidate_resources(hwnd, "session change");
+                }
+                LRESULT(0)
+            }
+            WM_INPUT => {
+                note_key_activity();
+                // Frees the raw input
+                DefWindowProcW(hwnd, msg, wparam, lparam)
+            }
+            WM_TIMER if wparam.0 == WATCHDOG_TIMER_ID => {
+                check_hook_watchdog(WATCHDOG_INTERVAL_MS);
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == STATS_TIMER_ID => {
+                stats::save_stats();
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == DEFER_TIMER_ID => {
+                run_deferred_moves();
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == SHOW_TIMER_ID => {
+                let _ = KillTimer(Some(hwnd), SHOW_TIMER_ID);
+                show_pending_overlay();
+                LRESULT(0)
+            }
+            WM_SHOW_OVERLAY => {
+                show_pending_overlay();
+                LRESULT(0)
+            }
+            WM_APP_EVENT => {
+                deliver_pending();
+                LRESULT(0)
+            }
+            WM_TOAST_ANSWER => {
+                toast::answer(wparam, lparam);
+                LRESULT(0)
+            }
+            WM_DESKTOP_SWITCHED => {
+                apply_desktop_layout(wparam.0);
+                LRESULT(0)
+            }
+            WM_CONFIG_FILE_CHANGED => {
+                SetTimer(Some(hwnd), RELOAD_TIMER_ID, RELOAD_DELAY_MS, None);
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == RELOAD_TIMER_ID => {
+                let _ = KillTimer(Some(hwnd), RELOAD_TIMER_ID);
+                reload_config();
                 LRESULT(0)
             }
             _ => DefWindowProcW(hwnd, msg, wparam, lparam),
@@ -212,24 +2169,208 @@ unsafe extern "system" fn window_proc(
     }
 }
 
-fn register_hotkey(hwnd: HWND) -> windows::core::Result<()> {
+/// Register the grid hotkey, Ctrl+Shift+G unless [hotkey] says otherwise
+fn register_grid_hotkey(hwnd: HWND) -> windows::core::Result<()> {
+    let hotkey = grid_hotkey();
     unsafe {
-        // Use Ctrl+Shift+G for Grid
         RegisterHotKey(
             Some(hwnd),
             HOTKEY_ID,
-            HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_SHIFT.0),
-            VK_G.0 as u32,
+            HOT_KEY_MODIFIERS(hotkey.modifiers),
+            hotkey.vk,
         )?;
-        println!("Registered Ctrl+Shift+G hotkey - press it to show grid overlay");
+    }
+    HOTKEY_REGISTERED.store(true, Ordering::SeqCst);
+    info!(
+        "Registered {} hotkey - press it to show grid overlay",
+        hotkey
+    );
+    Ok(())
+}
+
+/// The hotkey `config` sets, or the default if it can't be used
+fn configured_hotkey(config: &Config) -> Hotkey {
+    config.hotkey.hotkey().unwrap_or_else(|e| {
+        warning!("Ignoring [hotkey]: {}; using {}", e, Hotkey::DEFAULT);
+        Hotkey::DEFAULT
+    })
+}
+
+fn register_hotkey(hwnd: HWND) -> windows::core::Result<()> {
+    register_grid_hotkey(hwnd)?;
+    unsafe {
+        // Ctrl+Alt+Shift+F floats the foreground window; optional
+        if let Err(e) = RegisterHotKey(
+            Some(hwnd),
+            FLOAT_HOTKEY_ID,
+            HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_ALT.0 | MOD_SHIFT.0),
+            VK_F.0 as u32,
+        ) {
+            warning!("Failed to register float hotkey: {}", e);
+        }
+        // Ctrl+Alt+Shift+H hides it to the tray; optional
+        if let Err(e) = RegisterHotKey(
+            Some(hwnd),
+            HIDE_HOTKEY_ID,
+            HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_ALT.0 | MOD_SHIFT.0),
+            VK_H.0 as u32,
+        ) {
+            warning!("Failed to register hide hotkey: {}", e);
+        }
+        for (id, vk, _) in SPLIT_HOTKEYS {
+            if let Err(e) = RegisterHotKey(
+                Some(hwnd),
+                id,
+                HOT_KEY_MODIFIERS(MOD_CONTROL.0 | MOD_ALT.0 | MOD_SHIFT.0),
+                vk.0 as u32,
+            ) {
+                warning!("Failed to register split hotkey: {}", e);
+            }
+        }
+        register_layout_hotkeys(hwnd);
         Ok(())
     }
 }
 
+/// Sleep, RDP reconnects and fast user switching can silently kill the
+/// hotkey, the keyboard hook and the tray icon; set them all up again
+fn revalidate_resources(hwnd: HWND, reason: &str) {
+    let (paused, locked) = APP_STATE.with(|state| {
+        state
+            .try_borrow()
+            .ok()
+            .and_then(|state| state.as_ref().map(|app| (app.paused, app.locked)))
+            .unwrap_or_default()
+    });
+    if paused {
+        // Pausing let go of them on purpose; resuming sets them up
+        restore_tray_icon();
+        set_tray_state(IconState::Paused);
+        return;
+    }
+    info!(
+        "Re-validating hotkey, keyboard hook and tray icon after {}",
+        reason
+    );
+
+    unregister_hotkey(hwnd);
+    let mut working = true;
+    if let Err(e) = register_hotkey(hwnd) {
+        error!("Failed to re-register hotkey: {}", e);
+        working = false;
+    }
+    if locked {
+        // pause_when_locked: the hook comes back on unlock
+        info!("Session still locked; keyboard hook left paused");
+    } else if let Err(e) = reinstall_keyboard_hook() {
+        error!("Failed to reinstall keyboard hook: {}", e);
+        working = false;
+    }
+    restore_tray_icon();
+    set_tray_state(match (working, locked) {
+        (false, _) => IconState::Error,
+        (true, true) => IconState::Paused,
+        (true, false) => IconState::Active,
+    });
+}
+
+/// Close the overlay if it is open, forgetting any selection
+fn close_overlay(app: &mut AppState) {
+    clear_pending(app);
+    if let Some(ref mut selector) = app.selector {
+        selector.cancel();
+    }
+    if let Some(ref overlay) = app.overlay {
+        overlay.hide();
+        overlay.set_highlight(None);
+    }
+    app.overlay_visible = false;
+    publish(AppEvent::OverlayHidden);
+    app.previewing = None;
+    set_hook_active(false);
+}
+
+/// Drop every overlay and list the monitors afresh, after a session change
+/// left the old geometry and DPI behind
+fn reset_monitors() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        close_overlay(app);
+        app.overlay = None;
+        app.overlay_monitor = None;
+        app.cached_overlays.clear();
+        app.current_work_area = None;
+        app.cancelled = None;
+        app.monitors = get_all_monitors();
+        app.current_monitor_idx = 0;
+        info!("Session changed; found {} monitor(s)", app.monitors.len());
+        update_profile(app);
+    });
+}
+
+/// Close the overlay on lock and, if configured, stop hooking the keyboard
+/// until unlock, when revalidate_resources installs the hook again
+fn pause_while_locked() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(ref mut app) = *state {
+            close_overlay(app);
+            if app.config.behavior.pause_when_locked {
+                pause_keyboard_hook();
+                app.locked = true;
+                set_tray_state(IconState::Paused);
+                info!("Session locked; keyboard hook paused");
+            }
+        }
+    });
+}
+
+/// Windows is logging off or shutting down and may end the process as
+/// soon as this returns: save statistics, show hidden windows, make
+/// translucent ones opaque and let go of the hooks now, as the cleanup
+/// after the message loop may never run
+fn end_session() {
+    stats::save_stats();
+    APP_STATE.with(|state| {
+        if let Some(ref mut app) = *state.borrow_mut() {
+            show_hidden_windows(app, None);
+            reset_all();
+            unregister_hotkey(app.hwnd);
+        }
+    });
+    uninstall_keyboard_hook();
+    unsubscribe_all();
+    info!("Session ending; hooks removed");
+}
+
 fn unregister_hotkey(hwnd: HWND) {
     unsafe {
         let _ = UnregisterHotKey(Some(hwnd), HOTKEY_ID);
+        HOTKEY_REGISTERED.store(false, Ordering::SeqCst);
+        let _ = UnregisterHotKey(Some(hwnd), FLOAT_HOTKEY_ID);
+        let _ = UnregisterHotKey(Some(hwnd), HIDE_HOTKEY_ID);
+        for (id, ..) in SPLIT_HOTKEYS {
+            let _ = UnregisterHotKey(Some(hwnd), id);
+        }
     }
+    unregister_layout_hotkeys(hwnd);
+}
+
+/// Open the practice window, setting challenges on the grid the overlay
+/// would show
+fn open_practice() {
+    APP_STATE.with(|state| {
+        let state = state.borrow();
+        if let Some(ref app) = *state {
+            let monitors = get_all_monitors();
+            let monitor = monitors.first().copied().unwrap_or_default();
+            let (grid, _) = grid_for_monitor(app, None, monitor, RECT::default());
+            start_practice(grid, app.config.grid.second_key, monitors.len());
+        }
+    });
 }
 
 fn open_settings() {
@@ -241,12 +2382,39 @@ fn open_settings() {
     });
 }
 
+fn open_wizard() {
+    APP_STATE.with(|state| {
+        let state = state.borrow();
+        if let Some(ref app) = *state {
+            show_wizard(&app.config, current_config, on_settings_saved);
+        }
+    });
+}
+
+/// The config as it is now, for dialogs to save their changes onto
+fn current_config() -> Option<Config> {
+    APP_STATE.with(|state| Some(state.try_borrow().ok()?.as_ref()?.config.clone()))
+}
+
+fn run_diagnostics() {
+    APP_STATE.with(|state| {
+        let state = state.borrow();
+        if let Some(ref app) = *state {
+            let hotkey_registered = HOTKEY_REGISTERED.load(Ordering::SeqCst);
+            let checks = run_checks(hotkey_registered, app.config.behavior.input);
+            let text_percent = text_scale_percent(app.config.accessibility.text_scale);
+            show_diagnostics(format_report(&checks), text_percent);
+        }
+    });
+}
+
 fn on_settings_saved(new_config: Config) {
     APP_STATE.with(|state| {
         let mut state = state.borrow_mut();
         if let Some(ref mut app) = *state {
             app.config = new_config;
-            println!(
+            publish(AppEvent::ConfigChanged);
+            info!(
                 "Settings updated: {}x{} (gap: {})",
                 app.config.grid.cols, app.config.grid.rows, app.config.grid.gap
             );
@@ -254,6 +2422,56 @@ fn on_settings_saved(new_config: Config) {
     });
 }
 
+/// Take up edits made to the config file by hand. One that doesn't parse
+/// is reported and the config in use kept; so is a deleted one.
+fn reload_config() {
+    let config = match Config::reload() {
+        Ok(Some(config)) => config,
+        Ok(None) => return,
+        Err(e) => {
+            warning!("Config file not reloaded: {}", e);
+            notify("Config not reloaded", &e.to_string());
+            return;
+        }
+    };
+    // Saves from Settings land here too, already taken up
+    let changed = APP_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .is_some_and(|app| !app.config.same_as(&config))
+    });
+    if changed {
+        info!("Config file changed; reloading");
+        on_settings_saved(config);
+    }
+}
+
+/// Roll the config file back to before its last save
+fn restore_previous_settings() {
+    match Config::restore_backup() {
+        Ok(Some((config, left))) => {
+            info!("Restored settings from a backup; {} left", left);
+            on_settings_saved(config);
+            notify(
+                "Settings restored",
+                &format!(
+                    "Settings are back as they were before the last save. {} earlier versions remain.",
+                    left
+                ),
+            );
+        }
+        Ok(None) => notify(
+            "Nothing to restore",
+            "There are no earlier settings saved to go back to.",
+        ),
+        Err(e) => {
+            error!("Failed to restore settings: {}", e);
+            notify("Settings not restored", &e.to_string());
+        }
+    }
+}
+
 fn create_message_window() -> windows::core::Result<HWND> {
     unsafe {
         let hinstance = GetModuleHandleW(None)?;
@@ -300,70 +2518,342 @@ fn run_message_loop() {
     }
 }
 
+fn run_command(command: Command) {
+    match command {
+        Command::ShowGrid => handle_hotkey(),
+        Command::Settings => open_settings(),
+        Command::Equalize => equalize_windows(),
+        Command::Restore => restore_placements(),
+        Command::PreviewEqualize => preview_equalize(),
+        Command::IdentifyMonitors => identify_monitors(),
+        Command::TogglePause => toggle_pause(),
+        Command::ApplyLayout(index) => apply_layout(index),
+        Command::PreviewLayout(index) => preview_layout(index),
+    }
+}
+
+/// Let go of the hotkeys and keyboard hook so the keys reach other
+/// programs, or take them back
+fn toggle_pause() {
+    APP_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let Some(ref mut app) = *state else {
+            return;
+        };
+        if app.paused {
+            app.paused = false;
+            let mut working = true;
+            if let Err(e) = register_hotkey(app.hwnd) {
+                error!("Failed to re-register hotkey: {}", e);
+                working = false;
+            }
+            if let Err(e) = reinstall_keyboard_hook() {
+                error!("Failed to reinstall keyboard hook: {}", e);
+                working = false;
+            }
+            set_tray_state(if working {
+                IconState::Active
+            } else {
+                IconState::Error
+            });
+            info!("Resumed");
+            announce(app.config.accessibility.announce, "Tactile-Win resumed");
+        } else {
+            app.paused = true;
+            close_overlay(app);
+            unregister_hotkey(app.hwnd);
+            pause_keyboard_hook();
+            set_tray_state(IconState::Paused);
+            info!("Paused; hotkeys released");
+            announce(app.config.accessibility.announce, "Tactile-Win paused");
+        }
+    });
+}
+
 fn main() {
-    println!("Tactile-Win starting...");
-    println!("Press Ctrl+Shift+G to show the tiling grid overlay");
-    println!("Then press two keys (Q/W/E/R/A/S/D/F) to select a tile region");
-    println!("Press Escape to cancel");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let has_flag = |flag: &str| args.iter().any(|arg| arg == flag);
+    if has_flag("--list-windows") {
+        // Release builds have no console; print to the shell that ran us
+        attach_console();
+        // Diagnostic: what rules and layouts see, including cloaked windows
+        let filter = WindowFilter {
+            include_cloaked: true,
+            ..Default::default()
+        };
+        for entry in list_windows(&Win32Windows, &filter) {
+            println!("{}", entry);
+        }
+        return;
+    }
+    // A commented reference config, printed or written to the path given
+    if let Some(index) = args.iter().position(|arg| arg == "--print-default-config") {
+        attach_console();
+        let text = configdoc::default_config();
+        match args.get(index + 1).filter(|arg| !arg.starts_with("--")) {
+            Some(path) => {
+                if let Err(e) = std::fs::write(path, text) {
+                    eprintln!("tactile-win: can't write {}: {}", path, e);
+                    std::process::exit(Status::Failed.exit_code());
+                }
+            }
+            None => print!("{}", text),
+        }
+        return;
+    }
+    if has_flag("--status") {
+        attach_console();
+        match status::query_running_instance() {
+            Some(Ok(report)) => println!("{}", report),
+            Some(Err(status)) => {
+                eprintln!("tactile-win: status request {}", status);
+                std::process::exit(status.exit_code());
+            }
+            None => {
+                eprintln!("tactile-win: not running");
+                std::process::exit(Status::Failed.exit_code());
+            }
+        }
+        return;
+    }
+
+    // Jump list tasks and command-line arguments go to the running instance
+    let command = args.iter().find_map(|arg| Command::from_arg(arg));
+    if let Some(command) = command
+        && command.send_to_running_instance()
+    {
+        return;
+    }
+    // So do --place batches, which report back how they went
+    let batch = batch::parse_args(&args).unwrap_or_else(|e| {
+        attach_console();
+        eprintln!("tactile-win: {}", e);
+        std::process::exit(Status::Malformed.exit_code());
+    });
+    if !batch.is_empty()
+        && let Some(status) = batch::send_to_running_instance(&batch)
+    {
+        if status != Status::Done {
+            attach_console();
+            eprintln!("tactile-win: placement batch {}", status);
+        }
+        std::process::exit(status.exit_code());
+    }
+
+    // Load config, offering monitor setup when there's none yet
+    let first_run = Config::config_path().is_some_and(|path| !path.exists());
+    let mut config = Config::load();
+    config.validate();
+    log::init(has_flag("--verbose") || config.logging.verbose);
+
+    info!("Tactile-Win starting...");
+    set_grid_hotkey(configured_hotkey(&config));
+    set_layout_hotkeys(&config);
+    info!("Press {} to show the tiling grid overlay", grid_hotkey());
+    info!("Then press two keys (Q/W/E/R/A/S/D/F) to select a tile region");
+    info!("Press Escape to cancel");
 
     match create_message_window() {
         Ok(hwnd) => {
-            // Load config
-            let mut config = Config::load();
-            config.validate();
             if let Some(path) = Config::config_path() {
-                println!("Config file: {}", path.display());
+                info!("Config file: {}", path.display());
+            }
+            let key_issues = Config::check_file();
+            for issue in &key_issues {
+                warning!("Config: {}; ignored", issue);
             }
-            println!(
+            info!(
                 "Grid: {}x{} (gap: {})",
                 config.grid.cols, config.grid.rows, config.grid.gap
             );
+            if let Some(warning) = tile_size_warning(&config) {
+                warning!("{}", warning);
+            }
+
+            let update_checks = config.updates.clone();
+            let input_backend = config.behavior.input;
+            let start_hidden = config.behavior.start_hidden;
+
+            // Subsystems reacting to what happens elsewhere in the app
+            bus::init(hwnd, current_config);
+            toast::init(hwnd);
+            log::register();
+            tray::register();
+            overlay::register();
+            stats::register(apply_suggested_grid);
+            struts::register();
+            bus::subscribe(follow_app_events);
+            set_reserved(config.grid.struts.clone(), config.grid.dock_classes.clone());
+            set_dragged_callback(follow_splits);
+            // Hand edits to the config file are taken up as they're saved
+            if let Some(path) = Config::config_path() {
+                watch_config(path, hwnd);
+            }
+            // Layouts can follow virtual desktop switches
+            watch_desktops(hwnd);
 
             // Initialize app state
             APP_STATE.with(|state| {
                 *state.borrow_mut() = Some(AppState {
                     config,
                     overlay: None,
+                    overlay_monitor: None,
+                    overlay_visible: false,
+                    cached_overlays: HashMap::new(),
                     selector: None,
                     target_hwnd: None,
                     monitors: Vec::new(),
                     current_monitor_idx: 0,
                     current_work_area: None,
+                    cancelled: None,
+                    deferred: DeferredMoves::default(),
+                    hwnd,
+                    placements: PlacementStore::default(),
+                    managed: ManagedWindows::default(),
+                    profile: None,
+                    profile_override: None,
+                    previewing: None,
+                    monitor_count: get_all_monitors().len(),
+                    split_edges: Vec::new(),
+                    snap_group: Vec::new(),
+                    shrunk: None,
+                    pending: None,
+                    hidden: Vec::new(),
+                    paused: false,
+                    locked: false,
                 });
+                if let Some(ref mut app) = *state.borrow_mut() {
+                    update_profile(app);
+                }
             });
 
             // Create tray icon
             let _tray = match TrayIcon::new() {
                 Ok(tray) => {
-                    println!("Tray icon created - right-click to access menu");
+                    info!("Tray icon created - right-click to access menu");
                     set_settings_callback(open_settings);
+                    set_show_grid_callback(handle_hotkey);
+                    set_equalize_callback(equalize_windows);
+                    set_preview_equalize_callback(preview_equalize);
+                    set_restore_callback(restore_placements);
+                    set_practice_callback(open_practice);
+                    set_export_history_callback(export_history);
+                    set_save_snapshot_callback(save_grid_snapshot);
+                    set_profile_callback(choose_profile);
+                    set_setup_monitors_callback(open_wizard);
+                    set_diagnostics_callback(run_diagnostics);
+                    set_identify_callback(identify_monitors);
+                    set_restore_settings_callback(restore_previous_settings);
+                    set_hidden_callback(show_hidden);
+                    set_end_session_callback(end_session);
+                    if update_checks.check {
+                        tray.start_update_checks(update_checks.interval_hours);
+                    }
+                    if first_run {
+                        open_wizard();
+                    } else if let Some(issue) = key_issues.first() {
+                        let more = match key_issues.len() {
+                            1 => String::new(),
+                            n => format!(" There are {} more in the log.", n - 1),
+                        };
+                        notify("Config options ignored", &format!("{}.{}", issue, more));
+                    } else if !start_hidden {
+                        notify(
+                            "Tactile-Win is running",
+                            &format!("Press {} to show the grid.", grid_hotkey()),
+                        );
+                    }
                     Some(tray)
                 }
                 Err(e) => {
-                    eprintln!("Warning: Failed to create tray icon: {}", e);
+                    warning!("Failed to create tray icon: {}", e);
                     None
                 }
             };
 
-            // Install keyboard hook with direct callback
-            if let Err(e) = install_keyboard_hook(handle_key_input) {
-                eprintln!("Failed to install keyboard hook: {}", e);
-                return;
+            // Capture overlay keys with the configured backend
+            match input_backend {
+                InputBackend::Hook => {
+                    // Keep running with the tray showing the error; the hook
+                    // is tried again on resume and unlock
+                    if let Err(e) = install_keyboard_hook(handle_key_input) {
+                        error!("Failed to install keyboard hook: {}", e);
+                        set_tray_state(IconState::Error);
+                        notify("Grid keys not captured", &e.to_string());
+                    }
+                    // Lets the watchdog tell a dropped hook from an idle keyboard
+                    if let Err(e) = watch_key_activity(hwnd) {
+                        warning!("Keyboard hook watchdog is off: {}", e);
+                    }
+                }
+                InputBackend::Hotkeys => install_hotkey_input(hwnd, handle_key_input),
             }
             set_hook_active(false); // Start with hook inactive
+            set_work_area_changed_callback(refresh_work_area);
+            // Also notices tiled windows being moved off the grid
+            if let Err(e) = subscribe(WindowEvent::MoveSizeEnd, handle_window_moved) {
+                warning!("Failed to watch window moves: {}", e);
+            }
+            if let Err(e) = subscribe(WindowEvent::Destroyed, handle_window_destroyed) {
+                warning!("Failed to watch for windows closing: {}", e);
+            }
+            for event in [WindowEvent::LocationChanged, WindowEvent::Foreground] {
+                if let Err(e) = subscribe(event, follow_window_changes) {
+                    warning!("Failed to watch windows for splitters: {}", e);
+                }
+            }
+            unsafe {
+                SetTimer(Some(hwnd), WATCHDOG_TIMER_ID, WATCHDOG_INTERVAL_MS, None);
+                SetTimer(Some(hwnd), STATS_TIMER_ID, STATS_SAVE_MS, None);
+            }
+
+            // Get told about resume and session changes to re-validate resources
+            let power_notify = unsafe {
+                RegisterSuspendResumeNotification(HANDLE(hwnd.0), DEVICE_NOTIFY_WINDOW_HANDLE).ok()
+            };
+            let _ = unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) };
 
+            // The grid can still be shown from the tray menu
             if let Err(e) = register_hotkey(hwnd) {
-                eprintln!("Failed to register hotkey: {}", e);
-                return;
+                error!("Failed to register hotkey: {}", e);
+                set_tray_state(IconState::Error);
+                notify(
+                    "Hotkey not registered",
+                    &format!("{}: {}", grid_hotkey(), e),
+                );
+            }
+
+            let layouts = APP_STATE
+                .with(|state| state.borrow().as_ref().map(|app| layout_names(&app.config)));
+            if let Err(e) = register_jump_list(&layouts.unwrap_or_default()) {
+                warning!("Failed to register jump list: {}", e);
+            }
+            // Not already running: start up, then run the command
+            if let Some(command) = command {
+                command.post_to(hwnd);
+            }
+            if !batch.is_empty() {
+                run_batch(&batch, Instant::now() + ipc::REQUEST_TIMEOUT);
             }
 
             run_message_loop();
 
+            stats::save_stats();
+            // Nothing would be left to show them again
+            show_hidden(None);
+            reset_all();
             uninstall_keyboard_hook();
+            unsubscribe_all();
             unregister_hotkey(hwnd);
+            unsafe {
+                if let Some(power_notify) = power_notify {
+                    let _ = UnregisterSuspendResumeNotification(power_notify);
+                }
+                let _ = WTSUnRegisterSessionNotification(hwnd);
+            }
         }
         Err(e) => {
-            eprintln!("Failed to create message window: {}", e);
+            error!("Failed to create message window: {}", e);
         }
     }
 }
@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+use windows::Win32::Foundation::HWND;
+
+/// Which windows tactile-win is managing. A window is tiled from when it
+/// is placed on the grid until the user moves it by hand; floating windows
+/// are ones the user asked tactile-win to leave alone.
+#[derive(Default)]
+pub struct ManagedWindows {
+    tiled: HashSet<isize>,
+    floating: HashSet<isize>,
+}
+
+impl ManagedWindows {
+    /// The window was just placed on the grid
+    pub fn mark_tiled(&mut self, hwnd: HWND) {
+        if !self.is_floating(hwnd) {
+            self.tiled.insert(hwnd.0 as isize);
+        }
+    }
+
+    /// The window was moved off the grid, by the user or a restore
+    pub fn mark_untiled(&mut self, hwnd: HWND) {
+        self.tiled.remove(&(hwnd.0 as isize));
+    }
+
+    pub fn is_tiled(&self, hwnd: HWND) -> bool {
+        self.tiled.contains(&(hwnd.0 as isize))
+    }
+
+    pub fn tiled(&self) -> impl Iterator<Item = HWND> + '_ {
+        self.tiled.iter().map(|&key| HWND(key as *mut _))
+    }
+
+    pub fn is_floating(&self, hwnd: HWND) -> bool {
+        self.floating.contains(&(hwnd.0 as isize))
+    }
+
+    /// Float a window, or stop floating it. Returns whether it now floats.
+    pub fn toggle_floating(&mut self, hwnd: HWND) -> bool {
+        let key = hwnd.0 as isize;
+        if self.floating.remove(&key) {
+            false
+        } else {
+            self.floating.insert(key);
+            self.tiled.remove(&key);
+            true
+        }
+    }
+
+    /// Drop windows `alive` rejects, so a recycled handle starts fresh
+    pub fn prune(&mut self, alive: impl Fn(HWND) -> bool) {
+        let alive = |key: &isize| alive(HWND(*key as *mut _));
+        self.tiled.retain(alive);
+        self.floating.retain(alive);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiled_until_moved_or_floated() {
+        let mut managed = ManagedWindows::default();
+        let (a, b) = (HWND(0x10 as *mut _), HWND(0x20 as *mut _));
+        managed.mark_tiled(a);
+        managed.mark_tiled(b);
+        managed.mark_untiled(a);
+        assert!(!managed.is_tiled(a));
+        assert!(managed.is_tiled(b));
+
+        assert!(managed.toggle_floating(b));
+        assert!(managed.is_floating(b) && !managed.is_tiled(b));
+        // Floating windows stay out of the tiled set
+        managed.mark_tiled(b);
+        assert!(!managed.is_tiled(b));
+
+        assert!(!managed.toggle_floating(b));
+        managed.mark_tiled(b);
+        assert!(managed.is_tiled(b));
+
+        managed.prune(|hwnd| hwnd != b);
+        assert!(!managed.is_tiled(b));
+    }
+}
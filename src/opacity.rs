@@ -0,0 +1,148 @@
+use std::sync::Mutex;
+use windows::Win32::Foundation::{COLORREF, HWND};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetLayeredWindowAttributes, GetWindowLongW, IsWindow, SetLayeredWindowAttributes,
+    SetWindowLongW, GWL_EXSTYLE, LAYERED_WINDOW_ATTRIBUTES_FLAGS, LWA_ALPHA, WS_EX_LAYERED,
+};
+
+use crate::config::OpacityStep;
+use crate::log::{info, warning};
+
+/// A window made more or less opaque, and how to put it back
+struct Changed {
+    hwnd: isize,
+    /// WS_EX_LAYERED was added for the change, to take off again
+    added_layered: bool,
+    /// Its own alpha and layered flags, from before the first change
+    alpha: u8,
+    flags: LAYERED_WINDOW_ATTRIBUTES_FLAGS,
+}
+
+/// Windows whose opacity was changed
+static CHANGED: Mutex<Vec<Changed>> = Mutex::new(Vec::new());
+
+/// `alpha` after `step`, moving `step_percent` of full opacity at a time
+/// and going no lower than `min_percent` (or `alpha`, if already lower).
+/// Reset goes back to `own`, the window's opacity before any change.
+fn stepped(alpha: u8, step: OpacityStep, step_percent: u8, min_percent: u8, own: u8) -> u8 {
+    let delta = step_percent as u32 * 255 / 100;
+    let min = (min_percent as u32 * 255 / 100).min(alpha as u32);
+    match step {
+        OpacityStep::More => (alpha as u32 + delta).min(255) as u8,
+        OpacityStep::Less => (alpha as u32).saturating_sub(delta).max(min) as u8,
+        OpacityStep::Reset => own,
+    }
+}
+
+fn percent(alpha: u8) -> u8 {
+    ((alpha as u32 * 100 + 127) / 255) as u8
+}
+
+/// Make a window `step` more or less opaque, returning its new opacity as
+/// a percentage, or None if it can't be changed: it draws itself with
+/// UpdateLayeredWindow, or belongs to an elevated process
+pub fn step_opacity(
+    hwnd: HWND,
+    step: OpacityStep,
+    step_percent: u8,
+    min_percent: u8,
+) -> Option<u8> {
+    unsafe {
+        let style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+        let layered = style & WS_EX_LAYERED.0 != 0;
+        let mut key = COLORREF(0);
+        let mut alpha = 255;
+        let mut flags = LAYERED_WINDOW_ATTRIBUTES_FLAGS(0);
+        if layered
+            && GetLayeredWindowAttributes(hwnd, Some(&mut key), Some(&mut alpha), Some(&mut flags))
+                .is_err()
+        {
+            // Layered attributes would stop its own drawing working
+            return None;
+        }
+        if !flags.contains(LWA_ALPHA) {
+            alpha = 255;
+        }
+
+        let mut changed = CHANGED.lock().ok()?;
+        // Forget closed windows, before their handles are reused
+        changed.retain(|window| IsWindow(Some(HWND(window.hwnd as *mut _))).as_bool());
+        let index = changed
+            .iter()
+            .position(|window| window.hwnd == hwnd.0 as isize);
+        let own = index.map_or(alpha, |index| changed[index].alpha);
+        let new = stepped(alpha, step, step_percent, min_percent, own);
+        if new == own {
+            // Back as it was: its own attributes, or none if it had none
+            if let Some(index) = index {
+                let window = changed.remove(index);
+                if window.added_layered {
+                    SetWindowLongW(hwnd, GWL_EXSTYLE, (style & !WS_EX_LAYERED.0) as i32);
+                } else if let Err(e) =
+                    SetLayeredWindowAttributes(hwnd, key, window.alpha, window.flags)
+                {
+                    warning!("Failed to restore window opacity: {}", e);
+                    return None;
+                }
+            }
+            return Some(percent(new));
+        }
+        if !layered {
+            SetWindowLongW(hwnd, GWL_EXSTYLE, (style | WS_EX_LAYERED.0) as i32);
+        }
+        if let Err(e) = SetLayeredWindowAttributes(hwnd, key, new, flags | LWA_ALPHA) {
+            warning!("Failed to set window opacity: {}", e);
+            return None;
+        }
+        if index.is_none() {
+            changed.push(Changed {
+                hwnd: hwnd.0 as isize,
+                added_layered: !layered,
+                alpha,
+                flags,
+            });
+        }
+        Some(percent(new))
+    }
+}
+
+/// Put every window still changed back to its own opacity, as nothing
+/// will be left to once the app exits
+pub fn reset_all() {
+    let windows: Vec<isize> = CHANGED
+        .lock()
+        .map(|changed| changed.iter().map(|window| window.hwnd).collect())
+        .unwrap_or_default();
+    for hwnd in windows {
+        let hwnd = HWND(hwnd as *mut _);
+        if unsafe { IsWindow(Some(hwnd)) }.as_bool() {
+            step_opacity(hwnd, OpacityStep::Reset, 0, 0);
+        }
+    }
+    if let Ok(mut changed) = CHANGED.lock() {
+        changed.clear();
+    }
+    info!("Window opacity reset");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stepped() {
+        let less = |alpha| stepped(alpha, OpacityStep::Less, 10, 20, 255);
+        let more = |alpha| stepped(alpha, OpacityStep::More, 10, 20, 255);
+        assert_eq!(less(255), 230);
+        assert_eq!(percent(less(255)), 90);
+        assert_eq!(more(230), 255);
+        assert_eq!(more(250), 255);
+        // Stops at the minimum, or where the window already was below it
+        assert_eq!(less(60), 51);
+        assert_eq!(percent(less(60)), 20);
+        assert_eq!(less(30), 30);
+        assert_eq!(stepped(51, OpacityStep::Reset, 10, 20, 255), 255);
+        // A window translucent of its own goes back to that, not opaque
+        assert_eq!(stepped(51, OpacityStep::Reset, 10, 20, 204), 204);
+    }
+}
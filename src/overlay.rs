@@ -1,60 +1,772 @@
+use std::cell::RefCell;
 use std::ptr;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use windows::core::{w, PCWSTR};
-use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, SIZE, WPARAM};
+use windows::Win32::Graphics::Dwm::DwmFlush;
 use windows::Win32::Graphics::Gdi::{
-    BeginPaint, CreateFontW, CreateSolidBrush, DeleteObject, EndPaint, FillRect, InvalidateRect,
-    SelectObject, SetBkMode, SetTextColor, TextOutW, UpdateWindow, DEFAULT_CHARSET, HBRUSH,
-    OUT_DEFAULT_PRECIS, CLIP_DEFAULT_PRECIS, DEFAULT_QUALITY, PAINTSTRUCT, TRANSPARENT,
+    CreateCompatibleDC, CreateDIBSection, CreateFontW, CreateSolidBrush, DeleteDC, DeleteObject,
+    DrawTextW, FillRect, GdiFlush, GetSysColor, IntersectClipRect, SelectClipRgn, SelectObject,
+    SetBkMode, SetTextColor, AC_SRC_ALPHA, AC_SRC_OVER, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+    BLENDFUNCTION, CLIP_DEFAULT_PRECIS, COLOR_HIGHLIGHT, COLOR_HIGHLIGHTTEXT, COLOR_WINDOW,
+    COLOR_WINDOWTEXT, DEFAULT_CHARSET, DEFAULT_QUALITY, DIB_RGB_COLORS, DT_CENTER, DT_END_ELLIPSIS,
+    DT_SINGLELINE, DT_TOP, DT_VCENTER, HBITMAP, HBRUSH, HDC, HGDIOBJ, OUT_DEFAULT_PRECIS,
+    TRANSPARENT,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::WindowsAndMessaging::{
-    CreateWindowExW, DefWindowProcW, RegisterClassW, SetLayeredWindowAttributes, SetWindowPos,
-    ShowWindow, CS_HREDRAW, CS_VREDRAW, HWND_TOPMOST, LAYERED_WINDOW_ATTRIBUTES_FLAGS, LWA_ALPHA,
-    SET_WINDOW_POS_FLAGS, SW_HIDE, SW_SHOW, SWP_NOACTIVATE, WINDOW_EX_STYLE, WINDOW_STYLE,
-    WM_PAINT, WNDCLASSW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetClientRect, GetWindowLongPtrW, KillTimer,
+    PostMessageW, RegisterClassW, SetTimer, SetWindowLongPtrW, SetWindowPos, ShowWindow,
+    UpdateLayeredWindowIndirect, CS_HREDRAW, CS_VREDRAW, GWLP_USERDATA, HWND_TOPMOST,
+    SET_WINDOW_POS_FLAGS, SPI_SETWORKAREA, SWP_NOACTIVATE, SW_HIDE, SW_SHOW, ULW_ALPHA,
+    UPDATELAYEREDWINDOWINFO, WINDOW_EX_STYLE, WINDOW_STYLE, WM_DISPLAYCHANGE, WM_SETTINGCHANGE,
+    WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT,
+    WS_POPUP,
 };
 
-use crate::config::{AppearanceConfig, Config};
-use crate::grid::{Grid, TilePos};
+use crate::accessibility::{
+    is_appearance_change, is_high_contrast, is_transparency_disabled, text_scale_percent,
+};
+use crate::bus::{self, AppEvent};
+use crate::config::{AppearanceConfig, Config, HighContrastMode, LabelContent};
+use crate::grid::{scale_for_dpi, Grid, TilePos, BASE_DPI};
+use crate::keyboard::{key_label, refresh_keyboard_layout};
+use crate::log::{info, warning};
+use crate::selection::SecondKey;
 
 const OVERLAY_CLASS_NAME: PCWSTR = w!("TactileWinOverlay");
+// Polls for keyboard layout switches while the overlay is visible
+const LAYOUT_TIMER_ID: usize = 1;
+const LAYOUT_POLL_MS: u32 = 250;
+// Posted to itself when the work area may have changed, so the callback
+// runs from the message loop rather than inside a broadcast
+const WM_WORK_AREA_CHANGED: u32 = 0x8002; // WM_APP + 2
+
+// Posted to itself after invalidating, to paint everything invalidated
+// since in one go
+const WM_REDRAW: u32 = 0x8007; // WM_APP + 7
+
+// Paints slower than this are logged as warnings when paint_timing is on
+const PAINT_BUDGET: Duration = Duration::from_millis(10);
+
+// Combo hint maps are this fraction of a tile's width
+const HINT_WIDTH_DIVISOR: i32 = 4;
+// Thickness of preview outlines, in DIPs
+const PREVIEW_OUTLINE_WIDTH: i32 = 4;
+// Diagonal stripes across strips struts keep clear, in pixels
+const HATCH_SPACING: i32 = 16;
+const HATCH_WIDTH: i32 = 4;
+
+static WORK_AREA_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+
+thread_local! {
+    // Every overlay window, to redraw when the config changes
+    static OVERLAYS: RefCell<Vec<HWND>> = const { RefCell::new(Vec::new()) };
+}
+// When the grid hotkey was pressed, until the overlay next paints
+#[cfg(debug_assertions)]
+static HOTKEY_PRESSED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// What an overlay window paints. Each Overlay owns one, reachable from its
+/// window procedure through GWLP_USERDATA.
+struct OverlayState {
+    grid: Option<Grid>,
+    /// Screen rect the window covers: the work area, and the strips beside
+    /// it that struts keep clear
+    frame: RECT,
+    /// Those strips, in screen coordinates, drawn hatched
+    excluded: Vec<RECT>,
+    highlight: Option<TilePos>,
+    /// Target window's current rect, to dim second keys that wouldn't move it
+    target_rect: Option<RECT>,
+    appearance: AppearanceConfig,
+    /// DPI used to scale label fonts (BASE_DPI unless dpi_scaling is enabled)
+    dpi: u32,
+    /// Configured label size multiplier, applied with the system text scale
+    text_scale: f32,
+    /// How the second key picks the region, for dimming and combo hints
+    second_key: SecondKey,
+    /// Where windows would go, outlined instead of moving them
+    preview: Vec<PreviewRect>,
+    /// What's drawn on screen, kept between paints so only what changed
+    /// is repainted
+    surface: Option<Surface>,
+    /// Client area to repaint on the next WM_REDRAW
+    dirty: Option<RECT>,
+    /// A WM_REDRAW is queued, so further updates needn't post another
+    redraw_pending: bool,
+    /// Log how long each paint takes
+    paint_timing: bool,
+}
+
+impl OverlayState {
+    /// The plain grid for `frame`, drawn as `config` says
+    fn new(grid: Grid, frame: RECT, config: &Config) -> Self {
+        Self {
+            grid: Some(grid),
+            frame,
+            excluded: Vec::new(),
+            highlight: None,
+            target_rect: None,
+            appearance: config.appearance.clone(),
+            dpi: BASE_DPI,
+            text_scale: config.accessibility.text_scale,
+            second_key: config.grid.second_key,
+            preview: Vec::new(),
+            surface: None,
+            dirty: None,
+            redraw_pending: false,
+            paint_timing: config.logging.paint_timing,
+        }
+    }
+}
+
+/// A picture of the overlay, as RGBA pixels with straight (not
+/// premultiplied) alpha, top row first
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
 
-static OVERLAY_GRID: Mutex<Option<Grid>> = Mutex::new(None);
-static HIGHLIGHT_TILE: Mutex<Option<TilePos>> = Mutex::new(None);
-static APPEARANCE: Mutex<AppearanceConfig> = Mutex::new(AppearanceConfig {
-    tile_color: 0x00805030,
-    highlight_color: 0x0000A0FF,
-    background_color: 0x00302020,
-    text_color: 0x00FFFFFF,
-    alpha: 220,
-});
+/// A window's would-be rect in screen coordinates, labelled with its program
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewRect {
+    pub rect: RECT,
+    pub label: String,
+}
+
+/// An off-screen 32-bit bitmap the overlay is painted into, then handed
+/// to UpdateLayeredWindow so each pixel has its own alpha
+struct Surface {
+    dc: HDC,
+    bitmap: HBITMAP,
+    old_bitmap: HGDIOBJ,
+    bits: *mut u32,
+    width: i32,
+    height: i32,
+}
+
+impl Surface {
+    fn new(width: i32, height: i32) -> windows::core::Result<Self> {
+        let info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                // Negative for rows top to bottom, as client coordinates run
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        unsafe {
+            let mut bits = ptr::null_mut();
+            let bitmap = CreateDIBSection(None, &info, DIB_RGB_COLORS, &mut bits, None, 0)?;
+            let dc = CreateCompatibleDC(None);
+            let old_bitmap = SelectObject(dc, bitmap.into());
+            Ok(Self {
+                dc,
+                bitmap,
+                old_bitmap,
+                bits: bits as *mut u32,
+                width,
+                height,
+            })
+        }
+    }
+
+    /// Pixels as 0xAARRGGBB, top row first. GDI may still be drawing;
+    /// GdiFlush before reading them.
+    fn pixels(&mut self) -> &mut [u32] {
+        unsafe { std::slice::from_raw_parts_mut(self.bits, (self.width * self.height) as usize) }
+    }
+
+    /// Show the surface in `hwnd`, which is the same size, updating `dirty`
+    fn present(&self, hwnd: HWND, dirty: &RECT) -> bool {
+        let size = SIZE {
+            cx: self.width,
+            cy: self.height,
+        };
+        let origin = POINT::default();
+        let blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as u8,
+            BlendFlags: 0,
+            SourceConstantAlpha: 255,
+            AlphaFormat: AC_SRC_ALPHA as u8,
+        };
+        let info = UPDATELAYEREDWINDOWINFO {
+            cbSize: size_of::<UPDATELAYEREDWINDOWINFO>() as u32,
+            psize: &size,
+            hdcSrc: self.dc,
+            pptSrc: &origin,
+            pblend: &blend,
+            dwFlags: ULW_ALPHA,
+            prcDirty: dirty,
+            ..Default::default()
+        };
+        unsafe { UpdateLayeredWindowIndirect(hwnd, &info).as_bool() }
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        unsafe {
+            SelectObject(self.dc, self.old_bitmap);
+            let _ = DeleteObject(self.bitmap.into());
+            let _ = DeleteDC(self.dc);
+        }
+    }
+}
 
 pub struct Overlay {
     hwnd: HWND,
+    state: Box<RefCell<OverlayState>>,
 }
 
-fn draw_grid(hwnd: HWND) {
+/// The state attached to an overlay window, if any
+unsafe fn window_state<'a>(hwnd: HWND) -> Option<&'a RefCell<OverlayState>> {
     unsafe {
-        let mut ps = PAINTSTRUCT::default();
-        let hdc = BeginPaint(hwnd, &mut ps);
+        let ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const RefCell<OverlayState>;
+        ptr.as_ref()
+    }
+}
 
-        // Get appearance config
-        let appearance = APPEARANCE.lock().ok().map(|a| a.clone()).unwrap_or_default();
+/// Colors and sizes for painting, resolved from the appearance config and
+/// the system's high-contrast and transparency settings
+struct Palette {
+    /// Between the tiles and around the grid
+    gap: u32,
+    tile: u32,
+    highlight: u32,
+    text: u32,
+    highlight_text: u32,
+    /// Tiles that wouldn't change anything as the second key
+    dimmed_tile: u32,
+    dimmed_text: u32,
+    /// Tile outline color and width in DIPs, drawn in high-contrast mode
+    border: Option<(u32, i32)>,
+    /// Label height as a percentage of the normal 48px font
+    label_percent: i32,
+    alpha: u8,
+    gap_alpha: u8,
+}
 
-        // Dark background
-        let bg_brush = CreateSolidBrush(COLORREF(appearance.background_color));
-        FillRect(hdc, &ps.rcPaint, bg_brush);
-        let _ = DeleteObject(bg_brush.into());
+impl Palette {
+    fn resolve(appearance: &AppearanceConfig) -> Self {
+        let system_high_contrast = is_high_contrast();
+        let high_contrast = match appearance.high_contrast {
+            HighContrastMode::Auto => system_high_contrast,
+            HighContrastMode::On => true,
+            HighContrastMode::Off => false,
+        };
 
-        // Get grid
-        let grid_guard = OVERLAY_GRID.lock().ok();
-        let highlight_guard = HIGHLIGHT_TILE.lock().ok();
+        if !high_contrast {
+            // Reduced transparency keeps the configured colors but drops alpha
+            let opaque =
+                appearance.high_contrast == HighContrastMode::Auto && is_transparency_disabled();
+            return Self {
+                dimmed_tile: blend(appearance.tile_color, appearance.gap_color),
+                dimmed_text: blend(appearance.text_color, appearance.gap_color),
+                gap: appearance.gap_color,
+                tile: appearance.tile_color,
+                highlight: appearance.highlight_color,
+                text: appearance.text_color,
+                highlight_text: appearance.text_color,
+                border: None,
+                label_percent: 100,
+                alpha: if opaque { 255 } else { appearance.alpha },
+                gap_alpha: if opaque { 255 } else { appearance.gap_alpha },
+            };
+        }
+
+        let (background, text, highlight, highlight_text) = unsafe {
+            if system_high_contrast {
+                (
+                    GetSysColor(COLOR_WINDOW),
+                    GetSysColor(COLOR_WINDOWTEXT),
+                    GetSysColor(COLOR_HIGHLIGHT),
+                    GetSysColor(COLOR_HIGHLIGHTTEXT),
+                )
+            } else {
+                // Forced on without a system theme: black, white and yellow
+                (0x00000000, 0x00FFFFFF, 0x0000FFFF, 0x00000000)
+            }
+        };
+        Self {
+            gap: background,
+            tile: background,
+            dimmed_tile: background,
+            dimmed_text: blend(text, background),
+            highlight,
+            text,
+            highlight_text,
+            border: Some((text, 4)),
+            label_percent: 150,
+            alpha: 255,
+            gap_alpha: 255,
+        }
+    }
+}
 
-        if let Some(Some(ref grid)) = grid_guard.as_ref().map(|g| g.as_ref()) {
+/// Average two COLORREF values channel by channel
+fn blend(a: u32, b: u32) -> u32 {
+    let channel = |shift: u32| ((((a >> shift) & 0xFF) + ((b >> shift) & 0xFF)) / 2) << shift;
+    channel(0) | channel(8) | channel(16)
+}
+
+/// True when choosing `pos` after `first` would leave the window where it is
+fn is_noop_choice(
+    grid: &Grid,
+    second_key: SecondKey,
+    first: TilePos,
+    pos: TilePos,
+    current: Option<RECT>,
+) -> bool {
+    current == Some(second_key.rect(grid, first, pos))
+}
+
+/// Text for a tile's label. `first` is the tile chosen by the first key, if
+/// any; from then on labels show only what the second key press needs.
+fn tile_label(
+    content: LabelContent,
+    grid: &Grid,
+    pos: TilePos,
+    first: Option<TilePos>,
+    key_label: impl Fn(char) -> char,
+) -> String {
+    match content {
+        LabelContent::Key => grid
+            .tile_to_key(pos)
+            .map(|key| key_label(key).to_string())
+            .unwrap_or_default(),
+        LabelContent::Pair => match grid.tile_to_key(pos) {
+            Some(key) if first.is_some() => key_label(key).to_string(),
+            Some(key) => format!("{0}{0}", key_label(key)),
+            None => String::new(),
+        },
+        LabelContent::Coordinates => format!("{},{}", pos.col + 1, pos.row + 1),
+        LabelContent::None => String::new(),
+    }
+}
+
+/// Corner of a tile where its combo hint map goes, shaped like the work
+/// area. `tile` is in client coordinates.
+fn hint_map_rect(grid: &Grid, tile: RECT) -> RECT {
+    let work_area = grid.work_area;
+    let width = (tile.right - tile.left) / HINT_WIDTH_DIVISOR;
+    let height =
+        width * (work_area.bottom - work_area.top) / (work_area.right - work_area.left).max(1);
+    let margin = width / 8;
+    let (right, bottom) = (tile.right - margin, tile.bottom - margin);
+    RECT {
+        left: right - width,
+        top: bottom - height,
+        right,
+        bottom,
+    }
+}
+
+/// Scale `region`, a rect within `work_area`, down onto `map`
+fn map_region(work_area: RECT, region: RECT, map: RECT) -> RECT {
+    let scale = |value: i32, from: (i32, i32), to: (i32, i32)| {
+        to.0 + (value - from.0) * (to.1 - to.0) / (from.1 - from.0).max(1)
+    };
+    let x = |x| scale(x, (work_area.left, work_area.right), (map.left, map.right));
+    let y = |y| scale(y, (work_area.top, work_area.bottom), (map.top, map.bottom));
+    RECT {
+        left: x(region.left),
+        top: y(region.top),
+        right: x(region.right),
+        bottom: y(region.bottom),
+    }
+}
+
+/// Everything about a tile's rendering that depends on the selection
+#[derive(Debug, PartialEq)]
+struct TileLook {
+    highlighted: bool,
+    /// Once the first key is in, tiles that would be a no-op second key
+    dimmed: bool,
+    /// Keys shown as the active keyboard layout labels them
+    label: String,
+    /// With combo hints on, the region this tile selects as the second key
+    hint: Option<RECT>,
+}
+
+fn tile_look(
+    state: &OverlayState,
+    grid: &Grid,
+    pos: TilePos,
+    key_label: fn(char) -> char,
+) -> TileLook {
+    let highlighted = state.highlight == Some(pos);
+    let dimmed = !highlighted
+        && state
+            .highlight
+            .is_some_and(|first| {
+                is_noop_choice(grid, state.second_key, first, pos, state.target_rect)
+            });
+    let label = tile_label(
+        state.appearance.label,
+        grid,
+        pos,
+        state.highlight,
+        key_label,
+    );
+    let hint = state
+        .highlight
+        .filter(|_| state.appearance.combo_hints)
+        .map(|first| state.second_key.rect(grid, first, pos));
+    TileLook {
+        highlighted,
+        dimmed,
+        label,
+        hint,
+    }
+}
+
+/// `rect`, in screen coordinates, relative to an overlay covering `work_area`
+fn to_client(work_area: RECT, rect: RECT) -> RECT {
+    RECT {
+        left: rect.left - work_area.left,
+        top: rect.top - work_area.top,
+        right: rect.right - work_area.left,
+        bottom: rect.bottom - work_area.top,
+    }
+}
+
+/// A tile's rect relative to the overlay window covering `frame`
+fn client_tile_rect(frame: RECT, grid: &Grid, pos: TilePos) -> RECT {
+    to_client(frame, grid.tile_rect(pos))
+}
+
+/// The smallest rect containing both `a` and `b`
+fn union(a: RECT, b: RECT) -> RECT {
+    RECT {
+        left: a.left.min(b.left),
+        top: a.top.min(b.top),
+        right: a.right.max(b.right),
+        bottom: a.bottom.max(b.bottom),
+    }
+}
+
+/// Top, bottom, left and right edges of a `width` thick outline just
+/// inside `rect`
+fn outline_edges(rect: RECT, width: i32) -> [RECT; 4] {
+    let inner_top = rect.top + width;
+    let inner_bottom = rect.bottom - width;
+    [
+        RECT {
+            bottom: inner_top,
+            ..rect
+        },
+        RECT {
+            top: inner_bottom,
+            ..rect
+        },
+        RECT {
+            top: inner_top,
+            right: rect.left + width,
+            bottom: inner_bottom,
+            ..rect
+        },
+        RECT {
+            left: rect.right - width,
+            top: inner_top,
+            bottom: inner_bottom,
+            ..rect
+        },
+    ]
+}
+
+/// Set the highlighted tile and return the client area that needs
+/// repainting: the bounds of every tile whose look changed
+fn update_highlight(
+    state: &mut OverlayState,
+    pos: Option<TilePos>,
+    key_label: fn(char) -> char,
+) -> Option<RECT> {
+    let Some(grid) = state.grid.clone() else {
+        state.highlight = pos;
+        return None;
+    };
+    let tiles: Vec<TilePos> = (0..grid.rows)
+        .flat_map(|row| (0..grid.cols).map(move |col| TilePos { col, row }))
+        .collect();
+    let before: Vec<TileLook> = tiles
+        .iter()
+        .map(|&t| tile_look(state, &grid, t, key_label))
+        .collect();
+    state.highlight = pos;
+
+    tiles
+        .iter()
+        .zip(before)
+        .filter(|&(&t, ref old)| tile_look(state, &grid, t, key_label) != *old)
+        .map(|(&t, _)| client_tile_rect(state.frame, &grid, t))
+        .reduce(union)
+}
+
+/// Where the overlay is drawn at the tiles' opacity rather than the gaps':
+/// the tiles and preview outlines
+fn solid_rects(state: &OverlayState) -> Vec<RECT> {
+    let Some(grid) = &state.grid else {
+        return Vec::new();
+    };
+    let frame = state.frame;
+    let outline_width = scale_for_dpi(PREVIEW_OUTLINE_WIDTH, state.dpi);
+    let tiles = (0..grid.rows).flat_map(|row| {
+        (0..grid.cols).map(move |col| client_tile_rect(frame, grid, TilePos { col, row }))
+    });
+    let outlines = state
+        .preview
+        .iter()
+        .flat_map(|preview| outline_edges(to_client(frame, preview.rect), outline_width));
+    tiles.chain(outlines).collect()
+}
+
+/// The strips struts keep clear, relative to the overlay window
+fn hatched_rects(state: &OverlayState) -> Vec<RECT> {
+    state
+        .excluded
+        .iter()
+        .map(|&strip| to_client(state.frame, strip))
+        .collect()
+}
+
+/// Whether the pixel at `x`, `y` falls on one of the hatch's stripes
+fn on_stripe(x: i32, y: i32) -> bool {
+    (x + y).rem_euclid(HATCH_SPACING) < HATCH_WIDTH
+}
+
+/// `pixel` at `alpha`, its color premultiplied as UpdateLayeredWindow expects
+fn premultiply(pixel: u32, alpha: u8) -> u32 {
+    let alpha = alpha as u32;
+    let channel = |shift: u32| (((pixel >> shift) & 0xFF) * alpha / 255) << shift;
+    (alpha << 24) | channel(16) | channel(8) | channel(0)
+}
+
+/// Give the freshly painted pixels in `area` their alpha: `alpha` inside
+/// the `solid` rects and `gap_alpha` around them. The `hatched` rects get
+/// `alpha` on their stripes and are see-through between them, so what's
+/// under them shows. GDI doesn't draw alpha.
+fn set_alpha(
+    pixels: &mut [u32],
+    width: i32,
+    area: RECT,
+    solid: &[RECT],
+    hatched: &[RECT],
+    alpha: u8,
+    gap_alpha: u8,
+) {
+    let height = pixels.len() as i32 / width.max(1);
+    let column = |x: i32| x.clamp(0, width) as usize;
+    let (left, right) = (column(area.left), column(area.right));
+    let mut row_alpha = vec![gap_alpha; width.max(0) as usize];
+    for y in area.top.max(0)..area.bottom.min(height) {
+        row_alpha[left..right].fill(gap_alpha);
+        for rect in solid.iter().filter(|rect| rect.top <= y && y < rect.bottom) {
+            let (from, to) = (column(rect.left).max(left), column(rect.right).min(right));
+            if from < to {
+                row_alpha[from..to].fill(alpha);
+            }
+        }
+        for rect in hatched
+            .iter()
+            .filter(|rect| rect.top <= y && y < rect.bottom)
+        {
+            let (from, to) = (column(rect.left).max(left), column(rect.right).min(right));
+            for (x, pixel_alpha) in row_alpha.iter_mut().enumerate().take(to).skip(from) {
+                *pixel_alpha = if on_stripe(x as i32, y) { alpha } else { 0 };
+            }
+        }
+        let row = &mut pixels[y as usize * width as usize..][left..right];
+        for (pixel, &alpha) in row.iter_mut().zip(&row_alpha[left..right]) {
+            *pixel = premultiply(*pixel, alpha);
+        }
+    }
+}
+
+/// A premultiplied 0xAARRGGBB pixel as straight RGBA bytes
+fn straight_rgba(pixel: u32) -> [u8; 4] {
+    let alpha = pixel >> 24;
+    let channel = |shift: u32| {
+        let value = (pixel >> shift) & 0xFF;
+        (value * 255 + alpha / 2)
+            .checked_div(alpha)
+            .map_or(0, |value| value.min(255) as u8)
+    };
+    [channel(16), channel(8), channel(0), alpha as u8]
+}
+
+/// Paint `area` of `state` into `surface` and give it its alpha, ready to
+/// show or save
+fn draw(surface: &mut Surface, area: RECT, state: &OverlayState) {
+    let palette = Palette::resolve(&state.appearance);
+    unsafe {
+        SelectClipRgn(surface.dc, None);
+        IntersectClipRect(surface.dc, area.left, area.top, area.right, area.bottom);
+        paint_overlay(surface.dc, &area, state, &palette);
+        let _ = GdiFlush();
+    }
+    let solid = solid_rects(state);
+    let hatched = hatched_rects(state);
+    let width = surface.width;
+    set_alpha(
+        surface.pixels(),
+        width,
+        area,
+        &solid,
+        &hatched,
+        palette.alpha,
+        palette.gap_alpha,
+    );
+}
+
+/// Draw the overlay off screen, as it looks over `work_area` with `grid`
+/// and the strips struts keep clear beside it, `excluded`, at `dpi`. No
+/// window is needed, so the grid can be pictured without opening it.
+pub fn snapshot(
+    grid: Grid,
+    work_area: RECT,
+    excluded: Vec<RECT>,
+    dpi: u32,
+    config: &Config,
+) -> windows::core::Result<Snapshot> {
+    let frame = excluded.iter().copied().fold(work_area, union);
+    let mut state = OverlayState::new(grid, frame, config);
+    state.excluded = excluded;
+    state.dpi = dpi;
+    let (width, height) = (frame.right - frame.left, frame.bottom - frame.top);
+    let mut surface = Surface::new(width, height)?;
+    let client = RECT {
+        left: 0,
+        top: 0,
+        right: width,
+        bottom: height,
+    };
+    draw(&mut surface, client, &state);
+    let rgba = surface
+        .pixels()
+        .iter()
+        .flat_map(|&pixel| straight_rgba(pixel))
+        .collect();
+    Ok(Snapshot {
+        width: width as u32,
+        height: height as u32,
+        rgba,
+    })
+}
+
+/// Repaint `dirty`, or everything, once the messages already queued are
+/// handled, so keys pressed in quick succession cost one paint
+fn schedule_redraw(hwnd: HWND, state: &mut OverlayState, dirty: Option<RECT>) {
+    let dirty = dirty.unwrap_or_else(|| {
+        let mut client = RECT::default();
+        unsafe {
+            let _ = GetClientRect(hwnd, &mut client);
+        }
+        client
+    });
+    state.dirty = Some(state.dirty.map_or(dirty, |pending| union(pending, dirty)));
+    if !state.redraw_pending {
+        state.redraw_pending = true;
+        unsafe {
+            let _ = PostMessageW(Some(hwnd), WM_REDRAW, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Paint what's been scheduled, or `everything`, into the overlay's
+/// surface and show it. Layered windows drawn this way get no WM_PAINT.
+fn render(hwnd: HWND, everything: bool) {
+    let Some(state) = (unsafe { window_state(hwnd) }) else {
+        return;
+    };
+    let mut state = state.borrow_mut();
+    let mut client = RECT::default();
+    unsafe {
+        let _ = GetClientRect(hwnd, &mut client);
+    }
+    let (width, height) = (client.right, client.bottom);
+    if width <= 0 || height <= 0 {
+        return;
+    }
+    // A new surface, first time round or after a resize, starts blank
+    let mut surface = match state.surface.take() {
+        Some(surface) if (surface.width, surface.height) == (width, height) => surface,
+        _ => match Surface::new(width, height) {
+            Ok(surface) => {
+                state.dirty = Some(client);
+                surface
+            }
+            Err(e) => {
+                warning!(
+                    "Couldn't create the overlay's {}x{} surface: {}",
+                    width,
+                    height,
+                    e
+                );
+                return;
+            }
+        },
+    };
+    let dirty = state.dirty.take();
+    if let Some(area) = if everything { Some(client) } else { dirty } {
+        let started = Instant::now();
+        draw(&mut surface, area, &state);
+        if !surface.present(hwnd, &area) {
+            warning!(
+                "Couldn't update the overlay: {}",
+                windows::core::Error::from_win32()
+            );
+        }
+        #[cfg(debug_assertions)]
+        if let Some(pressed) = HOTKEY_PRESSED
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+        {
+            info!("Hotkey to first paint: {:?}", pressed.elapsed());
+        }
+        if state.paint_timing {
+            let elapsed = started.elapsed();
+            let size = (area.right - area.left, area.bottom - area.top);
+            if elapsed > PAINT_BUDGET {
+                warning!("Overlay paint of {}x{} took {:?}", size.0, size.1, elapsed);
+            } else {
+                info!("Overlay paint of {}x{} took {:?}", size.0, size.1, elapsed);
+            }
+        }
+    }
+    state.surface = Some(surface);
+}
+
+fn paint_overlay(hdc: HDC, area: &RECT, state: &OverlayState, palette: &Palette) {
+    unsafe {
+        // Gaps between and around the tiles
+        let gap_brush = CreateSolidBrush(COLORREF(palette.gap));
+        FillRect(hdc, area, gap_brush);
+        let _ = DeleteObject(gap_brush.into());
+
+        // Strips struts keep clear, striped when their alpha is set
+        let hatch_brush = CreateSolidBrush(COLORREF(palette.text));
+        for strip in hatched_rects(state) {
+            FillRect(hdc, &strip, hatch_brush);
+        }
+        let _ = DeleteObject(hatch_brush.into());
+
+        if let Some(ref grid) = state.grid {
             // Create font for labels
+            let dpi = state.dpi;
+            let label_percent = palette.label_percent * text_scale_percent(state.text_scale) / 100;
+            let font_height = scale_for_dpi(48 * label_percent / 100, dpi);
             let font = CreateFontW(
-                48,
+                font_height,
                 0,
                 0,
                 0,
@@ -71,55 +783,124 @@ fn draw_grid(hwnd: HWND) {
             );
             let old_font = SelectObject(hdc, font.into());
             SetBkMode(hdc, TRANSPARENT);
-            SetTextColor(hdc, COLORREF(appearance.text_color));
-
-            // Tile colors from config
-            let tile_brush = CreateSolidBrush(COLORREF(appearance.tile_color));
-            let highlight_brush = CreateSolidBrush(COLORREF(appearance.highlight_color));
 
-            let highlight = highlight_guard.as_ref().and_then(|h| h.as_ref());
+            // Tile colors from the resolved palette
+            let tile_brush = CreateSolidBrush(COLORREF(palette.tile));
+            let highlight_brush = CreateSolidBrush(COLORREF(palette.highlight));
+            let dimmed_brush = CreateSolidBrush(COLORREF(palette.dimmed_tile));
+            let border_brush = palette
+                .border
+                .map(|(color, _)| CreateSolidBrush(COLORREF(color)));
+            let border_width = palette
+                .border
+                .map(|(_, width)| scale_for_dpi(width, dpi))
+                .unwrap_or(0);
 
             for row in 0..grid.rows {
                 for col in 0..grid.cols {
                     let pos = TilePos { col, row };
-                    let rect = grid.tile_rect(pos);
-
-                    // Adjust rect to be relative to overlay window (0,0 based)
-                    let draw_rect = RECT {
-                        left: rect.left - grid.work_area.left,
-                        top: rect.top - grid.work_area.top,
-                        right: rect.right - grid.work_area.left,
-                        bottom: rect.bottom - grid.work_area.top,
+                    let mut draw_rect = client_tile_rect(state.frame, grid, pos);
+
+                    // Outline the tile, then fill inside the border
+                    if let Some(border_brush) = border_brush {
+                        FillRect(hdc, &draw_rect, border_brush);
+                    }
+                    let fill_rect = RECT {
+                        left: draw_rect.left + border_width,
+                        top: draw_rect.top + border_width,
+                        right: draw_rect.right - border_width,
+                        bottom: draw_rect.bottom - border_width,
                     };
 
-                    // Choose brush based on highlight
-                    let brush = if highlight == Some(&pos) {
+                    // Choose brush and text color based on highlight
+                    let TileLook {
+                        highlighted: is_highlighted,
+                        dimmed: is_dimmed,
+                        label,
+                        hint,
+                    } = tile_look(state, grid, pos, key_label);
+                    let brush = if is_highlighted {
                         highlight_brush
+                    } else if is_dimmed {
+                        dimmed_brush
                     } else {
                         tile_brush
                     };
 
-                    FillRect(hdc, &draw_rect, HBRUSH(brush.0));
+                    FillRect(hdc, &fill_rect, HBRUSH(brush.0));
 
-                    // Draw key label centered using tile_to_key
-                    if let Some(key) = grid.tile_to_key(pos) {
-                        let key_str: Vec<u16> = format!("{}", key).encode_utf16().collect();
+                    let text_color = if is_highlighted {
+                        palette.highlight_text
+                    } else if is_dimmed {
+                        palette.dimmed_text
+                    } else {
+                        palette.text
+                    };
 
-                        let center_x = (draw_rect.left + draw_rect.right) / 2 - 15;
-                        let center_y = (draw_rect.top + draw_rect.bottom) / 2 - 24;
+                    // Outlined map of the work area with the hinted region filled in
+                    if let Some(region) = hint {
+                        let map = hint_map_rect(grid, fill_rect);
+                        let inset = scale_for_dpi(1, dpi).max(1);
+                        let inner = RECT {
+                            left: map.left + inset,
+                            top: map.top + inset,
+                            right: map.right - inset,
+                            bottom: map.bottom - inset,
+                        };
+                        let hint_brush = CreateSolidBrush(COLORREF(text_color));
+                        FillRect(hdc, &map, hint_brush);
+                        FillRect(hdc, &inner, HBRUSH(brush.0));
+                        FillRect(hdc, &map_region(grid.work_area, region, inner), hint_brush);
+                        let _ = DeleteObject(hint_brush.into());
+                    }
+
+                    // Draw label centered
+                    if !label.is_empty() {
+                        let mut label_str: Vec<u16> = label.encode_utf16().collect();
+                        SetTextColor(hdc, COLORREF(text_color));
 
-                        let _ = TextOutW(hdc, center_x, center_y, &key_str);
+                        let _ = DrawTextW(
+                            hdc,
+                            &mut label_str,
+                            &mut draw_rect,
+                            DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+                        );
                     }
                 }
             }
 
+            // Outline each previewed window's target, its program at the top
+            let outline_width = scale_for_dpi(PREVIEW_OUTLINE_WIDTH, dpi);
+            SetTextColor(hdc, COLORREF(palette.highlight));
+            for preview in &state.preview {
+                let rect = to_client(state.frame, preview.rect);
+                for edge in outline_edges(rect, outline_width) {
+                    FillRect(hdc, &edge, highlight_brush);
+                }
+                let mut label_rect = RECT {
+                    left: rect.left + outline_width * 2,
+                    top: rect.top + outline_width * 2,
+                    right: rect.right - outline_width * 2,
+                    bottom: rect.bottom - outline_width * 2,
+                };
+                let mut label_str: Vec<u16> = preview.label.encode_utf16().collect();
+                let _ = DrawTextW(
+                    hdc,
+                    &mut label_str,
+                    &mut label_rect,
+                    DT_CENTER | DT_TOP | DT_SINGLELINE | DT_END_ELLIPSIS,
+                );
+            }
+
             let _ = DeleteObject(tile_brush.into());
             let _ = DeleteObject(highlight_brush.into());
+            let _ = DeleteObject(dimmed_brush.into());
+            if let Some(border_brush) = border_brush {
+                let _ = DeleteObject(border_brush.into());
+            }
             SelectObject(hdc, old_font);
             let _ = DeleteObject(font.into());
         }
-
-        EndPaint(hwnd, &ps);
     }
 }
 
@@ -131,8 +912,44 @@ unsafe extern "system" fn overlay_window_proc(
 ) -> LRESULT {
     unsafe {
         match msg {
-            WM_PAINT => {
-                draw_grid(hwnd);
+            WM_SETTINGCHANGE if wparam.0 as u32 == SPI_SETWORKAREA.0 => {
+                // Taskbar auto-hide, docked toolbars and similar
+                let _ = PostMessageW(Some(hwnd), WM_WORK_AREA_CHANGED, WPARAM(0), LPARAM(0));
+                LRESULT(0)
+            }
+            WM_DISPLAYCHANGE => {
+                let _ = PostMessageW(Some(hwnd), WM_WORK_AREA_CHANGED, WPARAM(0), LPARAM(0));
+                LRESULT(0)
+            }
+            // Theme, contrast or transparency changed: the palette, brushes
+            // and fonts are resolved again on the next paint
+            _ if is_appearance_change(msg, wparam, lparam) => {
+                if let Some(state) = window_state(hwnd) {
+                    schedule_redraw(hwnd, &mut state.borrow_mut(), None);
+                }
+                DefWindowProcW(hwnd, msg, wparam, lparam)
+            }
+            WM_REDRAW => {
+                if let Some(state) = window_state(hwnd) {
+                    state.borrow_mut().redraw_pending = false;
+                }
+                render(hwnd, false);
+                LRESULT(0)
+            }
+            WM_WORK_AREA_CHANGED => {
+                let callback = WORK_AREA_CALLBACK.lock().ok().and_then(|guard| *guard);
+                if let Some(callback) = callback {
+                    callback();
+                }
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == LAYOUT_TIMER_ID => {
+                // Relabel tiles if the user switched keyboard layouts
+                if refresh_keyboard_layout()
+                    && let Some(state) = window_state(hwnd)
+                {
+                    schedule_redraw(hwnd, &mut state.borrow_mut(), None);
+                }
                 LRESULT(0)
             }
             _ => DefWindowProcW(hwnd, msg, wparam, lparam),
@@ -158,7 +975,11 @@ impl Overlay {
             let width = work_area.right - work_area.left;
             let height = work_area.bottom - work_area.top;
 
-            let ex_style = WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW;
+            let mut ex_style = WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW;
+            if config.appearance.click_through {
+                // With WS_EX_LAYERED, hit-testing skips the window entirely
+                ex_style |= WS_EX_TRANSPARENT;
+            }
             let style = WS_POPUP;
 
             let hwnd = CreateWindowExW(
@@ -176,54 +997,80 @@ impl Overlay {
                 Some(ptr::null()),
             )?;
 
-            // Store appearance config
-            if let Ok(mut guard) = APPEARANCE.lock() {
-                *guard = config.appearance.clone();
-            }
-
-            SetLayeredWindowAttributes(
-                hwnd,
-                COLORREF(0),
-                config.appearance.alpha,
-                LAYERED_WINDOW_ATTRIBUTES_FLAGS(LWA_ALPHA.0),
-            )?;
-
-            // Create and store grid
+            // Create grid and attach this window's state
             let grid = Grid::new(
                 config.grid.cols,
                 config.grid.rows,
                 config.grid.gap,
                 work_area,
-            );
-            if let Ok(mut guard) = OVERLAY_GRID.lock() {
-                *guard = Some(grid);
-            }
+            )
+            .with_keyboard_layout(config.grid.keyboard_layout);
+            let state = Box::new(RefCell::new(OverlayState::new(grid, work_area, config)));
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, &*state as *const _ as isize);
+            OVERLAYS.with(|overlays| overlays.borrow_mut().push(hwnd));
 
-            Ok(Self { hwnd })
+            Ok(Self { hwnd, state })
         }
     }
 
     pub fn show(&self) {
         unsafe {
+            refresh_keyboard_layout();
+            // Contrast and transparency settings may have changed since last shown
+            render(self.hwnd, true);
             let _ = ShowWindow(self.hwnd, SW_SHOW);
-            let _ = UpdateWindow(self.hwnd);
+            SetTimer(Some(self.hwnd), LAYOUT_TIMER_ID, LAYOUT_POLL_MS, None);
         }
     }
 
     pub fn hide(&self) {
         unsafe {
+            let _ = KillTimer(Some(self.hwnd), LAYOUT_TIMER_ID);
             let _ = ShowWindow(self.hwnd, SW_HIDE);
         }
     }
 
+    /// Hide, then wait for the compositor to take the overlay off screen so
+    /// a window moved next isn't briefly drawn underneath it
+    pub fn hide_and_wait(&self) {
+        self.hide();
+        unsafe {
+            let _ = DwmFlush();
+        }
+    }
+
     pub fn set_highlight(&self, pos: Option<TilePos>) {
-        if let Ok(mut guard) = HIGHLIGHT_TILE.lock() {
-            *guard = pos;
+        // Only repaint the tiles that changed; a full repaint is slow on 4K
+        let mut state = self.state.borrow_mut();
+        let dirty = update_highlight(&mut state, pos, key_label);
+        if dirty.is_some() {
+            schedule_redraw(self.hwnd, &mut state, dirty);
         }
-        unsafe {
-            let _ = InvalidateRect(Some(self.hwnd), None, true);
-            let _ = UpdateWindow(self.hwnd);
+    }
+
+    /// Set the target window's current rect, used to dim no-op second keys
+    pub fn set_target_rect(&self, rect: Option<RECT>) {
+        self.state.borrow_mut().target_rect = rect;
+    }
+
+    /// Outline where windows would go; empty to show the plain grid
+    pub fn set_preview(&self, preview: Vec<PreviewRect>) {
+        let mut state = self.state.borrow_mut();
+        if state.preview == preview {
+            return;
         }
+        state.preview = preview;
+        schedule_redraw(self.hwnd, &mut state, None);
+    }
+
+    /// Set the DPI used to scale label fonts
+    pub fn set_dpi(&self, dpi: u32) {
+        self.state.borrow_mut().dpi = dpi;
+    }
+
+    /// Set how the second key picks the region
+    pub fn set_second_key(&self, second_key: SecondKey) {
+        self.state.borrow_mut().second_key = second_key;
     }
 
     pub fn hwnd(&self) -> HWND {
@@ -231,29 +1078,35 @@ impl Overlay {
     }
 
     pub fn grid(&self) -> Option<Grid> {
-        OVERLAY_GRID.lock().ok().and_then(|g| g.clone())
+        self.state.borrow().grid.clone()
     }
 
     pub fn set_grid(&self, grid: Grid) {
-        if let Ok(mut guard) = OVERLAY_GRID.lock() {
-            *guard = Some(grid);
-        }
-        unsafe {
-            let _ = InvalidateRect(Some(self.hwnd), None, true);
-            let _ = UpdateWindow(self.hwnd);
-        }
+        let mut state = self.state.borrow_mut();
+        state.grid = Some(grid);
+        schedule_redraw(self.hwnd, &mut state, None);
     }
 
-    pub fn update_position(&self, work_area: RECT) {
+    /// Cover `work_area`, and the `excluded` strips beside it that struts
+    /// keep clear, which are drawn hatched
+    pub fn update_position(&self, work_area: RECT, excluded: Vec<RECT>) {
+        let frame = excluded.iter().copied().fold(work_area, union);
+        let mut state = self.state.borrow_mut();
+        if state.frame != frame || state.excluded != excluded {
+            state.frame = frame;
+            state.excluded = excluded;
+            schedule_redraw(self.hwnd, &mut state, None);
+        }
+        drop(state);
         unsafe {
-            let width = work_area.right - work_area.left;
-            let height = work_area.bottom - work_area.top;
+            let width = frame.right - frame.left;
+            let height = frame.bottom - frame.top;
 
             let _ = SetWindowPos(
                 self.hwnd,
                 Some(HWND_TOPMOST),
-                work_area.left,
-                work_area.top,
+                frame.left,
+                frame.top,
                 width,
                 height,
                 SET_WINDOW_POS_FLAGS(SWP_NOACTIVATE.0),
@@ -261,3 +1114,302 @@ impl Overlay {
         }
     }
 }
+
+/// Log the time from now to the overlay's next paint, in debug builds, to
+/// keep an eye on how quickly the grid hotkey responds
+pub fn start_latency_probe() {
+    #[cfg(debug_assertions)]
+    if let Ok(mut guard) = HOTKEY_PRESSED.lock() {
+        *guard = Some(Instant::now());
+    }
+}
+
+/// Called when the work area may have changed while an overlay is open
+pub fn set_work_area_changed_callback(callback: fn()) {
+    if let Ok(mut guard) = WORK_AREA_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+/// Redraw every overlay, open or cached, as the config says when it changes
+pub fn register() {
+    bus::subscribe(follow_appearance);
+}
+
+fn follow_appearance(event: &AppEvent) {
+    if !matches!(event, AppEvent::ConfigChanged) {
+        return;
+    }
+    let Some(config) = bus::config() else {
+        return;
+    };
+    OVERLAYS.with(|overlays| {
+        for &hwnd in overlays.borrow().iter() {
+            let Some(state) = (unsafe { window_state(hwnd) }) else {
+                continue;
+            };
+            let mut state = state.borrow_mut();
+            state.appearance = config.appearance.clone();
+            state.text_scale = config.accessibility.text_scale;
+            state.paint_timing = config.logging.paint_timing;
+            schedule_redraw(hwnd, &mut state, None);
+        }
+    });
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        unsafe {
+            // Detach the state before it's freed, then close the window
+            SetWindowLongPtrW(self.hwnd, GWLP_USERDATA, 0);
+            OVERLAYS.with(|overlays| overlays.borrow_mut().retain(|&hwnd| hwnd != self.hwnd));
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_grid() -> Grid {
+        Grid::new(
+            4,
+            2,
+            10,
+            RECT {
+                left: 0,
+                top: 0,
+                right: 1920,
+                bottom: 1080,
+            },
+        )
+    }
+
+    fn label(content: LabelContent, pos: TilePos, first: Option<TilePos>) -> String {
+        tile_label(content, &test_grid(), pos, first, |c| c)
+    }
+
+    #[test]
+    fn test_tile_labels() {
+        let pos = TilePos { col: 1, row: 1 };
+        assert_eq!(label(LabelContent::Key, pos, None), "S");
+        assert_eq!(label(LabelContent::Coordinates, pos, None), "2,2");
+        assert_eq!(label(LabelContent::None, pos, None), "");
+    }
+
+    #[test]
+    fn test_pair_labels_after_first_key() {
+        let pos = TilePos { col: 1, row: 0 };
+        let first = Some(TilePos { col: 0, row: 0 });
+        assert_eq!(label(LabelContent::Pair, pos, None), "WW");
+        assert_eq!(label(LabelContent::Pair, pos, first), "W");
+    }
+
+    #[test]
+    fn test_noop_choice() {
+        let grid = test_grid();
+        let first = TilePos { col: 0, row: 0 };
+        let second = TilePos { col: 1, row: 1 };
+        let current = Some(grid.combine_tiles(first, second));
+        let corner = SecondKey::Corner;
+        assert!(is_noop_choice(&grid, corner, first, second, current));
+        assert!(!is_noop_choice(&grid, corner, first, first, current));
+        assert!(!is_noop_choice(&grid, corner, first, second, None));
+        // In extent mode, S means two columns and two rows
+        let size = TilePos { col: 1, row: 1 };
+        assert!(is_noop_choice(&grid, SecondKey::Extent, first, size, current));
+    }
+
+    fn test_state(label: LabelContent) -> OverlayState {
+        OverlayState {
+            grid: Some(test_grid()),
+            frame: test_grid().work_area,
+            excluded: Vec::new(),
+            highlight: None,
+            target_rect: None,
+            appearance: AppearanceConfig {
+                label,
+                ..Default::default()
+            },
+            dpi: BASE_DPI,
+            text_scale: 1.0,
+            second_key: SecondKey::Corner,
+            preview: Vec::new(),
+            surface: None,
+            dirty: None,
+            redraw_pending: false,
+            paint_timing: false,
+        }
+    }
+
+    #[test]
+    fn test_highlight_repaints_changed_tiles_only() {
+        let mut state = test_state(LabelContent::Key);
+        let first = TilePos { col: 1, row: 0 };
+        let grid = test_grid();
+
+        let dirty = update_highlight(&mut state, Some(first), |c| c);
+        assert_eq!(dirty, Some(client_tile_rect(state.frame, &grid, first)));
+        assert_eq!(update_highlight(&mut state, Some(first), |c| c), None);
+
+        // Pair labels change on every tile once the first key is in
+        let mut state = test_state(LabelContent::Pair);
+        let dirty = update_highlight(&mut state, Some(first), |c| c).unwrap();
+        let last = TilePos { col: 3, row: 1 };
+        assert_eq!(dirty, grid.combine_tiles(TilePos { col: 0, row: 0 }, last));
+    }
+
+    #[test]
+    fn test_combo_hints() {
+        let grid = test_grid();
+        let first = TilePos { col: 0, row: 0 };
+        let mut state = test_state(LabelContent::Key);
+        state.appearance.combo_hints = true;
+        state.highlight = Some(first);
+        let second = TilePos { col: 1, row: 1 };
+        let look = tile_look(&state, &grid, second, |c| c);
+        assert_eq!(look.hint, Some(grid.combine_tiles(first, second)));
+
+        // A 16:9 map in the bottom-right corner, inset by an eighth of its width
+        let tile = RECT {
+            left: 0,
+            top: 0,
+            right: 640,
+            bottom: 480,
+        };
+        let map = hint_map_rect(&grid, tile);
+        assert_eq!(
+            map,
+            RECT {
+                left: 460,
+                top: 370,
+                right: 620,
+                bottom: 460,
+            }
+        );
+        // The left half of the work area covers the left half of the map
+        let left_half = RECT {
+            right: 960,
+            ..grid.work_area
+        };
+        let mapped = map_region(grid.work_area, left_half, map);
+        assert_eq!((mapped.left, mapped.right), (460, 540));
+        assert_eq!((mapped.top, mapped.bottom), (370, 460));
+    }
+
+    #[test]
+    fn test_preview_outline() {
+        let work_area = RECT {
+            left: 1920,
+            top: 0,
+            right: 3840,
+            bottom: 1040,
+        };
+        let target = RECT {
+            left: 1930,
+            top: 10,
+            right: 2400,
+            bottom: 515,
+        };
+        let rect = to_client(work_area, target);
+        assert_eq!(
+            (rect.left, rect.top, rect.right, rect.bottom),
+            (10, 10, 480, 515)
+        );
+
+        let [top, bottom, left, right] = outline_edges(rect, 4);
+        assert_eq!(
+            (top.top, top.bottom, top.left, top.right),
+            (10, 14, 10, 480)
+        );
+        assert_eq!((bottom.top, bottom.bottom), (511, 515));
+        // The sides fit between the top and bottom edges
+        assert_eq!(
+            (left.left, left.right, left.top, left.bottom),
+            (10, 14, 14, 511)
+        );
+        assert_eq!((right.left, right.right), (476, 480));
+    }
+
+    #[test]
+    fn test_gap_alpha() {
+        let rect = |left, top, right, bottom| RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        // A 4x3 surface of white, with a 2x1 tile in the middle row
+        let mut pixels = vec![0x00FFFFFF; 12];
+        let tile = rect(1, 1, 3, 2);
+        set_alpha(&mut pixels, 4, rect(0, 0, 4, 3), &[tile], &[], 255, 0x80);
+        assert_eq!(pixels[0], 0x80808080);
+        assert_eq!(
+            &pixels[4..8],
+            [0x80808080, 0xFFFFFFFF, 0xFFFFFFFF, 0x80808080]
+        );
+
+        // Only the dirty area is touched; outside it stays premultiplied
+        pixels[5] = 0x00FFFFFF;
+        set_alpha(&mut pixels, 4, rect(1, 1, 2, 2), &[tile], &[], 0x40, 0x80);
+        assert_eq!(pixels[5], 0x40404040);
+        assert_eq!(pixels[6], 0xFFFFFFFF);
+
+        // Tiles and preview outlines are solid; the gaps between aren't
+        let mut state = test_state(LabelContent::Key);
+        assert_eq!(solid_rects(&state).len(), 8);
+        state.preview = vec![PreviewRect {
+            rect: rect(0, 0, 960, 540),
+            label: String::new(),
+        }];
+        let solid = solid_rects(&state);
+        assert_eq!(solid.len(), 12);
+        assert_eq!(solid[8], rect(0, 0, 960, 4));
+    }
+
+    #[test]
+    fn test_hatched_strips() {
+        let rect = |left, top, right, bottom| RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        // A strut keeps the left 64 pixels clear; the window covers them too
+        let mut state = test_state(LabelContent::Key);
+        state.grid = Some(test_grid().with_work_area(rect(64, 0, 1920, 1080)));
+        state.frame = rect(0, 0, 1920, 1080);
+        state.excluded = vec![rect(0, 0, 64, 1080)];
+        assert_eq!(hatched_rects(&state), [rect(0, 0, 64, 1080)]);
+        assert_eq!(solid_rects(&state)[0].left, 74);
+
+        // Stripes at full alpha, see-through between them
+        let mut pixels = vec![0x00FFFFFF; 32];
+        let strip = rect(0, 0, 32, 1);
+        set_alpha(&mut pixels, 32, strip, &[], &[strip], 255, 0x80);
+        assert_eq!(pixels[0], 0xFFFFFFFF);
+        assert_eq!(pixels[HATCH_WIDTH as usize], 0);
+        assert_eq!(pixels[HATCH_SPACING as usize], 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn test_straight_rgba() {
+        // Round trips through premultiply, give or take rounding
+        assert_eq!(
+            straight_rgba(premultiply(0x00FF8040, 255)),
+            [0xFF, 0x80, 0x40, 255]
+        );
+        assert_eq!(
+            straight_rgba(premultiply(0x00FF8040, 0x80)),
+            [0xFF, 0x80, 0x40, 0x80]
+        );
+        assert_eq!(straight_rgba(premultiply(0x00FF8040, 0)), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_blend() {
+        assert_eq!(blend(0x00FFFFFF, 0x00000000), 0x007F7F7F);
+        assert_eq!(blend(0x00204060, 0x00204060), 0x00204060);
+    }
+}
@@ -0,0 +1,187 @@
+use std::ptr;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, EndPaint, GetStockObject, GetWindowDC, InvalidateRect, PatBlt, ReleaseDC,
+    DSTINVERT, HBRUSH, PAINTSTRUCT, WHITE_BRUSH,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetCapture, ReleaseCapture, SetCapture};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DrawIcon, GetAncestor, GetCursorPos, GetWindowRect,
+    LoadCursorW, RegisterClassW, SetCursor, WindowFromPoint, CS_HREDRAW, CS_VREDRAW, GA_ROOT,
+    HICON, HMENU, IDC_ARROW, IDC_CROSS, WM_CAPTURECHANGED, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_MOUSEMOVE, WM_PAINT, WNDCLASSW, WS_CHILD, WS_TABSTOP, WS_VISIBLE,
+};
+
+use crate::rules::WindowInfo;
+use crate::window::{get_window_info, is_own_window};
+
+const PICKER_CLASS_NAME: PCWSTR = w!("TactileWinPicker");
+
+/// Size of the crosshair target
+pub const PICKER_SIZE: i32 = 32;
+/// Thickness of the frame drawn around the window under the crosshair
+const FRAME_WIDTH: i32 = 3;
+
+/// Called with the window the crosshair was dropped on
+pub type PickCallback = fn(WindowInfo);
+
+// Window currently framed while dragging, 0 for none
+static HIGHLIGHTED: AtomicIsize = AtomicIsize::new(0);
+// Only one crosshair can be dragged at a time, so one callback serves all
+static PICK_CALLBACK: Mutex<Option<PickCallback>> = Mutex::new(None);
+
+/// Add a crosshair target to `parent` at (`x`, `y`), like Spy++'s finder:
+/// drag it onto a window and `on_pick` gets that window's process, class
+/// and title. tactile-win's own windows can't be picked.
+pub fn create_picker(parent: HWND, x: i32, y: i32, id: i32, on_pick: PickCallback) {
+    if let Ok(mut guard) = PICK_CALLBACK.lock() {
+        *guard = Some(on_pick);
+    }
+
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap();
+        let wc = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(picker_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: PICKER_CLASS_NAME,
+            hbrBackground: HBRUSH(GetStockObject(WHITE_BRUSH).0),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            ..Default::default()
+        };
+        let _ = RegisterClassW(&wc);
+
+        let _ = CreateWindowExW(
+            Default::default(),
+            PICKER_CLASS_NAME,
+            PCWSTR::null(),
+            WS_CHILD | WS_VISIBLE | WS_TABSTOP,
+            x,
+            y,
+            PICKER_SIZE,
+            PICKER_SIZE,
+            Some(parent),
+            Some(HMENU(id as isize as *mut _)),
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+    }
+}
+
+/// Invert a frame around `hwnd`; inverting it again erases it
+fn invert_frame(hwnd: HWND) {
+    unsafe {
+        let mut rect = RECT::default();
+        if GetWindowRect(hwnd, &mut rect).is_err() {
+            return;
+        }
+        let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+        let hdc = GetWindowDC(Some(hwnd));
+        let _ = PatBlt(hdc, 0, 0, width, FRAME_WIDTH, DSTINVERT);
+        let _ = PatBlt(hdc, 0, height - FRAME_WIDTH, width, FRAME_WIDTH, DSTINVERT);
+        let _ = PatBlt(
+            hdc,
+            0,
+            FRAME_WIDTH,
+            FRAME_WIDTH,
+            height - FRAME_WIDTH * 2,
+            DSTINVERT,
+        );
+        let _ = PatBlt(
+            hdc,
+            width - FRAME_WIDTH,
+            FRAME_WIDTH,
+            FRAME_WIDTH,
+            height - FRAME_WIDTH * 2,
+            DSTINVERT,
+        );
+        ReleaseDC(Some(hwnd), hdc);
+    }
+}
+
+/// Frame `hwnd` instead of whatever was framed before
+fn set_highlight(hwnd: Option<HWND>) {
+    let key = hwnd.map_or(0, |hwnd| hwnd.0 as isize);
+    let previous = HIGHLIGHTED.swap(key, Ordering::SeqCst);
+    if previous == key {
+        return;
+    }
+    if previous != 0 {
+        invert_frame(HWND(previous as *mut _));
+    }
+    if let Some(hwnd) = hwnd {
+        invert_frame(hwnd);
+    }
+}
+
+/// The top-level window under the cursor, unless it is one of ours
+fn window_under_cursor() -> Option<HWND> {
+    unsafe {
+        let mut point = POINT::default();
+        GetCursorPos(&mut point).ok()?;
+        let root = GetAncestor(WindowFromPoint(point), GA_ROOT);
+        (!root.is_invalid() && !is_own_window(root)).then_some(root)
+    }
+}
+
+fn paint_picker(hwnd: HWND) {
+    unsafe {
+        let mut ps = PAINTSTRUCT::default();
+        let hdc = BeginPaint(hwnd, &mut ps);
+        // The crosshair leaves its target while being dragged, as in Spy++
+        if GetCapture() != hwnd
+            && let Ok(cross) = LoadCursorW(None, IDC_CROSS)
+        {
+            let _ = DrawIcon(hdc, 0, 0, HICON(cross.0));
+        }
+        let _ = EndPaint(hwnd, &ps);
+    }
+}
+
+unsafe extern "system" fn picker_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_PAINT => {
+                paint_picker(hwnd);
+                LRESULT(0)
+            }
+            WM_LBUTTONDOWN => {
+                SetCapture(hwnd);
+                if let Ok(cross) = LoadCursorW(None, IDC_CROSS) {
+                    SetCursor(Some(cross));
+                }
+                let _ = InvalidateRect(Some(hwnd), None, true);
+                LRESULT(0)
+            }
+            WM_MOUSEMOVE if GetCapture() == hwnd => {
+                set_highlight(window_under_cursor());
+                LRESULT(0)
+            }
+            WM_LBUTTONUP if GetCapture() == hwnd => {
+                let picked = window_under_cursor();
+                // Erases the frame, via WM_CAPTURECHANGED
+                let _ = ReleaseCapture();
+                let callback = PICK_CALLBACK.lock().ok().and_then(|guard| *guard);
+                if let (Some(picked), Some(callback)) = (picked, callback) {
+                    callback(get_window_info(picked));
+                }
+                LRESULT(0)
+            }
+            WM_CAPTURECHANGED => {
+                set_highlight(None);
+                let _ = InvalidateRect(Some(hwnd), None, true);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
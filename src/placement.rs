@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use windows::Win32::Foundation::{HWND, RECT};
+
+/// Most windows remembered at once; past it, the window remembered
+/// longest ago is forgotten
+const MAX_REMEMBERED: usize = 256;
+
+/// Where windows were before tactile-win first tiled them, so they can be
+/// put back when switching to free-form work
+#[derive(Default)]
+pub struct PlacementStore {
+    /// Original rects by HWND, oldest first
+    originals: VecDeque<(isize, RECT)>,
+}
+
+impl PlacementStore {
+    /// Record `rect` as the window's original placement, unless one is
+    /// already recorded: tiling a tiled window again keeps the first
+    pub fn remember(&mut self, hwnd: HWND, rect: RECT) {
+        let key = hwnd.0 as isize;
+        if self.originals.iter().any(|&(known, _)| known == key) {
+            return;
+        }
+        if self.originals.len() >= MAX_REMEMBERED {
+            self.originals.pop_front();
+        }
+        self.originals.push_back((key, rect));
+    }
+
+    /// Drop the window's placement, once it has closed and its handle
+    /// may be reused
+    pub fn forget(&mut self, hwnd: HWND) {
+        let key = hwnd.0 as isize;
+        self.originals.retain(|&(known, _)| known != key);
+    }
+
+    /// Remove and return every recorded placement
+    pub fn take_all(&mut self) -> Vec<(HWND, RECT)> {
+        self.originals
+            .drain(..)
+            .map(|(hwnd, rect)| (HWND(hwnd as *mut _), rect))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32) -> RECT {
+        RECT {
+            left,
+            top: 0,
+            right: left + 100,
+            bottom: 100,
+        }
+    }
+
+    #[test]
+    fn test_keeps_first_placement() {
+        let mut store = PlacementStore::default();
+        let (a, b) = (HWND(0x10 as *mut _), HWND(0x20 as *mut _));
+        store.remember(a, rect(10));
+        store.remember(b, rect(20));
+        store.remember(a, rect(30));
+
+        let mut placements = store.take_all();
+        placements.sort_by_key(|(hwnd, _)| hwnd.0 as isize);
+        assert_eq!(placements, vec![(a, rect(10)), (b, rect(20))]);
+        assert!(store.take_all().is_empty());
+    }
+
+    #[test]
+    fn test_forgets_closed_and_oldest() {
+        let mut store = PlacementStore::default();
+        for n in 1..=MAX_REMEMBERED + 1 {
+            store.remember(HWND(n as *mut _), rect(n as i32));
+        }
+        store.forget(HWND(2 as *mut _));
+
+        let placements = store.take_all();
+        assert_eq!(placements.len(), MAX_REMEMBERED - 1);
+        // The first was pushed out by the last, the second closed
+        assert_eq!(placements[0], (HWND(3 as *mut _), rect(3)));
+    }
+}
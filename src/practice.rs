@@ -0,0 +1,535 @@
+use std::cell::RefCell;
+use std::ptr;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateFontW, DeleteObject, DrawTextW, EndPaint, GetStockObject, InvalidateRect,
+    SelectObject, SetBkMode, CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET, DEFAULT_QUALITY, DT_LEFT,
+    DT_WORDBREAK, HBRUSH, HMONITOR, OUT_DEFAULT_PRECIS, PAINTSTRUCT, TRANSPARENT, WHITE_BRUSH,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetClientRect, RegisterClassW,
+    SetForegroundWindow, ShowWindow, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, SW_SHOW,
+    WINDOW_EX_STYLE, WM_CLOSE, WM_DESTROY, WM_PAINT, WNDCLASSW, WS_OVERLAPPEDWINDOW,
+};
+
+use crate::accessibility::describe_span;
+use crate::grid::{Grid, TilePos};
+use crate::icon::window_icon;
+use crate::keyboard::{grid_hotkey, key_label};
+use crate::keys::Hotkey;
+use crate::selection::SecondKey;
+use crate::window::get_window_monitor;
+
+const PRACTICE_CLASS_NAME: PCWSTR = w!("TactileWinPractice");
+
+/// Challenges in a session
+const ROUNDS: u32 = 10;
+/// Opening rounds on single tiles, before two-key combos
+const TILE_ROUNDS: u32 = 3;
+/// Closing rounds that need Tab, when there is more than one monitor
+const MONITOR_ROUNDS: u32 = 2;
+
+// Use atomic for HWND tracking since HWND is not Send
+static PRACTICE_HWND: AtomicIsize = AtomicIsize::new(0);
+
+thread_local! {
+    static SESSION: RefCell<Option<Session>> = const { RefCell::new(None) };
+}
+
+/// What a round teaches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lesson {
+    /// One tile: the same key twice
+    Tile,
+    /// A span: its first and last tiles
+    Combo,
+    /// A span on the next monitor, reached with Tab
+    Monitor,
+}
+
+/// Put the practice window on the span from `first` to `last`
+#[derive(Debug, Clone, Copy)]
+struct Challenge {
+    lesson: Lesson,
+    first: TilePos,
+    last: TilePos,
+    /// Monitor the window was on when the challenge was set
+    monitor: isize,
+}
+
+/// Corners of the span covering `a` and `b`, in either order
+fn normalize(a: TilePos, b: TilePos) -> (TilePos, TilePos) {
+    (
+        TilePos {
+            col: a.col.min(b.col),
+            row: a.row.min(b.row),
+        },
+        TilePos {
+            col: a.col.max(b.col),
+            row: a.row.max(b.row),
+        },
+    )
+}
+
+/// A practice run: the current challenge and the score so far
+struct Session {
+    /// Grid the challenges are set on, which also names the keys
+    grid: Grid,
+    /// How the second key picks the region, which changes the keys asked for
+    second_key: SecondKey,
+    monitors: usize,
+    round: u32,
+    correct: u32,
+    /// Time taken over the correct answers
+    total_time: Duration,
+    challenge: Challenge,
+    shown_at: Instant,
+    /// How the last attempt went
+    feedback: String,
+    rng: u64,
+}
+
+impl Session {
+    fn new(
+        grid: Grid,
+        second_key: SecondKey,
+        monitors: usize,
+        seed: u64,
+        monitor: isize,
+        now: Instant,
+    ) -> Self {
+        let origin = TilePos { col: 0, row: 0 };
+        let mut session = Self {
+            grid,
+            second_key,
+            monitors,
+            round: 0,
+            correct: 0,
+            total_time: Duration::ZERO,
+            challenge: Challenge {
+                lesson: Lesson::Tile,
+                first: origin,
+                last: origin,
+                monitor,
+            },
+            shown_at: now,
+            feedback: "Move this window with the grid overlay as asked below.".to_string(),
+            // Xorshift gets stuck on zero
+            rng: seed | 1,
+        };
+        session.set_challenge(monitor, now);
+        session
+    }
+
+    fn lesson(&self) -> Lesson {
+        if self.round < TILE_ROUNDS {
+            Lesson::Tile
+        } else if self.monitors > 1 && self.round >= ROUNDS - MONITOR_ROUNDS {
+            Lesson::Monitor
+        } else {
+            Lesson::Combo
+        }
+    }
+
+    fn random_tile(&mut self) -> TilePos {
+        let mut next = || {
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 7;
+            self.rng ^= self.rng << 17;
+            (self.rng >> 32) as u32
+        };
+        TilePos {
+            col: next() % self.grid.cols,
+            row: next() % self.grid.rows,
+        }
+    }
+
+    fn set_challenge(&mut self, monitor: isize, now: Instant) {
+        let lesson = self.lesson();
+        let first = self.random_tile();
+        let mut last = first;
+        if lesson != Lesson::Tile && self.grid.cols * self.grid.rows > 1 {
+            while last == first {
+                last = self.random_tile();
+            }
+        }
+        self.challenge = Challenge {
+            lesson,
+            first,
+            last,
+            monitor,
+        };
+        self.shown_at = now;
+    }
+
+    fn is_finished(&self) -> bool {
+        self.round >= ROUNDS
+    }
+
+    /// Score a move onto `span` of `monitor`, then set the next challenge.
+    /// Returns whether the move was the one asked for.
+    fn record(&mut self, span: (TilePos, TilePos), monitor: isize, now: Instant) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        let challenge = self.challenge;
+        let right_span = normalize(span.0, span.1) == normalize(challenge.first, challenge.last);
+        let right_monitor = challenge.lesson != Lesson::Monitor || monitor != challenge.monitor;
+        let correct = right_span && right_monitor;
+
+        self.feedback = if correct {
+            let taken = now - self.shown_at;
+            self.correct += 1;
+            self.total_time += taken;
+            format!("Correct, in {:.1} s.", taken.as_secs_f32())
+        } else if right_span {
+            "Not quite: that was the same monitor. Press Tab first.".to_string()
+        } else {
+            let (cols, rows) = (self.grid.cols, self.grid.rows);
+            format!(
+                "Not quite: that was the {}.",
+                describe_span(cols, rows, span.0, span.1)
+            )
+        };
+
+        self.round += 1;
+        if !self.is_finished() {
+            self.set_challenge(monitor, now);
+        }
+        correct
+    }
+
+    fn average_secs(&self) -> f32 {
+        if self.correct == 0 {
+            return 0.0;
+        }
+        self.total_time.as_secs_f32() / self.correct as f32
+    }
+
+    /// Instructions and score for the window, with the grid opened by
+    /// `hotkey` and keys as `key_label` names them
+    fn text(&self, hotkey: Hotkey, key_label: impl Fn(char) -> char) -> String {
+        if self.is_finished() {
+            return format!(
+                "{}\n\nPractice complete: {} of {} correct, {:.1} s on average.\n\n\
+                 Choose Practice from the tray menu to go again.",
+                self.feedback,
+                self.correct,
+                ROUNDS,
+                self.average_secs()
+            );
+        }
+
+        let challenge = self.challenge;
+        let key = |pos| {
+            self.grid
+                .tile_to_key(pos)
+                .map(|key| key_label(key).to_string())
+                .unwrap_or_default()
+        };
+        let (first_tile, second_tile) = match self.second_key {
+            SecondKey::Corner => (challenge.first, challenge.last),
+            SecondKey::Extent => {
+                let (first, last) = normalize(challenge.first, challenge.last);
+                (first, self.second_key.second_tile(first, last))
+            }
+        };
+        let (first, second) = (key(first_tile), key(second_tile));
+        let keys_mean = match self.second_key {
+            SecondKey::Corner => "opposite corners of the region",
+            SecondKey::Extent => "the region's top-left tile, then its size",
+        };
+        let (title, hint) = match challenge.lesson {
+            Lesson::Tile if first == second => (
+                "single tiles",
+                format!("Press {}, then {} twice.", hotkey, first),
+            ),
+            Lesson::Tile => (
+                "single tiles",
+                format!("Press {}, then {} and {}.", hotkey, first, second),
+            ),
+            Lesson::Combo => (
+                "two-key combos",
+                format!(
+                    "Press {}, then {} and {}: {}.",
+                    hotkey, first, second, keys_mean
+                ),
+            ),
+            Lesson::Monitor => (
+                "other monitors",
+                format!(
+                    "Press {}, then Tab to move the grid to the next monitor, \
+                     then {} and {}.",
+                    hotkey, first, second
+                ),
+            ),
+        };
+        let (cols, rows) = (self.grid.cols, self.grid.rows);
+        let region = describe_span(cols, rows, challenge.first, challenge.last);
+        let place = if challenge.lesson == Lesson::Monitor {
+            " on the next monitor"
+        } else {
+            ""
+        };
+        format!(
+            "{}\n\nRound {} of {}: {}\nMove this window to the {}{}.\n{}\n\n\
+             Score: {} of {} correct, {:.1} s on average",
+            self.feedback,
+            self.round + 1,
+            ROUNDS,
+            title,
+            region,
+            place,
+            hint,
+            self.correct,
+            self.round,
+            self.average_secs()
+        )
+    }
+}
+
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Open the practice window, or start over in the one already open.
+/// Challenges are set on `grid` and name keys as `second_key` reads them;
+/// Tab rounds are added when `monitors` > 1.
+pub fn start_practice(grid: Grid, second_key: SecondKey, monitors: usize) {
+    unsafe {
+        let mut hwnd = HWND(PRACTICE_HWND.load(Ordering::SeqCst) as *mut _);
+        if hwnd.is_invalid() {
+            let hinstance = GetModuleHandleW(None).unwrap();
+            let wc = WNDCLASSW {
+                style: CS_HREDRAW | CS_VREDRAW,
+                lpfnWndProc: Some(practice_window_proc),
+                hInstance: hinstance.into(),
+                lpszClassName: PRACTICE_CLASS_NAME,
+                hbrBackground: HBRUSH(GetStockObject(WHITE_BRUSH).0),
+                hIcon: window_icon(),
+                ..Default::default()
+            };
+            let _ = RegisterClassW(&wc);
+
+            let Ok(created) = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                PRACTICE_CLASS_NAME,
+                w!("Tactile-Win Practice"),
+                WS_OVERLAPPEDWINDOW,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                560,
+                340,
+                None,
+                None,
+                Some(hinstance.into()),
+                Some(ptr::null()),
+            ) else {
+                return;
+            };
+            hwnd = created;
+            PRACTICE_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
+        }
+
+        let monitor = get_window_monitor(hwnd).0 as isize;
+        let seed = random_seed();
+        let session = Session::new(grid, second_key, monitors, seed, monitor, Instant::now());
+        SESSION.with(|cell| *cell.borrow_mut() = Some(session));
+
+        let _ = InvalidateRect(Some(hwnd), None, true);
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        let _ = SetForegroundWindow(hwnd);
+    }
+}
+
+/// The practice window, which the overlay may tile despite being ours
+pub fn is_practice_window(hwnd: HWND) -> bool {
+    let practice = PRACTICE_HWND.load(Ordering::SeqCst);
+    practice != 0 && hwnd.0 as isize == practice
+}
+
+/// Score the practice window being moved onto `span` of `monitor`
+pub fn record_practice_move(span: (TilePos, TilePos), monitor: HMONITOR) {
+    SESSION.with(|cell| {
+        if let Some(ref mut session) = *cell.borrow_mut() {
+            session.record(span, monitor.0 as isize, Instant::now());
+        }
+    });
+    let hwnd = HWND(PRACTICE_HWND.load(Ordering::SeqCst) as *mut _);
+    unsafe {
+        let _ = InvalidateRect(Some(hwnd), None, true);
+    }
+}
+
+fn paint_practice(hwnd: HWND) {
+    unsafe {
+        let mut ps = PAINTSTRUCT::default();
+        let hdc = BeginPaint(hwnd, &mut ps);
+        let text = SESSION.with(|cell| {
+            cell.borrow()
+                .as_ref()
+                .map(|s| s.text(grid_hotkey(), key_label))
+        });
+        if let Some(text) = text {
+            let font = CreateFontW(
+                20,
+                0,
+                0,
+                0,
+                400,
+                0,
+                0,
+                0,
+                DEFAULT_CHARSET,
+                OUT_DEFAULT_PRECIS,
+                CLIP_DEFAULT_PRECIS,
+                DEFAULT_QUALITY,
+                0,
+                w!("Segoe UI"),
+            );
+            let old_font = SelectObject(hdc, font.into());
+            SetBkMode(hdc, TRANSPARENT);
+
+            let mut rect = RECT::default();
+            let _ = GetClientRect(hwnd, &mut rect);
+            rect.left += 16;
+            rect.top += 16;
+            rect.right -= 16;
+            rect.bottom -= 16;
+            let mut text: Vec<u16> = text.encode_utf16().collect();
+            let _ = DrawTextW(hdc, &mut text, &mut rect, DT_LEFT | DT_WORDBREAK);
+
+            SelectObject(hdc, old_font);
+            let _ = DeleteObject(font.into());
+        }
+        let _ = EndPaint(hwnd, &ps);
+    }
+}
+
+unsafe extern "system" fn practice_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_PAINT => {
+                paint_practice(hwnd);
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                PRACTICE_HWND.store(0, Ordering::SeqCst);
+                SESSION.with(|cell| *cell.borrow_mut() = None);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(monitors: usize) -> Session {
+        let grid = Grid::new(4, 2, 0, RECT::default());
+        Session::new(grid, SecondKey::Corner, monitors, 42, 1, Instant::now())
+    }
+
+    /// Answer the current challenge, on monitor 2 so Tab rounds count
+    fn answer(session: &mut Session, correct: bool) -> bool {
+        let challenge = session.challenge;
+        let span = if correct {
+            (challenge.last, challenge.first)
+        } else {
+            let other = TilePos {
+                col: (challenge.first.col + 1) % 4,
+                row: challenge.first.row,
+            };
+            (other, other)
+        };
+        session.record(span, 2, session.shown_at + Duration::from_secs(2))
+    }
+
+    #[test]
+    fn test_lessons_progress() {
+        let mut single = session(1);
+        let mut dual = session(2);
+        let mut lessons = (Vec::new(), Vec::new());
+        while !single.is_finished() {
+            lessons.0.push(single.challenge.lesson);
+            lessons.1.push(dual.challenge.lesson);
+            answer(&mut single, true);
+            answer(&mut dual, true);
+        }
+        assert_eq!(lessons.0.len(), ROUNDS as usize);
+        assert_eq!(lessons.0[..3], [Lesson::Tile; 3]);
+        assert!(!lessons.0.contains(&Lesson::Monitor));
+        assert_eq!(lessons.1[8..], [Lesson::Monitor; 2]);
+    }
+
+    #[test]
+    fn test_scoring() {
+        let mut session = session(1);
+        assert_eq!(session.challenge.first, session.challenge.last);
+        assert!(answer(&mut session, true));
+        assert!(!answer(&mut session, false));
+        assert!(session.feedback.starts_with("Not quite"));
+        assert_eq!((session.round, session.correct), (2, 1));
+        assert_eq!(session.average_secs(), 2.0);
+        assert!(session
+            .text(Hotkey::DEFAULT, |c| c)
+            .contains("Round 3 of 10"));
+
+        while !session.is_finished() {
+            answer(&mut session, true);
+        }
+        assert!(!answer(&mut session, true));
+        assert!(session
+            .text(Hotkey::DEFAULT, |c| c)
+            .contains("9 of 10 correct"));
+    }
+
+    #[test]
+    fn test_extent_keys() {
+        let grid = Grid::new(4, 2, 0, RECT::default());
+        let mut session = Session::new(grid, SecondKey::Extent, 1, 42, 1, Instant::now());
+        session.challenge.first = TilePos { col: 2, row: 1 };
+        session.challenge.last = TilePos { col: 1, row: 0 };
+        session.challenge.lesson = Lesson::Combo;
+        // Top-left W, then S for two columns and two rows
+        assert!(session
+            .text(Hotkey::DEFAULT, |c| c)
+            .contains("then W and S: the region's top-left"));
+
+        session.challenge.last = session.challenge.first;
+        session.challenge.lesson = Lesson::Tile;
+        assert!(session
+            .text(Hotkey::DEFAULT, |c| c)
+            .contains("then D and Q."));
+    }
+
+    #[test]
+    fn test_monitor_rounds_need_another_monitor() {
+        let mut session = session(2);
+        while session.lesson() != Lesson::Monitor {
+            answer(&mut session, true);
+        }
+        let challenge = session.challenge;
+        let span = (challenge.first, challenge.last);
+        assert!(!session.record(span, challenge.monitor, Instant::now()));
+        assert!(session.feedback.contains("Press Tab"));
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use windows::Win32::Foundation::RECT;
+
+use crate::grid::{Grid, TilePos};
+
+mod parser;
+
+/// A placement within a work area, written as a region expression such as
+/// "left-half", "left 33%", "colspan(0..2, row 1)" or "rect(10,10,50%,50%)".
+/// See the parser module for the full syntax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Region {
+    /// The expression as written, kept for saving and announcements
+    source: String,
+    shape: Shape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    /// Edges (left, top, right, bottom) as fractions of the work area.
+    /// These line up with grid tile edges at the same fractions.
+    Fraction([f64; 4]),
+    /// Grid tiles; `None` covers every column or row
+    Tiles {
+        cols: Option<TileRange>,
+        rows: Option<TileRange>,
+    },
+    /// Offset and size within the work area, ignoring the gap
+    Rect {
+        x: Length,
+        y: Length,
+        width: Length,
+        height: Length,
+    },
+}
+
+/// Inclusive range of grid columns or rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TileRange {
+    start: u32,
+    end: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Length {
+    Px(i32),
+    Percent(f64),
+}
+
+impl Length {
+    fn is_zero(self) -> bool {
+        match self {
+            Length::Px(px) => px == 0,
+            Length::Percent(percent) => percent == 0.0,
+        }
+    }
+
+    /// Pixels along an axis `extent` pixels long
+    fn to_px(self, extent: i32) -> i32 {
+        match self {
+            Length::Px(px) => px,
+            Length::Percent(percent) => (percent / 100.0 * extent as f64).round() as i32,
+        }
+    }
+}
+
+/// Pixel position of a fractional edge along one axis. Matches the grid's
+/// tile layout: with `n` tiles, tile `i` starts at fraction `i / n`.
+fn edge(start: i32, end: i32, (gap, margin): (i32, i32), fraction: f64, is_start: bool) -> i32 {
+    // Tolerance keeps exact thirds etc. from flooring a pixel short
+    let offset = (fraction * (end - start - margin * 2 + gap) as f64 + 1e-6).floor() as i32;
+    if is_start {
+        start + margin + offset
+    } else {
+        start + margin + offset - gap
+    }
+}
+
+/// First and last tile of `range` on a grid axis with `count` tiles,
+/// clamped to the grid
+fn tile_bounds(range: Option<TileRange>, count: u32) -> (u32, u32) {
+    let last = count - 1;
+    match range {
+        Some(range) => (range.start.min(last), range.end.min(last)),
+        None => (0, last),
+    }
+}
+
+impl Region {
+    /// The region's rect within the grid's work area
+    pub fn resolve(&self, grid: &Grid) -> RECT {
+        let work = grid.work_area;
+        let (col_spacing, row_spacing) = (grid.col_spacing(), grid.row_spacing());
+        match self.shape {
+            Shape::Fraction([left, top, right, bottom]) => RECT {
+                left: edge(work.left, work.right, col_spacing, left, true),
+                top: edge(work.top, work.bottom, row_spacing, top, true),
+                right: edge(work.left, work.right, col_spacing, right, false),
+                bottom: edge(work.top, work.bottom, row_spacing, bottom, false),
+            },
+            Shape::Tiles { cols, rows } => {
+                let (first_col, last_col) = tile_bounds(cols, grid.cols);
+                let (first_row, last_row) = tile_bounds(rows, grid.rows);
+                grid.combine_tiles(
+                    TilePos {
+                        col: first_col,
+                        row: first_row,
+                    },
+                    TilePos {
+                        col: last_col,
+                        row: last_row,
+                    },
+                )
+            }
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (work_width, work_height) = (work.right - work.left, work.bottom - work.top);
+                let left = work.left + x.to_px(work_width);
+                let top = work.top + y.to_px(work_height);
+                RECT {
+                    left,
+                    top,
+                    right: left + width.to_px(work_width),
+                    bottom: top + height.to_px(work_height),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            source: s.trim().to_string(),
+            shape: parser::parse(s)?,
+        })
+    }
+}
+
+impl TryFrom<String> for Region {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Region> for String {
+    fn from(region: Region) -> Self {
+        region.source
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(cols: u32, rows: u32) -> Grid {
+        Grid::new(
+            cols,
+            rows,
+            10,
+            RECT {
+                left: 0,
+                top: 0,
+                right: 1920,
+                bottom: 1080,
+            },
+        )
+    }
+
+    fn span(grid: &Grid, c1: u32, r1: u32, c2: u32, r2: u32) -> RECT {
+        grid.combine_tiles(TilePos { col: c1, row: r1 }, TilePos { col: c2, row: r2 })
+    }
+
+    fn resolve(region: &str, grid: &Grid) -> RECT {
+        region.parse::<Region>().unwrap().resolve(grid)
+    }
+
+    #[test]
+    fn test_named_regions_match_grid_spans() {
+        let g = grid(4, 2);
+        assert_eq!(resolve("full", &g), span(&g, 0, 0, 3, 1));
+        assert_eq!(resolve("left-half", &g), span(&g, 0, 0, 1, 1));
+        assert_eq!(resolve("Right-Quarter", &g), span(&g, 3, 0, 3, 1));
+        assert_eq!(resolve("bottom-half", &g), span(&g, 0, 1, 3, 1));
+        assert_eq!(resolve("top-right", &g), span(&g, 2, 0, 3, 0));
+
+        let g = grid(3, 3);
+        assert_eq!(resolve("right-two-thirds", &g), span(&g, 1, 0, 2, 2));
+        assert_eq!(resolve("top-third", &g), span(&g, 0, 0, 2, 0));
+        assert_eq!(resolve("left-third", &g), span(&g, 0, 0, 0, 2));
+
+        let g = grid(4, 2).with_margin(0);
+        assert_eq!(resolve("full", &g), span(&g, 0, 0, 3, 1));
+        assert_eq!(resolve("right-half", &g), span(&g, 2, 0, 3, 1));
+    }
+
+    #[test]
+    fn test_percentages_and_spans() {
+        let g = grid(4, 2);
+        assert_eq!(resolve("left 50%", &g), span(&g, 0, 0, 1, 1));
+        assert_eq!(resolve("bottom 50%", &g), span(&g, 0, 1, 3, 1));
+        assert_eq!(resolve("colspan(0..2, row 1)", &g), span(&g, 0, 1, 1, 1));
+        assert_eq!(resolve("colspan(3)", &g), span(&g, 3, 0, 3, 1));
+        assert_eq!(resolve("rowspan(0, cols 1..=2)", &g), span(&g, 1, 0, 2, 0));
+        // Spans past the grid's edge are clamped to it
+        assert_eq!(resolve("colspan(2..9, row 5)", &g), span(&g, 2, 1, 3, 1));
+    }
+
+    #[test]
+    fn test_rects() {
+        let g = Grid::new(
+            4,
+            2,
+            10,
+            RECT {
+                left: -1920,
+                top: 40,
+                right: 0,
+                bottom: 1080,
+            },
+        );
+        assert_eq!(
+            resolve("rect(10,10,50%,50%)", &g),
+            RECT {
+                left: -1910,
+                top: 50,
+                right: -950,
+                bottom: 570,
+            }
+        );
+        assert_eq!(
+            resolve("rect(25%, 0, 800px, 100%)", &g),
+            RECT {
+                left: -1440,
+                top: 40,
+                right: -640,
+                bottom: 1080,
+            }
+        );
+    }
+
+    #[test]
+    fn test_display_and_serde() {
+        let region: Region = " left-Half ".parse().unwrap();
+        assert_eq!(region.to_string(), "left-Half");
+        assert!("middle".parse::<Region>().is_err());
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            region: Region,
+        }
+        let parsed: Wrapper = toml::from_str(r#"region = "colspan(0..2)""#).unwrap();
+        assert_eq!(parsed.region.to_string(), "colspan(0..2)");
+        assert!(toml::from_str::<Wrapper>(r#"region = "rect(1,2)""#).is_err());
+    }
+}
@@ -0,0 +1,398 @@
+//! Parser for region expressions:
+//!
+//! ```text
+//! region   = name | side percent | span | rect
+//! name     = "full" | "top-left" | ... | side "-" size    e.g. "left-half"
+//! side     = "left" | "right" | "top" | "bottom"
+//! span     = ("colspan" | "rowspan") "(" range [ "," axis range ] ")"
+//! axis     = "row" | "rows" | "col" | "cols"
+//! range    = int [ ".." int | "..=" int ]                 half-open unless "..="
+//! rect     = "rect(" length "," length "," length "," length ")"   x, y, w, h
+//! length   = number [ "%" | "px" ]
+//! percent  = number "%"
+//! ```
+//!
+//! Matching is case-insensitive and ignores surrounding whitespace.
+
+use super::{Length, Shape, TileRange};
+
+fn size_fraction(size: &str) -> Option<f64> {
+    match size {
+        "half" => Some(1.0 / 2.0),
+        "third" => Some(1.0 / 3.0),
+        "two-thirds" => Some(2.0 / 3.0),
+        "quarter" => Some(1.0 / 4.0),
+        "three-quarters" => Some(3.0 / 4.0),
+        _ => None,
+    }
+}
+
+/// Fractional edges (left, top, right, bottom) for `side` covering `size`
+fn side_edges(side: &str, size: f64) -> Option<[f64; 4]> {
+    match side {
+        "left" => Some([0.0, 0.0, size, 1.0]),
+        "right" => Some([1.0 - size, 0.0, 1.0, 1.0]),
+        "top" => Some([0.0, 0.0, 1.0, size]),
+        "bottom" => Some([0.0, 1.0 - size, 1.0, 1.0]),
+        _ => None,
+    }
+}
+
+fn named_edges(name: &str) -> Option<[f64; 4]> {
+    match name {
+        "full" => Some([0.0, 0.0, 1.0, 1.0]),
+        "top-left" => Some([0.0, 0.0, 0.5, 0.5]),
+        "top-right" => Some([0.5, 0.0, 1.0, 0.5]),
+        "bottom-left" => Some([0.0, 0.5, 0.5, 1.0]),
+        "bottom-right" => Some([0.5, 0.5, 1.0, 1.0]),
+        _ => {
+            let (side, size) = name.split_once('-')?;
+            side_edges(side, size_fraction(size)?)
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Description of the upcoming input for error messages
+    fn found(&self) -> String {
+        match self.rest().chars().next() {
+            Some(c) => format!("\"{}\"", c),
+            None => "end of input".to_string(),
+        }
+    }
+
+    /// Consume `token` (after whitespace) if it comes next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(format!("expected \"{}\", found {}", token, self.found()))
+        }
+    }
+
+    fn word(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let mut len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        // A fraction part, but not the start of a ".." range
+        if rest[len..].starts_with('.') && !rest[len..].starts_with("..") {
+            len += 1;
+            len += rest[len..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - len);
+        }
+        let value = rest[..len]
+            .parse()
+            .map_err(|_| format!("expected a number, found {}", self.found()))?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn integer(&mut self) -> Result<u32, String> {
+        let value = self.number()?;
+        if value.fract() != 0.0 || value > u32::MAX as f64 {
+            return Err(format!("expected a whole number, found {}", value));
+        }
+        Ok(value as u32)
+    }
+
+    fn percent(&mut self) -> Result<f64, String> {
+        let value = self.number()?;
+        self.expect("%")?;
+        if value <= 0.0 || value > 100.0 {
+            return Err(format!("percentage {}% is outside 0-100%", value));
+        }
+        Ok(value)
+    }
+
+    fn length(&mut self) -> Result<Length, String> {
+        let value = self.number()?;
+        if self.eat("%") {
+            if value > 100.0 {
+                return Err(format!("percentage {}% is over 100%", value));
+            }
+            Ok(Length::Percent(value))
+        } else {
+            self.eat("px");
+            if value.fract() != 0.0 {
+                return Err(format!("pixel length {} isn't a whole number", value));
+            }
+            Ok(Length::Px(value as i32))
+        }
+    }
+
+    fn range(&mut self) -> Result<TileRange, String> {
+        let start = self.integer()?;
+        let end = if self.eat("..=") {
+            self.integer()?
+        } else if self.eat("..") {
+            let end = self.integer()?;
+            if end <= start {
+                return Err(format!("range {}..{} is empty", start, end));
+            }
+            end - 1
+        } else {
+            start
+        };
+        if end < start {
+            return Err(format!("range {}..={} is empty", start, end));
+        }
+        Ok(TileRange { start, end })
+    }
+
+    /// `colspan(...)` or `rowspan(...)`, after the function name
+    fn span(&mut self, is_colspan: bool) -> Result<Shape, String> {
+        self.expect("(")?;
+        let primary = self.range()?;
+        let mut secondary = None;
+        if self.eat(",") {
+            let axis = self.word();
+            let expected = if is_colspan { "row" } else { "col" };
+            if axis.trim_end_matches('s') != expected {
+                return Err(format!("expected \"{}\", found \"{}\"", expected, axis));
+            }
+            secondary = Some(self.range()?);
+        }
+        self.expect(")")?;
+        Ok(if is_colspan {
+            Shape::Tiles {
+                cols: Some(primary),
+                rows: secondary,
+            }
+        } else {
+            Shape::Tiles {
+                cols: secondary,
+                rows: Some(primary),
+            }
+        })
+    }
+
+    /// `rect(x, y, w, h)`, after the function name
+    fn rect(&mut self) -> Result<Shape, String> {
+        self.expect("(")?;
+        let x = self.length()?;
+        self.expect(",")?;
+        let y = self.length()?;
+        self.expect(",")?;
+        let width = self.length()?;
+        self.expect(",")?;
+        let height = self.length()?;
+        self.expect(")")?;
+        if width.is_zero() || height.is_zero() {
+            return Err("rect width and height must be more than zero".to_string());
+        }
+        Ok(Shape::Rect {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Parse a region expression into its shape
+pub(super) fn parse(input: &str) -> Result<Shape, String> {
+    let text = input.trim().to_ascii_lowercase();
+    if text.is_empty() {
+        return Err("empty region".to_string());
+    }
+    if let Some(edges) = named_edges(&text) {
+        return Ok(Shape::Fraction(edges));
+    }
+
+    let mut parser = Parser {
+        text: &text,
+        pos: 0,
+    };
+    let shape = match parser.word() {
+        "colspan" => parser.span(true)?,
+        "rowspan" => parser.span(false)?,
+        "rect" => parser.rect()?,
+        side @ ("left" | "right" | "top" | "bottom") => {
+            if parser.rest().starts_with('-') {
+                return Err(format!("unknown region \"{}\"", text));
+            }
+            let size = parser.percent()? / 100.0;
+            Shape::Fraction(side_edges(side, size).expect("known side"))
+        }
+        "" => return Err(format!("unexpected {}", parser.found())),
+        word => return Err(format!("unknown region \"{}\"", word)),
+    };
+
+    parser.skip_whitespace();
+    if !parser.rest().is_empty() {
+        return Err(format!("unexpected \"{}\" at end", parser.rest()));
+    }
+    Ok(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiles(cols: Option<(u32, u32)>, rows: Option<(u32, u32)>) -> Shape {
+        let range = |(start, end)| TileRange { start, end };
+        Shape::Tiles {
+            cols: cols.map(range),
+            rows: rows.map(range),
+        }
+    }
+
+    fn err(input: &str) -> String {
+        parse(input).unwrap_err()
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(parse("full"), Ok(Shape::Fraction([0.0, 0.0, 1.0, 1.0])));
+        assert_eq!(
+            parse("  Bottom-Right "),
+            Ok(Shape::Fraction([0.5, 0.5, 1.0, 1.0]))
+        );
+        assert_eq!(
+            parse("right-quarter"),
+            Ok(Shape::Fraction([0.75, 0.0, 1.0, 1.0]))
+        );
+        assert_eq!(
+            parse("top-three-quarters"),
+            Ok(Shape::Fraction([0.0, 0.0, 1.0, 0.75]))
+        );
+        for side in ["left", "right", "top", "bottom"] {
+            for size in ["half", "third", "two-thirds", "quarter", "three-quarters"] {
+                assert!(parse(&format!("{}-{}", side, size)).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_side_percentages() {
+        assert_eq!(
+            parse("left 33%"),
+            Ok(Shape::Fraction([0.0, 0.0, 0.33, 1.0]))
+        );
+        assert_eq!(
+            parse("BOTTOM 12.5 %"),
+            Ok(Shape::Fraction([0.0, 0.875, 1.0, 1.0]))
+        );
+        assert_eq!(
+            parse("right 100%"),
+            Ok(Shape::Fraction([0.0, 0.0, 1.0, 1.0]))
+        );
+        assert_eq!(err("left 0%"), "percentage 0% is outside 0-100%");
+        assert_eq!(err("top 150%"), "percentage 150% is outside 0-100%");
+        assert_eq!(err("left 33"), "expected \"%\", found end of input");
+        assert_eq!(err("left"), "expected a number, found end of input");
+    }
+
+    #[test]
+    fn test_spans() {
+        assert_eq!(
+            parse("colspan(0..2, row 1)"),
+            Ok(tiles(Some((0, 1)), Some((1, 1))))
+        );
+        assert_eq!(parse("colspan(3)"), Ok(tiles(Some((3, 3)), None)));
+        assert_eq!(
+            parse("ColSpan( 1..=2 , rows 0..2 )"),
+            Ok(tiles(Some((1, 2)), Some((0, 1))))
+        );
+        assert_eq!(parse("rowspan(1)"), Ok(tiles(None, Some((1, 1)))));
+        assert_eq!(
+            parse("rowspan(0..=1,col 2)"),
+            Ok(tiles(Some((2, 2)), Some((0, 1))))
+        );
+    }
+
+    #[test]
+    fn test_span_errors() {
+        assert_eq!(err("colspan(2..2)"), "range 2..2 is empty");
+        assert_eq!(err("colspan(3..=1)"), "range 3..=1 is empty");
+        assert_eq!(err("colspan(1.5)"), "expected a whole number, found 1.5");
+        assert_eq!(
+            err("colspan(0..2, col 1)"),
+            "expected \"row\", found \"col\""
+        );
+        assert_eq!(err("rowspan(0, row 1)"), "expected \"col\", found \"row\"");
+        assert_eq!(err("colspan(0..2"), "expected \")\", found end of input");
+        assert_eq!(err("colspan 0..2"), "expected \"(\", found \"0\"");
+        assert_eq!(err("colspan()"), "expected a number, found \")\"");
+    }
+
+    #[test]
+    fn test_rects() {
+        assert_eq!(
+            parse("rect(10,10,50%,50%)"),
+            Ok(Shape::Rect {
+                x: Length::Px(10),
+                y: Length::Px(10),
+                width: Length::Percent(50.0),
+                height: Length::Percent(50.0),
+            })
+        );
+        assert_eq!(
+            parse("RECT( 25%, 0px, 800px, 100% )"),
+            Ok(Shape::Rect {
+                x: Length::Percent(25.0),
+                y: Length::Px(0),
+                width: Length::Px(800),
+                height: Length::Percent(100.0),
+            })
+        );
+        assert_eq!(
+            err("rect(0,0,0,50%)"),
+            "rect width and height must be more than zero"
+        );
+        assert_eq!(err("rect(0,0,120%,50%)"), "percentage 120% is over 100%");
+        assert_eq!(
+            err("rect(0,0,10.5,5)"),
+            "pixel length 10.5 isn't a whole number"
+        );
+        assert_eq!(err("rect(0,0,50%)"), "expected \",\", found \")\"");
+    }
+
+    #[test]
+    fn test_general_errors() {
+        assert_eq!(err(""), "empty region");
+        assert_eq!(err("   "), "empty region");
+        assert_eq!(err("middle"), "unknown region \"middle\"");
+        assert_eq!(err("left-fifth"), "unknown region \"left-fifth\"");
+        assert_eq!(err("(1,2)"), "unexpected \"(\"");
+        assert_eq!(err("colspan(1) extra"), "unexpected \"extra\" at end");
+        assert_eq!(err("left 50% right"), "unexpected \"right\" at end");
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::RulesConfig;
+use crate::region::Region;
+
+/// Which windows the hotkey is allowed to tile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RulesMode {
+    /// Tile everything except windows matching a `block` rule
+    #[default]
+    Blocklist,
+    /// Tile only windows matching an `allow` rule
+    Allowlist,
+}
+
+/// Matches windows by process, class and title. Every field that is set
+/// must match; process and class compare case-insensitively, title matches
+/// a case-insensitive substring.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowRule {
+    /// Executable file name, e.g. "notepad.exe"
+    pub process: Option<String>,
+    pub class: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Grid size and spacing for windows matching `rule`, in place of the
+/// grid's. Sizes are in the same units as `[grid] gap`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlacementRule {
+    #[serde(flatten)]
+    pub rule: WindowRule,
+    /// Columns, ahead of any desktop's or profile's
+    pub cols: Option<u32>,
+    pub rows: Option<u32>,
+    /// Space between tiles
+    pub gap: Option<i32>,
+    /// Space between the outer tiles and the work area's edges; defaults
+    /// to the gap
+    pub margin: Option<i32>,
+}
+
+/// One placement in a batch: the topmost window `window` matches goes to
+/// `region` on its monitor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlacementStep {
+    #[serde(flatten)]
+    pub window: WindowRule,
+    pub region: Region,
+}
+
+impl PlacementStep {
+    /// Parse `<window>=<region>`, where the window is an executable name or
+    /// comma-separated `process:`, `class:` and `title:` matches, e.g.
+    /// `notepad.exe=left-half` or `class:CabinetWClass=right 40%`
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let (matcher, region) = arg
+            .split_once('=')
+            .ok_or_else(|| format!("expected <window>=<region>: {}", arg))?;
+        let mut window = WindowRule::default();
+        for part in matcher.split(',').map(str::trim) {
+            let (field, value) = match part.split_once(':') {
+                Some((field, value)) => (field.trim(), value.trim()),
+                None => ("process", part),
+            };
+            let value = Some(value.to_string());
+            match field {
+                "process" => window.process = value,
+                "class" => window.class = value,
+                "title" => window.title = value,
+                _ => return Err(format!("unknown window field \"{}\"", field)),
+            }
+        }
+        let region = region.trim().parse()?;
+        Ok(Self { window, region })
+    }
+}
+
+/// Identifying details of a window, for matching against rules
+#[derive(Debug, Clone, Default)]
+pub struct WindowInfo {
+    pub process: String,
+    pub class: String,
+    pub title: String,
+}
+
+impl WindowRule {
+    /// A rule for every window of the same program as `info`: matched by
+    /// process, or by class when the process couldn't be read
+    pub fn for_window(info: &WindowInfo) -> Self {
+        if info.process.is_empty() {
+            Self {
+                class: Some(info.class.clone()),
+                ..Self::default()
+            }
+        } else {
+            Self {
+                process: Some(info.process.clone()),
+                ..Self::default()
+            }
+        }
+    }
+
+    /// Short description of what the rule matches, e.g. "notepad.exe"
+    pub fn describe(&self) -> String {
+        [&self.process, &self.class, &self.title]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        // An empty rule would match everything; treat it as a typo instead
+        if self.process.is_none() && self.class.is_none() && self.title.is_none() {
+            return false;
+        }
+        let process_ok = self
+            .process
+            .as_ref()
+            .is_none_or(|p| p.eq_ignore_ascii_case(&info.process));
+        let class_ok = self
+            .class
+            .as_ref()
+            .is_none_or(|c| c.eq_ignore_ascii_case(&info.class));
+        let title_ok = self
+            .title
+            .as_ref()
+            .is_none_or(|t| info.title.to_lowercase().contains(&t.to_lowercase()));
+        process_ok && class_ok && title_ok
+    }
+}
+
+fn any_match(rules: &[WindowRule], info: &WindowInfo) -> bool {
+    rules.iter().any(|rule| rule.matches(info))
+}
+
+/// Whether the hotkey may tile this window under the configured rules.
+/// A block rule wins in either mode, so "Never tile" works in allowlist
+/// mode too.
+pub fn is_allowed(rules: &RulesConfig, info: &WindowInfo) -> bool {
+    if any_match(&rules.block, info) {
+        return false;
+    }
+    match rules.mode {
+        RulesMode::Blocklist => true,
+        RulesMode::Allowlist => any_match(&rules.allow, info),
+    }
+}
+
+/// The first placement rule matching this window
+pub fn placement_rule<'a>(rules: &'a RulesConfig, info: &WindowInfo) -> Option<&'a PlacementRule> {
+    rules
+        .placement
+        .iter()
+        .find(|placement| placement.rule.matches(info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notepad() -> WindowInfo {
+        WindowInfo {
+            process: "Notepad.exe".to_string(),
+            class: "Notepad".to_string(),
+            title: "notes.txt - Notepad".to_string(),
+        }
+    }
+
+    fn rule(process: Option<&str>, class: Option<&str>, title: Option<&str>) -> WindowRule {
+        WindowRule {
+            process: process.map(String::from),
+            class: class.map(String::from),
+            title: title.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        assert!(rule(Some("notepad.exe"), None, None).matches(&notepad()));
+        assert!(rule(None, Some("NOTEPAD"), Some("NOTES")).matches(&notepad()));
+        assert!(!rule(Some("notepad.exe"), Some("Edit"), None).matches(&notepad()));
+        assert!(!rule(None, None, None).matches(&notepad()));
+    }
+
+    #[test]
+    fn test_rule_for_window() {
+        let picked = WindowRule::for_window(&notepad());
+        assert_eq!(picked, rule(Some("Notepad.exe"), None, None));
+        assert!(picked.matches(&notepad()));
+        assert_eq!(picked.describe(), "Notepad.exe");
+
+        let elevated = WindowInfo {
+            process: String::new(),
+            ..notepad()
+        };
+        let picked = WindowRule::for_window(&elevated);
+        assert_eq!(picked, rule(None, Some("Notepad"), None));
+        assert!(picked.matches(&elevated));
+    }
+
+    #[test]
+    fn test_blocklist_and_allowlist() {
+        let mut rules = RulesConfig::default();
+        assert!(is_allowed(&rules, &notepad()));
+
+        rules.block = vec![rule(Some("notepad.exe"), None, None)];
+        assert!(!is_allowed(&rules, &notepad()));
+
+        rules.mode = RulesMode::Allowlist;
+        rules.block.clear();
+        assert!(!is_allowed(&rules, &notepad()));
+
+        rules.allow = vec![rule(None, Some("Notepad"), None)];
+        assert!(is_allowed(&rules, &notepad()));
+    }
+
+    #[test]
+    fn test_block_wins_in_allowlist_mode() {
+        let rules = RulesConfig {
+            mode: RulesMode::Allowlist,
+            allow: vec![rule(Some("notepad.exe"), None, None)],
+            block: vec![rule(None, None, Some("notes.txt"))],
+            ..Default::default()
+        };
+        assert!(!is_allowed(&rules, &notepad()));
+
+        let mut other = notepad();
+        other.title = "todo.txt - Notepad".to_string();
+        assert!(is_allowed(&rules, &other));
+    }
+
+    #[test]
+    fn test_placement_rule() {
+        let placement = |process, gap| PlacementRule {
+            rule: rule(Some(process), None, None),
+            gap: Some(gap),
+            ..PlacementRule::default()
+        };
+        let mut rules = RulesConfig::default();
+        assert_eq!(placement_rule(&rules, &notepad()), None);
+
+        rules.placement = vec![
+            placement("code.exe", 4),
+            placement("notepad.exe", 0),
+            placement("NOTEPAD.EXE", 20),
+        ];
+        assert_eq!(placement_rule(&rules, &notepad()).unwrap().gap, Some(0));
+    }
+}
@@ -1,4 +1,7 @@
 use crate::grid::{Grid, TilePos};
+use crate::region::Region;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use windows::Win32::Foundation::RECT;
 
 #[derive(Debug, Clone, Copy)]
@@ -25,9 +28,68 @@ impl PartialEq for SelectionState {
 
 impl Eq for SelectionState {}
 
+/// Axis a selection is stretched along to fill the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Full height, keeping the column span
+    Vertical,
+    /// Full width, keeping the row span
+    Horizontal,
+}
+
+/// How the second key of a selection picks the region
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecondKey {
+    /// The region's opposite corner
+    #[default]
+    Corner,
+    /// The region's size, reaching right and down from the first tile: the
+    /// key of the tile in column c, row r spans c + 1 columns and r + 1
+    /// rows, so Q then R covers 4 columns on a QWERTY grid
+    Extent,
+}
+
+impl SecondKey {
+    /// First and last tiles of the region chosen by `first` then `second`
+    pub fn span(self, grid: &Grid, first: TilePos, second: TilePos) -> (TilePos, TilePos) {
+        match self {
+            SecondKey::Corner => (first, second),
+            SecondKey::Extent => (
+                first,
+                TilePos {
+                    col: (first.col + second.col).min(grid.cols - 1),
+                    row: (first.row + second.row).min(grid.rows - 1),
+                },
+            ),
+        }
+    }
+
+    /// Screen rect of the region chosen by `first` then `second`
+    pub fn rect(self, grid: &Grid, first: TilePos, second: TilePos) -> RECT {
+        let (first, last) = self.span(grid, first, second);
+        grid.combine_tiles(first, last)
+    }
+
+    /// The tile whose key, pressed after `first`, selects through `last`.
+    /// `first` must be the region's top-left tile.
+    pub fn second_tile(self, first: TilePos, last: TilePos) -> TilePos {
+        match self {
+            SecondKey::Corner => last,
+            SecondKey::Extent => TilePos {
+                col: last.col - first.col,
+                row: last.row - first.row,
+            },
+        }
+    }
+}
+
 pub struct TileSelector {
     state: SelectionState,
     grid: Grid,
+    /// One-press placements for keys the grid doesn't use
+    bindings: Vec<(char, Region)>,
+    second_key: SecondKey,
 }
 
 impl TileSelector {
@@ -35,10 +97,60 @@ impl TileSelector {
         Self {
             state: SelectionState::Idle,
             grid,
+            bindings: Vec::new(),
+            second_key: SecondKey::Corner,
         }
     }
 
+    pub fn with_second_key(mut self, second_key: SecondKey) -> Self {
+        self.second_key = second_key;
+        self
+    }
+
+    /// First and last tiles of the region `first` then `second` selects
+    pub fn span(&self, first: TilePos, second: TilePos) -> (TilePos, TilePos) {
+        self.second_key.span(&self.grid, first, second)
+    }
+
+    /// Add direct placement bindings, keyed by single characters. Keys
+    /// that aren't one character are ignored; grid keys take precedence.
+    pub fn with_bindings(mut self, bindings: &BTreeMap<String, Region>) -> Self {
+        self.bindings = bindings
+            .iter()
+            .filter_map(|(key, region)| {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some((c.to_ascii_uppercase(), region.clone())),
+                    _ => None,
+                }
+            })
+            .collect();
+        self
+    }
+
+    /// The region bound to `key`, unless the grid uses that key
+    pub fn binding(&self, key: char) -> Option<&Region> {
+        if self.grid.key_to_tile(key).is_some() {
+            return None;
+        }
+        let key = key.to_ascii_uppercase();
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, region)| region)
+    }
+
     pub fn handle_key(&mut self, key: char) -> SelectionState {
+        if matches!(
+            self.state,
+            SelectionState::Idle | SelectionState::FirstKeyPressed(_)
+        ) && let Some(region) = self.binding(key)
+        {
+            // Bound keys place the window in one press, even mid-selection
+            self.state = SelectionState::Complete(region.resolve(&self.grid));
+            return self.state;
+        }
+
         match self.state {
             SelectionState::Idle => {
                 if let Some(pos) = self.grid.key_to_tile(key) {
@@ -48,13 +160,7 @@ impl TileSelector {
             }
             SelectionState::FirstKeyPressed(first_pos) => {
                 if let Some(second_pos) = self.grid.key_to_tile(key) {
-                    let rect = if first_pos == second_pos {
-                        // Same key twice = single tile
-                        self.grid.tile_rect(first_pos)
-                    } else {
-                        // Different keys = combined rectangle
-                        self.grid.combine_tiles(first_pos, second_pos)
-                    };
+                    let rect = self.second_key.rect(&self.grid, first_pos, second_pos);
                     self.state = SelectionState::Complete(rect);
                 }
                 self.state
@@ -66,6 +172,51 @@ impl TileSelector {
         }
     }
 
+    /// Stretch a span to the grid's full height or width, like Win+Shift+Up
+    /// but grid-aligned. The span kept on the other axis is the first
+    /// tile's if one is chosen, otherwise the one `current` (the target
+    /// window's rect) is nearest.
+    pub fn maximize(&mut self, axis: Axis, current: RECT) -> SelectionState {
+        let (first, last) = match self.state {
+            SelectionState::Idle => self.grid.rect_to_span(current),
+            SelectionState::FirstKeyPressed(pos) => (pos, pos),
+            SelectionState::Complete(_) | SelectionState::Cancelled => return self.state,
+        };
+        let (first, last) = match axis {
+            Axis::Vertical => (
+                TilePos {
+                    col: first.col,
+                    row: 0,
+                },
+                TilePos {
+                    col: last.col,
+                    row: self.grid.rows - 1,
+                },
+            ),
+            Axis::Horizontal => (
+                TilePos {
+                    col: 0,
+                    row: first.row,
+                },
+                TilePos {
+                    col: self.grid.cols - 1,
+                    row: last.row,
+                },
+            ),
+        };
+        self.state = SelectionState::Complete(self.grid.combine_tiles(first, last));
+        self.state
+    }
+
+    /// Start from an already-chosen first tile, e.g. one kept from a
+    /// cancelled session. Ignored if the tile is outside this grid.
+    pub fn resume(&mut self, first: TilePos) -> SelectionState {
+        if first.col < self.grid.cols && first.row < self.grid.rows {
+            self.state = SelectionState::FirstKeyPressed(first);
+        }
+        self.state
+    }
+
     pub fn cancel(&mut self) {
         self.state = SelectionState::Cancelled;
     }
@@ -74,6 +225,16 @@ impl TileSelector {
         self.state = SelectionState::Idle;
     }
 
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Swap in a rebuilt grid with the same dimensions, keeping any
+    /// first-key selection
+    pub fn set_grid(&mut self, grid: Grid) {
+        self.grid = grid;
+    }
+
     pub fn state(&self) -> SelectionState {
         self.state
     }
@@ -129,6 +290,43 @@ mod tests {
         assert!(matches!(state, SelectionState::Complete(_)));
     }
 
+    #[test]
+    fn test_extent_second_key() {
+        let grid = test_grid();
+        let span = |c1, r1, c2, r2| {
+            grid.combine_tiles(TilePos { col: c1, row: r1 }, TilePos { col: c2, row: r2 })
+        };
+        let selector = || TileSelector::new(test_grid()).with_second_key(SecondKey::Extent);
+
+        // Q then R: 4 columns, 1 row
+        let mut extent = selector();
+        extent.handle_key('Q');
+        assert_eq!(
+            extent.handle_key('R'),
+            SelectionState::Complete(span(0, 0, 3, 0))
+        );
+
+        // S then W: 2 columns from the second column, clamped to the grid
+        let mut extent = selector();
+        extent.handle_key('S');
+        assert_eq!(
+            extent.handle_key('W'),
+            SelectionState::Complete(span(1, 1, 2, 1))
+        );
+        let mut extent = selector();
+        extent.handle_key('F');
+        assert_eq!(
+            extent.handle_key('F'),
+            SelectionState::Complete(span(3, 1, 3, 1))
+        );
+
+        let first = TilePos { col: 1, row: 0 };
+        let last = TilePos { col: 2, row: 1 };
+        let second = SecondKey::Extent.second_tile(first, last);
+        assert_eq!(SecondKey::Extent.span(&grid, first, second), (first, last));
+        assert_eq!(SecondKey::Corner.second_tile(first, last), last);
+    }
+
     #[test]
     fn test_cancel() {
         let mut selector = TileSelector::new(test_grid());
@@ -149,4 +347,82 @@ mod tests {
 
         assert_eq!(selector.state(), SelectionState::Idle);
     }
+
+    #[test]
+    fn test_resume() {
+        let mut selector = TileSelector::new(test_grid());
+
+        let first = TilePos { col: 1, row: 0 };
+        assert_eq!(
+            selector.resume(first),
+            SelectionState::FirstKeyPressed(first)
+        );
+        assert!(matches!(
+            selector.handle_key('F'),
+            SelectionState::Complete(_)
+        ));
+
+        let mut selector = TileSelector::new(test_grid());
+        assert_eq!(
+            selector.resume(TilePos { col: 9, row: 0 }),
+            SelectionState::Idle
+        );
+    }
+
+    #[test]
+    fn test_bindings() {
+        let bindings = BTreeMap::from([
+            ("g".to_string(), "full".parse().unwrap()),
+            ("Q".to_string(), "left-half".parse().unwrap()),
+            ("VB".to_string(), "right-half".parse().unwrap()),
+        ]);
+        let mut selector = TileSelector::new(test_grid()).with_bindings(&bindings);
+        let full =
+            test_grid().combine_tiles(TilePos { col: 0, row: 0 }, TilePos { col: 3, row: 1 });
+
+        // One press, from idle or after a first key
+        assert_eq!(selector.handle_key('G'), SelectionState::Complete(full));
+        let mut selector = TileSelector::new(test_grid()).with_bindings(&bindings);
+        selector.handle_key('W');
+        assert!(matches!(
+            selector.handle_key('G'),
+            SelectionState::Complete(_)
+        ));
+
+        // Grid keys win over bindings; multi-character keys are dropped
+        assert!(selector.binding('Q').is_none());
+        assert!(selector.binding('V').is_none());
+    }
+
+    #[test]
+    fn test_maximize() {
+        let grid = test_grid();
+        let span = |c1, r1, c2, r2| {
+            grid.combine_tiles(TilePos { col: c1, row: r1 }, TilePos { col: c2, row: r2 })
+        };
+
+        // Keeps the target's columns, stretched to full height
+        let mut selector = TileSelector::new(test_grid());
+        let current = span(1, 0, 2, 0);
+        assert_eq!(
+            selector.maximize(Axis::Vertical, current),
+            SelectionState::Complete(span(1, 0, 2, 1))
+        );
+
+        // A chosen first tile wins over the target's rect
+        let mut selector = TileSelector::new(test_grid());
+        selector.handle_key('D');
+        assert_eq!(
+            selector.maximize(Axis::Horizontal, current),
+            SelectionState::Complete(span(0, 1, 3, 1))
+        );
+
+        // Ignored once the selection is over
+        let mut selector = TileSelector::new(test_grid());
+        selector.cancel();
+        assert_eq!(
+            selector.maximize(Axis::Vertical, current),
+            SelectionState::Cancelled
+        );
+    }
 }
@@ -2,10 +2,10 @@ use std::ptr;
 use std::sync::atomic::{AtomicIsize, Ordering};
 use std::sync::Mutex;
 use windows::core::{w, PCWSTR};
-use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
 use windows::Win32::Graphics::Gdi::{
-    CreateFontW, GetStockObject, HBRUSH, DEFAULT_CHARSET, OUT_DEFAULT_PRECIS,
-    CLIP_DEFAULT_PRECIS, DEFAULT_QUALITY, WHITE_BRUSH,
+    CreateFontW, GetStockObject, MapWindowPoints, HBRUSH, DEFAULT_CHARSET, OUT_DEFAULT_PRECIS,
+    CLIP_DEFAULT_PRECIS, DEFAULT_QUALITY, HMONITOR, WHITE_BRUSH,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Controls::{
@@ -13,14 +13,26 @@ use windows::Win32::UI::Controls::{
     UDM_SETPOS, UDM_SETRANGE, UDS_ALIGNRIGHT, UDS_ARROWKEYS, UDS_AUTOBUDDY, UDS_SETBUDDYINT,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    CreateWindowExW, DefWindowProcW, DestroyWindow, EnumChildWindows, GetDlgItemInt,
-    GetSystemMetrics, GetWindowRect, RegisterClassW, SendMessageW, SetWindowPos, ShowWindow,
-    CS_HREDRAW, CS_VREDRAW, HWND_TOP, SM_CXSCREEN, SM_CYSCREEN, SWP_NOSIZE, SWP_NOZORDER,
-    SW_SHOW, WM_CLOSE, WM_COMMAND, WM_CREATE, WM_DESTROY, WM_SETFONT, WNDCLASSW, WS_CAPTION,
-    WS_CHILD, WS_EX_DLGMODALFRAME, WS_OVERLAPPED, WS_SYSMENU, WS_TABSTOP, WS_VISIBLE,
+    CreateWindowExW, DefWindowProcW, DestroyWindow, EnumChildWindows, FlashWindowEx, GetDlgItemInt,
+    GetParent, GetWindowRect, IsIconic, MessageBoxW, MoveWindow, RegisterClassW, SendMessageW,
+    SetDlgItemTextW, SetWindowPos, ShowWindow, CS_HREDRAW, CS_VREDRAW, FLASHWINFO, FLASHW_ALL,
+    HMENU, HWND_TOP, IDNO, MB_ICONWARNING, MB_YESNO, SWP_NOSIZE, SWP_NOZORDER, SW_RESTORE, SW_SHOW,
+    WM_CLOSE, WM_COMMAND, WM_CREATE, WM_DESTROY, WM_SETFONT, WNDCLASSW, WS_CAPTION, WS_CHILD,
+    WS_EX_DLGMODALFRAME, WS_OVERLAPPED, WS_SYSMENU, WS_TABSTOP, WS_VISIBLE,
 };
 
+use crate::accessibility::text_scale_percent;
 use crate::config::Config;
+use crate::grid::BASE_DPI;
+use crate::icon::window_icon;
+use crate::log::{error, info};
+use crate::picker::{create_picker, PICKER_SIZE};
+use crate::rules::{WindowInfo, WindowRule};
+use crate::tray::notify;
+use crate::window::{
+    bring_to_foreground, get_all_monitors, get_cursor_monitor, get_monitor_dpi,
+    get_monitor_work_area, get_window_monitor,
+};
 
 const SETTINGS_CLASS_NAME: PCWSTR = w!("TactileWinSettings");
 
@@ -28,6 +40,8 @@ const SETTINGS_CLASS_NAME: PCWSTR = w!("TactileWinSettings");
 const ID_COLS_EDIT: i32 = 101;
 const ID_ROWS_EDIT: i32 = 103;
 const ID_GAP_EDIT: i32 = 105;
+const ID_PICKER: i32 = 107;
+const ID_BLOCKED_LABEL: i32 = 108;
 const ID_SAVE_BTN: i32 = 110;
 const ID_CANCEL_BTN: i32 = 111;
 
@@ -36,7 +50,34 @@ static SETTINGS_HWND: AtomicIsize = AtomicIsize::new(0);
 static CURRENT_CONFIG: Mutex<Option<Config>> = Mutex::new(None);
 static ON_SAVE_CALLBACK: Mutex<Option<fn(Config)>> = Mutex::new(None);
 
+/// Why `config` would leave tiles too small on a connected monitor, if it
+/// would
+pub fn tile_size_warning(config: &Config) -> Option<String> {
+    get_all_monitors()
+        .into_iter()
+        .enumerate()
+        .find_map(|(index, monitor)| {
+            let work_area = get_monitor_work_area(monitor)?;
+            let dpi = if config.grid.dpi_scaling {
+                get_monitor_dpi(monitor)
+            } else {
+                BASE_DPI
+            };
+            let (width, height) = config.cramped_tiles(work_area, dpi)?;
+            Some(format!(
+                "Tiles on monitor {} would be as small as {}x{} px, under the {} px minimum; \
+                 the gap will shrink to make room",
+                index + 1,
+                width,
+                height,
+                config.grid.min_tile_size
+            ))
+        })
+}
+
 pub fn show_settings(config: Config, on_save: fn(Config)) {
+    let text_percent = text_scale_percent(config.accessibility.text_scale);
+
     // Store config and callback
     if let Ok(mut guard) = CURRENT_CONFIG.lock() {
         *guard = Some(config);
@@ -45,9 +86,11 @@ pub fn show_settings(config: Config, on_save: fn(Config)) {
         *guard = Some(on_save);
     }
 
-    // Check if already open
-    if SETTINGS_HWND.load(Ordering::SeqCst) != 0 {
-        return; // Already open
+    // Already open: show that one rather than a second
+    let open = HWND(SETTINGS_HWND.load(Ordering::SeqCst) as *mut _);
+    if !open.0.is_null() {
+        unsafe { raise_window(open) };
+        return;
     }
 
     unsafe {
@@ -66,6 +109,7 @@ pub fn show_settings(config: Config, on_save: fn(Config)) {
             hInstance: hinstance.into(),
             lpszClassName: SETTINGS_CLASS_NAME,
             hbrBackground: HBRUSH(GetStockObject(WHITE_BRUSH).0),
+            hIcon: window_icon(),
             ..Default::default()
         };
 
@@ -78,8 +122,8 @@ pub fn show_settings(config: Config, on_save: fn(Config)) {
             WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU,
             100,
             100,
-            320,
-            220,
+            320 * text_percent / 100,
+            255 * text_percent / 100,
             None,
             None,
             Some(hinstance.into()),
@@ -103,7 +147,7 @@ unsafe extern "system" fn settings_window_proc(
         match msg {
             WM_CREATE => {
                 create_controls(hwnd);
-                set_dialog_font(hwnd);
+                set_dialog_font(hwnd, dialog_text_percent());
                 center_window(hwnd);
                 LRESULT(0)
             }
@@ -111,8 +155,11 @@ unsafe extern "system" fn settings_window_proc(
                 let id = (wparam.0 & 0xFFFF) as i32;
                 match id {
                     ID_SAVE_BTN => {
-                        save_settings(hwnd);
-                        let _ = DestroyWindow(hwnd);
+                        // Stay open if the user backs out of a warning
+                        let saved = save_settings(hwnd);
+                        if saved {
+                            let _ = DestroyWindow(hwnd);
+                        }
                     }
                     ID_CANCEL_BTN => {
                         let _ = DestroyWindow(hwnd);
@@ -359,6 +406,37 @@ unsafe fn create_controls(hwnd: HWND) {
             }
         }
 
+        // Crosshair that blocks the window it's dropped on
+        let _ = CreateWindowExW(
+            Default::default(),
+            w!("STATIC"),
+            w!("Never tile:"),
+            label_style,
+            20,
+            117,
+            100,
+            20,
+            Some(hwnd),
+            None,
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+        create_picker(hwnd, 130, 110, ID_PICKER, on_window_picked);
+        let _ = CreateWindowExW(
+            Default::default(),
+            w!("STATIC"),
+            w!("Drag onto a window"),
+            label_style,
+            140 + PICKER_SIZE,
+            117,
+            130,
+            20,
+            Some(hwnd),
+            Some(HMENU(ID_BLOCKED_LABEL as isize as *mut _)),
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+
         // Save button
         let _ = CreateWindowExW(
             Default::default(),
@@ -366,7 +444,7 @@ unsafe fn create_controls(hwnd: HWND) {
             w!("Save"),
             button_style,
             60,
-            130,
+            165,
             80,
             30,
             Some(hwnd),
@@ -382,7 +460,7 @@ unsafe fn create_controls(hwnd: HWND) {
             w!("Cancel"),
             button_style,
             160,
-            130,
+            165,
             80,
             30,
             Some(hwnd),
@@ -393,23 +471,113 @@ unsafe fn create_controls(hwnd: HWND) {
     }
 }
 
-unsafe fn center_window(hwnd: HWND) {
+/// Add a block rule for the window picked with the crosshair, saved with
+/// the rest of the dialog
+fn on_window_picked(info: WindowInfo) {
+    let rule = WindowRule::for_window(&info);
+    let text = format!("Won't tile {}", rule.describe());
+    info!("Blocking {}", rule.describe());
+    if let Ok(mut guard) = CURRENT_CONFIG.lock()
+        && let Some(ref mut config) = *guard
+        && !config.rules.block.contains(&rule)
+    {
+        config.rules.block.push(rule);
+    }
+
+    let hwnd = HWND(SETTINGS_HWND.load(Ordering::SeqCst) as *mut _);
+    let text: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let _ = SetDlgItemTextW(hwnd, ID_BLOCKED_LABEL, PCWSTR(text.as_ptr()));
+    }
+}
+
+/// Center `hwnd` on `monitor`'s work area, keeping its title bar on
+/// screen if it's larger
+unsafe fn center_on_monitor(hwnd: HWND, monitor: HMONITOR) {
+    let Some(area) = get_monitor_work_area(monitor) else {
+        return;
+    };
     unsafe {
         let mut rect = RECT::default();
         let _ = GetWindowRect(hwnd, &mut rect);
-
         let width = rect.right - rect.left;
         let height = rect.bottom - rect.top;
+        let x = (area.left + (area.right - area.left - width) / 2).max(area.left);
+        let y = (area.top + (area.bottom - area.top - height) / 2).max(area.top);
+        let _ = SetWindowPos(hwnd, Some(HWND_TOP), x, y, 0, 0, SWP_NOSIZE | SWP_NOZORDER);
+    }
+}
 
-        // Get screen dimensions
-        let screen_width = GetSystemMetrics(SM_CXSCREEN);
-        let screen_height = GetSystemMetrics(SM_CYSCREEN);
+/// Bring an open window back to the front, flashing it so it's noticed,
+/// and move it to the monitor the cursor is on if it's on another
+pub unsafe fn raise_window(hwnd: HWND) {
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+        if let Some(monitor) = get_cursor_monitor()
+            && monitor != get_window_monitor(hwnd)
+        {
+            center_on_monitor(hwnd, monitor);
+        }
+        bring_to_foreground(hwnd);
+        let flash = FLASHWINFO {
+            cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+            hwnd,
+            dwFlags: FLASHW_ALL,
+            uCount: 3,
+            dwTimeout: 0,
+        };
+        let _ = FlashWindowEx(&flash);
+    }
+}
 
-        let x = (screen_width - width) / 2;
-        let y = (screen_height - height) / 2;
+/// Center `hwnd` on the monitor the cursor is on, e.g. the one whose tray
+/// icon was clicked, rather than always the primary
+pub unsafe fn center_window(hwnd: HWND) {
+    let monitor = get_cursor_monitor().unwrap_or_else(|| get_window_monitor(hwnd));
+    unsafe { center_on_monitor(hwnd, monitor) }
+}
 
-        let _ = SetWindowPos(hwnd, Some(HWND_TOP), x, y, 0, 0, SWP_NOSIZE | SWP_NOZORDER);
+/// Text scale for the open dialog, from the config it was opened with
+fn dialog_text_percent() -> i32 {
+    CURRENT_CONFIG
+        .lock()
+        .ok()
+        .and_then(|guard| guard.as_ref().map(|c| c.accessibility.text_scale))
+        .map_or(100, text_scale_percent)
+}
+
+// Callback for EnumChildWindows to grow children with the text scale
+unsafe extern "system" fn scale_child_callback(hwnd: HWND, lparam: LPARAM) -> windows::core::BOOL {
+    unsafe {
+        let percent = lparam.0 as i32;
+        let mut rect = RECT::default();
+        if GetWindowRect(hwnd, &mut rect).is_ok()
+            && let Ok(parent) = GetParent(hwnd)
+        {
+            let mut points = [
+                POINT {
+                    x: rect.left,
+                    y: rect.top,
+                },
+                POINT {
+                    x: rect.right,
+                    y: rect.bottom,
+                },
+            ];
+            MapWindowPoints(None, Some(parent), &mut points);
+            let _ = MoveWindow(
+                hwnd,
+                points[0].x * percent / 100,
+                points[0].y * percent / 100,
+                (points[1].x - points[0].x) * percent / 100,
+                (points[1].y - points[0].y) * percent / 100,
+                false,
+            );
+        }
     }
+    windows::core::BOOL(1) // Continue enumeration
 }
 
 // Callback for EnumChildWindows to set font on all children
@@ -421,11 +589,21 @@ unsafe extern "system" fn set_font_callback(hwnd: HWND, lparam: LPARAM) -> windo
     windows::core::BOOL(1) // Continue enumeration
 }
 
-unsafe fn set_dialog_font(hwnd: HWND) {
+/// Set the dialog font on every control, grown with `text_percent`
+pub unsafe fn set_dialog_font(hwnd: HWND, text_percent: i32) {
     unsafe {
+        // Controls are laid out for 12px text; grow them to fit larger text
+        if text_percent != 100 {
+            let _ = EnumChildWindows(
+                Some(hwnd),
+                Some(scale_child_callback),
+                LPARAM(text_percent as isize),
+            );
+        }
+
         // Create Segoe UI font (Windows default UI font)
         let font = CreateFontW(
-            -12, // Height (negative for character height)
+            -12 * text_percent / 100, // Height (negative for character height)
             0,
             0,
             0,
@@ -450,25 +628,45 @@ unsafe fn set_dialog_font(hwnd: HWND) {
     }
 }
 
-unsafe fn save_settings(hwnd: HWND) {
+/// Save the dialog's values, unless the user backs out of a warning about
+/// them. Returns whether they were saved.
+unsafe fn save_settings(hwnd: HWND) -> bool {
     unsafe {
         let cols = GetDlgItemInt(hwnd, ID_COLS_EDIT, None, false);
         let rows = GetDlgItemInt(hwnd, ID_ROWS_EDIT, None, false);
         let gap = GetDlgItemInt(hwnd, ID_GAP_EDIT, None, false) as i32;
 
         // Get current config and update it
-        let mut config = CURRENT_CONFIG
+        let base = CURRENT_CONFIG
             .lock()
             .ok()
             .and_then(|g| g.clone())
             .unwrap_or_default();
+        let mut config = base.clone();
         config.grid.cols = cols.clamp(1, 8);
         config.grid.rows = rows.clamp(1, 4);
         config.grid.gap = gap.clamp(0, 50);
 
-        // Save to file
-        if let Err(e) = config.save() {
-            eprintln!("Failed to save config: {}", e);
+        if let Some(warning) = tile_size_warning(&config) {
+            let text: Vec<u16> = format!("{}.\n\nSave anyway?", warning)
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .collect();
+            let answer = MessageBoxW(
+                Some(hwnd),
+                PCWSTR(text.as_ptr()),
+                w!("Tactile-Win Settings"),
+                MB_YESNO | MB_ICONWARNING,
+            );
+            if answer == IDNO {
+                return false;
+            }
+        }
+
+        // Save to file, keeping changes made to it since the dialog opened
+        if let Err(e) = config.save(&base) {
+            error!("Failed to save config: {}", e);
+            notify("Config not saved", &e.to_string());
         }
 
         // Call the callback
@@ -477,5 +675,6 @@ unsafe fn save_settings(hwnd: HWND) {
                 callback(config);
             }
         }
+        true
     }
 }
@@ -0,0 +1,191 @@
+use windows::Win32::Foundation::{HWND, RECT};
+
+use crate::grid::TilePos;
+
+/// Windows overlapping by at most this much are still side by side; their
+/// invisible resize borders overlap
+const OVERLAP_TOLERANCE: i32 = 16;
+/// A group must cover at least this share of the work area, as Snap
+/// layouts fill it
+const MIN_COVERAGE: f64 = 0.95;
+
+fn area(rect: &RECT) -> i64 {
+    (rect.right - rect.left).max(0) as i64 * (rect.bottom - rect.top).max(0) as i64
+}
+
+fn within(rect: &RECT, bounds: &RECT) -> bool {
+    rect.left >= bounds.left - OVERLAP_TOLERANCE
+        && rect.top >= bounds.top - OVERLAP_TOLERANCE
+        && rect.right <= bounds.right + OVERLAP_TOLERANCE
+        && rect.bottom <= bounds.bottom + OVERLAP_TOLERANCE
+}
+
+fn overlap(a: &RECT, b: &RECT) -> bool {
+    a.right.min(b.right) - a.left.max(b.left) > OVERLAP_TOLERANCE
+        && a.bottom.min(b.bottom) - a.top.max(b.top) > OVERLAP_TOLERANCE
+}
+
+/// The windows `target` looks to be snapped alongside: others in `windows`
+/// (topmost first) on the same work area that, with it, fill the work area
+/// without overlapping. Windows has no API for Snap groups, so this is a
+/// best guess. Sorted top to bottom, then left to right.
+pub fn find_group(target: RECT, windows: &[(HWND, RECT)], work_area: RECT) -> Vec<(HWND, RECT)> {
+    if !within(&target, &work_area) {
+        return Vec::new();
+    }
+    let mut group: Vec<(HWND, RECT)> = Vec::new();
+    for &(hwnd, rect) in windows {
+        if within(&rect, &work_area)
+            && !overlap(&rect, &target)
+            && !group.iter().any(|(_, other)| overlap(&rect, other))
+        {
+            group.push((hwnd, rect));
+        }
+    }
+    let covered: i64 = area(&target) + group.iter().map(|(_, rect)| area(rect)).sum::<i64>();
+    if group.is_empty() || (covered as f64) < area(&work_area) as f64 * MIN_COVERAGE {
+        return Vec::new();
+    }
+    group.sort_by_key(|(_, rect)| (rect.top, rect.left));
+    group
+}
+
+/// Whether a group's windows are one above the other, rather than side by
+/// side
+pub fn is_stacked(group: &[(HWND, RECT)]) -> bool {
+    group
+        .windows(2)
+        .all(|pair| (pair[0].1.left - pair[1].1.left).abs() <= OVERLAP_TOLERANCE)
+}
+
+/// Spans beside `placed` on a `cols`x`rows` grid for `count` companion
+/// windows: the largest strip of the grid left over, split as evenly as
+/// it goes into rows if `stacked`, else columns. Split the other way when
+/// there aren't enough tiles; None if there aren't either way.
+pub fn group_spans(
+    cols: u32,
+    rows: u32,
+    (first, last): (TilePos, TilePos),
+    count: usize,
+    stacked: bool,
+) -> Option<Vec<(TilePos, TilePos)>> {
+    let span = |col1, row1, col2, row2| {
+        (
+            TilePos {
+                col: col1,
+                row: row1,
+            },
+            TilePos {
+                col: col2,
+                row: row2,
+            },
+        )
+    };
+    let strips = [
+        (first.col > 0).then(|| span(0, 0, first.col - 1, rows - 1)),
+        (last.col + 1 < cols).then(|| span(last.col + 1, 0, cols - 1, rows - 1)),
+        (first.row > 0).then(|| span(0, 0, cols - 1, first.row - 1)),
+        (last.row + 1 < rows).then(|| span(0, last.row + 1, cols - 1, rows - 1)),
+    ];
+    let size = |(a, b): &(TilePos, TilePos)| (b.col - a.col + 1, b.row - a.row + 1);
+    let mut largest: Option<(TilePos, TilePos)> = None;
+    for strip in strips.into_iter().flatten() {
+        let (w, h) = size(&strip);
+        if largest.as_ref().is_none_or(|best| {
+            let (best_w, best_h) = size(best);
+            w * h > best_w * best_h
+        }) {
+            largest = Some(strip);
+        }
+    }
+    let (start, end) = largest?;
+    let (w, h) = size(&(start, end));
+    let count = count as u32;
+    if count == 0 || (w < count && h < count) {
+        return None;
+    }
+    let split_cols = if stacked { h < count } else { w >= count };
+    let n = if split_cols { w } else { h };
+    let spans = (0..count)
+        .map(|i| {
+            let (from, to) = (i * n / count, (i + 1) * n / count - 1);
+            if split_cols {
+                span(start.col + from, start.row, start.col + to, end.row)
+            } else {
+                span(start.col, start.row + from, end.col, start.row + to)
+            }
+        })
+        .collect();
+    Some(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn pos(col: u32, row: u32) -> TilePos {
+        TilePos { col, row }
+    }
+
+    #[test]
+    fn test_find_group() {
+        let work_area = rect(0, 0, 1920, 1040);
+        let (a, b, c) = (
+            HWND(0x10 as *mut _),
+            HWND(0x20 as *mut _),
+            HWND(0x30 as *mut _),
+        );
+        // Left half snapped beside two quarters, borders overlapping a little
+        let target = rect(-7, 0, 967, 1047);
+        let windows = [
+            (c, rect(953, 513, 1927, 1047)),
+            (b, rect(953, 0, 1927, 527)),
+            // Underneath the others
+            (a, rect(200, 200, 1200, 800)),
+        ];
+        let group = find_group(target, &windows, work_area);
+        assert_eq!(group, vec![windows[1], windows[0]]);
+        assert!(is_stacked(&group));
+
+        // Leaves a hole, so not a layout
+        assert!(find_group(target, &windows[..1], work_area).is_empty());
+        // Floating, with nothing beside it
+        assert!(find_group(rect(100, 100, 900, 700), &windows, work_area).is_empty());
+    }
+
+    #[test]
+    fn test_group_spans() {
+        // Placed in the left half of 4x2; the right half goes to the group
+        let placed = (pos(0, 0), pos(1, 1));
+        assert_eq!(
+            group_spans(4, 2, placed, 2, true),
+            Some(vec![(pos(2, 0), pos(3, 0)), (pos(2, 1), pos(3, 1))])
+        );
+        assert_eq!(
+            group_spans(4, 2, placed, 2, false),
+            Some(vec![(pos(2, 0), pos(2, 1)), (pos(3, 0), pos(3, 1))])
+        );
+        // Three windows won't fit side by side in two columns, or stacked
+        // in two rows
+        assert_eq!(group_spans(4, 2, placed, 3, true), None);
+
+        // Top row of 3x2: one row below, so stacking gives way to columns,
+        // split as evenly as three tiles go
+        let placed = (pos(0, 0), pos(2, 0));
+        assert_eq!(
+            group_spans(3, 2, placed, 2, true),
+            Some(vec![(pos(0, 1), pos(0, 1)), (pos(1, 1), pos(2, 1))])
+        );
+        // Nothing left over
+        assert_eq!(group_spans(2, 2, (pos(0, 0), pos(1, 1)), 1, false), None);
+    }
+}
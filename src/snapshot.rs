@@ -0,0 +1,78 @@
+use windows::Win32::Foundation::SYSTEMTIME;
+
+/// Name of a snapshot of a `cols` x `rows` grid taken at local time
+/// `taken`, so later snapshots don't replace earlier ones
+pub fn file_name(cols: u32, rows: u32, taken: &SYSTEMTIME) -> String {
+    format!(
+        "tactile-win-grid-{}x{}-{:04}{:02}{:02}-{:02}{:02}{:02}.png",
+        cols,
+        rows,
+        taken.wYear,
+        taken.wMonth,
+        taken.wDay,
+        taken.wHour,
+        taken.wMinute,
+        taken.wSecond
+    )
+}
+
+/// A PNG of `width` by `height` 8-bit RGBA pixels, `rgba` holding the rows
+/// top first
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    writer.finish()?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        let taken = SYSTEMTIME {
+            wYear: 2026,
+            wMonth: 3,
+            wDay: 7,
+            wHour: 9,
+            wMinute: 5,
+            wSecond: 30,
+            ..Default::default()
+        };
+        assert_eq!(
+            file_name(4, 2, &taken),
+            "tactile-win-grid-4x2-20260307-090530.png"
+        );
+    }
+
+    #[test]
+    fn test_encode_png_round_trips() {
+        // 2x1: opaque red, half-transparent blue
+        let rgba = [255, 0, 0, 255, 0, 0, 255, 128];
+        let png = encode_png(2, 1, &rgba).unwrap();
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(pixels, rgba);
+    }
+
+    #[test]
+    fn test_encode_png_compresses() {
+        // Mostly flat color, as the grid is
+        let rgba: Vec<u8> = [30, 30, 30, 200].repeat(400 * 300);
+        let png = encode_png(400, 300, &rgba).unwrap();
+        assert!(png.len() < rgba.len() / 50, "{} bytes", png.len());
+    }
+
+    #[test]
+    fn test_encode_png_checks_size() {
+        assert!(encode_png(2, 2, &[0; 8]).is_err());
+    }
+}
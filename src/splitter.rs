@@ -0,0 +1,597 @@
+use std::ptr;
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{GetStockObject, BLACK_BRUSH, HBRUSH};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetCapture, ReleaseCapture, SetCapture};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetCursorPos, GetTopWindow, GetWindow,
+    LoadCursorW, RegisterClassW, SetCursor, SetLayeredWindowAttributes, SetWindowPos, GW_HWNDNEXT,
+    GW_HWNDPREV, HWND_TOP, IDC_SIZENS, IDC_SIZEWE, LWA_ALPHA, SWP_NOACTIVATE, SWP_NOMOVE,
+    SWP_NOSIZE, SWP_NOZORDER, WM_CAPTURECHANGED, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE,
+    WM_SETCURSOR, WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_POPUP,
+    WS_VISIBLE,
+};
+
+use crate::config::AfterMove;
+use crate::keyboard::Direction;
+use crate::log::error;
+use crate::window::{get_window_rect, move_window, rects_intersect};
+
+const SPLITTER_CLASS_NAME: PCWSTR = w!("TactileWinSplitter");
+
+/// Neighbours share an edge when the space between them is at most
+/// MAX_GAP wide, or their invisible borders overlap by at most MAX_OVERLAP
+const MAX_GAP: i32 = 64;
+const MAX_OVERLAP: i32 = 16;
+/// ...and they run alongside each other for at least this long
+const MIN_SHARED_LENGTH: i32 = 100;
+/// Narrower splitters are widened about the edge, to be easy to grab
+const MIN_HANDLE_WIDTH: i32 = 8;
+/// Neither window is dragged smaller than this
+const MIN_PANE_SIZE: i32 = 100;
+
+/// Which way a shared edge runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Windows side by side; the splitter drags left and right
+    Vertical,
+    /// Windows one above the other; the splitter drags up and down
+    Horizontal,
+}
+
+/// Two tiled windows with a grid line between them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SharedEdge {
+    pub axis: Axis,
+    /// The left or top window
+    pub first: HWND,
+    /// The right or bottom window
+    pub second: HWND,
+    /// Where the splitter goes, in screen coordinates
+    pub area: RECT,
+}
+
+/// Widen `start..end` about its middle to at least `width`
+fn widen(start: i32, end: i32, width: i32) -> (i32, i32) {
+    if end - start >= width {
+        return (start, end);
+    }
+    let left = (start + end) / 2 - width / 2;
+    (left, left + width)
+}
+
+/// The edge `a` shares with `b` on its right or bottom, if any
+fn shared_edge((first, a): (HWND, RECT), (second, b): (HWND, RECT)) -> Option<SharedEdge> {
+    let near = |gap: i32| (-MAX_OVERLAP..=MAX_GAP).contains(&gap);
+    let (axis, area) = if near(b.left - a.right) {
+        let (left, right) = widen(a.right.min(b.left), a.right.max(b.left), MIN_HANDLE_WIDTH);
+        let (top, bottom) = (a.top.max(b.top), a.bottom.min(b.bottom));
+        (
+            Axis::Vertical,
+            RECT {
+                left,
+                top,
+                right,
+                bottom,
+            },
+        )
+    } else if near(b.top - a.bottom) {
+        let (top, bottom) = widen(a.bottom.min(b.top), a.bottom.max(b.top), MIN_HANDLE_WIDTH);
+        let (left, right) = (a.left.max(b.left), a.right.min(b.right));
+        (
+            Axis::Horizontal,
+            RECT {
+                left,
+                top,
+                right,
+                bottom,
+            },
+        )
+    } else {
+        return None;
+    };
+    let length = match axis {
+        Axis::Vertical => area.bottom - area.top,
+        Axis::Horizontal => area.right - area.left,
+    };
+    (length >= MIN_SHARED_LENGTH).then_some(SharedEdge {
+        axis,
+        first,
+        second,
+        area,
+    })
+}
+
+/// Every edge shared by a pair of `windows`
+pub fn find_shared_edges(windows: &[(HWND, RECT)]) -> Vec<SharedEdge> {
+    let mut edges = Vec::new();
+    for &first in windows {
+        for &second in windows {
+            if first.0 != second.0
+                && let Some(edge) = shared_edge(first, second)
+            {
+                edges.push(edge);
+            }
+        }
+    }
+    edges
+}
+
+/// Whether a window stacked above both of `edge`'s windows overlaps its
+/// splitter. `stacked` lists windows and their rects, topmost first.
+pub fn is_edge_covered(edge: &SharedEdge, stacked: &[(HWND, RECT)]) -> bool {
+    stacked
+        .iter()
+        .take_while(|&&(hwnd, _)| hwnd != edge.first && hwnd != edge.second)
+        .any(|&(_, rect)| rects_intersect(rect, edge.area))
+}
+
+/// Move the edge between `first` and `second` by `delta` along `axis`,
+/// keeping the space between them and neither smaller than MIN_PANE_SIZE.
+/// Returns the new rects and how far the edge actually moved.
+fn resize_pair(axis: Axis, first: RECT, second: RECT, delta: i32) -> (RECT, RECT, i32) {
+    let (mut first, mut second) = (first, second);
+    let delta = match axis {
+        Axis::Vertical => {
+            let delta = delta.clamp(
+                (first.left + MIN_PANE_SIZE - first.right).min(0),
+                (second.right - MIN_PANE_SIZE - second.left).max(0),
+            );
+            first.right += delta;
+            second.left += delta;
+            delta
+        }
+        Axis::Horizontal => {
+            let delta = delta.clamp(
+                (first.top + MIN_PANE_SIZE - first.bottom).min(0),
+                (second.bottom - MIN_PANE_SIZE - second.top).max(0),
+            );
+            first.bottom += delta;
+            second.top += delta;
+            delta
+        }
+    };
+    (first, second, delta)
+}
+
+/// The edge `focused` shares with its neighbor in `direction`, or failing
+/// that with the one opposite, so moving it that way grows or shrinks
+/// `focused`
+fn edge_toward(edges: &[SharedEdge], focused: HWND, direction: Direction) -> Option<&SharedEdge> {
+    let (axis, forward) = match direction {
+        Direction::Left => (Axis::Vertical, false),
+        Direction::Right => (Axis::Vertical, true),
+        Direction::Up => (Axis::Horizontal, false),
+        Direction::Down => (Axis::Horizontal, true),
+    };
+    let mut beside = edges
+        .iter()
+        .filter(|edge| edge.axis == axis && (edge.first == focused || edge.second == focused));
+    let ahead = beside
+        .clone()
+        .find(|edge| (edge.first == focused) == forward);
+    ahead.or_else(|| beside.next())
+}
+
+/// Move the split between `focused` and a neighbor `step` pixels in
+/// `direction`, resizing both. False if there's no neighbor that way or
+/// the split can't move any further.
+pub fn step_split(edges: &[SharedEdge], focused: HWND, direction: Direction, step: i32) -> bool {
+    let Some(edge) = edge_toward(edges, focused, direction) else {
+        return false;
+    };
+    let (Some(first), Some(second)) = (get_window_rect(edge.first), get_window_rect(edge.second))
+    else {
+        return false;
+    };
+    let delta = match direction {
+        Direction::Left | Direction::Up => -step,
+        Direction::Right | Direction::Down => step,
+    };
+    let (first, second, moved) = resize_pair(edge.axis, first, second, delta);
+    if moved == 0 {
+        return false;
+    }
+    for (hwnd, rect) in [(edge.first, first), (edge.second, second)] {
+        if let Err(e) = move_window(hwnd, &rect, AfterMove::Keep) {
+            error!("Failed to move split: {}", e);
+        }
+    }
+    true
+}
+
+/// A splitter window and the windows either side of it
+struct Splitter {
+    window: isize,
+    axis: Axis,
+    first: isize,
+    second: isize,
+}
+
+/// A drag in progress: where it started, and where everything was then
+struct Drag {
+    start: POINT,
+    first: RECT,
+    second: RECT,
+    area: RECT,
+}
+
+static SPLITTERS: Mutex<Vec<Splitter>> = Mutex::new(Vec::new());
+static DRAG: Mutex<Option<Drag>> = Mutex::new(None);
+// Run after a drag resizes windows, so the other splitters can follow
+static DRAGGED_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+
+pub fn set_dragged_callback(callback: fn()) {
+    if let Ok(mut guard) = DRAGGED_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+/// The axis and windows of the splitter `window`, if it is one
+fn find_splitter(window: HWND) -> Option<(Axis, HWND, HWND)> {
+    let splitters = SPLITTERS.lock().ok()?;
+    let splitter = splitters
+        .iter()
+        .find(|splitter| splitter.window == window.0 as isize)?;
+    Some((
+        splitter.axis,
+        HWND(splitter.first as *mut _),
+        HWND(splitter.second as *mut _),
+    ))
+}
+
+/// Put splitter `window` just above the higher of `first` and `second`,
+/// so windows in front of the pair cover it as they cover them
+fn stack_above_pair(window: HWND, first: HWND, second: HWND) {
+    unsafe {
+        let mut hwnd = GetTopWindow(None).ok();
+        while let Some(candidate) = hwnd
+            && candidate != first
+            && candidate != second
+        {
+            hwnd = GetWindow(candidate, GW_HWNDNEXT).ok();
+        }
+        let Some(upper) = hwnd else {
+            return;
+        };
+        let above = GetWindow(upper, GW_HWNDPREV).ok();
+        if above == Some(window) {
+            return;
+        }
+        let _ = SetWindowPos(
+            window,
+            Some(above.unwrap_or(HWND_TOP)),
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        );
+    }
+}
+
+/// Keep each splitter just above its windows, after windows in front of
+/// them change
+pub fn restack_splitters() {
+    let stacks: Vec<(HWND, HWND, HWND)> = SPLITTERS
+        .lock()
+        .map(|guard| {
+            guard
+                .iter()
+                .map(|splitter| {
+                    (
+                        HWND(splitter.window as *mut _),
+                        HWND(splitter.first as *mut _),
+                        HWND(splitter.second as *mut _),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    for (window, first, second) in stacks {
+        stack_above_pair(window, first, second);
+    }
+}
+
+/// Replace the splitters with one per edge in `edges`. Left alone while
+/// one is being dragged.
+pub fn show_splitters(edges: &[SharedEdge]) {
+    if DRAG.lock().is_ok_and(|drag| drag.is_some()) {
+        return;
+    }
+    let old = SPLITTERS
+        .lock()
+        .map(|mut guard| std::mem::take(&mut *guard))
+        .unwrap_or_default();
+    for splitter in old {
+        unsafe {
+            let _ = DestroyWindow(HWND(splitter.window as *mut _));
+        }
+    }
+    if edges.is_empty() {
+        return;
+    }
+
+    unsafe {
+        let Ok(hinstance) = GetModuleHandleW(None) else {
+            return;
+        };
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(splitter_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: SPLITTER_CLASS_NAME,
+            hbrBackground: HBRUSH(GetStockObject(BLACK_BRUSH).0),
+            ..Default::default()
+        };
+        let _ = RegisterClassW(&wc);
+
+        for edge in edges {
+            let area = edge.area;
+            let window = CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                SPLITTER_CLASS_NAME,
+                PCWSTR::null(),
+                WS_POPUP | WS_VISIBLE,
+                area.left,
+                area.top,
+                area.right - area.left,
+                area.bottom - area.top,
+                None,
+                None,
+                Some(hinstance.into()),
+                Some(ptr::null()),
+            );
+            let Ok(window) = window else {
+                continue;
+            };
+            // Almost fully transparent: unseen, but still hit by the mouse
+            let _ = SetLayeredWindowAttributes(window, COLORREF(0), 1, LWA_ALPHA);
+            stack_above_pair(window, edge.first, edge.second);
+            if let Ok(mut guard) = SPLITTERS.lock() {
+                guard.push(Splitter {
+                    window: window.0 as isize,
+                    axis: edge.axis,
+                    first: edge.first.0 as isize,
+                    second: edge.second.0 as isize,
+                });
+            }
+        }
+    }
+}
+
+fn start_drag(window: HWND) {
+    let Some((_, first, second)) = find_splitter(window) else {
+        return;
+    };
+    let mut start = POINT::default();
+    if unsafe { GetCursorPos(&mut start) }.is_err() {
+        return;
+    }
+    // Where the windows are now, in case something else moved them
+    let (Some(first), Some(second), Some(area)) = (
+        get_window_rect(first),
+        get_window_rect(second),
+        get_window_rect(window),
+    ) else {
+        return;
+    };
+    if let Ok(mut guard) = DRAG.lock() {
+        *guard = Some(Drag {
+            start,
+            first,
+            second,
+            area,
+        });
+    }
+    unsafe {
+        SetCapture(window);
+    }
+}
+
+/// Resize both windows to follow the cursor, and the splitter with them
+fn continue_drag(window: HWND) {
+    let Some((axis, first_hwnd, second_hwnd)) = find_splitter(window) else {
+        return;
+    };
+    let mut cursor = POINT::default();
+    if unsafe { GetCursorPos(&mut cursor) }.is_err() {
+        return;
+    }
+    let Ok(guard) = DRAG.lock() else {
+        return;
+    };
+    let Some(ref drag) = *guard else {
+        return;
+    };
+    let delta = match axis {
+        Axis::Vertical => cursor.x - drag.start.x,
+        Axis::Horizontal => cursor.y - drag.start.y,
+    };
+    let (first, second, delta) = resize_pair(axis, drag.first, drag.second, delta);
+    let (x, y) = match axis {
+        Axis::Vertical => (drag.area.left + delta, drag.area.top),
+        Axis::Horizontal => (drag.area.left, drag.area.top + delta),
+    };
+    drop(guard);
+
+    for (hwnd, rect) in [(first_hwnd, first), (second_hwnd, second)] {
+        if let Err(e) = move_window(hwnd, &rect, AfterMove::Keep) {
+            error!("Failed to resize window from splitter: {}", e);
+        }
+    }
+    unsafe {
+        let _ = SetWindowPos(
+            window,
+            None,
+            x,
+            y,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+}
+
+unsafe extern "system" fn splitter_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_SETCURSOR => {
+                let cursor = match find_splitter(hwnd) {
+                    Some((Axis::Horizontal, ..)) => IDC_SIZENS,
+                    _ => IDC_SIZEWE,
+                };
+                if let Ok(cursor) = LoadCursorW(None, cursor) {
+                    SetCursor(Some(cursor));
+                }
+                LRESULT(1)
+            }
+            WM_LBUTTONDOWN => {
+                start_drag(hwnd);
+                LRESULT(0)
+            }
+            WM_MOUSEMOVE if GetCapture() == hwnd => {
+                continue_drag(hwnd);
+                LRESULT(0)
+            }
+            WM_LBUTTONUP if GetCapture() == hwnd => {
+                // Ends the drag, via WM_CAPTURECHANGED
+                let _ = ReleaseCapture();
+                LRESULT(0)
+            }
+            WM_CAPTURECHANGED => {
+                let dragged = DRAG.lock().ok().and_then(|mut guard| guard.take());
+                let callback = DRAGGED_CALLBACK.lock().ok().and_then(|guard| *guard);
+                if let (Some(_), Some(callback)) = (dragged, callback) {
+                    callback();
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn test_find_shared_edges() {
+        let (a, b, c) = (
+            HWND(0x10 as *mut _),
+            HWND(0x20 as *mut _),
+            HWND(0x30 as *mut _),
+        );
+        let windows = [
+            // Left half, with an 8px gap to the right column
+            (a, rect(0, 0, 956, 1040)),
+            (b, rect(964, 0, 1920, 516)),
+            (c, rect(964, 524, 1920, 1040)),
+        ];
+        let edges = find_shared_edges(&windows);
+        assert_eq!(edges.len(), 3);
+        assert!(edges.contains(&SharedEdge {
+            axis: Axis::Vertical,
+            first: a,
+            second: b,
+            area: rect(956, 0, 964, 516),
+        }));
+        assert!(edges.contains(&SharedEdge {
+            axis: Axis::Horizontal,
+            first: b,
+            second: c,
+            area: rect(964, 516, 1920, 524),
+        }));
+
+        // Touching windows get a handle wide enough to grab
+        let touching = [(a, rect(0, 0, 960, 1040)), (b, rect(960, 0, 1920, 1040))];
+        assert_eq!(
+            find_shared_edges(&touching)[0].area,
+            rect(956, 0, 964, 1040)
+        );
+
+        // Too far apart, or barely alongside each other
+        let apart = [(a, rect(0, 0, 800, 1040)), (b, rect(960, 0, 1920, 1040))];
+        assert!(find_shared_edges(&apart).is_empty());
+        let corner = [(a, rect(0, 0, 960, 520)), (b, rect(960, 480, 1920, 1040))];
+        assert!(find_shared_edges(&corner).is_empty());
+    }
+
+    #[test]
+    fn test_is_edge_covered() {
+        let (a, b, c) = (
+            HWND(0x10 as *mut _),
+            HWND(0x20 as *mut _),
+            HWND(0x30 as *mut _),
+        );
+        let (left, right) = ((a, rect(0, 0, 956, 1040)), (b, rect(964, 0, 1920, 1040)));
+        let edges = find_shared_edges(&[left, right]);
+        let edge = &edges[0];
+        let dialog = (c, rect(800, 400, 1100, 600));
+        // Over the split and in front of both windows
+        assert!(is_edge_covered(edge, &[dialog, left, right]));
+        // Behind one of them
+        assert!(!is_edge_covered(edge, &[right, dialog, left]));
+        // In front, but clear of the split
+        let beside = (c, rect(0, 0, 500, 500));
+        assert!(!is_edge_covered(edge, &[beside, left, right]));
+    }
+
+    #[test]
+    fn test_resize_pair_keeps_gap_and_min_size() {
+        let first = rect(0, 0, 956, 1040);
+        let second = rect(964, 0, 1920, 1040);
+        let (a, b, moved) = resize_pair(Axis::Vertical, first, second, 200);
+        assert_eq!((a.right, b.left, moved), (1156, 1164, 200));
+
+        // Stops with MIN_PANE_SIZE left for the window being squeezed
+        let (a, b, moved) = resize_pair(Axis::Vertical, first, second, 5000);
+        assert_eq!(b.right - b.left, MIN_PANE_SIZE);
+        assert_eq!((a.right, moved), (1812, 856));
+
+        let (a, b, _) = resize_pair(Axis::Horizontal, first, rect(0, 1048, 956, 2000), -2000);
+        assert_eq!(a.bottom - a.top, MIN_PANE_SIZE);
+        assert_eq!(b.top - a.bottom, 8);
+    }
+
+    #[test]
+    fn test_edge_toward() {
+        let (a, b, c) = (
+            HWND(0x10 as *mut _),
+            HWND(0x20 as *mut _),
+            HWND(0x30 as *mut _),
+        );
+        // Three columns
+        let edges = find_shared_edges(&[
+            (a, rect(0, 0, 636, 1040)),
+            (b, rect(644, 0, 1276, 1040)),
+            (c, rect(1284, 0, 1920, 1040)),
+        ]);
+        let neighbor = |focused, direction| {
+            edge_toward(&edges, focused, direction).map(|edge| (edge.first, edge.second))
+        };
+        // The middle column's edge on the side it's moving to
+        assert_eq!(neighbor(b, Direction::Right), Some((b, c)));
+        assert_eq!(neighbor(b, Direction::Left), Some((a, b)));
+        // With nothing that side, the edge behind it moves instead
+        assert_eq!(neighbor(a, Direction::Left), Some((a, b)));
+        assert_eq!(neighbor(c, Direction::Right), Some((b, c)));
+        assert_eq!(neighbor(b, Direction::Up), None);
+    }
+}
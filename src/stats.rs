@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::accessibility::describe_span;
+use crate::bus::{self, AppEvent};
+use crate::grid::TilePos;
+use crate::history::{self, PlacementRecord};
+use crate::log::{error, info};
+use crate::toast;
+use crate::window::get_window_info;
+
+/// No suggestions until this many placements are recorded
+const MIN_PLACEMENTS: u32 = 50;
+/// Placements between checks for a better grid
+const SUGGEST_EVERY: u32 = 25;
+/// Share of placements, in percent, a suggested grid must still express
+const COVERAGE_PERCENT: u32 = 90;
+// Grid size limits, as in Config::validate
+const MAX_COLS: u32 = 8;
+const MAX_ROWS: u32 = 4;
+
+/// How windows have been placed, kept across runs so the grid can be
+/// fitted to how it is actually used
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlacementStats {
+    pub placements: u32,
+    /// Placements counted by the fewest columns that can express them,
+    /// e.g. 2 for halves and 3 for thirds; index 0 is unused
+    col_needs: Vec<u32>,
+    row_needs: Vec<u32>,
+    /// Last grid suggested, so the same one isn't offered twice
+    suggested: Option<(u32, u32)>,
+}
+
+/// A grid that fits the recorded placements better than the current one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub cols: u32,
+    pub rows: u32,
+    pub text: String,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: u32, b: u32) -> u32 {
+    a / gcd(a, b) * b
+}
+
+/// Fewest divisions of an axis that have edges at `start / count` and
+/// `end / count`
+fn divisions_needed(start: u32, end: u32, count: u32) -> u32 {
+    let denominator = |edge: u32| count / gcd(edge, count);
+    lcm(denominator(start), denominator(end))
+}
+
+/// Fewest divisions that express at least COVERAGE_PERCENT of the
+/// placements counted in `needs`, up to `max`
+fn best_divisions(needs: &[u32], max: u32) -> u32 {
+    let total: u32 = needs.iter().sum();
+    (1..=max)
+        .find(|&n| {
+            let covered: u32 = needs
+                .iter()
+                .enumerate()
+                .filter(|&(need, _)| need > 0 && n.is_multiple_of(need as u32))
+                .map(|(_, &count)| count)
+                .sum();
+            covered * 100 >= total * COVERAGE_PERCENT
+        })
+        .unwrap_or(max)
+}
+
+fn fraction_name(divisions: usize) -> Option<&'static str> {
+    let names = [
+        "halves", "thirds", "quarters", "fifths", "sixths", "sevenths", "eighths",
+    ];
+    names.get(divisions.checked_sub(2)?).copied()
+}
+
+impl PlacementStats {
+    fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|p| p.join(".tactile-win-stats.toml"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(path) = Self::path() {
+            fs::write(path, toml::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+
+    /// Count a placement on the span from `first` to `last` of a
+    /// `cols` x `rows` grid
+    pub fn record(&mut self, first: TilePos, last: TilePos, cols: u32, rows: u32) {
+        let count = |needs: &mut Vec<u32>, need: u32| {
+            if needs.len() <= need as usize {
+                needs.resize(need as usize + 1, 0);
+            }
+            needs[need as usize] += 1;
+        };
+        self.placements += 1;
+        count(
+            &mut self.col_needs,
+            divisions_needed(first.col, last.col + 1, cols),
+        );
+        count(
+            &mut self.row_needs,
+            divisions_needed(first.row, last.row + 1, rows),
+        );
+    }
+
+    /// The grid last offered by `suggest`
+    pub fn suggested(&self) -> Option<(u32, u32)> {
+        self.suggested
+    }
+
+    /// Now and then, offer the smallest grid that still expresses nearly
+    /// every recorded placement, if it differs from `current`
+    pub fn suggest(&mut self, current: (u32, u32)) -> Option<Suggestion> {
+        if self.placements < MIN_PLACEMENTS || !self.placements.is_multiple_of(SUGGEST_EVERY) {
+            return None;
+        }
+        let cols = best_divisions(&self.col_needs, MAX_COLS);
+        let rows = best_divisions(&self.row_needs, MAX_ROWS);
+        if (cols, rows) == current || self.suggested == Some((cols, rows)) {
+            return None;
+        }
+        self.suggested = Some((cols, rows));
+
+        let used: Vec<&str> = self
+            .col_needs
+            .iter()
+            .enumerate()
+            .filter(|&(need, &count)| count > 0 && cols.is_multiple_of(need.max(1) as u32))
+            .filter_map(|(need, _)| fraction_name(need))
+            .collect();
+        let habit = match used.as_slice() {
+            [] => "full-width windows".to_string(),
+            [only] => only.to_string(),
+            [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+        };
+        Some(Suggestion {
+            cols,
+            rows,
+            text: format!(
+                "You almost always use {}. Switch to a {}x{} grid?",
+                habit, cols, rows
+            ),
+        })
+    }
+}
+
+thread_local! {
+    // Loaded by register; placements are all published on the main thread
+    static STATS: RefCell<PlacementStats> = RefCell::new(PlacementStats::default());
+    // Placements recorded since the statistics were last saved
+    static DIRTY: Cell<bool> = const { Cell::new(false) };
+}
+// Switches to the suggested grid when the user takes it up
+static ACCEPT_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+
+/// Log placements to the history if it's enabled and, with suggest_grid,
+/// count them and once in a while offer a grid that fits the user's
+/// habits better, calling `on_accept` if they take it
+pub fn register(on_accept: fn()) {
+    STATS.with(|stats| *stats.borrow_mut() = PlacementStats::load());
+    if let Ok(mut guard) = ACCEPT_CALLBACK.lock() {
+        *guard = Some(on_accept);
+    }
+    bus::subscribe(record_placement);
+}
+
+/// The grid last suggested, for on_accept to switch to
+pub fn suggested_grid() -> Option<(u32, u32)> {
+    STATS.with(|stats| stats.borrow().suggested())
+}
+
+/// Write the statistics out if placements were recorded since they
+/// last were. Called from a timer and on exit rather than per placement.
+pub fn save_stats() {
+    if !DIRTY.replace(false) {
+        return;
+    }
+    if let Err(e) = STATS.with(|stats| stats.borrow().save()) {
+        error!("Failed to save placement statistics: {}", e);
+    }
+}
+
+fn record_placement(event: &AppEvent) {
+    let &AppEvent::PlacementPerformed {
+        hwnd,
+        first,
+        last,
+        cols,
+        rows,
+        monitor,
+    } = event
+    else {
+        return;
+    };
+    let Some(config) = bus::config() else {
+        return;
+    };
+    if config.history.enabled {
+        let info = get_window_info(hwnd);
+        let record = PlacementRecord::new(
+            &info.process,
+            &info.title,
+            monitor,
+            describe_span(cols, rows, first, last),
+            (first, last),
+            (cols, rows),
+        );
+        if let Err(e) = history::append(&record) {
+            error!("Failed to log placement: {}", e);
+        }
+    }
+    if !config.history.suggest_grid {
+        return;
+    }
+    let suggestion = STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        stats.record(first, last, cols, rows);
+        stats.suggest((config.grid.cols, config.grid.rows))
+    });
+    DIRTY.set(true);
+    let on_accept = ACCEPT_CALLBACK.lock().ok().and_then(|guard| *guard);
+    if let (Some(suggestion), Some(on_accept)) = (suggestion, on_accept) {
+        info!("Suggesting a {}x{} grid", suggestion.cols, suggestion.rows);
+        toast::ask("Try a different grid?", &suggestion.text, on_accept);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(first: u32, last: u32) -> (TilePos, TilePos) {
+        (
+            TilePos { col: first, row: 0 },
+            TilePos { col: last, row: 1 },
+        )
+    }
+
+    #[test]
+    fn test_divisions_needed() {
+        assert_eq!(divisions_needed(0, 6, 6), 1);
+        assert_eq!(divisions_needed(0, 3, 6), 2);
+        assert_eq!(divisions_needed(2, 6, 6), 3);
+        assert_eq!(divisions_needed(3, 5, 6), 6);
+        assert_eq!(divisions_needed(1, 3, 4), 4);
+    }
+
+    #[test]
+    fn test_suggests_grid_for_halves_and_thirds() {
+        let mut stats = PlacementStats::default();
+        // On 12 columns: left halves, right thirds, and the odd quarter
+        for i in 0..MIN_PLACEMENTS {
+            let (first, last) = match i % 10 {
+                0..=4 => span(0, 5),
+                5..=8 => span(8, 11),
+                _ => span(0, 2),
+            };
+            stats.record(first, last, 12, 2);
+            if i + 1 < MIN_PLACEMENTS {
+                assert_eq!(stats.suggest((8, 2)), None);
+            }
+        }
+
+        let suggestion = stats.suggest((8, 2)).unwrap();
+        assert_eq!((suggestion.cols, suggestion.rows), (6, 1));
+        assert_eq!(
+            suggestion.text,
+            "You almost always use halves and thirds. Switch to a 6x1 grid?"
+        );
+        assert_eq!(stats.suggested(), Some((6, 1)));
+    }
+
+    #[test]
+    fn test_no_repeat_or_current_suggestion() {
+        let mut stats = PlacementStats::default();
+        for _ in 0..MIN_PLACEMENTS {
+            let (first, last) = span(0, 1);
+            stats.record(first, last, 4, 2);
+        }
+        assert_eq!(stats.suggest((2, 1)), None);
+
+        let mut again = stats.clone();
+        assert!(stats.suggest((4, 2)).is_some());
+        again.suggested = Some((2, 1));
+        assert_eq!(again.suggest((4, 2)), None);
+    }
+
+    #[test]
+    fn test_stats_round_trip() {
+        let mut stats = PlacementStats::default();
+        let (first, last) = span(0, 1);
+        stats.record(first, last, 4, 2);
+        let text = toml::to_string_pretty(&stats).unwrap();
+        assert_eq!(toml::from_str::<PlacementStats>(&text).unwrap(), stats);
+    }
+}
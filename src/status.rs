@@ -0,0 +1,232 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, FindWindowExW, RegisterClassW,
+    SendMessageTimeoutW, HWND_MESSAGE, SMTO_ABORTIFHUNG, WINDOW_EX_STYLE, WINDOW_STYLE,
+    WM_COPYDATA, WNDCLASSW,
+};
+
+use crate::config::InputBackend;
+use crate::ipc::{Status, REQUEST_TIMEOUT};
+use crate::CLASS_NAME;
+
+/// COPYDATASTRUCT dwData asking for a status report ("TWST"). wparam is the
+/// window to send the report back to, as a WM_COPYDATA with the same
+/// dwData and the report as JSON.
+pub const STATUS_COPYDATA_ID: usize = 0x5457_5354;
+
+const REPLY_CLASS_NAME: PCWSTR = w!("TactileWinStatusReply");
+
+/// A rectangle in screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Area {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl From<RECT> for Area {
+    fn from(rect: RECT) -> Self {
+        Self {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
+
+/// The settings that decide how the grid behaves
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigSummary {
+    pub path: Option<String>,
+    pub cols: u32,
+    pub rows: u32,
+    pub gap: i32,
+    /// Profile whose grid size is in use
+    pub profile: Option<String>,
+    pub input: InputBackend,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonitorStatus {
+    /// Display device name, e.g. "\\.\DISPLAY1"
+    pub device: Option<String>,
+    pub bounds: Option<Area>,
+    pub work_area: Option<Area>,
+    pub dpi: u32,
+}
+
+/// What the running instance is doing, for bug reports and dashboards
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusReport {
+    pub version: &'static str,
+    pub config: ConfigSummary,
+    pub monitors: Vec<MonitorStatus>,
+    pub overlay_visible: bool,
+    /// Whether the low-level keyboard hook is installed
+    pub keyboard_hook: bool,
+}
+
+/// Whether a WM_COPYDATA message is a status request; its wparam is the
+/// window to reply to
+///
+/// # Safety
+/// `lparam` must be the LPARAM of a WM_COPYDATA message being handled.
+pub unsafe fn is_request(lparam: LPARAM) -> bool {
+    let data = unsafe { &*(lparam.0 as *const COPYDATASTRUCT) };
+    data.dwData == STATUS_COPYDATA_ID
+}
+
+/// Send `report` back to the window that asked for it
+pub fn reply(requester: HWND, report: &StatusReport) -> Status {
+    let Ok(json) = serde_json::to_vec(report) else {
+        return Status::Failed;
+    };
+    match send(requester, None, &json) {
+        Some(_) => Status::Done,
+        None => Status::Failed,
+    }
+}
+
+/// Send `bytes` to `to` as a status WM_COPYDATA, waiting up to twice
+/// REQUEST_TIMEOUT for its reply. None if it didn't answer.
+fn send(to: HWND, from: Option<HWND>, bytes: &[u8]) -> Option<usize> {
+    let data = COPYDATASTRUCT {
+        dwData: STATUS_COPYDATA_ID,
+        cbData: bytes.len() as u32,
+        lpData: bytes.as_ptr() as *mut _,
+    };
+    let wparam = WPARAM(from.map_or(0, |hwnd| hwnd.0 as usize));
+    let mut result = 0;
+    let sent = unsafe {
+        SendMessageTimeoutW(
+            to,
+            WM_COPYDATA,
+            wparam,
+            LPARAM(&data as *const _ as isize),
+            SMTO_ABORTIFHUNG,
+            (REQUEST_TIMEOUT * 2).as_millis() as u32,
+            Some(&mut result),
+        )
+    };
+    (sent.0 != 0).then_some(result)
+}
+
+thread_local! {
+    // Report received by the reply window
+    static REPLY: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+unsafe extern "system" fn reply_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        if msg == WM_COPYDATA {
+            let data = &*(lparam.0 as *const COPYDATASTRUCT);
+            if data.dwData == STATUS_COPYDATA_ID && !data.lpData.is_null() {
+                let bytes =
+                    std::slice::from_raw_parts(data.lpData as *const u8, data.cbData as usize);
+                let json = String::from_utf8_lossy(bytes).into_owned();
+                REPLY.with(|reply| *reply.borrow_mut() = Some(json));
+                return LRESULT(1);
+            }
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+}
+
+/// Ask the running instance for its status report. None if there isn't
+/// one, else the report as JSON or why there's no report.
+pub fn query_running_instance() -> Option<Result<String, Status>> {
+    let instance = unsafe { FindWindowExW(Some(HWND_MESSAGE), None, CLASS_NAME, None) }.ok()?;
+    // A message window of our own for the report to come back to; it
+    // arrives while we wait for the request's reply
+    let window = unsafe {
+        let hinstance = GetModuleHandleW(None).ok()?;
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(reply_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: REPLY_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            REPLY_CLASS_NAME,
+            PCWSTR::null(),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            Some(hinstance.into()),
+            None,
+        )
+        .ok()?
+    };
+    let status = match send(instance, Some(window), &[]) {
+        Some(result) => Status::from_code(result as isize).unwrap_or(Status::Failed),
+        None => Status::TimedOut,
+    };
+    unsafe {
+        let _ = DestroyWindow(window);
+    }
+    let report = REPLY.with(|reply| reply.borrow_mut().take());
+    Some(match (status, report) {
+        (Status::Done, Some(report)) => Ok(report),
+        (Status::Done, None) => Err(Status::Failed),
+        (status, _) => Err(status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json() {
+        let area = Area {
+            left: 0,
+            top: 0,
+            right: 1920,
+            bottom: 1040,
+        };
+        let report = StatusReport {
+            version: "1.2.3",
+            config: ConfigSummary {
+                path: None,
+                cols: 4,
+                rows: 2,
+                gap: 8,
+                profile: Some("Laptop".to_string()),
+                input: InputBackend::Hotkeys,
+            },
+            monitors: vec![MonitorStatus {
+                device: Some(r"\\.\DISPLAY1".to_string()),
+                bounds: None,
+                work_area: Some(area),
+                dpi: 96,
+            }],
+            overlay_visible: false,
+            keyboard_hook: true,
+        };
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["config"]["input"], "hotkeys");
+        assert_eq!(json["config"]["profile"], "Laptop");
+        assert_eq!(json["monitors"][0]["device"], r"\\.\DISPLAY1");
+        assert_eq!(json["monitors"][0]["work_area"]["bottom"], 1040);
+        assert!(json["monitors"][0]["bounds"].is_null());
+        assert_eq!(json["keyboard_hook"], true);
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Mutex;
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, IsWindowVisible};
+
+use crate::bus::{self, AppEvent};
+use crate::config::Strut;
+use crate::window::{get_window_rect, rects_intersect};
+
+/// Struts from the config, and window classes of docks to reserve space
+/// for wherever they are
+struct Reserved {
+    struts: Vec<Strut>,
+    dock_classes: Vec<String>,
+    /// Where those docks are, found on first use after refresh_docks
+    /// rather than on every work area lookup
+    docks: Option<Vec<RECT>>,
+}
+
+static RESERVED: Mutex<Reserved> = Mutex::new(Reserved {
+    struts: Vec::new(),
+    dock_classes: Vec::new(),
+    docks: None,
+});
+
+/// Keep `struts`, and the space docks of `dock_classes` take up, out of
+/// work areas from now on
+pub fn set_reserved(struts: Vec<Strut>, dock_classes: Vec<String>) {
+    if let Ok(mut reserved) = RESERVED.lock() {
+        *reserved = Reserved {
+            struts,
+            dock_classes,
+            docks: None,
+        };
+    }
+}
+
+/// Take up the config's struts and dock classes when it changes
+pub fn register() {
+    bus::subscribe(follow_struts);
+}
+
+fn follow_struts(event: &AppEvent) {
+    if !matches!(event, AppEvent::ConfigChanged) {
+        return;
+    }
+    if let Some(config) = bus::config() {
+        set_reserved(config.grid.struts, config.grid.dock_classes);
+    }
+}
+
+/// Look for the docks again on the next work area lookup, e.g. as the
+/// overlay opens, in case they've moved since
+pub fn refresh_docks() {
+    if let Ok(mut reserved) = RESERVED.lock() {
+        reserved.docks = None;
+    }
+}
+
+/// Screen rects of the visible windows of class `class`
+fn find_docks(class: &str) -> Vec<RECT> {
+    let class = HSTRING::from(class);
+    let mut docks = Vec::new();
+    let mut after: Option<HWND> = None;
+    while let Ok(hwnd) = unsafe { FindWindowExW(None, after, &class, None) } {
+        if unsafe { IsWindowVisible(hwnd) }.as_bool()
+            && let Some(rect) = get_window_rect(hwnd)
+        {
+            docks.push(rect);
+        }
+        after = Some(hwnd);
+    }
+    docks
+}
+
+/// The strut a dock at `dock` needs on a monitor with `bounds`: a strip
+/// along the edge it's nearest, reaching as far in as it does. Docks
+/// taller than wide sit on the left or right edge.
+fn dock_strut(dock: RECT, bounds: RECT) -> Strut {
+    let mut strut = Strut::default();
+    if dock.right - dock.left < dock.bottom - dock.top {
+        if dock.left - bounds.left <= bounds.right - dock.right {
+            strut.left = dock.right - bounds.left;
+        } else {
+            strut.right = bounds.right - dock.left;
+        }
+    } else if dock.top - bounds.top <= bounds.bottom - dock.bottom {
+        strut.top = dock.bottom - bounds.top;
+    } else {
+        strut.bottom = bounds.bottom - dock.top;
+    }
+    strut
+}
+
+/// `work_area` shrunk to leave every strut's space clear, measured from
+/// the edges of the monitor's `bounds`; unchanged if they'd leave nothing
+fn shrink(work_area: RECT, bounds: RECT, struts: &[Strut]) -> RECT {
+    let mut area = work_area;
+    for strut in struts {
+        area.left = area.left.max(bounds.left + strut.left);
+        area.top = area.top.max(bounds.top + strut.top);
+        area.right = area.right.min(bounds.right - strut.right);
+        area.bottom = area.bottom.min(bounds.bottom - strut.bottom);
+    }
+    if area.right <= area.left || area.bottom <= area.top {
+        return work_area;
+    }
+    area
+}
+
+/// Work area `work_area` of the monitor `device` with `bounds`, less the
+/// configured struts for it and the space docks on it take up
+pub fn reserve(device: Option<&str>, bounds: RECT, work_area: RECT) -> RECT {
+    let Ok(mut reserved) = RESERVED.lock() else {
+        return work_area;
+    };
+    let mut struts: Vec<Strut> = reserved
+        .struts
+        .iter()
+        .filter(|strut| match (&strut.device, device) {
+            (None, _) => true,
+            (Some(wanted), Some(device)) => wanted.eq_ignore_ascii_case(device),
+            (Some(_), None) => false,
+        })
+        .cloned()
+        .collect();
+    if reserved.docks.is_none() {
+        let docks = reserved
+            .dock_classes
+            .iter()
+            .flat_map(|class| find_docks(class))
+            .collect();
+        reserved.docks = Some(docks);
+    }
+    for &dock in reserved.docks.iter().flatten() {
+        if rects_intersect(dock, bounds) {
+            struts.push(dock_strut(dock, bounds));
+        }
+    }
+    shrink(work_area, bounds, &struts)
+}
+
+/// The strips of `work_area` that `reserved`, a rect within it, leaves
+/// out: full-width strips above and below, then the sides between them
+pub fn excluded_strips(work_area: RECT, reserved: RECT) -> Vec<RECT> {
+    let strips = [
+        RECT {
+            bottom: reserved.top,
+            ..work_area
+        },
+        RECT {
+            top: reserved.bottom,
+            ..work_area
+        },
+        RECT {
+            top: reserved.top,
+            right: reserved.left,
+            bottom: reserved.bottom,
+            ..work_area
+        },
+        RECT {
+            left: reserved.right,
+            top: reserved.top,
+            bottom: reserved.bottom,
+            ..work_area
+        },
+    ];
+    strips
+        .into_iter()
+        .filter(|strip| strip.right > strip.left && strip.bottom > strip.top)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn test_dock_strut() {
+        let bounds = rect(0, 0, 1920, 1080);
+        // Upright dock on the left edge
+        let strut = dock_strut(rect(0, 200, 64, 880), bounds);
+        assert_eq!((strut.left, strut.right), (64, 0));
+        // Bar floating a little above the bottom edge
+        let strut = dock_strut(rect(400, 980, 1520, 1060), bounds);
+        assert_eq!((strut.top, strut.bottom), (0, 100));
+        // On a second monitor to the right
+        let bounds = rect(1920, 0, 3840, 1080);
+        let strut = dock_strut(rect(3790, 100, 3840, 900), bounds);
+        assert_eq!(strut.right, 50);
+    }
+
+    #[test]
+    fn test_shrink() {
+        let bounds = rect(0, 0, 1920, 1080);
+        // Taskbar already takes the bottom 48 pixels
+        let work_area = rect(0, 0, 1920, 1032);
+        let struts = [
+            Strut {
+                left: 64,
+                ..Strut::default()
+            },
+            // Within the taskbar, so nothing more to take
+            Strut {
+                bottom: 40,
+                ..Strut::default()
+            },
+        ];
+        assert_eq!(shrink(work_area, bounds, &struts), rect(64, 0, 1920, 1032));
+        // Nothing left over
+        let struts = [Strut {
+            left: 1000,
+            right: 1000,
+            ..Strut::default()
+        }];
+        assert_eq!(shrink(work_area, bounds, &struts), work_area);
+    }
+
+    #[test]
+    fn test_excluded_strips() {
+        let work_area = rect(0, 0, 1920, 1032);
+        assert!(excluded_strips(work_area, work_area).is_empty());
+        // A dock on the left and a bar along the top
+        let strips = excluded_strips(work_area, rect(64, 30, 1920, 1032));
+        assert_eq!(strips, [rect(0, 0, 1920, 30), rect(0, 30, 64, 1032)]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use windows::core::{w, Interface, HSTRING};
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::Foundation::TypedEventHandler;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::System::Registry::{RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+use windows::UI::Notifications::{
+    ToastActivatedEventArgs, ToastNotification, ToastNotificationManager,
+};
+
+use crate::log::{error, warning};
+use crate::tray::notify_with_action;
+
+/// Asks the main window to run a prompt's answer; wparam is the prompt's
+/// id, lparam 1 for Yes and 0 for No
+pub const WM_TOAST_ANSWER: u32 = 0x8006; // WM_APP + 6
+
+/// Identifies tactile-win's toasts to Windows, which needs one registered
+/// before an unpackaged app can show them
+const APP_ID: &str = "andynu.TactileWin";
+
+/// Unanswered prompts kept for a late answer from the notification center
+const MAX_PENDING: usize = 8;
+
+/// A question shown as a toast, waiting for its answer
+struct Prompt {
+    id: usize,
+    on_yes: fn(),
+    /// Kept alive so its Activated handler stays registered
+    _toast: ToastNotification,
+}
+
+// Main window, which runs answers on the thread that owns the app state
+static TOAST_HWND: AtomicIsize = AtomicIsize::new(0);
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+static PENDING: Mutex<Vec<Prompt>> = Mutex::new(Vec::new());
+static REGISTER_APP_ID: Once = Once::new();
+
+/// Deliver answers through `hwnd`, which must pass WM_TOAST_ANSWER on to
+/// answer
+pub fn init(hwnd: HWND) {
+    TOAST_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
+}
+
+/// `text` with the characters XML gives meaning to escaped
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Toast content for prompt `id`, with Yes and No buttons whose arguments
+/// parse_arguments reads back
+fn toast_xml(id: usize, title: &str, question: &str) -> String {
+    format!(
+        concat!(
+            r#"<toast launch="open:{id}"><visual><binding template="ToastGeneric">"#,
+            r#"<text>{title}</text><text>{question}</text></binding></visual><actions>"#,
+            r#"<action content="Yes" arguments="yes:{id}" activationType="foreground"/>"#,
+            r#"<action content="No" arguments="no:{id}" activationType="foreground"/>"#,
+            r#"</actions></toast>"#
+        ),
+        id = id,
+        title = escape_xml(title),
+        question = escape_xml(question),
+    )
+}
+
+/// The prompt a button was for and whether it was Yes. Clicking the toast
+/// itself isn't an answer.
+fn parse_arguments(arguments: &str) -> Option<(usize, bool)> {
+    let (answer, id) = arguments.split_once(':')?;
+    let yes = match answer {
+        "yes" => true,
+        "no" => false,
+        _ => return None,
+    };
+    Some((id.parse().ok()?, yes))
+}
+
+/// Register APP_ID under the current user so toasts can be shown with it
+fn register_app_id() {
+    let name: Vec<u16> = "Tactile-Win".encode_utf16().chain(Some(0)).collect();
+    let key = HSTRING::from(format!(r"Software\Classes\AppUserModelId\{}", APP_ID));
+    let result = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            w!("DisplayName"),
+            REG_SZ.0,
+            Some(name.as_ptr() as *const _),
+            (name.len() * 2) as u32,
+        )
+    };
+    if result.is_err() {
+        warning!("Failed to register for toasts: {:?}", result);
+    }
+}
+
+fn show_toast(id: usize, title: &str, question: &str) -> windows::core::Result<ToastNotification> {
+    REGISTER_APP_ID.call_once(register_app_id);
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(toast_xml(id, title, question)))?;
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    // Runs on a thread pool thread; the answer is handed to the main window
+    toast.Activated(&TypedEventHandler::new(
+        |_, args: windows::core::Ref<windows::core::IInspectable>| {
+            let arguments = args.ok()?.cast::<ToastActivatedEventArgs>()?.Arguments()?;
+            if let Some((id, yes)) = parse_arguments(&arguments.to_string()) {
+                let hwnd = TOAST_HWND.load(Ordering::SeqCst);
+                unsafe {
+                    PostMessageW(
+                        Some(HWND(hwnd as *mut _)),
+                        WM_TOAST_ANSWER,
+                        WPARAM(id),
+                        LPARAM(yes as isize),
+                    )?;
+                }
+            }
+            Ok(())
+        },
+    ))?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)?;
+    Ok(toast)
+}
+
+/// Ask `question` in a toast with Yes and No buttons, running `on_yes` if
+/// Yes is clicked, rather than blocking with a message box. Where toasts
+/// can't be shown, a tray balloon asks instead, and clicking it is Yes.
+pub fn ask(title: &str, question: &str, on_yes: fn()) {
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let toast = match show_toast(id, title, question) {
+        Ok(toast) => toast,
+        Err(e) => {
+            warning!("Toast failed, asking from the tray instead: {}", e);
+            notify_with_action(title, &format!("{} Click for yes.", question), on_yes);
+            return;
+        }
+    };
+    if let Ok(mut pending) = PENDING.lock() {
+        if pending.len() == MAX_PENDING {
+            pending.remove(0);
+        }
+        pending.push(Prompt {
+            id,
+            on_yes,
+            _toast: toast,
+        });
+    }
+}
+
+/// Run the answer to the prompt a WM_TOAST_ANSWER is for, once
+pub fn answer(wparam: WPARAM, lparam: LPARAM) {
+    let prompt = PENDING.lock().ok().and_then(|mut pending| {
+        let index = pending.iter().position(|prompt| prompt.id == wparam.0)?;
+        Some(pending.remove(index))
+    });
+    match prompt {
+        Some(prompt) if lparam.0 != 0 => (prompt.on_yes)(),
+        Some(_) => {}
+        None => error!("Answer to an unknown prompt {}", wparam.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toast_xml_escapes_text() {
+        let xml = toast_xml(7, "Try a 3x2 grid?", "Most <tiles> span \"two\" & more");
+        assert!(xml.contains("<text>Most &lt;tiles&gt; span &quot;two&quot; &amp; more</text>"));
+        assert!(xml.contains(r#"arguments="yes:7""#));
+        assert!(xml.contains(r#"arguments="no:7""#));
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(parse_arguments("yes:7"), Some((7, true)));
+        assert_eq!(parse_arguments("no:12"), Some((12, false)));
+        assert_eq!(parse_arguments("open:7"), None);
+        assert_eq!(parse_arguments("yes:"), None);
+        assert_eq!(parse_arguments(""), None);
+    }
+}
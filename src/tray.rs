@@ -1,29 +1,100 @@
 use std::ptr;
-use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
 use std::sync::Mutex;
 use windows::core::{w, PCWSTR};
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Shell::{
-    Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+    ShellExecuteW, Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIM_ADD,
+    NIM_DELETE, NIM_MODIFY, NIN_BALLOONUSERCLICK, NOTIFYICONDATAW,
 };
 use windows::Win32::UI::WindowsAndMessaging::HICON;
 use windows::Win32::UI::WindowsAndMessaging::{
-    AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, GetCursorPos,
-    LoadImageW, PostQuitMessage, RegisterClassW, SetForegroundWindow, TrackPopupMenu,
-    IMAGE_ICON, LR_DEFAULTSIZE, LR_SHARED, MF_STRING, TPM_BOTTOMALIGN, TPM_LEFTALIGN,
-    WINDOW_EX_STYLE, WINDOW_STYLE, WM_COMMAND, WM_LBUTTONUP, WM_RBUTTONUP, WNDCLASSW,
+    AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyIcon, DestroyMenu,
+    GetCursorPos, LoadIconW, PostQuitMessage, RegisterClassW, SetForegroundWindow, SetTimer,
+    TrackPopupMenu, HMENU, IDI_APPLICATION, MF_CHECKED, MF_POPUP, MF_SEPARATOR, MF_STRING,
+    MF_UNCHECKED, SW_SHOWNORMAL, TPM_BOTTOMALIGN, TPM_LEFTALIGN, WINDOW_EX_STYLE, WINDOW_STYLE,
+    WM_COMMAND, WM_ENDSESSION, WM_LBUTTONUP, WM_QUERYENDSESSION, WM_RBUTTONUP, WM_TIMER, WNDCLASSW,
 };
 
+use crate::bus::{self, AppEvent};
+use crate::icon::{tray_icon, IconState};
+use crate::keyboard::grid_hotkey;
+use crate::log::{error, warning};
+use crate::update::{check_in_background, take_result, UpdateCheck, WM_UPDATE_CHECKED};
+
 const TRAY_CLASS_NAME: PCWSTR = w!("TactileWinTrayClass");
 const WM_TRAYICON: u32 = 0x8000; // WM_APP
 
 const IDM_QUIT: u16 = 1001;
 const IDM_ABOUT: u16 = 1002;
 const IDM_SETTINGS: u16 = 1003;
+const IDM_SHOW_GRID: u16 = 1004;
+const IDM_CHECK_UPDATES: u16 = 1005;
+const IDM_EQUALIZE: u16 = 1006;
+const IDM_RESTORE: u16 = 1007;
+const IDM_PRACTICE: u16 = 1008;
+const IDM_EXPORT_HISTORY: u16 = 1009;
+const IDM_PROFILE_AUTO: u16 = 1010;
+const IDM_SETUP_MONITORS: u16 = 1011;
+const IDM_PREVIEW_EQUALIZE: u16 = 1012;
+const IDM_DIAGNOSTICS: u16 = 1013;
+const IDM_IDENTIFY_MONITORS: u16 = 1014;
+const IDM_SHOW_ALL_HIDDEN: u16 = 1015;
+const IDM_RESTORE_SETTINGS: u16 = 1016;
+const IDM_SAVE_SNAPSHOT: u16 = 1017;
+// Profile n is chosen with IDM_PROFILE_BASE + n
+const IDM_PROFILE_BASE: u16 = 1100;
+// Hidden window n is shown again with IDM_HIDDEN_BASE + n
+const IDM_HIDDEN_BASE: u16 = 1200;
+
+const UPDATE_TIMER_ID: usize = 1;
 
 static TRAY_HWND: AtomicIsize = AtomicIsize::new(0);
+// Icon drawn for the current state, destroyed when replaced
+static TRAY_ICON: AtomicIsize = AtomicIsize::new(0);
+static TRAY_STATE: Mutex<IconState> = Mutex::new(IconState::Active);
+// Whether the icon wears the overlay-open badge
+static OVERLAY_OPEN: AtomicBool = AtomicBool::new(false);
 static SHOW_SETTINGS_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static SHOW_GRID_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static EQUALIZE_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static PREVIEW_EQUALIZE_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static RESTORE_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static PRACTICE_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static EXPORT_HISTORY_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static SAVE_SNAPSHOT_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static SETUP_MONITORS_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static IDENTIFY_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static RESTORE_SETTINGS_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+static DIAGNOSTICS_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+// Run when Windows is logging off or shutting down
+static END_SESSION_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+// Called with the profile picked from the Profile submenu, or None for Automatic
+type ProfileCallback = fn(Option<usize>);
+static PROFILE_CALLBACK: Mutex<Option<ProfileCallback>> = Mutex::new(None);
+static PROFILE_MENU: Mutex<ProfileMenu> = Mutex::new(ProfileMenu {
+    names: Vec::new(),
+    active: None,
+    automatic: true,
+});
+// Called with the hidden window picked to show again, or None for Show All
+type HiddenCallback = fn(Option<usize>);
+static HIDDEN_CALLBACK: Mutex<Option<HiddenCallback>> = Mutex::new(None);
+// Titles of the windows listed in the Hidden Windows submenu
+static HIDDEN_MENU: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// The release page of the "update available" balloon showing, opened if it's clicked
+static PENDING_DOWNLOAD: Mutex<Option<String>> = Mutex::new(None);
+// Run when the balloon shown by notify_with_action is clicked
+static BALLOON_CALLBACK: Mutex<Option<fn()>> = Mutex::new(None);
+
+/// What the Profile submenu lists and checks
+struct ProfileMenu {
+    names: Vec<String>,
+    active: Option<usize>,
+    /// Whether the active profile follows the monitors connected
+    automatic: bool,
+}
 
 pub struct TrayIcon {
     hwnd: HWND,
@@ -43,6 +114,23 @@ unsafe extern "system" fn tray_window_proc(
                     x if x == WM_RBUTTONUP || x == WM_LBUTTONUP => {
                         show_context_menu(hwnd);
                     }
+                    NIN_BALLOONUSERCLICK => {
+                        let download = PENDING_DOWNLOAD
+                            .lock()
+                            .ok()
+                            .and_then(|mut guard| guard.take());
+                        if let Some(url) = download {
+                            open_release_page(&url);
+                        } else {
+                            let callback = BALLOON_CALLBACK
+                                .lock()
+                                .ok()
+                                .and_then(|mut guard| guard.take());
+                            if let Some(callback) = callback {
+                                callback();
+                            }
+                        }
+                    }
                     _ => {}
                 }
                 LRESULT(0)
@@ -63,6 +151,140 @@ unsafe extern "system" fn tray_window_proc(
                             }
                         }
                     }
+                    IDM_CHECK_UPDATES => {
+                        check_in_background(hwnd, true);
+                    }
+                    IDM_SHOW_GRID => {
+                        let callback = SHOW_GRID_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_EQUALIZE => {
+                        let callback = EQUALIZE_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_PREVIEW_EQUALIZE => {
+                        let callback = PREVIEW_EQUALIZE_CALLBACK
+                            .lock()
+                            .ok()
+                            .and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_RESTORE => {
+                        let callback = RESTORE_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_PRACTICE => {
+                        let callback = PRACTICE_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_EXPORT_HISTORY => {
+                        let callback = EXPORT_HISTORY_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_SAVE_SNAPSHOT => {
+                        let callback = SAVE_SNAPSHOT_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_SETUP_MONITORS => {
+                        let callback = SETUP_MONITORS_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_RESTORE_SETTINGS => {
+                        let callback = RESTORE_SETTINGS_CALLBACK
+                            .lock()
+                            .ok()
+                            .and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_IDENTIFY_MONITORS => {
+                        let callback = IDENTIFY_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_DIAGNOSTICS => {
+                        let callback = DIAGNOSTICS_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback();
+                        }
+                    }
+                    IDM_PROFILE_AUTO | IDM_PROFILE_BASE..IDM_HIDDEN_BASE => {
+                        let profile = cmd
+                            .checked_sub(IDM_PROFILE_BASE)
+                            .map(|index| index as usize);
+                        let callback = PROFILE_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback(profile);
+                        }
+                    }
+                    IDM_SHOW_ALL_HIDDEN | IDM_HIDDEN_BASE.. => {
+                        let window = cmd.checked_sub(IDM_HIDDEN_BASE).map(|index| index as usize);
+                        let callback = HIDDEN_CALLBACK.lock().ok().and_then(|guard| *guard);
+                        if let Some(callback) = callback {
+                            callback(window);
+                        }
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            // Only top-level windows hear about logoff and shutdown; the
+            // main window is message-only, so this one speaks for it
+            WM_QUERYENDSESSION => LRESULT(1),
+            WM_ENDSESSION if wparam.0 != 0 => {
+                // The process can be ended as soon as this returns
+                let callback = END_SESSION_CALLBACK.lock().ok().and_then(|guard| *guard);
+                if let Some(callback) = callback {
+                    callback();
+                }
+                delete_icon(hwnd);
+                LRESULT(0)
+            }
+            WM_TIMER if wparam.0 == UPDATE_TIMER_ID => {
+                check_in_background(hwnd, false);
+                LRESULT(0)
+            }
+            WM_UPDATE_CHECKED => {
+                let manual = wparam.0 != 0;
+                match take_result() {
+                    Some(UpdateCheck::Available(release)) => {
+                        if let Ok(mut guard) = PENDING_DOWNLOAD.lock() {
+                            *guard = Some(release.html_url);
+                        }
+                        show_notification(
+                            hwnd,
+                            "Update available",
+                            &format!(
+                                "Tactile-Win {} is available. Click to download.",
+                                release.tag_name
+                            ),
+                        );
+                    }
+                    // Background checks stay quiet unless there's something new
+                    Some(UpdateCheck::UpToDate) if manual => {
+                        show_notification(hwnd, "No updates", "Tactile-Win is up to date.");
+                    }
+                    Some(UpdateCheck::Failed) if manual => {
+                        show_notification(hwnd, "Update check failed", "Could not reach GitHub.");
+                    }
                     _ => {}
                 }
                 LRESULT(0)
@@ -76,7 +298,71 @@ unsafe fn show_context_menu(hwnd: HWND) {
     unsafe {
         let menu = CreatePopupMenu().unwrap();
 
+        let _ = AppendMenuW(menu, MF_STRING, IDM_SHOW_GRID as usize, w!("Show Grid"));
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_EQUALIZE as usize,
+            w!("Equalize Windows"),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_PREVIEW_EQUALIZE as usize,
+            w!("Preview Equalize"),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_RESTORE as usize,
+            w!("Restore Window Sizes"),
+        );
+        let _ = AppendMenuW(menu, MF_STRING, IDM_PRACTICE as usize, w!("Practice..."));
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_EXPORT_HISTORY as usize,
+            w!("Export Placement History"),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_SAVE_SNAPSHOT as usize,
+            w!("Save Grid Snapshot"),
+        );
+        append_profile_menu(menu);
+        append_hidden_menu(menu);
         let _ = AppendMenuW(menu, MF_STRING, IDM_SETTINGS as usize, w!("Settings..."));
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_RESTORE_SETTINGS as usize,
+            w!("Restore Previous Settings"),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_SETUP_MONITORS as usize,
+            w!("Set Up Monitors..."),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_IDENTIFY_MONITORS as usize,
+            w!("Identify Monitors"),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_DIAGNOSTICS as usize,
+            w!("Run Diagnostics"),
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            IDM_CHECK_UPDATES as usize,
+            w!("Check for Updates"),
+        );
         let _ = AppendMenuW(menu, MF_STRING, IDM_ABOUT as usize, w!("About Tactile-Win"));
         let _ = AppendMenuW(menu, MF_STRING, IDM_QUIT as usize, w!("Quit"));
 
@@ -92,6 +378,277 @@ unsafe fn show_context_menu(hwnd: HWND) {
     }
 }
 
+/// Add the Profile submenu, if the config names any profiles
+unsafe fn append_profile_menu(menu: HMENU) {
+    unsafe {
+        let Ok(profiles) = PROFILE_MENU.lock() else {
+            return;
+        };
+        if profiles.names.is_empty() {
+            return;
+        }
+        let Ok(submenu) = CreatePopupMenu() else {
+            return;
+        };
+        let checked = |on: bool| if on { MF_CHECKED } else { MF_UNCHECKED };
+
+        let _ = AppendMenuW(
+            submenu,
+            MF_STRING | checked(profiles.automatic),
+            IDM_PROFILE_AUTO as usize,
+            w!("Automatic"),
+        );
+        let _ = AppendMenuW(submenu, MF_SEPARATOR, 0, PCWSTR::null());
+        for (index, name) in profiles.names.iter().enumerate() {
+            let label: Vec<u16> = name.encode_utf16().chain(Some(0)).collect();
+            let _ = AppendMenuW(
+                submenu,
+                MF_STRING | checked(profiles.active == Some(index)),
+                IDM_PROFILE_BASE as usize + index,
+                PCWSTR(label.as_ptr()),
+            );
+        }
+        // The menu owns and destroys the submenu
+        let _ = AppendMenuW(menu, MF_POPUP, submenu.0 as usize, w!("Profile"));
+    }
+}
+
+/// Add the Hidden Windows submenu, if any windows are hidden
+unsafe fn append_hidden_menu(menu: HMENU) {
+    unsafe {
+        let Ok(titles) = HIDDEN_MENU.lock() else {
+            return;
+        };
+        if titles.is_empty() {
+            return;
+        }
+        let Ok(submenu) = CreatePopupMenu() else {
+            return;
+        };
+        for (index, title) in titles.iter().enumerate() {
+            let label: Vec<u16> = title.encode_utf16().chain(Some(0)).collect();
+            let _ = AppendMenuW(
+                submenu,
+                MF_STRING,
+                IDM_HIDDEN_BASE as usize + index,
+                PCWSTR(label.as_ptr()),
+            );
+        }
+        let _ = AppendMenuW(submenu, MF_SEPARATOR, 0, PCWSTR::null());
+        let _ = AppendMenuW(
+            submenu,
+            MF_STRING,
+            IDM_SHOW_ALL_HIDDEN as usize,
+            w!("Show All"),
+        );
+        let _ = AppendMenuW(menu, MF_POPUP, submenu.0 as usize, w!("Hidden Windows"));
+    }
+}
+
+/// Copy a string into a fixed-size, NUL-terminated UTF-16 buffer
+fn copy_wide(dest: &mut [u16], text: &str) {
+    let wide: Vec<u16> = text.encode_utf16().take(dest.len() - 1).collect();
+    dest[..wide.len()].copy_from_slice(&wide);
+    dest[wide.len()] = 0;
+}
+
+/// Show a balloon notification from the tray icon
+unsafe fn show_notification(hwnd: HWND, title: &str, text: &str) {
+    unsafe {
+        let mut nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            uFlags: NIF_INFO,
+            dwInfoFlags: NIIF_INFO,
+            ..Default::default()
+        };
+        copy_wide(&mut nid.szInfoTitle, title);
+        copy_wide(&mut nid.szInfo, text);
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    }
+}
+
+fn open_release_page(url: &str) {
+    let url: Vec<u16> = url.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        ShellExecuteW(
+            None,
+            w!("open"),
+            PCWSTR(url.as_ptr()),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        );
+    }
+}
+
+/// The tray icon for `state`, drawn at runtime so it needs no resources.
+/// If drawing fails, the stock application icon, so the tray still
+/// appears.
+unsafe fn load_icon(state: IconState) -> HICON {
+    match tray_icon(state, OVERLAY_OPEN.load(Ordering::SeqCst)) {
+        Ok(icon) => {
+            // The shell keeps its own copy of the icon it was given
+            destroy_drawn_icon();
+            TRAY_ICON.store(icon.0 as isize, Ordering::SeqCst);
+            icon
+        }
+        Err(e) => {
+            warning!("Failed to draw the tray icon, using the stock icon: {}", e);
+            unsafe { LoadIconW(None, IDI_APPLICATION) }.unwrap_or_default()
+        }
+    }
+}
+
+fn destroy_drawn_icon() {
+    let icon = TRAY_ICON.swap(0, Ordering::SeqCst);
+    if icon != 0 {
+        unsafe {
+            let _ = DestroyIcon(HICON(icon as *mut _));
+        }
+    }
+}
+
+fn current_state() -> IconState {
+    TRAY_STATE.lock().map_or(IconState::Active, |state| *state)
+}
+
+fn tooltip(state: IconState) -> String {
+    match state.label() {
+        Some(label) => format!("Tactile-Win ({})", label),
+        None => format!("Tactile-Win ({})", grid_hotkey()),
+    }
+}
+
+/// Add the notification-area icon for the tray window
+unsafe fn add_icon(hwnd: HWND) -> windows::core::Result<()> {
+    unsafe {
+        let state = current_state();
+        let icon = load_icon(state);
+
+        let mut nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            uFlags: NIF_ICON | NIF_MESSAGE | NIF_TIP,
+            uCallbackMessage: WM_TRAYICON,
+            hIcon: icon,
+            ..Default::default()
+        };
+
+        copy_wide(&mut nid.szTip, &tooltip(state));
+
+        if !Shell_NotifyIconW(NIM_ADD, &nid).as_bool() {
+            return Err(windows::core::Error::from_win32());
+        }
+        Ok(())
+    }
+}
+
+/// Show a balloon notification from the tray icon, if there is one
+pub fn notify(title: &str, text: &str) {
+    if let Ok(mut guard) = BALLOON_CALLBACK.lock() {
+        *guard = None;
+    }
+    let hwnd = HWND(TRAY_HWND.load(Ordering::SeqCst) as *mut _);
+    if !hwnd.0.is_null() {
+        unsafe { show_notification(hwnd, title, text) }
+    }
+}
+
+/// Show a balloon notification that runs `on_click` when clicked
+pub fn notify_with_action(title: &str, text: &str, on_click: fn()) {
+    notify(title, text);
+    if let Ok(mut guard) = BALLOON_CALLBACK.lock() {
+        *guard = Some(on_click);
+    }
+}
+
+/// Take the icon out of the notification area, so none is left behind
+unsafe fn delete_icon(hwnd: HWND) {
+    unsafe {
+        let nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            ..Default::default()
+        };
+
+        let _ = Shell_NotifyIconW(NIM_DELETE, &nid);
+    }
+}
+
+/// Re-add the tray icon if the shell lost it, e.g. after sleep or an RDP
+/// reconnect. A no-op if the icon is still there.
+pub fn restore_tray_icon() {
+    let hwnd = HWND(TRAY_HWND.load(Ordering::SeqCst) as *mut _);
+    if hwnd.0.is_null() {
+        return;
+    }
+    unsafe {
+        let nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            ..Default::default()
+        };
+        // Modifying with no flags only succeeds if the icon still exists
+        if Shell_NotifyIconW(NIM_MODIFY, &nid).as_bool() {
+            return;
+        }
+        if let Err(e) = add_icon(hwnd) {
+            error!("Failed to restore tray icon: {}", e);
+        }
+    }
+}
+
+/// Show `state` in the tray icon's color and tooltip
+pub fn set_tray_state(state: IconState) {
+    if let Ok(mut current) = TRAY_STATE.lock() {
+        if *current == state {
+            return;
+        }
+        *current = state;
+    }
+    redraw_icon(state);
+}
+
+/// Badge the tray icon while the overlay is open
+pub fn register() {
+    bus::subscribe(follow_overlay);
+}
+
+fn follow_overlay(event: &AppEvent) {
+    let open = match event {
+        AppEvent::OverlayShown { .. } => true,
+        AppEvent::OverlayHidden => false,
+        _ => return,
+    };
+    if OVERLAY_OPEN.swap(open, Ordering::SeqCst) != open {
+        redraw_icon(current_state());
+    }
+}
+
+fn redraw_icon(state: IconState) {
+    let hwnd = HWND(TRAY_HWND.load(Ordering::SeqCst) as *mut _);
+    if hwnd.0.is_null() {
+        return;
+    }
+    unsafe {
+        let mut nid = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: 1,
+            uFlags: NIF_ICON | NIF_TIP,
+            hIcon: load_icon(state),
+            ..Default::default()
+        };
+        copy_wide(&mut nid.szTip, &tooltip(state));
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    }
+}
+
 impl TrayIcon {
     pub fn new() -> windows::core::Result<Self> {
         unsafe {
@@ -123,52 +680,24 @@ impl TrayIcon {
 
             TRAY_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
 
-            // Load custom icon from resources (ID 1)
-            let icon_handle = LoadImageW(
-                Some(hinstance.into()),
-                PCWSTR(1 as *const u16),  // Resource ID 1
-                IMAGE_ICON,
-                0,
-                0,
-                LR_DEFAULTSIZE | LR_SHARED,
-            )?;
-            let icon = HICON(icon_handle.0);
-
-            let mut nid = NOTIFYICONDATAW {
-                cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
-                hWnd: hwnd,
-                uID: 1,
-                uFlags: NIF_ICON | NIF_MESSAGE | NIF_TIP,
-                uCallbackMessage: WM_TRAYICON,
-                hIcon: icon,
-                ..Default::default()
-            };
-
-            // Set tooltip
-            let tip = "Tactile-Win (Ctrl+Shift+G)";
-            let tip_wide: Vec<u16> = tip.encode_utf16().chain(std::iter::once(0)).collect();
-            nid.szTip[..tip_wide.len().min(128)]
-                .copy_from_slice(&tip_wide[..tip_wide.len().min(128)]);
-
-            if !Shell_NotifyIconW(NIM_ADD, &nid).as_bool() {
-                return Err(windows::core::Error::from_win32());
-            }
+            add_icon(hwnd)?;
 
             Ok(Self { hwnd })
         }
     }
 
-    pub fn remove(&self) {
+    /// Check for updates now and then every `interval_hours`
+    pub fn start_update_checks(&self, interval_hours: u32) {
+        let interval_ms = interval_hours.max(1).saturating_mul(60 * 60 * 1000);
         unsafe {
-            let nid = NOTIFYICONDATAW {
-                cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
-                hWnd: self.hwnd,
-                uID: 1,
-                ..Default::default()
-            };
-
-            let _ = Shell_NotifyIconW(NIM_DELETE, &nid);
+            SetTimer(Some(self.hwnd), UPDATE_TIMER_ID, interval_ms, None);
         }
+        check_in_background(self.hwnd, false);
+    }
+
+    pub fn remove(&self) {
+        unsafe { delete_icon(self.hwnd) }
+        destroy_drawn_icon();
     }
 }
 
@@ -183,3 +712,106 @@ pub fn set_settings_callback(callback: fn()) {
         *guard = Some(callback);
     }
 }
+
+pub fn set_show_grid_callback(callback: fn()) {
+    if let Ok(mut guard) = SHOW_GRID_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_equalize_callback(callback: fn()) {
+    if let Ok(mut guard) = EQUALIZE_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_restore_callback(callback: fn()) {
+    if let Ok(mut guard) = RESTORE_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_practice_callback(callback: fn()) {
+    if let Ok(mut guard) = PRACTICE_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_preview_equalize_callback(callback: fn()) {
+    if let Ok(mut guard) = PREVIEW_EQUALIZE_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_export_history_callback(callback: fn()) {
+    if let Ok(mut guard) = EXPORT_HISTORY_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_save_snapshot_callback(callback: fn()) {
+    if let Ok(mut guard) = SAVE_SNAPSHOT_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_setup_monitors_callback(callback: fn()) {
+    if let Ok(mut guard) = SETUP_MONITORS_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_restore_settings_callback(callback: fn()) {
+    if let Ok(mut guard) = RESTORE_SETTINGS_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_identify_callback(callback: fn()) {
+    if let Ok(mut guard) = IDENTIFY_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_diagnostics_callback(callback: fn()) {
+    if let Ok(mut guard) = DIAGNOSTICS_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_end_session_callback(callback: fn()) {
+    if let Ok(mut guard) = END_SESSION_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_profile_callback(callback: ProfileCallback) {
+    if let Ok(mut guard) = PROFILE_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+pub fn set_hidden_callback(callback: HiddenCallback) {
+    if let Ok(mut guard) = HIDDEN_CALLBACK.lock() {
+        *guard = Some(callback);
+    }
+}
+
+/// List hidden windows by `titles` in the Hidden Windows submenu
+pub fn set_hidden_menu(titles: Vec<String>) {
+    if let Ok(mut guard) = HIDDEN_MENU.lock() {
+        *guard = titles;
+    }
+}
+
+/// List profiles `names` in the Profile submenu, checking `active`, or
+/// Automatic when `automatic` is set
+pub fn set_profile_menu(names: Vec<String>, active: Option<usize>, automatic: bool) {
+    if let Ok(mut guard) = PROFILE_MENU.lock() {
+        *guard = ProfileMenu {
+            names,
+            active,
+            automatic,
+        };
+    }
+}
@@ -0,0 +1,209 @@
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::thread;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Networking::WinHttp::{
+    WinHttpCloseHandle, WinHttpConnect, WinHttpOpen, WinHttpOpenRequest, WinHttpQueryHeaders,
+    WinHttpReadData, WinHttpReceiveResponse, WinHttpSendRequest, INTERNET_DEFAULT_HTTPS_PORT,
+    WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE, WINHTTP_QUERY_FLAG_NUMBER,
+    WINHTTP_QUERY_STATUS_CODE,
+};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+const API_HOST: PCWSTR = w!("api.github.com");
+const LATEST_RELEASE_PATH: PCWSTR = w!("/repos/andynu/tactile-win/releases/latest");
+
+/// Posted to the notify window when a background check finishes;
+/// WPARAM is 1 for checks the user asked for
+pub const WM_UPDATE_CHECKED: u32 = 0x8001; // WM_APP + 1
+
+/// The fields of the GitHub API's latest release that the check uses
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    /// The release's page, where its downloads are
+    pub html_url: String,
+}
+
+/// Outcome of a release check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateCheck {
+    Available(Release),
+    UpToDate,
+    Failed,
+}
+
+static LAST_RESULT: Mutex<Option<UpdateCheck>> = Mutex::new(None);
+
+/// Parse "1.2.3" or "v1.2.3", ignoring any pre-release or build suffix
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+/// True when `latest` is a newer version than `current`
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// GET the latest release JSON from the GitHub API
+fn fetch_latest_release() -> Option<String> {
+    unsafe {
+        let session = WinHttpOpen(
+            w!("tactile-win"),
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            0,
+        );
+        if session.is_null() {
+            return None;
+        }
+        let connect = WinHttpConnect(session, API_HOST, INTERNET_DEFAULT_HTTPS_PORT, 0);
+        let request = if connect.is_null() {
+            std::ptr::null_mut()
+        } else {
+            WinHttpOpenRequest(
+                connect,
+                w!("GET"),
+                LATEST_RELEASE_PATH,
+                PCWSTR::null(),
+                PCWSTR::null(),
+                std::ptr::null(),
+                WINHTTP_FLAG_SECURE,
+            )
+        };
+
+        let body = if request.is_null() {
+            None
+        } else {
+            read_response(request)
+        };
+
+        for handle in [request, connect, session] {
+            if !handle.is_null() {
+                let _ = WinHttpCloseHandle(handle);
+            }
+        }
+        body
+    }
+}
+
+unsafe fn read_response(request: *mut core::ffi::c_void) -> Option<String> {
+    unsafe {
+        let headers: Vec<u16> = "Accept: application/vnd.github+json\r\n"
+            .encode_utf16()
+            .collect();
+        WinHttpSendRequest(request, Some(&headers), None, 0, 0, 0).ok()?;
+        WinHttpReceiveResponse(request, std::ptr::null_mut()).ok()?;
+
+        let mut status = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        WinHttpQueryHeaders(
+            request,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some(&mut status as *mut _ as *mut _),
+            &mut size,
+            std::ptr::null_mut(),
+        )
+        .ok()?;
+        if status != 200 {
+            return None;
+        }
+
+        let mut body = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let mut read = 0u32;
+            WinHttpReadData(
+                request,
+                chunk.as_mut_ptr() as *mut _,
+                chunk.len() as u32,
+                &mut read,
+            )
+            .ok()?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..read as usize]);
+        }
+        String::from_utf8(body).ok()
+    }
+}
+
+fn check_now() -> UpdateCheck {
+    let Some(release) =
+        fetch_latest_release().and_then(|body| serde_json::from_str::<Release>(&body).ok())
+    else {
+        return UpdateCheck::Failed;
+    };
+    if is_newer(&release.tag_name, env!("CARGO_PKG_VERSION")) {
+        UpdateCheck::Available(release)
+    } else {
+        UpdateCheck::UpToDate
+    }
+}
+
+/// Check for a newer release on a background thread, then post
+/// WM_UPDATE_CHECKED to `notify_hwnd`
+pub fn check_in_background(notify_hwnd: HWND, manual: bool) {
+    // HWND isn't Send; pass the raw value across
+    let notify = notify_hwnd.0 as isize;
+    thread::spawn(move || {
+        let result = check_now();
+        if let Ok(mut guard) = LAST_RESULT.lock() {
+            *guard = Some(result);
+        }
+        unsafe {
+            let _ = PostMessageW(
+                Some(HWND(notify as *mut _)),
+                WM_UPDATE_CHECKED,
+                WPARAM(manual as usize),
+                LPARAM(0),
+            );
+        }
+    });
+}
+
+/// Result of the most recent background check, if not yet taken
+pub fn take_result() -> Option<UpdateCheck> {
+    LAST_RESULT.lock().ok().and_then(|mut guard| guard.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_comparison() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0-beta", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn test_release() {
+        let body = r#"{"url": "https://api.github.com/x", "tag_name" : "v0.3.1", "id": 5,
+            "name": "Say \"hi\"", "html_url": "https://github.com/andynu/tactile-win/releases/v0.3.1"}"#;
+        let release: Release = serde_json::from_str(body).unwrap();
+        assert_eq!(release.tag_name, "v0.3.1");
+        assert_eq!(
+            release.html_url,
+            "https://github.com/andynu/tactile-win/releases/v0.3.1"
+        );
+        assert!(serde_json::from_str::<Release>(r#"{"tag_name": "v0.3.1"}"#).is_err());
+    }
+}
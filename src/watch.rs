@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{HWND, LPARAM, WAIT_OBJECT_0, WPARAM};
+use windows::Win32::Storage::FileSystem::{
+    FindCloseChangeNotification, FindFirstChangeNotificationW, FindNextChangeNotification,
+    FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+};
+use windows::Win32::System::Threading::{WaitForSingleObject, INFINITE};
+use windows::Win32::UI::WindowsAndMessaging::PostMessageW;
+
+use crate::log::{info, warning};
+
+/// Posted to the main window when the config file was written, renamed
+/// into place or deleted
+pub const WM_CONFIG_FILE_CHANGED: u32 = 0x8008; // WM_APP + 8
+
+/// When `path` was last written, or None if it isn't there
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Post WM_CONFIG_FILE_CHANGED to `hwnd` whenever the file at `path`
+/// changes, from a thread of its own. Its folder is watched, since
+/// editors and Config::save replace the file rather than write into it;
+/// changes to the folder's other files are ignored.
+pub fn watch_config(path: PathBuf, hwnd: HWND) {
+    // A bare file name is in the working directory
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let notify = hwnd.0 as isize;
+    thread::spawn(move || {
+        let changes = FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_LAST_WRITE;
+        let handle = match unsafe {
+            FindFirstChangeNotificationW(&HSTRING::from(dir.as_os_str()), false, changes)
+        } {
+            Ok(handle) => handle,
+            Err(e) => {
+                warning!("Can't watch {} for config changes: {}", dir.display(), e);
+                return;
+            }
+        };
+        info!("Watching {} for changes", path.display());
+        let mut last = modified(&path);
+        while unsafe { WaitForSingleObject(handle, INFINITE) } == WAIT_OBJECT_0 {
+            let now = modified(&path);
+            if now != last {
+                last = now;
+                unsafe {
+                    let _ = PostMessageW(
+                        Some(HWND(notify as *mut _)),
+                        WM_CONFIG_FILE_CHANGED,
+                        WPARAM(0),
+                        LPARAM(0),
+                    );
+                }
+            }
+            if let Err(e) = unsafe { FindNextChangeNotification(handle) } {
+                warning!("Stopped watching the config file: {}", e);
+                break;
+            }
+        }
+        unsafe {
+            let _ = FindCloseChangeNotification(handle);
+        }
+    });
+}
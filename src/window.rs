@@ -1,15 +1,45 @@
 use std::cell::RefCell;
-use windows::Win32::Foundation::{HWND, LPARAM, RECT};
+use std::collections::VecDeque;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+use windows::core::{w, HSTRING, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, ERROR_TIMEOUT, HANDLE, HWND, LPARAM, POINT, RECT};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
 use windows::Win32::Graphics::Gdi::{
-    EnumDisplayMonitors, GetMonitorInfoW, MonitorFromWindow, HDC, HMONITOR, MONITORINFO,
-    MONITOR_DEFAULTTONEAREST,
+    EnumDisplayDevicesW, EnumDisplayMonitors, GetMonitorInfoW, MonitorFromPoint, MonitorFromWindow,
+    DISPLAY_DEVICEW, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST,
 };
+use windows::Win32::Storage::FileSystem::{
+    GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW,
+};
+use windows::Win32::System::Threading::{
+    GetProcessTimes, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use windows::Win32::UI::Input::KeyboardAndMouse::IsWindowEnabled;
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetForegroundWindow, GetWindowPlacement, SetWindowPlacement, SetWindowPos, HWND_TOP,
-    SET_WINDOW_POS_FLAGS, SHOW_WINDOW_CMD, SWP_NOACTIVATE, SWP_NOZORDER, SW_MAXIMIZE, SW_MINIMIZE,
-    SW_RESTORE, WINDOWPLACEMENT,
+    GetAncestor, GetClassNameW, GetCursorPos, GetForegroundWindow, GetGUIThreadInfo, GetTopWindow,
+    GetWindow, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId, IsHungAppWindow, IsIconic,
+    IsWindow, IsZoomed, SetForegroundWindow, SetWindowPos, ShowWindow, ShowWindowAsync,
+    EDD_GET_DEVICE_INTERFACE_NAME, GA_ROOTOWNER, GUITHREADINFO, GUI_INMENUMODE, GUI_INMOVESIZE,
+    GUI_POPUPMENUMODE, GUI_SYSTEMMENUMODE, GW_HWNDNEXT, HWND_TOP, SET_WINDOW_POS_FLAGS,
+    SWP_ASYNCWINDOWPOS, SWP_NOACTIVATE, SWP_NOZORDER, SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE,
+    SW_RESTORE, SW_SHOW,
 };
 
+use crate::config::AfterMove;
+use crate::enumwin::{alt_tab_rejection, list_windows, Win32Windows, WindowEntry, WindowFilter};
+use crate::log::{info, window_title};
+use crate::practice::is_practice_window;
+use crate::rules::WindowInfo;
+use crate::struts::{excluded_strips, reserve};
+
+// Foreground checks before falling back to walking the z-order
+const FOREGROUND_ATTEMPTS: u32 = 3;
+const FOREGROUND_RETRY_DELAY: Duration = Duration::from_millis(25);
+
 pub fn get_foreground_window() -> Option<HWND> {
     unsafe {
         let hwnd = GetForegroundWindow();
@@ -21,28 +51,407 @@ pub fn get_foreground_window() -> Option<HWND> {
     }
 }
 
-pub fn get_work_area(hwnd: HWND) -> Option<RECT> {
+/// Shell surfaces that show up as top-level windows but are never tiled
+const SHELL_CLASSES: &[&str] = &[
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    "NotifyIconOverflowWindow",
+    "TopLevelWindowForOverflowXamlIsland",
+    "Progman",
+    "WorkerW",
+    "Windows.UI.Core.CoreWindow",
+    "XamlExplorerHostIslandWindow",
+];
+
+pub fn is_shell_class(class_name: &str) -> bool {
+    SHELL_CLASSES.contains(&class_name)
+}
+
+pub fn get_class_name(hwnd: HWND) -> String {
+    unsafe {
+        let mut buf = [0u16; 256];
+        let len = GetClassNameW(hwnd, &mut buf);
+        String::from_utf16_lossy(&buf[..len.max(0) as usize])
+    }
+}
+
+pub fn get_window_title(hwnd: HWND) -> String {
+    unsafe {
+        let mut buf = [0u16; 512];
+        let len = GetWindowTextW(hwnd, &mut buf);
+        String::from_utf16_lossy(&buf[..len.max(0) as usize])
+    }
+}
+
+/// Executable details of a process, for rules, statistics and pickers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessDetails {
+    pub pid: u32,
+    /// Full path of the executable
+    pub path: String,
+    /// The executable's FileDescription, e.g. "Notepad", or its file stem
+    /// if it has none
+    pub description: String,
+}
+
+impl ProcessDetails {
+    /// Executable file name, e.g. "notepad.exe"
+    pub fn exe_name(&self) -> &str {
+        exe_name(&self.path)
+    }
+}
+
+fn exe_name(path: &str) -> &str {
+    path.rsplit('\\').next().unwrap_or_default()
+}
+
+/// Most processes cached at once; past it, the one used longest ago is
+/// dropped
+const MAX_CACHED_PROCESSES: usize = 64;
+
+/// Process details keyed by PID. A PID can be reused once its process
+/// exits, so entries also record the process's start time.
+#[derive(Default)]
+struct ProcessCache {
+    /// PID, start time and details, least recently used first
+    entries: VecDeque<(u32, u64, ProcessDetails)>,
+}
+
+impl ProcessCache {
+    fn get_or_insert_with(
+        &mut self,
+        pid: u32,
+        started: u64,
+        resolve: impl FnOnce() -> Option<ProcessDetails>,
+    ) -> Option<ProcessDetails> {
+        if let Some(index) = self.entries.iter().position(|&(cached, ..)| cached == pid) {
+            let entry = self.entries.remove(index)?;
+            if entry.1 == started {
+                let details = entry.2.clone();
+                self.entries.push_back(entry);
+                return Some(details);
+            }
+        }
+        let details = resolve()?;
+        if self.entries.len() >= MAX_CACHED_PROCESSES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((pid, started, details.clone()));
+        Some(details)
+    }
+}
+
+thread_local! {
+    static PROCESS_CACHE: RefCell<ProcessCache> = RefCell::new(ProcessCache::default());
+}
+
+/// ID of the process owning a window, or 0 if the window is gone
+pub fn get_process_id(hwnd: HWND) -> u32 {
+    let mut process_id = 0u32;
     unsafe {
-        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
-        if monitor.is_invalid() {
+        GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+    }
+    process_id
+}
+
+fn query_image_path(process: HANDLE) -> Option<String> {
+    let mut buf = [0u16; 1024];
+    let mut len = buf.len() as u32;
+    unsafe {
+        QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buf.as_mut_ptr()),
+            &mut len,
+        )
+        .ok()?;
+    }
+    Some(String::from_utf16_lossy(&buf[..len as usize]))
+}
+
+/// The FileDescription from an executable's version resource
+fn file_description(path: &str) -> Option<String> {
+    let path = HSTRING::from(path);
+    unsafe {
+        let size = GetFileVersionInfoSizeW(&path, None);
+        if size == 0 {
+            return None;
+        }
+        let mut data = vec![0u8; size as usize];
+        GetFileVersionInfoW(&path, None, size, data.as_mut_ptr() as *mut _).ok()?;
+
+        // The first language/code page pair names the string table to read
+        let mut translation: *mut std::ffi::c_void = ptr::null_mut();
+        let mut len = 0u32;
+        if !VerQueryValueW(
+            data.as_ptr() as *const _,
+            w!("\\VarFileInfo\\Translation"),
+            &mut translation,
+            &mut len,
+        )
+        .as_bool()
+            || len < 4
+        {
+            return None;
+        }
+        let [language, code_page] = *(translation as *const [u16; 2]);
+
+        let query = HSTRING::from(format!(
+            "\\StringFileInfo\\{:04x}{:04x}\\FileDescription",
+            language, code_page
+        ));
+        let mut description: *mut std::ffi::c_void = ptr::null_mut();
+        if !VerQueryValueW(
+            data.as_ptr() as *const _,
+            &query,
+            &mut description,
+            &mut len,
+        )
+        .as_bool()
+            || len == 0
+        {
             return None;
         }
+        let text = std::slice::from_raw_parts(description as *const u16, len as usize);
+        let text = String::from_utf16_lossy(text);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+/// Path and description of a process, cached until the PID is reused
+pub fn get_process_details(pid: u32) -> Option<ProcessDetails> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let (mut created, mut exited, mut kernel, mut user) = Default::default();
+        let started = GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user)
+            .map(|_| (created.dwHighDateTime as u64) << 32 | created.dwLowDateTime as u64)
+            .unwrap_or_default();
+        let details = PROCESS_CACHE.with(|cache| {
+            cache.borrow_mut().get_or_insert_with(pid, started, || {
+                let path = query_image_path(process)?;
+                let description = file_description(&path).unwrap_or_else(|| {
+                    let name = exe_name(&path);
+                    name.strip_suffix(".exe").unwrap_or(name).to_string()
+                });
+                Some(ProcessDetails {
+                    pid,
+                    path,
+                    description,
+                })
+            })
+        });
+        let _ = CloseHandle(process);
+        details
+    }
+}
+
+/// Details of the process owning a window
+pub fn get_window_process(hwnd: HWND) -> Option<ProcessDetails> {
+    get_process_details(get_process_id(hwnd))
+}
+
+/// Executable file name of the process owning a window, e.g. "notepad.exe"
+pub fn get_process_name(hwnd: HWND) -> String {
+    get_window_process(hwnd)
+        .map(|details| details.exe_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Process, class and title of a window, for matching rules
+pub fn get_window_info(hwnd: HWND) -> WindowInfo {
+    WindowInfo {
+        process: get_process_name(hwnd),
+        class: get_class_name(hwnd),
+        title: get_window_title(hwnd),
+    }
+}
+
+pub fn is_cloaked(hwnd: HWND) -> bool {
+    unsafe {
+        let mut cloaked = 0u32;
+        DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut _ as *mut _,
+            std::mem::size_of::<u32>() as u32,
+        )
+        .is_ok()
+            && cloaked != 0
+    }
+}
+
+/// True for tactile-win's own windows (overlay, settings, tray)
+pub fn is_own_window(hwnd: HWND) -> bool {
+    get_process_id(hwnd) == std::process::id()
+}
+
+/// Why Alt-Tab wouldn't list this window, or None if it would
+fn app_window_rejection(hwnd: HWND) -> Option<&'static str> {
+    if hwnd.0.is_null() {
+        return Some("no window");
+    }
+    alt_tab_rejection(&Win32Windows, hwnd)
+}
+
+/// Why the overlay can't tile this window, or None if it can
+fn target_rejection(hwnd: HWND) -> Option<&'static str> {
+    app_window_rejection(hwnd).or_else(|| {
+        (is_own_window(hwnd) && !is_practice_window(hwnd)).then_some("tactile-win window")
+    })
+}
+
+/// Another application's window the overlay could tile
+pub fn is_target_candidate(hwnd: HWND) -> bool {
+    target_rejection(hwnd).is_none()
+}
+
+/// Call `attempt` up to `attempts` times, pausing `delay` between tries,
+/// until it returns a value
+fn retry<T>(attempts: u32, delay: Duration, mut attempt: impl FnMut() -> Option<T>) -> Option<T> {
+    for i in 0..attempts {
+        if i > 0 {
+            thread::sleep(delay);
+        }
+        if let Some(value) = attempt() {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// The window to tile for `foreground`: the window that owns it, unless
+/// that is disabled because `foreground` is a modal dialog, which is tiled
+/// itself
+fn owner_or_self(foreground: HWND, root: HWND, root_enabled: bool) -> HWND {
+    if root.0.is_null() || !root_enabled {
+        foreground
+    } else {
+        root
+    }
+}
+
+/// The foreground window as a target, if it is one. Dialogs and other owned
+/// popups stand in for the window that owns them, modal dialogs excepted.
+fn foreground_target() -> Option<HWND> {
+    let foreground = get_foreground_window()?;
+    let root = unsafe { GetAncestor(foreground, GA_ROOTOWNER) };
+    let root_enabled = unsafe { IsWindowEnabled(root) }.as_bool();
+    let hwnd = owner_or_self(foreground, root, root_enabled);
+    match target_rejection(hwnd) {
+        None => Some(hwnd),
+        Some(reason) => {
+            info!(
+                "Skipping foreground window {} ({}): {}",
+                window_title(&get_window_title(hwnd)),
+                get_class_name(hwnd),
+                reason
+            );
+            None
+        }
+    }
+}
+
+/// Pick the window the overlay should tile: the foreground window (or the
+/// window owning a foreground modeless dialog) if it is another application's
+/// window, otherwise the next such window below it in z-order. Covers the
+/// overlay being opened while settings, the taskbar, a tray menu or a
+/// flyout has focus.
+pub fn find_target_window() -> Option<HWND> {
+    // Just after the hotkey the foreground can briefly be the taskbar or a
+    // closing popup, so give it a moment to settle before walking z-order
+    if let Some(hwnd) = retry(
+        FOREGROUND_ATTEMPTS,
+        FOREGROUND_RETRY_DELAY,
+        foreground_target,
+    ) {
+        return Some(hwnd);
+    }
+    unsafe {
+        let foreground = get_foreground_window();
+        let mut hwnd = match foreground {
+            Some(hwnd) => GetWindow(hwnd, GW_HWNDNEXT).ok(),
+            None => GetTopWindow(None).ok(),
+        };
+        while let Some(candidate) = hwnd {
+            if is_target_candidate(candidate) {
+                info!(
+                    "Targeting {} ({}) instead",
+                    window_title(&get_window_title(candidate)),
+                    get_class_name(candidate)
+                );
+                return Some(candidate);
+            }
+            hwnd = GetWindow(candidate, GW_HWNDNEXT).ok();
+        }
+    }
+    None
+}
+
+/// Every window the overlay could tile, topmost first
+pub fn get_target_windows() -> Vec<WindowEntry> {
+    let filter = WindowFilter {
+        alt_tab_only: true,
+        ..Default::default()
+    };
+    list_windows(&Win32Windows, &filter)
+        .into_iter()
+        .filter(|entry| !is_own_window(entry.hwnd))
+        .collect()
+}
+
+pub fn bring_to_foreground(hwnd: HWND) {
+    unsafe {
+        let _ = SetForegroundWindow(hwnd);
+    }
+}
+
+pub fn get_work_area(hwnd: HWND) -> Option<RECT> {
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    if monitor.is_invalid() {
+        return None;
+    }
+    get_monitor_work_area(monitor)
+}
 
+/// Get work area for a specific monitor by HMONITOR, less any struts
+/// kept for docks that don't reserve their space with Windows
+pub fn get_monitor_work_area(monitor: HMONITOR) -> Option<RECT> {
+    unsafe {
         let mut info = MONITORINFO {
             cbSize: std::mem::size_of::<MONITORINFO>() as u32,
             ..Default::default()
         };
 
         if GetMonitorInfoW(monitor, &mut info).as_bool() {
-            Some(info.rcWork)
+            let device = get_monitor_device(monitor);
+            Some(reserve(device.as_deref(), info.rcMonitor, info.rcWork))
         } else {
             None
         }
     }
 }
 
-/// Get work area for a specific monitor by HMONITOR
-pub fn get_monitor_work_area(monitor: HMONITOR) -> Option<RECT> {
+/// Parts of a monitor's work area that struts keep clear of tiles, left
+/// out of get_monitor_work_area
+pub fn get_monitor_excluded(monitor: HMONITOR) -> Vec<RECT> {
+    unsafe {
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return Vec::new();
+        }
+        let device = get_monitor_device(monitor);
+        let reserved = reserve(device.as_deref(), info.rcMonitor, info.rcWork);
+        excluded_strips(info.rcWork, reserved)
+    }
+}
+
+/// Full bounds of a monitor, taskbar included
+pub fn get_monitor_bounds(monitor: HMONITOR) -> Option<RECT> {
     unsafe {
         let mut info = MONITORINFO {
             cbSize: std::mem::size_of::<MONITORINFO>() as u32,
@@ -50,13 +459,59 @@ pub fn get_monitor_work_area(monitor: HMONITOR) -> Option<RECT> {
         };
 
         if GetMonitorInfoW(monitor, &mut info).as_bool() {
-            Some(info.rcWork)
+            Some(info.rcMonitor)
         } else {
             None
         }
     }
 }
 
+/// Display device name of a monitor, e.g. "\\.\DISPLAY1", which names it in
+/// the config
+pub fn get_monitor_device(monitor: HMONITOR) -> Option<String> {
+    unsafe {
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+
+        // The extended struct is passed as its leading MONITORINFO
+        let info_ptr = &mut info as *mut MONITORINFOEXW as *mut MONITORINFO;
+        if GetMonitorInfoW(monitor, info_ptr).as_bool() {
+            let len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(0);
+            Some(String::from_utf16_lossy(&info.szDevice[..len]))
+        } else {
+            None
+        }
+    }
+}
+
+/// Device interface path of the monitor itself, e.g.
+/// "\\?\DISPLAY#DEL40F5#5&2e3d1c7&0&UID4353#{...}". Unlike the display
+/// device name, it doesn't change when Windows numbers displays anew.
+pub fn get_monitor_id(monitor: HMONITOR) -> Option<String> {
+    let device = HSTRING::from(get_monitor_device(monitor)?);
+    unsafe {
+        let mut display = DISPLAY_DEVICEW {
+            cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+            ..Default::default()
+        };
+        // The first device on the adapter output is the monitor on it
+        if !EnumDisplayDevicesW(&device, 0, &mut display, EDD_GET_DEVICE_INTERFACE_NAME).as_bool() {
+            return None;
+        }
+        let len = display.DeviceID.iter().position(|&c| c == 0).unwrap_or(0);
+        (len > 0).then(|| String::from_utf16_lossy(&display.DeviceID[..len]))
+    }
+}
+
+/// The names a monitor goes by in the config: its id, then its display
+/// device name
+pub fn get_monitor_names(monitor: HMONITOR) -> Vec<String> {
+    [get_monitor_id(monitor), get_monitor_device(monitor)]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
 // Thread-local storage for monitor enumeration callback
 thread_local! {
     static MONITOR_LIST: RefCell<Vec<HMONITOR>> = const { RefCell::new(Vec::new()) };
@@ -95,25 +550,162 @@ pub fn get_window_monitor(hwnd: HWND) -> HMONITOR {
     unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) }
 }
 
-pub fn restore_if_maximized(hwnd: HWND) {
+/// Get the monitor the mouse cursor is on
+pub fn get_cursor_monitor() -> Option<HMONITOR> {
+    let mut point = POINT::default();
+    unsafe {
+        GetCursorPos(&mut point).ok()?;
+        Some(MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST))
+    }
+}
+
+/// Get the effective DPI of a monitor, falling back to 96 if unavailable
+pub fn get_monitor_dpi(monitor: HMONITOR) -> u32 {
+    unsafe {
+        let mut dpi_x = 0u32;
+        let mut dpi_y = 0u32;
+        if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y).is_ok()
+            && dpi_x != 0
+        {
+            dpi_x
+        } else {
+            crate::grid::BASE_DPI
+        }
+    }
+}
+
+/// Current screen rectangle of a window
+pub fn get_window_rect(hwnd: HWND) -> Option<RECT> {
+    unsafe {
+        let mut rect = RECT::default();
+        GetWindowRect(hwnd, &mut rect).ok().map(|_| rect)
+    }
+}
+
+/// Whether two rects share any area; touching edges don't count
+pub fn rects_intersect(a: RECT, b: RECT) -> bool {
+    a.left < b.right && b.left < a.right && a.top < b.bottom && b.top < a.bottom
+}
+
+/// How much smaller `after`'s area is than `before`'s, in percent; 0 if
+/// it isn't smaller
+pub fn shrink_percent(before: RECT, after: RECT) -> u32 {
+    let area = |rect: RECT| {
+        (rect.right - rect.left).max(0) as u64 * (rect.bottom - rect.top).max(0) as u64
+    };
+    let (before, after) = (area(before), area(after));
+    if before == 0 || after >= before {
+        return 0;
+    }
+    ((before - after) * 100 / before) as u32
+}
+
+fn busy_reason_from(flags: u32, enabled: bool) -> Option<&'static str> {
+    if flags & GUI_INMOVESIZE.0 != 0 {
+        Some("being moved or resized")
+    } else if flags & (GUI_INMENUMODE.0 | GUI_POPUPMENUMODE.0 | GUI_SYSTEMMENUMODE.0) != 0 {
+        Some("showing a menu")
+    } else if !enabled {
+        Some("showing a modal dialog")
+    } else {
+        None
+    }
+}
+
+/// Why moving the window now could confuse its app, or None if it's free:
+/// its thread is in a move/size or menu modal loop, or a modal dialog has
+/// disabled it
+pub fn busy_reason(hwnd: HWND) -> Option<&'static str> {
     unsafe {
-        let mut placement = WINDOWPLACEMENT {
-            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+        let mut info = GUITHREADINFO {
+            cbSize: std::mem::size_of::<GUITHREADINFO>() as u32,
             ..Default::default()
         };
+        let thread = GetWindowThreadProcessId(hwnd, None);
+        let flags = if thread != 0 && GetGUIThreadInfo(thread, &mut info).is_ok() {
+            info.flags.0
+        } else {
+            0
+        };
+        busy_reason_from(flags, IsWindowEnabled(hwnd).as_bool())
+    }
+}
 
-        if GetWindowPlacement(hwnd, &mut placement).is_ok() {
-            // Check if window is maximized or minimized
-            let show_cmd = SHOW_WINDOW_CMD(placement.showCmd as i32);
-            if show_cmd == SW_MAXIMIZE || show_cmd == SW_MINIMIZE {
-                placement.showCmd = SW_RESTORE.0 as u32;
-                let _ = SetWindowPlacement(hwnd, &placement);
-            }
+pub fn is_maximized(hwnd: HWND) -> bool {
+    unsafe { IsZoomed(hwnd).as_bool() }
+}
+
+pub fn is_minimized(hwnd: HWND) -> bool {
+    unsafe { IsIconic(hwnd).as_bool() }
+}
+
+/// Restore a maximized or minimized window to its normal size, so it can
+/// be moved and sized
+pub fn restore_if_maximized(hwnd: HWND) {
+    unsafe {
+        if IsZoomed(hwnd).as_bool() || IsIconic(hwnd).as_bool() {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
         }
     }
 }
 
-pub fn move_window(hwnd: HWND, rect: &RECT) -> windows::core::Result<()> {
+/// Maximize the window on the monitor it is on. This also activates it.
+pub fn maximize_window(hwnd: HWND) {
+    unsafe {
+        // Queued like move_window's SetWindowPos, so it lands after the move
+        let _ = ShowWindowAsync(hwnd, SW_MAXIMIZE);
+    }
+}
+
+/// Minimize the window, as to its taskbar button
+pub fn minimize_window(hwnd: HWND) {
+    unsafe {
+        let _ = ShowWindowAsync(hwnd, SW_MINIMIZE);
+    }
+}
+
+/// Take a window off the screen and the taskbar, leaving it running
+pub fn hide_window(hwnd: HWND) {
+    unsafe {
+        // Async, so a hung window can't hang the caller
+        let _ = ShowWindowAsync(hwnd, SW_HIDE);
+    }
+}
+
+/// Show a window hidden with hide_window again; false if it's since closed
+pub fn unhide_window(hwnd: HWND) -> bool {
+    unsafe {
+        if !IsWindow(Some(hwnd)).as_bool() {
+            return false;
+        }
+        let _ = ShowWindowAsync(hwnd, SW_SHOW);
+        true
+    }
+}
+
+pub fn move_window(hwnd: HWND, rect: &RECT, after_move: AfterMove) -> windows::core::Result<()> {
+    // The window belongs to another thread; don't stall if it is busy
+    set_window_pos(hwnd, rect, after_move, SWP_ASYNCWINDOWPOS.0)
+}
+
+/// Move the window and wait until it has moved, so GetWindowRect shows
+/// where it landed. A hung window fails rather than stalling the caller.
+pub fn move_window_and_wait(hwnd: HWND, rect: &RECT) -> windows::core::Result<()> {
+    if unsafe { IsHungAppWindow(hwnd) }.as_bool() {
+        return Err(windows::core::Error::new(
+            ERROR_TIMEOUT.to_hresult(),
+            "the window isn't responding",
+        ));
+    }
+    set_window_pos(hwnd, rect, AfterMove::Keep, 0)
+}
+
+fn set_window_pos(
+    hwnd: HWND,
+    rect: &RECT,
+    after_move: AfterMove,
+    extra_flags: u32,
+) -> windows::core::Result<()> {
     unsafe {
         // First restore if maximized
         restore_if_maximized(hwnd);
@@ -121,6 +713,13 @@ pub fn move_window(hwnd: HWND, rect: &RECT) -> windows::core::Result<()> {
         let width = rect.right - rect.left;
         let height = rect.bottom - rect.top;
 
+        let flags = match after_move {
+            AfterMove::Keep => SWP_NOZORDER.0 | SWP_NOACTIVATE.0,
+            AfterMove::Raise => SWP_NOACTIVATE.0,
+            AfterMove::Activate => 0,
+        };
+        let flags = flags | extra_flags;
+
         SetWindowPos(
             hwnd,
             Some(HWND_TOP),
@@ -128,8 +727,13 @@ pub fn move_window(hwnd: HWND, rect: &RECT) -> windows::core::Result<()> {
             rect.top,
             width,
             height,
-            SET_WINDOW_POS_FLAGS(SWP_NOZORDER.0 | SWP_NOACTIVATE.0),
-        )
+            SET_WINDOW_POS_FLAGS(flags),
+        )?;
+
+        if after_move == AfterMove::Activate {
+            let _ = SetForegroundWindow(hwnd);
+        }
+        Ok(())
     }
 }
 
@@ -142,6 +746,131 @@ mod tests {
         // This test may fail in headless environments
         let hwnd = get_foreground_window();
         // Just verify it doesn't crash - may or may not return a window
-        println!("Foreground window: {:?}", hwnd);
+        info!("Foreground window: {:?}", hwnd);
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut calls = 0;
+        let found = retry(3, Duration::ZERO, || {
+            calls += 1;
+            (calls == 2).then_some(calls)
+        });
+        assert_eq!(found, Some(2));
+
+        let mut calls = 0;
+        let found: Option<()> = retry(3, Duration::ZERO, || {
+            calls += 1;
+            None
+        });
+        assert_eq!((found, calls), (None, 3));
+    }
+
+    #[test]
+    fn test_shrink_percent() {
+        let rect = |left, top, right, bottom| RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        let full = rect(0, 0, 1920, 1040);
+        assert_eq!(shrink_percent(full, rect(0, 0, 960, 1040)), 50);
+        assert_eq!(shrink_percent(full, rect(0, 0, 480, 520)), 87);
+        // Growing, or from a rect with no area
+        assert_eq!(shrink_percent(rect(0, 0, 960, 1040), full), 0);
+        assert_eq!(shrink_percent(rect(0, 0, 0, 0), full), 0);
+    }
+
+    #[test]
+    fn test_rects_intersect() {
+        let rect = |left, top, right, bottom| RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        let work_area = rect(0, 0, 1920, 1040);
+        assert!(rects_intersect(rect(-100, 500, 50, 600), work_area));
+        assert!(rects_intersect(rect(100, 100, 200, 200), work_area));
+        // Windows on another monitor, or parked off-screen
+        assert!(!rects_intersect(rect(1920, 0, 2500, 500), work_area));
+        assert!(!rects_intersect(
+            rect(-32000, -32000, -31840, -31972),
+            work_area
+        ));
+    }
+
+    #[test]
+    fn test_process_cache() {
+        let details = |path: &str| ProcessDetails {
+            pid: 42,
+            path: path.to_string(),
+            description: String::new(),
+        };
+        let mut cache = ProcessCache::default();
+        let first = cache.get_or_insert_with(42, 100, || Some(details("C:\\a.exe")));
+        assert_eq!(first.unwrap().exe_name(), "a.exe");
+
+        // Same process: no second lookup
+        let cached = cache.get_or_insert_with(42, 100, || panic!("resolved twice"));
+        assert_eq!(cached.unwrap().path, "C:\\a.exe");
+
+        // PID reused by a newer process
+        let reused = cache.get_or_insert_with(42, 200, || Some(details("C:\\b.exe")));
+        assert_eq!(reused.unwrap().path, "C:\\b.exe");
+        assert_eq!(cache.get_or_insert_with(7, 1, || None), None);
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_process_cache_drops_least_recently_used() {
+        let details = |pid: u32| ProcessDetails {
+            pid,
+            path: format!("C:\\{}.exe", pid),
+            description: String::new(),
+        };
+        let mut cache = ProcessCache::default();
+        for pid in 0..MAX_CACHED_PROCESSES as u32 {
+            cache.get_or_insert_with(pid, 1, || Some(details(pid)));
+        }
+        // Using the oldest keeps it; the next oldest goes instead
+        cache.get_or_insert_with(0, 1, || panic!("resolved twice"));
+        let pid = MAX_CACHED_PROCESSES as u32;
+        cache.get_or_insert_with(pid, 1, || Some(details(pid)));
+        assert_eq!(cache.entries.len(), MAX_CACHED_PROCESSES);
+        assert!(cache.get_or_insert_with(0, 1, || None).is_some());
+        assert_eq!(cache.get_or_insert_with(1, 1, || None), None);
+    }
+
+    #[test]
+    fn test_busy_reason() {
+        assert_eq!(busy_reason_from(0, true), None);
+        assert_eq!(
+            busy_reason_from(GUI_INMOVESIZE.0 | GUI_INMENUMODE.0, false),
+            Some("being moved or resized")
+        );
+        assert_eq!(
+            busy_reason_from(GUI_POPUPMENUMODE.0, true),
+            Some("showing a menu")
+        );
+        assert_eq!(busy_reason_from(0, false), Some("showing a modal dialog"));
+    }
+
+    #[test]
+    fn test_modal_dialog_is_its_own_target() {
+        let (dialog, owner) = (HWND(0x10 as *mut _), HWND(0x20 as *mut _));
+        assert_eq!(owner_or_self(dialog, owner, true), owner);
+        // The owner is disabled while a modal dialog is up
+        assert_eq!(owner_or_self(dialog, owner, false), dialog);
+        assert_eq!(owner_or_self(dialog, HWND::default(), false), dialog);
+    }
+
+    #[test]
+    fn test_shell_classes_are_not_targets() {
+        assert!(is_shell_class("Shell_TrayWnd"));
+        assert!(is_shell_class("Progman"));
+        assert!(!is_shell_class("Notepad"));
+        assert!(!is_shell_class("shell_traywnd"));
     }
 }
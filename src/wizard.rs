@@ -0,0 +1,414 @@
+use std::ptr;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::Graphics::Gdi::{GetStockObject, HBRUSH, WHITE_BRUSH};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetDlgItemInt, RegisterClassW, SetDlgItemInt,
+    ShowWindow, CS_HREDRAW, CS_VREDRAW, HMENU, SW_SHOW, WINDOW_STYLE, WM_CLOSE, WM_COMMAND,
+    WM_CREATE, WM_DESTROY, WNDCLASSW, WS_CAPTION, WS_CHILD, WS_EX_DLGMODALFRAME, WS_OVERLAPPED,
+    WS_SYSMENU, WS_TABSTOP, WS_VISIBLE,
+};
+
+use crate::accessibility::text_scale_percent;
+use crate::config::Config;
+use crate::grid::BASE_DPI;
+use crate::icon::window_icon;
+use crate::log::{error, info};
+use crate::settings::{center_window, raise_window, set_dialog_font};
+use crate::tray::notify;
+use crate::window::{get_all_monitors, get_monitor_bounds, get_monitor_dpi, get_monitor_names};
+
+const WIZARD_CLASS_NAME: PCWSTR = w!("TactileWinWizard");
+
+// Control IDs; monitor n's columns and rows are ID_FIRST_EDIT + 2n and + 2n + 1
+const ID_SAVE_BTN: i32 = 110;
+const ID_CANCEL_BTN: i32 = 111;
+const ID_FIRST_EDIT: i32 = 200;
+
+// Layout, in dialog units before text scaling
+const ROW_HEIGHT: i32 = 30;
+const FIRST_ROW_Y: i32 = 45;
+
+// Use atomic for HWND tracking since HWND is not Send
+static WIZARD_HWND: AtomicIsize = AtomicIsize::new(0);
+static WIZARD: Mutex<Option<Wizard>> = Mutex::new(None);
+static ON_SAVE_CALLBACK: Mutex<Option<fn(Config)>> = Mutex::new(None);
+// Gives the config as it is when the wizard saves, which may have changed
+// since it opened
+type ConfigSource = fn() -> Option<Config>;
+static CURRENT_CONFIG_CALLBACK: Mutex<Option<ConfigSource>> = Mutex::new(None);
+
+/// A connected monitor, as the wizard lists it
+#[derive(Debug, Clone)]
+struct MonitorSetup {
+    /// The monitor's id, then its display device name
+    names: Vec<String>,
+    width: i32,
+    height: i32,
+    dpi: u32,
+    /// The monitor's configured grid, or a proposed one
+    cols: u32,
+    rows: u32,
+}
+
+struct Wizard {
+    text_percent: i32,
+    monitors: Vec<MonitorSetup>,
+}
+
+/// Grid for a `width` x `height` pixel monitor at `dpi`: about a column
+/// per 480 and a row per 540 device-independent pixels, so 1080p screens
+/// get the default 4x2
+fn propose_grid(width: i32, height: i32, dpi: u32) -> (u32, u32) {
+    let dips = |pixels: i32| pixels as f64 * BASE_DPI as f64 / dpi as f64;
+    let cols = (dips(width) / 480.0).round().clamp(1.0, 8.0) as u32;
+    let rows = (dips(height) / 540.0).round().clamp(1.0, 4.0) as u32;
+    (cols, rows)
+}
+
+fn describe_monitor(index: usize, monitor: &MonitorSetup) -> String {
+    format!(
+        "Monitor {}: {}x{} at {}%",
+        index + 1,
+        monitor.width,
+        monitor.height,
+        monitor.dpi * 100 / BASE_DPI
+    )
+}
+
+/// Connected monitors, with the grids `config` gives them or proposed ones
+fn detect_monitors(config: &Config) -> Vec<MonitorSetup> {
+    get_all_monitors()
+        .into_iter()
+        .filter_map(|monitor| {
+            let names = get_monitor_names(monitor);
+            if names.is_empty() {
+                return None;
+            }
+            let bounds = get_monitor_bounds(monitor)?;
+            let (width, height) = (bounds.right - bounds.left, bounds.bottom - bounds.top);
+            let dpi = get_monitor_dpi(monitor);
+            let (cols, rows) = config
+                .grid
+                .monitor_dimensions(&names.iter().map(String::as_str).collect::<Vec<_>>())
+                .unwrap_or_else(|| propose_grid(width, height, dpi));
+            Some(MonitorSetup {
+                names,
+                width,
+                height,
+                dpi,
+                cols,
+                rows,
+            })
+        })
+        .collect()
+}
+
+/// Open the monitor setup wizard, which gives each connected monitor a
+/// grid of its own and saves them onto the config `current_config` gives
+/// at the time. Only one is open at once.
+pub fn show_wizard(config: &Config, current_config: ConfigSource, on_save: fn(Config)) {
+    // Already open: show that one rather than a second
+    let open = HWND(WIZARD_HWND.load(Ordering::SeqCst) as *mut _);
+    if !open.0.is_null() {
+        unsafe { raise_window(open) };
+        return;
+    }
+
+    let text_percent = text_scale_percent(config.accessibility.text_scale);
+    let monitors = detect_monitors(config);
+    let count = monitors.len() as i32;
+
+    if let Ok(mut guard) = WIZARD.lock() {
+        *guard = Some(Wizard {
+            text_percent,
+            monitors,
+        });
+    }
+    if let Ok(mut guard) = ON_SAVE_CALLBACK.lock() {
+        *guard = Some(on_save);
+    }
+    if let Ok(mut guard) = CURRENT_CONFIG_CALLBACK.lock() {
+        *guard = Some(current_config);
+    }
+
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap();
+
+        let wc = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(wizard_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: WIZARD_CLASS_NAME,
+            hbrBackground: HBRUSH(GetStockObject(WHITE_BRUSH).0),
+            hIcon: window_icon(),
+            ..Default::default()
+        };
+        let _ = RegisterClassW(&wc);
+
+        let height = FIRST_ROW_Y + ROW_HEIGHT * count + 105;
+        let hwnd = CreateWindowExW(
+            WS_EX_DLGMODALFRAME,
+            WIZARD_CLASS_NAME,
+            w!("Tactile-Win Monitor Setup"),
+            WS_OVERLAPPED | WS_CAPTION | WS_SYSMENU,
+            100,
+            100,
+            400 * text_percent / 100,
+            height * text_percent / 100,
+            None,
+            None,
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+
+        if let Ok(hwnd) = hwnd {
+            WIZARD_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
+            let _ = ShowWindow(hwnd, SW_SHOW);
+        }
+    }
+}
+
+unsafe extern "system" fn wizard_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CREATE => {
+                let text_percent = create_controls(hwnd);
+                set_dialog_font(hwnd, text_percent);
+                center_window(hwnd);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                match id {
+                    ID_SAVE_BTN => {
+                        save_wizard(hwnd);
+                        let _ = DestroyWindow(hwnd);
+                    }
+                    ID_CANCEL_BTN => {
+                        dismiss_wizard();
+                        let _ = DestroyWindow(hwnd);
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                dismiss_wizard();
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                WIZARD_HWND.store(0, Ordering::SeqCst);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+unsafe fn create_control(
+    hwnd: HWND,
+    class: PCWSTR,
+    text: &str,
+    style: WINDOW_STYLE,
+    (x, y, width, height): (i32, i32, i32, i32),
+    id: Option<i32>,
+) {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap();
+        let text: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+        let _ = CreateWindowExW(
+            Default::default(),
+            class,
+            PCWSTR(text.as_ptr()),
+            style,
+            x,
+            y,
+            width,
+            height,
+            Some(hwnd),
+            id.map(|id| HMENU(id as isize as *mut _)),
+            Some(hinstance.into()),
+            Some(ptr::null()),
+        );
+    }
+}
+
+/// Lay out a row per monitor. Returns the text scale to lay them out at.
+unsafe fn create_controls(hwnd: HWND) -> i32 {
+    unsafe {
+        let guard = WIZARD.lock().ok();
+        let Some(wizard) = guard.as_ref().and_then(|guard| guard.as_ref()) else {
+            return 100;
+        };
+
+        let label_style = WS_CHILD | WS_VISIBLE;
+        let edit_style = WS_CHILD | WS_VISIBLE | WS_TABSTOP;
+
+        create_control(
+            hwnd,
+            w!("STATIC"),
+            "Grid for each monitor, in columns (1-8) x rows (1-4):",
+            label_style,
+            (20, 15, 360, 20),
+            None,
+        );
+
+        for (index, monitor) in wizard.monitors.iter().enumerate() {
+            let y = FIRST_ROW_Y + ROW_HEIGHT * index as i32;
+            let id = ID_FIRST_EDIT + 2 * index as i32;
+            create_control(
+                hwnd,
+                w!("STATIC"),
+                &describe_monitor(index, monitor),
+                label_style,
+                (20, y + 2, 220, 20),
+                None,
+            );
+            create_control(hwnd, w!("EDIT"), "", edit_style, (250, y, 40, 22), Some(id));
+            create_control(
+                hwnd,
+                w!("STATIC"),
+                "x",
+                label_style,
+                (298, y + 2, 12, 20),
+                None,
+            );
+            create_control(
+                hwnd,
+                w!("EDIT"),
+                "",
+                edit_style,
+                (315, y, 40, 22),
+                Some(id + 1),
+            );
+            let _ = SetDlgItemInt(hwnd, id, monitor.cols, false);
+            let _ = SetDlgItemInt(hwnd, id + 1, monitor.rows, false);
+        }
+
+        let buttons_y = FIRST_ROW_Y + ROW_HEIGHT * wizard.monitors.len() as i32 + 15;
+        let button_style = WS_CHILD | WS_VISIBLE | WS_TABSTOP;
+        create_control(
+            hwnd,
+            w!("BUTTON"),
+            "Save",
+            button_style,
+            (100, buttons_y, 80, 30),
+            Some(ID_SAVE_BTN),
+        );
+        create_control(
+            hwnd,
+            w!("BUTTON"),
+            "Cancel",
+            button_style,
+            (200, buttons_y, 80, 30),
+            Some(ID_CANCEL_BTN),
+        );
+
+        wizard.text_percent
+    }
+}
+
+/// The config as it is now, to save onto
+fn current_config() -> Option<Config> {
+    let callback = CURRENT_CONFIG_CALLBACK
+        .lock()
+        .ok()
+        .and_then(|guard| *guard)?;
+    callback()
+}
+
+/// Write the config file as it is if there isn't one yet, so a first run
+/// wizard closed without saving doesn't open again on the next start
+fn dismiss_wizard() {
+    if WIZARD.lock().is_ok_and(|mut guard| guard.take().is_none()) {
+        return;
+    }
+    if Config::config_path().is_none_or(|path| path.exists()) {
+        return;
+    }
+    let Some(mut config) = current_config() else {
+        return;
+    };
+    let base = config.clone();
+    match config.save(&base) {
+        Ok(()) => info!("Monitor setup skipped; wrote the config file as it is"),
+        Err(e) => {
+            error!("Failed to save config: {}", e);
+            notify("Config not saved", &e.to_string());
+        }
+    }
+}
+
+unsafe fn save_wizard(hwnd: HWND) {
+    unsafe {
+        let Some(Wizard { monitors, .. }) = WIZARD.lock().ok().and_then(|mut guard| guard.take())
+        else {
+            return;
+        };
+        let Some(mut config) = current_config() else {
+            return;
+        };
+
+        let base = config.clone();
+        for (index, monitor) in monitors.iter().enumerate() {
+            let id = ID_FIRST_EDIT + 2 * index as i32;
+            let cols = GetDlgItemInt(hwnd, id, None, false);
+            let rows = GetDlgItemInt(hwnd, id + 1, None, false);
+            let names: Vec<&str> = monitor.names.iter().map(String::as_str).collect();
+            config.grid.set_monitor_grid(&names, cols, rows);
+        }
+        config.validate();
+        info!("Saved grids for {} monitors", monitors.len());
+
+        if let Err(e) = config.save(&base) {
+            error!("Failed to save config: {}", e);
+            notify("Config not saved", &e.to_string());
+        }
+
+        let callback = ON_SAVE_CALLBACK.lock().ok().and_then(|guard| *guard);
+        if let Some(callback) = callback {
+            callback(config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_propose_grid() {
+        assert_eq!(propose_grid(1920, 1080, 96), (4, 2));
+        // Same size in device-independent pixels at 200%
+        assert_eq!(propose_grid(3840, 2160, 192), (4, 2));
+        assert_eq!(propose_grid(3440, 1440, 96), (7, 3));
+        assert_eq!(propose_grid(1080, 1920, 96), (2, 4));
+        assert_eq!(propose_grid(7680, 4320, 96), (8, 4));
+    }
+
+    #[test]
+    fn test_describe_monitor() {
+        let monitor = MonitorSetup {
+            names: vec![r"\\.\DISPLAY2".to_string()],
+            width: 2560,
+            height: 1440,
+            dpi: 144,
+            cols: 5,
+            rows: 3,
+        };
+        assert_eq!(
+            describe_monitor(1, &monitor),
+            "Monitor 2: 2560x1440 at 150%"
+        );
+    }
+}
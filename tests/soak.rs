@@ -0,0 +1,306 @@
+//! Soak test for the leaks a long-running tray app builds up: drives a real
+//! instance through many overlay show and hide cycles and placements over
+//! its IPC interface, then checks its GDI and USER handle counts and
+//! private memory have levelled off rather than grown.
+//!
+//! It needs an interactive desktop with no other instance running, and
+//! takes a few minutes, so it's ignored by default:
+//!
+//! ```text
+//! cargo test --release --test soak -- --ignored --nocapture
+//! ```
+//!
+//! SOAK_CYCLES sets the number of cycles (default 1000).
+
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::ProcessStatus::{
+    GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS, PROCESS_MEMORY_COUNTERS_EX,
+};
+use windows::Win32::System::Threading::{
+    GetGuiResources, OpenProcess, GR_GDIOBJECTS, GR_USEROBJECTS, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, FindWindowExW, PeekMessageW,
+    PostMessageW, RegisterClassW, SendMessageTimeoutW, SetForegroundWindow, TranslateMessage,
+    HWND_MESSAGE, MSG, PM_REMOVE, SMTO_ABORTIFHUNG, WINDOW_EX_STYLE, WM_COPYDATA, WNDCLASSW,
+    WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+};
+
+/// The running instance's message window
+const CLASS_NAME: PCWSTR = w!("TactileWinClass");
+const TARGET_CLASS_NAME: PCWSTR = w!("TactileWinSoakTarget");
+
+/// command.rs; wparam 0 is Command::ShowGrid
+const WM_RUN_COMMAND: u32 = 0x8003;
+const SHOW_GRID: usize = 0;
+/// batch.rs ("TWPB")
+const BATCH_COPYDATA_ID: usize = 0x5457_5042;
+/// Status::Done's message result
+const DONE: usize = 1;
+
+/// Keeps under the instance's limit of 10 requests a second
+const REQUEST_INTERVAL: Duration = Duration::from_millis(120);
+const DEFAULT_CYCLES: usize = 1000;
+/// Cycles run before taking the baseline, so caches and lazily created
+/// windows are already there
+const WARM_UP_CYCLES: usize = 20;
+
+/// Growth allowed over the run, for the odd handle Windows hands out late
+const MAX_GDI_GROWTH: u32 = 16;
+const MAX_USER_GROWTH: u32 = 16;
+const MAX_PRIVATE_GROWTH: usize = 8 * 1024 * 1024;
+
+const CONFIG: &str = r#"
+[behavior]
+when_open = "close"
+start_hidden = true
+"#;
+
+/// A tactile-win process started for the test, killed when dropped
+struct Instance {
+    child: Child,
+    process: HANDLE,
+    hwnd: HWND,
+    config: PathBuf,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        unsafe {
+            let _ = CloseHandle(self.process);
+        }
+        let _ = std::fs::remove_file(&self.config);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    gdi: u32,
+    user: u32,
+    private_bytes: usize,
+}
+
+fn find_instance() -> Option<HWND> {
+    unsafe { FindWindowExW(Some(HWND_MESSAGE), None, CLASS_NAME, None) }.ok()
+}
+
+/// Start tactile-win with a config of its own, once it's ready for requests
+fn start_instance() -> Instance {
+    let config = std::env::temp_dir().join(format!("tactile-win-soak-{}.toml", std::process::id()));
+    std::fs::write(&config, CONFIG).expect("writing the soak config");
+    let child = Command::new(env!("CARGO_BIN_EXE_tactile-win"))
+        .env("TACTILE_WIN_CONFIG", &config)
+        .spawn()
+        .expect("starting tactile-win");
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, child.id()) }
+        .expect("opening the tactile-win process");
+    let mut instance = Instance {
+        child,
+        process,
+        hwnd: HWND::default(),
+        config,
+    };
+    let deadline = Instant::now() + Duration::from_secs(10);
+    instance.hwnd = loop {
+        if let Some(hwnd) = find_instance() {
+            break hwnd;
+        }
+        assert!(Instant::now() < deadline, "tactile-win didn't start");
+        thread::sleep(Duration::from_millis(100));
+    };
+    instance
+}
+
+fn usage(process: HANDLE) -> Usage {
+    let mut counters = PROCESS_MEMORY_COUNTERS_EX {
+        cb: size_of::<PROCESS_MEMORY_COUNTERS_EX>() as u32,
+        ..Default::default()
+    };
+    unsafe {
+        GetProcessMemoryInfo(
+            process,
+            &mut counters as *mut _ as *mut PROCESS_MEMORY_COUNTERS,
+            counters.cb,
+        )
+        .expect("reading tactile-win's memory use");
+        Usage {
+            gdi: GetGuiResources(process, GR_GDIOBJECTS),
+            user: GetGuiResources(process, GR_USEROBJECTS),
+            private_bytes: counters.PrivateUsage,
+        }
+    }
+}
+
+/// Handle this thread's messages for `duration`, so the target window
+/// answers while it's moved
+fn pump(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    let mut msg = MSG::default();
+    while Instant::now() < deadline {
+        unsafe {
+            while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+unsafe extern "system" fn target_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
+
+/// A plain top-level window for the overlay to open on and batches to place
+fn create_target() -> HWND {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).expect("module handle");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(target_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: TARGET_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc);
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            TARGET_CLASS_NAME,
+            w!("tactile-win soak target"),
+            WS_OVERLAPPEDWINDOW | WS_VISIBLE,
+            100,
+            100,
+            800,
+            600,
+            None,
+            None,
+            Some(hinstance.into()),
+            None,
+        )
+        .expect("creating the target window");
+        let _ = SetForegroundWindow(hwnd);
+        hwnd
+    }
+}
+
+fn show_grid(instance: &Instance) {
+    unsafe {
+        PostMessageW(
+            Some(instance.hwnd),
+            WM_RUN_COMMAND,
+            WPARAM(SHOW_GRID),
+            LPARAM(0),
+        )
+    }
+    .expect("posting ShowGrid");
+}
+
+/// Place the target window in `region`, returning the message result
+fn place(instance: &Instance, region: &str) -> usize {
+    let json = format!(
+        r#"[{{"class":"TactileWinSoakTarget","region":"{}"}}]"#,
+        region
+    );
+    let data = COPYDATASTRUCT {
+        dwData: BATCH_COPYDATA_ID,
+        cbData: json.len() as u32,
+        lpData: json.as_ptr() as *mut _,
+    };
+    let mut result = 0;
+    // Messages the instance sends the target while moving it are handled
+    // while this waits
+    let sent = unsafe {
+        SendMessageTimeoutW(
+            instance.hwnd,
+            WM_COPYDATA,
+            WPARAM(0),
+            LPARAM(&data as *const _ as isize),
+            SMTO_ABORTIFHUNG,
+            10_000,
+            Some(&mut result),
+        )
+    };
+    assert!(sent.0 != 0, "placement request timed out");
+    result
+}
+
+/// Open the overlay, close it again, and place the target
+fn cycle(instance: &Instance, n: usize) {
+    show_grid(instance);
+    pump(REQUEST_INTERVAL);
+    // when_open = "close" makes the second ShowGrid close the overlay
+    show_grid(instance);
+    pump(REQUEST_INTERVAL);
+    let region = if n.is_multiple_of(2) {
+        "left-half"
+    } else {
+        "right-half"
+    };
+    assert_eq!(place(instance, region), DONE, "placement {} failed", n);
+    pump(REQUEST_INTERVAL);
+}
+
+#[test]
+#[ignore = "needs an interactive desktop and takes minutes"]
+fn soak_overlay_and_placement() {
+    assert!(
+        find_instance().is_none(),
+        "quit the running tactile-win before soaking"
+    );
+    let cycles = std::env::var("SOAK_CYCLES")
+        .ok()
+        .and_then(|cycles| cycles.parse().ok())
+        .unwrap_or(DEFAULT_CYCLES);
+    let target = create_target();
+    let instance = start_instance();
+
+    for n in 0..WARM_UP_CYCLES {
+        cycle(&instance, n);
+    }
+    let baseline = usage(instance.process);
+    println!("Baseline after {} cycles: {:?}", WARM_UP_CYCLES, baseline);
+    for n in 0..cycles {
+        cycle(&instance, n);
+        if (n + 1) % 100 == 0 {
+            println!("After {} cycles: {:?}", n + 1, usage(instance.process));
+        }
+    }
+    let end = usage(instance.process);
+    println!("After {} cycles: {:?}", cycles, end);
+
+    assert!(
+        end.gdi <= baseline.gdi + MAX_GDI_GROWTH,
+        "GDI objects grew from {} to {}",
+        baseline.gdi,
+        end.gdi
+    );
+    assert!(
+        end.user <= baseline.user + MAX_USER_GROWTH,
+        "USER objects grew from {} to {}",
+        baseline.user,
+        end.user
+    );
+    assert!(
+        end.private_bytes <= baseline.private_bytes + MAX_PRIVATE_GROWTH,
+        "private memory grew from {} to {} bytes",
+        baseline.private_bytes,
+        end.private_bytes
+    );
+    drop(instance);
+    unsafe {
+        let _ = DestroyWindow(target);
+    }
+}